@@ -0,0 +1,55 @@
+//! Size and speed of JSON against the binary encoding of drawings.
+//!
+//! Run with `cargo bench -p canvas-core --bench drawing_encoding`.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use canvas_core::glam::Vec2;
+use canvas_core::{decode_commands, encode_commands, DrawingCommand, InkBrush, InkSample, InkStroke};
+
+/// An ink heavy drawing: `strokes` handwriting-like strokes of 300 samples.
+fn ink_drawing(strokes: usize) -> Vec<DrawingCommand> {
+    let mut commands = Vec::new();
+    for stroke in 0..strokes {
+        let mut ink = InkStroke::new(InkBrush::new([0.1, 0.1, 0.2, 1.0], 3.0)).unwrap();
+        let origin = Vec2::new((stroke % 20) as f32 * 60.0, (stroke / 20) as f32 * 80.0);
+        ink.extend((0..300).map(|i| {
+            let t = i as f32 * 0.04;
+            let wobble = Vec2::new(12.0 * (3.1 * t + stroke as f32).sin(), 25.0 * (2.3 * t).cos());
+            InkSample::new(origin + Vec2::new(4.0 * t, 0.0) + wobble, 0.4 + 0.5 * (1.3 * t).sin().abs())
+        }));
+        commands.extend(ink.to_commands());
+    }
+    commands
+}
+
+/// Average time of `run` over enough runs to take about half a second.
+fn time<T>(mut run: impl FnMut() -> T) -> Duration {
+    let start = Instant::now();
+    let mut runs = 0;
+    while start.elapsed() < Duration::from_millis(500) {
+        black_box(run());
+        runs += 1;
+    }
+    start.elapsed() / runs
+}
+
+fn main() {
+    println!("{:>8} {:>10} {:>12} {:>10} {:>12} {:>12} {:>12} {:>12}",
+        "strokes", "commands", "json bytes", "bin bytes", "json encode", "bin encode", "json decode", "bin decode");
+    for strokes in [10, 100, 1000] {
+        let commands = ink_drawing(strokes);
+        let json = serde_json::to_vec(&commands).unwrap();
+        let binary = encode_commands(&commands).unwrap();
+        assert_eq!(decode_commands(&binary).unwrap(), commands);
+
+        let json_encode = time(|| serde_json::to_vec(&commands).unwrap());
+        let binary_encode = time(|| encode_commands(&commands).unwrap());
+        let json_decode = time(|| serde_json::from_slice::<Vec<DrawingCommand>>(&json).unwrap());
+        let binary_decode = time(|| decode_commands(&binary).unwrap());
+
+        println!("{:>8} {:>10} {:>12} {:>10} {:>12.2?} {:>12.2?} {:>12.2?} {:>12.2?}",
+            strokes, commands.len(), json.len(), binary.len(), json_encode, binary_encode, json_decode, binary_decode);
+    }
+}
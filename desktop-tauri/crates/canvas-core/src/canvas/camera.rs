@@ -1,6 +1,8 @@
 use glam::{Mat4, Vec2, Vec3};
 use serde::{Deserialize, Serialize};
 
+use crate::geometry::Rect;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Camera {
     pub position: Vec2,
@@ -27,6 +29,15 @@ impl Camera {
         }
     }
 
+    /// Camera centered on `rect` and zoomed so it fits into a viewport of
+    /// `viewport` pixels with `padding` pixels to spare on each side.
+    pub fn framing(rect: Rect, viewport: Vec2, padding: f32) -> Self {
+        let available = (viewport - Vec2::splat(2.0 * padding)).max(Vec2::ONE);
+        let size = rect.size().max(Vec2::splat(f32::EPSILON));
+        let zoom = (available / size).min_element().clamp(0.1, 10.0);
+        Self::new(rect.center(), zoom, 0.0)
+    }
+
     pub fn view_matrix(&self) -> Mat4 {
         let translation = Mat4::from_translation(Vec3::new(-self.position.x, -self.position.y, 0.0));
         let rotation = Mat4::from_rotation_z(self.rotation);
@@ -65,7 +76,7 @@ impl Camera {
 
     pub fn zoom_at(&mut self, factor: f32, target: Vec2) {
         let old_zoom = self.zoom;
-        self.zoom = (self.zoom * factor).max(0.1).min(10.0);
+        self.zoom = (self.zoom * factor).clamp(0.1, 10.0);
         
         // Adjust position to zoom at target point
         let zoom_delta = 1.0 / old_zoom - 1.0 / self.zoom;
@@ -75,6 +86,31 @@ impl Camera {
     pub fn rotate(&mut self, angle: f32) {
         self.rotation += angle;
         // Normalize rotation to [0, 2π)
-        self.rotation = self.rotation % (2.0 * std::f32::consts::PI);
+        self.rotation %= 2.0 * std::f32::consts::PI;
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn screen_world_round_trip() {
+        let camera = Camera::new(Vec2::new(120.0, -40.0), 2.5, 0.3);
+        let screen_size = Vec2::new(800.0, 600.0);
+        let world = Vec2::new(17.0, 33.0);
+
+        let screen = camera.world_to_screen(world, screen_size);
+        let back = camera.screen_to_world(screen, screen_size);
+        assert!((back - world).length() < 1e-3, "{:?} != {:?}", back, world);
+    }
+
+    #[test]
+    fn zoom_is_clamped() {
+        let mut camera = Camera::default();
+        camera.zoom_at(1000.0, Vec2::ZERO);
+        assert_eq!(camera.zoom, 10.0);
+        camera.zoom_at(0.0001, Vec2::ZERO);
+        assert_eq!(camera.zoom, 0.1);
+    }
+}
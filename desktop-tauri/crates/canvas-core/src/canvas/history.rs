@@ -0,0 +1,117 @@
+use std::collections::HashMap;
+
+use crate::tiles::{Tile, TileId};
+
+/// Maximum number of undo steps kept before the oldest ones are dropped.
+const DEFAULT_HISTORY_LIMIT: usize = 100;
+
+/// Snapshot based undo/redo stack for the tiles of a canvas.
+///
+/// Every entry is the full tile map as it was before a mutation, so one
+/// entry is one undo unit no matter how many tiles the mutation touched.
+#[derive(Debug, Clone)]
+pub struct History {
+    undo_stack: Vec<HashMap<TileId, Tile>>,
+    redo_stack: Vec<HashMap<TileId, Tile>>,
+    limit: usize,
+}
+
+impl Default for History {
+    fn default() -> Self {
+        Self {
+            undo_stack: Vec::new(),
+            redo_stack: Vec::new(),
+            limit: DEFAULT_HISTORY_LIMIT,
+        }
+    }
+}
+
+impl History {
+    pub fn record(&mut self, snapshot: HashMap<TileId, Tile>) {
+        self.undo_stack.push(snapshot);
+        if self.undo_stack.len() > self.limit {
+            self.undo_stack.remove(0);
+        }
+        self.redo_stack.clear();
+    }
+
+    pub fn undo(&mut self, current: HashMap<TileId, Tile>) -> Option<HashMap<TileId, Tile>> {
+        let previous = self.undo_stack.pop()?;
+        self.redo_stack.push(current);
+        Some(previous)
+    }
+
+    pub fn redo(&mut self, current: HashMap<TileId, Tile>) -> Option<HashMap<TileId, Tile>> {
+        let next = self.redo_stack.pop()?;
+        self.undo_stack.push(current);
+        Some(next)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo_stack.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo_stack.is_empty()
+    }
+
+    pub fn clear_redo(&mut self) {
+        self.redo_stack.clear();
+    }
+
+    /// Forgets all undo and redo steps.
+    pub fn clear(&mut self) {
+        self.undo_stack.clear();
+        self.redo_stack.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(len: usize) -> HashMap<TileId, Tile> {
+        use crate::tiles::SkiaTile;
+        use glam::Vec2;
+
+        (0..len)
+            .map(|_| {
+                let tile = SkiaTile::new().to_tile(Vec2::ZERO, Vec2::splat(100.0), String::new());
+                (TileId(uuid::Uuid::new_v4()), tile)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn undo_then_redo_round_trips() {
+        let mut history = History::default();
+        history.record(snapshot(1));
+
+        let restored = history.undo(snapshot(2)).unwrap();
+        assert_eq!(restored.len(), 1);
+        assert!(history.can_redo());
+
+        let redone = history.redo(restored).unwrap();
+        assert_eq!(redone.len(), 2);
+        assert!(history.can_undo());
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn recording_clears_redo() {
+        let mut history = History::default();
+        history.record(snapshot(0));
+        history.undo(snapshot(1));
+        history.record(snapshot(3));
+        assert!(!history.can_redo());
+    }
+
+    #[test]
+    fn drops_oldest_entries_past_limit() {
+        let mut history = History::default();
+        for len in 0..DEFAULT_HISTORY_LIMIT + 5 {
+            history.record(snapshot(len % 2));
+        }
+        assert_eq!(history.undo_stack.len(), DEFAULT_HISTORY_LIMIT);
+    }
+}
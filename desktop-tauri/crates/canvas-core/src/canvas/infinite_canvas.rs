@@ -0,0 +1,735 @@
+use crate::error::CanvasError;
+use crate::markdown;
+use crate::path_ops::DEFAULT_TOLERANCE;
+use crate::shapes;
+use crate::tiles::{DrawingCommand, SkiaTile, Tile, TileData, TileId, TileStyle};
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use uuid::Uuid;
+
+use super::operations::{validate_size, validate_vec2};
+use super::{AppliedBatch, CanvasOperation, Camera, History};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InfiniteCanvas {
+    pub camera: Camera,
+    pub tiles: HashMap<TileId, Tile>,
+    pub selected_tile_id: Option<TileId>,
+    pub canvas_size: Vec2,
+    pub background_color: [f32; 4],
+    pub grid_visible: bool,
+    pub grid_size: f32,
+    pub grid_color: [f32; 4],
+    /// Named tile styles, applied with [`CanvasOperation::ApplyStylePreset`].
+    #[serde(default)]
+    pub style_presets: BTreeMap<String, TileStyle>,
+    #[serde(skip)]
+    pub history: History,
+}
+
+/// Copy of the mutable parts of a canvas that a batch works on.
+struct Staged {
+    tiles: HashMap<TileId, Tile>,
+    camera: Camera,
+    style_presets: BTreeMap<String, TileStyle>,
+}
+
+impl Default for InfiniteCanvas {
+    fn default() -> Self {
+        Self {
+            camera: Camera::default(),
+            tiles: HashMap::new(),
+            selected_tile_id: None,
+            canvas_size: Vec2::new(800.0, 600.0),
+            background_color: [0.1, 0.1, 0.1, 1.0],
+            grid_visible: true,
+            grid_size: 50.0,
+            grid_color: [0.2, 0.2, 0.2, 1.0],
+            style_presets: BTreeMap::new(),
+            history: History::default(),
+        }
+    }
+}
+
+impl InfiniteCanvas {
+    pub fn new(canvas_size: Vec2) -> Self {
+        Self {
+            canvas_size,
+            ..Default::default()
+        }
+    }
+
+    pub fn add_tile(&mut self, tile: Tile) -> TileId {
+        let id = TileId(Uuid::new_v4());
+        self.tiles.insert(id, tile);
+        id
+    }
+
+    /// Adds a tile under a caller chosen id, e.g. from an import or another
+    /// machine. Fails instead of overwriting if the id is already in use.
+    pub fn add_tile_with_id(&mut self, id: TileId, tile: Tile) -> Result<TileId, CanvasError> {
+        if self.tiles.contains_key(&id) {
+            return Err(CanvasError::TileAlreadyExists { tile_id: id });
+        }
+        self.tiles.insert(id, tile);
+        Ok(id)
+    }
+
+    pub fn remove_tile(&mut self, id: TileId) -> Option<Tile> {
+        if self.selected_tile_id == Some(id) {
+            self.selected_tile_id = None;
+        }
+        self.tiles.remove(&id)
+    }
+
+    pub fn get_tile(&self, id: TileId) -> Option<&Tile> {
+        self.tiles.get(&id)
+    }
+
+    pub fn get_tile_mut(&mut self, id: TileId) -> Option<&mut Tile> {
+        self.tiles.get_mut(&id)
+    }
+
+    pub fn select_tile(&mut self, id: Option<TileId>) {
+        self.selected_tile_id = id;
+    }
+
+    pub fn get_selected_tile(&self) -> Option<&Tile> {
+        self.selected_tile_id.and_then(|id| self.tiles.get(&id))
+    }
+
+    pub fn get_selected_tile_mut(&mut self) -> Option<&mut Tile> {
+        if let Some(id) = self.selected_tile_id {
+            self.tiles.get_mut(&id)
+        } else {
+            None
+        }
+    }
+
+    pub fn resize(&mut self, new_size: Vec2) {
+        self.canvas_size = new_size;
+    }
+
+    pub fn tile_at_position(&self, screen_pos: Vec2) -> Option<TileId> {
+        let world_pos = self.camera.screen_to_world(screen_pos, self.canvas_size);
+        
+        // Check tiles in reverse order (top to bottom in z-order)
+        let mut tiles: Vec<(&TileId, &Tile)> = self.tiles.iter().collect();
+        tiles.sort_by(|(_, a), (_, b)| b.z_index.total_cmp(&a.z_index));
+        
+        for (id, tile) in tiles {
+            if tile.contains_point(world_pos) {
+                return Some(*id);
+            }
+        }
+        
+        None
+    }
+
+    pub fn update_grid(&mut self, visible: bool, size: f32, color: [f32; 4]) {
+        self.grid_visible = visible;
+        self.grid_size = size;
+        self.grid_color = color;
+    }
+
+    /// Records the current tiles as one undo step. Call before mutating.
+    pub fn checkpoint(&mut self) {
+        self.history.record(self.tiles.clone());
+    }
+
+    pub fn undo(&mut self) -> bool {
+        let current = self.tiles.clone();
+        match self.history.undo(current) {
+            Some(previous) => {
+                self.tiles = previous;
+                self.clear_stale_selection();
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn redo(&mut self) -> bool {
+        let current = self.tiles.clone();
+        match self.history.redo(current) {
+            Some(next) => {
+                self.tiles = next;
+                self.clear_stale_selection();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Validates and applies a batch of operations as a single unit.
+    ///
+    /// The operations run against a staged copy of the tiles and camera; the
+    /// canvas is only touched once every operation succeeded, so a failing
+    /// batch leaves it unchanged. A successful batch that changes tiles is
+    /// recorded as one undo step.
+    pub fn apply_operations(
+        &mut self,
+        operations: Vec<CanvasOperation>,
+    ) -> Result<AppliedBatch, CanvasError> {
+        let mut staged = Staged {
+            tiles: self.tiles.clone(),
+            camera: self.camera.clone(),
+            style_presets: self.style_presets.clone(),
+        };
+        let mut batch = AppliedBatch::default();
+
+        for (index, operation) in operations.into_iter().enumerate() {
+            Self::apply_operation(&mut staged, &mut batch, operation).map_err(
+                |source| CanvasError::OperationFailed {
+                    index,
+                    source: Box::new(source),
+                },
+            )?;
+        }
+
+        if batch.changes_tiles() {
+            self.checkpoint();
+            self.tiles = staged.tiles;
+            self.clear_stale_selection();
+        }
+        self.camera = staged.camera;
+        self.style_presets = staged.style_presets;
+
+        Ok(batch)
+    }
+
+    fn apply_operation(
+        staged: &mut Staged,
+        batch: &mut AppliedBatch,
+        operation: CanvasOperation,
+    ) -> Result<(), CanvasError> {
+        let Staged { tiles, camera, style_presets } = staged;
+        match operation {
+            CanvasOperation::Add { tile_id, tile, position, size, title } => {
+                let position = validate_vec2("position", position)?;
+                let size = validate_size(size)?;
+                let id = tile_id.unwrap_or_else(|| TileId(Uuid::new_v4()));
+                if tiles.contains_key(&id) {
+                    return Err(CanvasError::TileAlreadyExists { tile_id: id });
+                }
+                let tile = tile.into_tile(position, size, title);
+                tile.check_size().map_err(|violation| CanvasError::constraint(id, violation))?;
+                tiles.insert(id, tile);
+                batch.added.push(id);
+            }
+            CanvasOperation::Upsert { tile_id, tile, position, size, title } => {
+                let position = validate_vec2("position", position)?;
+                let size = validate_size(size)?;
+                let new_tile = tile.into_tile(position, size, title);
+                match tiles.get_mut(&tile_id) {
+                    Some(existing) => {
+                        existing.move_to(new_tile.position)
+                            .map_err(|violation| CanvasError::constraint(tile_id, violation))?;
+                        existing.resize_exact(new_tile.size)
+                            .map_err(|violation| CanvasError::constraint(tile_id, violation))?;
+                        existing.title = new_tile.title;
+                        existing.tile_type = new_tile.tile_type;
+                        existing.data = new_tile.data;
+                        batch.mark_changed(tile_id);
+                    }
+                    None => {
+                        new_tile.check_size().map_err(|violation| CanvasError::constraint(tile_id, violation))?;
+                        tiles.insert(tile_id, new_tile);
+                        batch.added.push(tile_id);
+                    }
+                }
+            }
+            CanvasOperation::Move { tile_id, position } => {
+                let position = validate_vec2("position", position)?;
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                tile.move_to(position).map_err(|violation| CanvasError::constraint(tile_id, violation))?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::Resize { tile_id, size, fit } => {
+                let size = validate_size(size)?;
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                let resized = if fit { tile.resize(size).map(|_| ()) } else { tile.resize_exact(size) };
+                resized.map_err(|violation| CanvasError::constraint(tile_id, violation))?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::SetLock { tile_id, movable, resizable } => {
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                tile.set_lock(movable, resizable);
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::SetConstraints { tile_id, constraints } => {
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                tile.set_constraints(constraints)
+                    .map_err(|violation| CanvasError::constraint(tile_id, violation))?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::Remove { tile_id } => {
+                tiles.remove(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                batch.mark_removed(tile_id);
+            }
+            CanvasOperation::SetVisibility { tile_id, visible } => {
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                if tile.visible != visible {
+                    tile.visible = visible;
+                    batch.mark_changed(tile_id);
+                }
+            }
+            CanvasOperation::AddTags { tile_id, tags } => {
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                if tile.add_tags(&tags)? {
+                    batch.mark_changed(tile_id);
+                }
+            }
+            CanvasOperation::RemoveTags { tile_id, tags } => {
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                if tile.remove_tags(&tags) {
+                    batch.mark_changed(tile_id);
+                }
+            }
+            CanvasOperation::SetProperty { tile_id, key, value } => {
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                tile.set_property(&key, value)?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::RemoveProperty { tile_id, key } => {
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                if tile.remove_property(&key).is_some() {
+                    batch.mark_changed(tile_id);
+                }
+            }
+            CanvasOperation::SetStyle { tile_id, style } => {
+                style.validate()?;
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                tile.style = style;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::ApplyStylePreset { tile_id, preset } => {
+                let style = style_presets.get(&preset)
+                    .ok_or_else(|| CanvasError::invalid_argument("preset", format!("no preset named `{}`", preset)))?;
+                let tile = tiles.get_mut(&tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+                tile.style = style.clone();
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::SaveStylePreset { name, style } => {
+                if name.trim().is_empty() {
+                    return Err(CanvasError::invalid_argument("name", "must not be empty"));
+                }
+                style.validate()?;
+                style_presets.insert(name, style);
+                batch.presets_changed = true;
+            }
+            CanvasOperation::DeleteStylePreset { name } => {
+                if style_presets.remove(&name).is_none() {
+                    return Err(CanvasError::invalid_argument("name", format!("no preset named `{}`", name)));
+                }
+                batch.presets_changed = true;
+            }
+            CanvasOperation::SetNoteText { tile_id, markdown } => {
+                *note_source(tiles, tile_id)? = markdown;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::SetWidgetConfig { tile_id, config } => {
+                match tiles.get_mut(&tile_id) {
+                    Some(Tile { data: TileData::Egui { config: current, .. }, .. }) => *current = config,
+                    Some(_) => return Err(CanvasError::invalid_argument("tile_id", "is not an egui tile")),
+                    None => return Err(CanvasError::TileNotFound { tile_id }),
+                }
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::ReplaceNoteText { tile_id, start, end, text } => {
+                let source = note_source(tiles, tile_id)?;
+                let byte_offset = |offset: usize| {
+                    source.char_indices().map(|(i, _)| i).chain([source.len()]).nth(offset)
+                };
+                let range = match (byte_offset(start), byte_offset(end)) {
+                    (Some(start), Some(end)) if start <= end => start..end,
+                    _ => return Err(CanvasError::invalid_argument("range", "must lie within the note text")),
+                };
+                source.replace_range(range, &text);
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::ToggleNoteCheckbox { tile_id, index, checked } => {
+                markdown::set_checkbox(note_source(tiles, tile_id)?, index, checked)?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::SetDrawing { tile_id, commands } => {
+                SkiaTile::validate_commands(&commands)?;
+                *drawing_commands(tiles, tile_id)? = commands;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::AppendDrawing { tile_id, commands } => {
+                SkiaTile::validate_commands(&commands)?;
+                drawing_commands(tiles, tile_id)?.extend(commands);
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::SpliceDrawing { tile_id, start, end, commands } => {
+                SkiaTile::validate_commands(&commands)?;
+                let drawing = drawing_commands(tiles, tile_id)?;
+                if start > end || end > drawing.len() {
+                    return Err(CanvasError::invalid_argument("range", "must lie within the drawing commands"));
+                }
+                drawing.splice(start..end, commands);
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::DeleteShapes { tile_id, shapes } => {
+                shapes::delete_shapes(drawing_commands(tiles, tile_id)?, &shapes)?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::MoveShapes { tile_id, shapes, offset } => {
+                let offset = validate_vec2("offset", offset)?;
+                shapes::move_shapes(drawing_commands(tiles, tile_id)?, &shapes, offset)?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::RecolorShapes { tile_id, shapes, fill, stroke } => {
+                shapes::recolor_shapes(drawing_commands(tiles, tile_id)?, &shapes, fill, stroke)?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::CombineShapes { tile_id, shapes, operation, tolerance } => {
+                let tolerance = tolerance.unwrap_or(DEFAULT_TOLERANCE);
+                shapes::combine_shapes(drawing_commands(tiles, tile_id)?, &shapes, operation, tolerance)?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::OffsetShapes { tile_id, shapes, distance, tolerance } => {
+                let tolerance = tolerance.unwrap_or(DEFAULT_TOLERANCE);
+                shapes::offset_shapes(drawing_commands(tiles, tile_id)?, &shapes, distance, tolerance)?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::OutlineStrokes { tile_id, shapes } => {
+                shapes::outline_strokes(drawing_commands(tiles, tile_id)?, &shapes)?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::FlattenShapes { tile_id, shapes, tolerance } => {
+                let tolerance = tolerance.unwrap_or(DEFAULT_TOLERANCE);
+                shapes::flatten_shapes(drawing_commands(tiles, tile_id)?, &shapes, tolerance)?;
+                batch.mark_changed(tile_id);
+            }
+            CanvasOperation::EraseDrawing { tile_id, path, radius } => {
+                let path: Vec<Vec2> = path.into_iter().map(Vec2::from).collect();
+                if shapes::erase(drawing_commands(tiles, tile_id)?, &path, radius)? {
+                    batch.mark_changed(tile_id);
+                }
+            }
+            CanvasOperation::PanCamera { delta } => {
+                camera.pan(validate_vec2("delta", delta)?);
+                batch.camera_changed = true;
+            }
+            CanvasOperation::ZoomCamera { factor, target } => {
+                if !factor.is_finite() || factor <= 0.0 {
+                    return Err(CanvasError::invalid_argument("factor", "must be positive"));
+                }
+                camera.zoom_at(factor, validate_vec2("target", target)?);
+                batch.camera_changed = true;
+            }
+            CanvasOperation::RotateCamera { angle } => {
+                if !angle.is_finite() {
+                    return Err(CanvasError::invalid_argument("angle", "must be finite"));
+                }
+                camera.rotate(angle);
+                batch.camera_changed = true;
+            }
+            CanvasOperation::ResetCamera => {
+                *camera = Camera::default();
+                batch.camera_changed = true;
+            }
+        }
+        Ok(())
+    }
+
+    fn clear_stale_selection(&mut self) {
+        if let Some(id) = self.selected_tile_id {
+            if !self.tiles.contains_key(&id) {
+                self.selected_tile_id = None;
+            }
+        }
+    }
+}
+
+/// Mutable Markdown source of note tile `tile_id`.
+fn note_source(tiles: &mut HashMap<TileId, Tile>, tile_id: TileId) -> Result<&mut String, CanvasError> {
+    match tiles.get_mut(&tile_id) {
+        Some(Tile { data: TileData::Note { markdown }, .. }) => Ok(markdown),
+        Some(_) => Err(CanvasError::invalid_argument("tile_id", "is not a note tile")),
+        None => Err(CanvasError::TileNotFound { tile_id }),
+    }
+}
+
+/// Mutable drawing commands of Skia tile `tile_id`.
+fn drawing_commands(tiles: &mut HashMap<TileId, Tile>, tile_id: TileId) -> Result<&mut Vec<DrawingCommand>, CanvasError> {
+    match tiles.get_mut(&tile_id) {
+        Some(Tile { data: TileData::Skia { drawing_commands }, .. }) => Ok(drawing_commands),
+        Some(_) => Err(CanvasError::invalid_argument("tile_id", "is not a Skia tile")),
+        None => Err(CanvasError::TileNotFound { tile_id }),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::canvas::NewTile;
+    use crate::tiles::{ConstraintViolation, SizeConstraints, SkiaTile, TileData, DEFAULT_MIN_SIZE};
+
+    /// Adds a 100×100 tile at the origin as its own batch.
+    pub(crate) fn add_tile(canvas: &mut InfiniteCanvas, tile: NewTile) -> TileId {
+        let add = CanvasOperation::Add {
+            tile_id: None,
+            tile,
+            position: [0.0, 0.0],
+            size: [100.0, 100.0],
+            title: "Tile".into(),
+        };
+        let [tile_id] = canvas.apply_operations(vec![add]).unwrap().added[..] else {
+            panic!("expected one tile")
+        };
+        tile_id
+    }
+
+    fn add_skia(position: [f32; 2]) -> CanvasOperation {
+        CanvasOperation::Add {
+            tile_id: None,
+            tile: NewTile::Skia,
+            position,
+            size: [100.0, 100.0],
+            title: "Sketch".into(),
+        }
+    }
+
+    #[test]
+    fn batch_is_one_undo_step() {
+        let mut canvas = InfiniteCanvas::default();
+        let batch = canvas.apply_operations(vec![add_skia([0.0, 0.0]), add_skia([200.0, 0.0])]).unwrap();
+        assert_eq!(batch.added.len(), 2);
+
+        let moves = batch.added.iter()
+            .map(|tile_id| CanvasOperation::Move { tile_id: *tile_id, position: [5.0, 5.0] })
+            .collect();
+        canvas.apply_operations(moves).unwrap();
+
+        assert!(canvas.undo());
+        assert_eq!(canvas.tiles.len(), 2);
+        assert!(canvas.tiles.values().all(|tile| tile.position != Vec2::splat(5.0)));
+        assert!(canvas.undo());
+        assert!(canvas.tiles.is_empty());
+        assert!(!canvas.undo());
+    }
+
+    #[test]
+    fn failing_batch_changes_nothing() {
+        let mut canvas = InfiniteCanvas::default();
+        let added = canvas.apply_operations(vec![add_skia([0.0, 0.0])]).unwrap().added[0];
+        let missing = TileId(Uuid::new_v4());
+
+        let error = canvas.apply_operations(vec![
+            CanvasOperation::Move { tile_id: added, position: [300.0, 300.0] },
+            CanvasOperation::PanCamera { delta: [10.0, 0.0] },
+            CanvasOperation::Remove { tile_id: missing },
+        ]).unwrap_err();
+
+        match error {
+            CanvasError::OperationFailed { index, source } => {
+                assert_eq!(index, 2);
+                assert_eq!(source.code(), "tile_not_found");
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+        assert_eq!(canvas.get_tile(added).unwrap().position, Vec2::ZERO);
+        assert_eq!(canvas.camera.position, Vec2::ZERO);
+    }
+
+    #[test]
+    fn rejects_invalid_sizes() {
+        let mut canvas = InfiniteCanvas::default();
+        let error = canvas.apply_operations(vec![CanvasOperation::Add {
+            tile_id: None,
+            tile: NewTile::Skia,
+            position: [0.0, 0.0],
+            size: [f32::NAN, 10.0],
+            title: String::new(),
+        }]).unwrap_err();
+        assert_eq!(error.code(), "operation_failed");
+        assert!(canvas.tiles.is_empty());
+    }
+
+    #[test]
+    fn add_and_remove_in_one_batch_reports_nothing() {
+        let mut canvas = InfiniteCanvas::default();
+        let first = canvas.apply_operations(vec![add_skia([0.0, 0.0])]).unwrap().added[0];
+
+        let batch = canvas.apply_operations(vec![
+            CanvasOperation::Move { tile_id: first, position: [1.0, 1.0] },
+            CanvasOperation::Remove { tile_id: first },
+        ]).unwrap();
+        assert!(batch.changed.is_empty());
+        assert_eq!(batch.removed, vec![first]);
+    }
+
+    #[test]
+    fn visibility_changes_are_undoable() {
+        let mut canvas = InfiniteCanvas::default();
+        let tile_id = canvas.apply_operations(vec![add_skia([0.0, 0.0])]).unwrap().added[0];
+
+        let hide = || CanvasOperation::SetVisibility { tile_id, visible: false };
+        assert_eq!(canvas.apply_operations(vec![hide()]).unwrap().changed, [tile_id]);
+        assert!(!canvas.get_tile(tile_id).unwrap().visible);
+        // Hiding a hidden tile changes nothing
+        assert!(canvas.apply_operations(vec![hide()]).unwrap().changed.is_empty());
+
+        assert!(canvas.undo());
+        assert!(canvas.get_tile(tile_id).unwrap().visible);
+    }
+
+    #[test]
+    fn camera_only_batch_is_not_recorded() {
+        let mut canvas = InfiniteCanvas::default();
+        let batch = canvas.apply_operations(vec![CanvasOperation::ZoomCamera {
+            factor: 2.0,
+            target: [0.0, 0.0],
+        }]).unwrap();
+        assert!(batch.camera_changed);
+        assert_eq!(canvas.camera.zoom, 2.0);
+        assert!(!canvas.history.can_undo());
+    }
+
+    #[test]
+    fn caller_supplied_ids_are_kept_and_conflicts_rejected() {
+        let mut canvas = InfiniteCanvas::default();
+        let id = TileId(Uuid::new_v4());
+        let add = CanvasOperation::Add {
+            tile_id: Some(id),
+            tile: NewTile::Skia,
+            position: [0.0, 0.0],
+            size: [100.0, 100.0],
+            title: "Imported".into(),
+        };
+
+        assert_eq!(canvas.apply_operations(vec![add.clone()]).unwrap().added, vec![id]);
+
+        // Retrying the same add is reported instead of silently duplicating
+        let error = canvas.apply_operations(vec![add]).unwrap_err();
+        match error {
+            CanvasError::OperationFailed { source, .. } => {
+                assert!(matches!(*source, CanvasError::TileAlreadyExists { tile_id } if tile_id == id));
+            }
+            other => panic!("unexpected error {:?}", other),
+        }
+
+        let tile = SkiaTile::new().to_tile(Vec2::ZERO, Vec2::splat(10.0), String::new());
+        assert_eq!(canvas.add_tile_with_id(id, tile).unwrap_err().code(), "tile_already_exists");
+        assert_eq!(canvas.tiles.len(), 1);
+    }
+
+    #[test]
+    fn upsert_creates_then_updates() {
+        let mut canvas = InfiniteCanvas::default();
+        let id = TileId(Uuid::new_v4());
+        let upsert = |url: &str, x: f32| CanvasOperation::Upsert {
+            tile_id: id,
+            tile: NewTile::WebView { url: url.into() },
+            position: [x, 0.0],
+            size: [100.0, 100.0],
+            title: "Synced".into(),
+        };
+
+        let created = canvas.apply_operations(vec![upsert("https://a.example", 0.0)]).unwrap();
+        assert_eq!(created.added, vec![id]);
+
+        canvas.get_tile_mut(id).unwrap().z_index = 3.0;
+        let updated = canvas.apply_operations(vec![upsert("https://b.example", 50.0)]).unwrap();
+        assert!(updated.added.is_empty());
+        assert_eq!(updated.changed, vec![id]);
+
+        let tile = canvas.get_tile(id).unwrap();
+        assert_eq!(tile.position, Vec2::new(50.0, 0.0));
+        assert_eq!(tile.z_index, 3.0);
+        assert!(matches!(&tile.data, TileData::WebView { url, .. } if url == "https://b.example"));
+    }
+
+    #[test]
+    fn locks_and_constraints_are_enforced() {
+        let mut canvas = InfiniteCanvas::default();
+        let id = add_tile(&mut canvas, NewTile::Skia);
+
+        canvas.apply_operations(vec![CanvasOperation::SetLock { tile_id: id, movable: false, resizable: true }]).unwrap();
+        let error = canvas.apply_operations(vec![CanvasOperation::Move { tile_id: id, position: [5.0, 5.0] }]).unwrap_err();
+        let CanvasError::OperationFailed { source, .. } = &error else { panic!("unexpected {:?}", error) };
+        assert!(matches!(**source, CanvasError::ConstraintViolated { violation: ConstraintViolation::NotMovable, .. }));
+        // Staying in place is not a move
+        canvas.apply_operations(vec![CanvasOperation::Move { tile_id: id, position: [0.0, 0.0] }]).unwrap();
+
+        canvas.apply_operations(vec![CanvasOperation::SetConstraints {
+            tile_id: id,
+            constraints: SizeConstraints { aspect_ratio: Some(2.0), ..Default::default() },
+        }]).unwrap();
+        assert_eq!(canvas.get_tile(id).unwrap().size, Vec2::new(100.0, 50.0));
+
+        let resize = |size: [f32; 2], fit| CanvasOperation::Resize { tile_id: id, size, fit };
+        assert!(canvas.apply_operations(vec![resize([300.0, 100.0], false)]).is_err());
+        assert!(canvas.apply_operations(vec![resize([20.0, 10.0], false)]).is_err());
+        canvas.apply_operations(vec![resize([300.0, 100.0], true)]).unwrap();
+        assert_eq!(canvas.get_tile(id).unwrap().size, Vec2::new(300.0, 150.0));
+        // Without `fit`, sizes are clamped like the minimum size always was
+        let clamped = serde_json::from_value(serde_json::json!({ "op": "resize", "tile_id": id, "size": [10.0, 10.0] })).unwrap();
+        canvas.apply_operations(vec![clamped]).unwrap();
+        assert_eq!(canvas.get_tile(id).unwrap().size, Vec2::new(100.0, 50.0));
+
+        // Locked tiles only take constraints their size already satisfies
+        canvas.apply_operations(vec![CanvasOperation::SetLock { tile_id: id, movable: true, resizable: false }]).unwrap();
+        let constrain = |aspect_ratio| CanvasOperation::SetConstraints {
+            tile_id: id,
+            constraints: SizeConstraints { aspect_ratio, ..Default::default() },
+        };
+        assert!(canvas.apply_operations(vec![constrain(Some(1.0))]).is_err());
+        canvas.apply_operations(vec![constrain(None)]).unwrap();
+        assert_eq!(canvas.get_tile(id).unwrap().size, Vec2::new(100.0, 50.0));
+    }
+
+    #[test]
+    fn new_tiles_must_satisfy_their_constraints() {
+        let mut canvas = InfiniteCanvas::default();
+        let tiny = CanvasOperation::Add {
+            tile_id: None,
+            tile: NewTile::Skia,
+            position: [0.0, 0.0],
+            size: [1.0, 1.0],
+            title: "Dot".into(),
+        };
+        let error = canvas.apply_operations(vec![add_skia([0.0, 0.0]), tiny]).unwrap_err();
+        let CanvasError::OperationFailed { index: 1, source } = &error else { panic!("unexpected {:?}", error) };
+        assert!(matches!(**source, CanvasError::ConstraintViolated { violation: ConstraintViolation::TooSmall { .. }, .. }));
+        assert!(canvas.tiles.is_empty());
+
+        let upsert = CanvasOperation::Upsert {
+            tile_id: TileId(Uuid::new_v4()),
+            tile: NewTile::Skia,
+            position: [0.0, 0.0],
+            size: [DEFAULT_MIN_SIZE.x - 1.0, 100.0],
+            title: "Sliver".into(),
+        };
+        assert!(canvas.apply_operations(vec![upsert]).is_err());
+        assert!(canvas.tiles.is_empty());
+    }
+
+    #[test]
+    fn widget_configs_are_replaced_and_undoable() {
+        let mut canvas = InfiniteCanvas::default();
+        let config = serde_json::json!({ "checked": false });
+        let id = add_tile(&mut canvas, NewTile::Egui { widget_type: "checkbox".into(), config });
+        let sketch = add_tile(&mut canvas, NewTile::Skia);
+        let config = |canvas: &InfiniteCanvas| match &canvas.get_tile(id).unwrap().data {
+            TileData::Egui { config, .. } => config.clone(),
+            other => panic!("{:?}", other),
+        };
+
+        let checked = serde_json::json!({ "checked": true });
+        let batch = canvas.apply_operations(vec![CanvasOperation::SetWidgetConfig { tile_id: id, config: checked.clone() }]).unwrap();
+        assert_eq!(batch.changed, vec![id]);
+        assert_eq!(config(&canvas), checked);
+        assert!(canvas.apply_operations(vec![
+            CanvasOperation::SetWidgetConfig { tile_id: sketch, config: checked },
+        ]).is_err());
+
+        canvas.undo();
+        assert_eq!(config(&canvas), serde_json::json!({ "checked": false }));
+    }
+
+}
@@ -0,0 +1,12 @@
+mod infinite_canvas;
+mod camera;
+mod history;
+mod operations;
+
+pub use infinite_canvas::InfiniteCanvas;
+pub use camera::Camera;
+pub use history::History;
+pub use operations::{AppliedBatch, CanvasOperation, NewTile};
+
+#[cfg(test)]
+pub(crate) use infinite_canvas::tests::add_tile;
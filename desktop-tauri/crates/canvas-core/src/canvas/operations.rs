@@ -0,0 +1,344 @@
+use std::collections::HashMap;
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CanvasError;
+use crate::path_ops::PathOp;
+use crate::tiles::{DrawingCommand, EguiTile, ImageTile, NoteTile, PropertyValue, SizeConstraints, SkiaTile, TerminalTile, TileStyle, Tile, TileId, WebViewTile};
+
+/// Content of a tile created by a [`CanvasOperation::Add`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum NewTile {
+    WebView {
+        url: String,
+    },
+    Egui {
+        widget_type: String,
+        config: serde_json::Value,
+    },
+    Skia,
+    Note {
+        #[serde(default)]
+        markdown: String,
+    },
+    /// An image file. The tile is created even if the file can't be read;
+    /// it then shows a placeholder.
+    Image {
+        path: String,
+    },
+    Terminal {
+        #[serde(default)]
+        cwd: Option<String>,
+    },
+}
+
+impl NewTile {
+    pub fn into_tile(self, position: Vec2, size: Vec2, title: String) -> Tile {
+        match self {
+            NewTile::WebView { url } => WebViewTile::new(url).to_tile(position, size, title),
+            NewTile::Egui { widget_type, config } => {
+                EguiTile::new(widget_type, config).to_tile(position, size, title)
+            }
+            NewTile::Skia => SkiaTile::new().to_tile(position, size, title),
+            NewTile::Note { markdown } => NoteTile::new(markdown).to_tile(position, size, title),
+            NewTile::Image { path } => ImageTile::new(path).to_tile(position, size, title),
+            NewTile::Terminal { cwd } => TerminalTile::new(cwd).to_tile(position, size, title),
+        }
+    }
+}
+
+fn default_fit() -> bool {
+    true
+}
+
+/// A single mutation of the canvas, applied as part of a batch by
+/// [`InfiniteCanvas::apply_operations`](super::InfiniteCanvas::apply_operations).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "op", rename_all = "snake_case")]
+pub enum CanvasOperation {
+    /// Creates a tile. A caller supplied `tile_id` must not be in use yet;
+    /// without one a fresh id is generated.
+    Add {
+        #[serde(default)]
+        tile_id: Option<TileId>,
+        tile: NewTile,
+        position: [f32; 2],
+        size: [f32; 2],
+        title: String,
+    },
+    /// Creates the tile if `tile_id` is unused, otherwise replaces its
+    /// content, position, size and title and keeps everything else. Moving
+    /// or resizing an existing tile is subject to its lock and constraints.
+    Upsert {
+        tile_id: TileId,
+        tile: NewTile,
+        position: [f32; 2],
+        size: [f32; 2],
+        title: String,
+    },
+    /// Fails if the tile is locked against moving.
+    Move {
+        tile_id: TileId,
+        position: [f32; 2],
+    },
+    /// Fails if the tile is locked against resizing. A size that breaks the
+    /// tile's constraints is replaced by the closest admissible one, unless
+    /// `fit` is false, which rejects it instead.
+    Resize {
+        tile_id: TileId,
+        size: [f32; 2],
+        #[serde(default = "default_fit")]
+        fit: bool,
+    },
+    SetLock {
+        tile_id: TileId,
+        movable: bool,
+        resizable: bool,
+    },
+    /// Replaces the size constraints and fits the tile into them.
+    SetConstraints {
+        tile_id: TileId,
+        constraints: SizeConstraints,
+    },
+    Remove {
+        tile_id: TileId,
+    },
+    /// Shows or hides a tile, and a WebView tile's webview with it.
+    SetVisibility {
+        tile_id: TileId,
+        visible: bool,
+    },
+    AddTags {
+        tile_id: TileId,
+        tags: Vec<String>,
+    },
+    RemoveTags {
+        tile_id: TileId,
+        tags: Vec<String>,
+    },
+    /// Sets or replaces one custom property.
+    SetProperty {
+        tile_id: TileId,
+        key: String,
+        value: PropertyValue,
+    },
+    RemoveProperty {
+        tile_id: TileId,
+        key: String,
+    },
+    SetStyle {
+        tile_id: TileId,
+        style: TileStyle,
+    },
+    /// Copies a preset stored on the canvas onto the tile.
+    ApplyStylePreset {
+        tile_id: TileId,
+        preset: String,
+    },
+    /// Stores a named preset on the canvas, replacing one with the same
+    /// name.
+    SaveStylePreset {
+        name: String,
+        style: TileStyle,
+    },
+    DeleteStylePreset {
+        name: String,
+    },
+    /// Replaces the Markdown source of a note tile.
+    SetNoteText {
+        tile_id: TileId,
+        markdown: String,
+    },
+    /// Replaces the `[start, end)` character range of a note's source with
+    /// `text`, for incremental edits.
+    ReplaceNoteText {
+        tile_id: TileId,
+        start: usize,
+        end: usize,
+        text: String,
+    },
+    /// Sets a task list checkbox of a note, addressed by its index in
+    /// document order, or flips it when `checked` is omitted.
+    ToggleNoteCheckbox {
+        tile_id: TileId,
+        index: usize,
+        #[serde(default)]
+        checked: Option<bool>,
+    },
+    /// Replaces the config of an egui tile's widget, such as after the
+    /// widget was used.
+    SetWidgetConfig {
+        tile_id: TileId,
+        config: serde_json::Value,
+    },
+    /// Replaces all drawing commands of a Skia tile.
+    SetDrawing {
+        tile_id: TileId,
+        commands: Vec<DrawingCommand>,
+    },
+    /// Adds drawing commands to the end of a Skia tile's drawing.
+    AppendDrawing {
+        tile_id: TileId,
+        commands: Vec<DrawingCommand>,
+    },
+    /// Replaces the `[start, end)` range of a Skia tile's drawing commands
+    /// with `commands`. An empty range inserts at `start`, no commands
+    /// remove the range.
+    SpliceDrawing {
+        tile_id: TileId,
+        start: usize,
+        end: usize,
+        #[serde(default)]
+        commands: Vec<DrawingCommand>,
+    },
+    /// Removes shapes of a Skia tile's drawing, by their index in
+    /// [`shapes`](crate::shapes::shapes).
+    DeleteShapes {
+        tile_id: TileId,
+        shapes: Vec<usize>,
+    },
+    /// Moves shapes of a Skia tile's drawing by `offset` in content area
+    /// units.
+    MoveShapes {
+        tile_id: TileId,
+        shapes: Vec<usize>,
+        offset: [f32; 2],
+    },
+    /// Repaints shapes of a Skia tile's drawing in one fill and one stroke
+    /// color; an omitted color keeps that paint.
+    RecolorShapes {
+        tile_id: TileId,
+        shapes: Vec<usize>,
+        #[serde(default)]
+        fill: Option<[f32; 4]>,
+        #[serde(default)]
+        stroke: Option<[f32; 4]>,
+    },
+    /// Combines shapes of a Skia tile's drawing into the lowest of them,
+    /// see [`combine_shapes`](crate::shapes::combine_shapes). Curves are
+    /// flattened within `tolerance`, by default
+    /// [`DEFAULT_TOLERANCE`](crate::path_ops::DEFAULT_TOLERANCE).
+    CombineShapes {
+        tile_id: TileId,
+        shapes: Vec<usize>,
+        operation: PathOp,
+        #[serde(default)]
+        tolerance: Option<f32>,
+    },
+    /// Grows the areas of shapes of a Skia tile's drawing by `distance` in
+    /// content area units, or shrinks them for a negative one.
+    OffsetShapes {
+        tile_id: TileId,
+        shapes: Vec<usize>,
+        distance: f32,
+        #[serde(default)]
+        tolerance: Option<f32>,
+    },
+    /// Turns the strokes of shapes of a Skia tile's drawing into filled
+    /// outlines.
+    OutlineStrokes {
+        tile_id: TileId,
+        shapes: Vec<usize>,
+    },
+    /// Replaces the curves shapes of a Skia tile's drawing paint with lines
+    /// within `tolerance`.
+    FlattenShapes {
+        tile_id: TileId,
+        shapes: Vec<usize>,
+        #[serde(default)]
+        tolerance: Option<f32>,
+    },
+    /// Erases along a polyline in a Skia tile's content area, see
+    /// [`erase`](crate::shapes::erase).
+    EraseDrawing {
+        tile_id: TileId,
+        path: Vec<[f32; 2]>,
+        radius: f32,
+    },
+    PanCamera {
+        delta: [f32; 2],
+    },
+    ZoomCamera {
+        factor: f32,
+        target: [f32; 2],
+    },
+    RotateCamera {
+        angle: f32,
+    },
+    ResetCamera,
+}
+
+/// Summary of what a successfully applied batch changed.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AppliedBatch {
+    /// Ids of created tiles, in the order of their `Add`/`Upsert` operations.
+    pub added: Vec<TileId>,
+    /// Tiles that were updated and still exist after the batch.
+    pub changed: Vec<TileId>,
+    /// Tiles that existed before the batch and were removed by it.
+    pub removed: Vec<TileId>,
+    pub camera_changed: bool,
+    #[serde(default)]
+    pub presets_changed: bool,
+}
+
+impl AppliedBatch {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.changed.is_empty()
+            && self.removed.is_empty()
+            && !self.camera_changed
+            && !self.presets_changed
+    }
+
+    /// Whether any tile was added, changed or removed.
+    pub fn changes_tiles(&self) -> bool {
+        !self.added.is_empty() || !self.changed.is_empty() || !self.removed.is_empty()
+    }
+
+    /// Describes the difference between two tile maps, e.g. around an undo.
+    /// Tiles present on both sides are reported as changed.
+    pub fn diff(before: &HashMap<TileId, Tile>, after: &HashMap<TileId, Tile>) -> Self {
+        Self {
+            added: after.keys().filter(|id| !before.contains_key(id)).copied().collect(),
+            changed: after.keys().filter(|id| before.contains_key(id)).copied().collect(),
+            removed: before.keys().filter(|id| !after.contains_key(id)).copied().collect(),
+            camera_changed: false,
+            presets_changed: false,
+        }
+    }
+
+    pub(crate) fn mark_changed(&mut self, id: TileId) {
+        if !self.added.contains(&id) && !self.changed.contains(&id) {
+            self.changed.push(id);
+        }
+    }
+
+    pub(crate) fn mark_removed(&mut self, id: TileId) {
+        self.changed.retain(|changed| *changed != id);
+        if let Some(index) = self.added.iter().position(|added| *added == id) {
+            // Added and removed within the same batch: nothing to report.
+            self.added.remove(index);
+        } else {
+            self.removed.push(id);
+        }
+    }
+}
+
+pub(crate) fn validate_vec2(field: &str, value: [f32; 2]) -> Result<Vec2, CanvasError> {
+    let value = Vec2::from(value);
+    if !value.is_finite() {
+        return Err(CanvasError::invalid_argument(field, "must be finite"));
+    }
+    Ok(value)
+}
+
+pub(crate) fn validate_size(value: [f32; 2]) -> Result<Vec2, CanvasError> {
+    let size = validate_vec2("size", value)?;
+    if size.x <= 0.0 || size.y <= 0.0 {
+        return Err(CanvasError::invalid_argument("size", "must be positive"));
+    }
+    Ok(size)
+}
@@ -0,0 +1,114 @@
+//! Saving and loading whole canvases.
+//!
+//! Documents carry a format version, so future changes to the tile model
+//! can be migrated on load. They come in two forms: JSON, wrapped in an
+//! envelope with the version, for reading and debugging, and a compact
+//! binary form for storage, in which drawings are
+//! [encoded](crate::drawing_codec) rather than spelled out.
+
+use serde::{Deserialize, Serialize};
+
+use crate::canvas::InfiniteCanvas;
+use crate::error::CanvasError;
+
+/// Format version written by [`to_json`] and [`to_binary`].
+pub const DOCUMENT_VERSION: u32 = 1;
+
+/// Starts every binary document, followed by the version as a
+/// little-endian `u32` and the canvas in CBOR.
+const BINARY_MAGIC: &[u8; 4] = b"ICVS";
+
+#[derive(Serialize)]
+struct DocumentRef<'a> {
+    version: u32,
+    canvas: &'a InfiniteCanvas,
+}
+
+#[derive(Deserialize)]
+struct DocumentHeader {
+    version: u32,
+}
+
+#[derive(Deserialize)]
+struct Document {
+    canvas: InfiniteCanvas,
+}
+
+pub fn to_json(canvas: &InfiniteCanvas) -> Result<String, CanvasError> {
+    let document = DocumentRef {
+        version: DOCUMENT_VERSION,
+        canvas,
+    };
+    serde_json::to_string(&document).map_err(invalid_document)
+}
+
+/// Loads a canvas saved by [`to_json`]. The undo history starts out empty.
+pub fn from_json(json: &str) -> Result<InfiniteCanvas, CanvasError> {
+    let header: DocumentHeader = serde_json::from_str(json).map_err(invalid_document)?;
+    if header.version > DOCUMENT_VERSION {
+        return Err(CanvasError::UnsupportedDocumentVersion { version: header.version });
+    }
+
+    let document: Document = serde_json::from_str(json).map_err(invalid_document)?;
+    Ok(document.canvas)
+}
+
+pub fn to_binary(canvas: &InfiniteCanvas) -> Result<Vec<u8>, CanvasError> {
+    let mut bytes = BINARY_MAGIC.to_vec();
+    bytes.extend_from_slice(&DOCUMENT_VERSION.to_le_bytes());
+    ciborium::into_writer(canvas, &mut bytes).map_err(invalid_document)?;
+    Ok(bytes)
+}
+
+/// Loads a canvas saved by [`to_binary`]. The undo history starts out
+/// empty.
+pub fn from_binary(bytes: &[u8]) -> Result<InfiniteCanvas, CanvasError> {
+    let Some(rest) = bytes.strip_prefix(BINARY_MAGIC.as_slice()) else {
+        return Err(CanvasError::InvalidDocument { message: "not a binary canvas document".into() });
+    };
+    let Some((version, canvas)) = rest.split_at_checked(4) else {
+        return Err(CanvasError::InvalidDocument { message: "binary document ends before its version".into() });
+    };
+    let version = u32::from_le_bytes(version.try_into().unwrap());
+    if version > DOCUMENT_VERSION {
+        return Err(CanvasError::UnsupportedDocumentVersion { version });
+    }
+
+    ciborium::from_reader(canvas).map_err(invalid_document)
+}
+
+fn invalid_document(error: impl std::fmt::Display) -> CanvasError {
+    CanvasError::InvalidDocument {
+        message: error.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rejects_newer_versions() {
+        let json = r#"{ "version": 99, "canvas": {} }"#;
+        let error = from_json(json).unwrap_err();
+        assert_eq!(error.code(), "unsupported_document_version");
+    }
+
+    #[test]
+    fn rejects_malformed_json() {
+        let error = from_json("{").unwrap_err();
+        assert_eq!(error.code(), "invalid_document");
+    }
+
+    #[test]
+    fn rejects_malformed_binary() {
+        let canvas = to_binary(&InfiniteCanvas::default()).unwrap();
+        for bytes in [&b"{}"[..], &canvas[..6], &canvas[..canvas.len() - 1]] {
+            assert_eq!(from_binary(bytes).unwrap_err().code(), "invalid_document");
+        }
+
+        let mut newer = canvas.clone();
+        newer[4..8].copy_from_slice(&99u32.to_le_bytes());
+        assert_eq!(from_binary(&newer).unwrap_err().code(), "unsupported_document_version");
+    }
+}
@@ -0,0 +1,660 @@
+//! Compact binary encoding of Skia drawings.
+//!
+//! JSON spells out every coordinate of every command, which makes ink
+//! heavy drawings megabytes large. The binary encoding instead stores
+//!
+//! - each command as a one byte opcode, and a run of commands of the same
+//!   kind as one opcode and a count,
+//! - points in hundredths of a unit, the precision ink strokes are rounded
+//!   to, as the difference from the point before them, in variable length
+//!   integers of mostly one or two bytes,
+//! - everything else, such as colors, widths, angles and transforms, as
+//!   exact 32-bit floats.
+//!
+//! Points round to the nearest hundredth, all other values decode exactly.
+//! JSON stays the human readable form: [`TileData`](crate::tiles::TileData)
+//! serializes drawings as commands for JSON and other human readable
+//! formats, and as this encoding for binary ones such as the document
+//! format of [`crate::document::to_binary`].
+
+use glam::Vec2;
+
+use crate::error::CanvasError;
+use crate::text::{TextAlign, TextStyle};
+use crate::tiles::{DrawingCommand, Gradient, GradientStop, LineCap, LineJoin, TextBox};
+
+/// Starts every encoded drawing.
+const MAGIC: &[u8; 4] = b"DRWC";
+/// Version written after the magic.
+pub const ENCODING_VERSION: u8 = 1;
+
+/// Steps points are stored in, per unit.
+pub const POINT_STEPS_PER_UNIT: f32 = 100.0;
+/// Largest step count a point may have on either axis, so it decodes
+/// within the precision of a float.
+const MAX_POINT_STEPS: i64 = 1 << 40;
+
+/// Set on an opcode that starts a run of commands of that kind.
+const RUN: u8 = 0x80;
+
+/// Encodes `commands` as described in the [module documentation](self).
+pub fn encode_commands(commands: &[DrawingCommand]) -> Result<Vec<u8>, CanvasError> {
+    let mut writer = Writer { bytes: MAGIC.to_vec(), cursor: (0, 0) };
+    writer.bytes.push(ENCODING_VERSION);
+    writer.varint(commands.len() as u64);
+
+    let mut index = 0;
+    while index < commands.len() {
+        let opcode = opcode(&commands[index]);
+        let run = commands[index..].iter().take_while(|command| self::opcode(command) == opcode).count();
+        if run > 1 {
+            writer.bytes.push(opcode | RUN);
+            writer.varint(run as u64);
+        } else {
+            writer.bytes.push(opcode);
+        }
+        for command in &commands[index..index + run] {
+            writer.command(command).map_err(|reason| {
+                CanvasError::invalid_argument(format!("commands[{}]", index), reason)
+            })?;
+            index += 1;
+        }
+    }
+    Ok(writer.bytes)
+}
+
+/// Decodes a drawing encoded by [`encode_commands`].
+pub fn decode_commands(bytes: &[u8]) -> Result<Vec<DrawingCommand>, CanvasError> {
+    let invalid = |reason: String| CanvasError::InvalidDocument { message: format!("drawing data {}", reason) };
+    let Some(rest) = bytes.strip_prefix(MAGIC.as_slice()) else {
+        return Err(invalid("does not start with the drawing header".into()));
+    };
+    let mut reader = Reader { bytes: rest, cursor: (0, 0) };
+    let version = reader.byte().map_err(invalid)?;
+    if version > ENCODING_VERSION {
+        return Err(invalid(format!("has version {}, newer than {}", version, ENCODING_VERSION)));
+    }
+
+    let count = reader.varint().map_err(invalid)?;
+    // Every command takes at least a byte, so a count beyond that is corrupt
+    if count > reader.bytes.len() as u64 {
+        return Err(invalid(format!("claims {} commands in {} bytes", count, reader.bytes.len())));
+    }
+    let mut commands = Vec::with_capacity(count as usize);
+    while (commands.len() as u64) < count {
+        let byte = reader.byte().map_err(invalid)?;
+        let run = if byte & RUN != 0 { reader.varint().map_err(invalid)? } else { 1 };
+        if run > count - commands.len() as u64 {
+            return Err(invalid(format!("has a run past its {} commands", count)));
+        }
+        for _ in 0..run {
+            let command = reader.command(byte & !RUN).map_err(|reason| invalid(format!("at command {}: {}", commands.len(), reason)))?;
+            commands.push(command);
+        }
+    }
+    if !reader.bytes.is_empty() {
+        return Err(invalid(format!("has {} bytes after the last command", reader.bytes.len())));
+    }
+    Ok(commands)
+}
+
+fn opcode(command: &DrawingCommand) -> u8 {
+    match command {
+        DrawingCommand::MoveTo(_) => 0,
+        DrawingCommand::LineTo(_) => 1,
+        DrawingCommand::QuadTo(..) => 2,
+        DrawingCommand::CubicTo(..) => 3,
+        DrawingCommand::ClosePath => 4,
+        DrawingCommand::SetFillColor(_) => 5,
+        DrawingCommand::SetStrokeColor(_) => 6,
+        DrawingCommand::SetStrokeWidth(_) => 7,
+        DrawingCommand::Fill => 8,
+        DrawingCommand::Stroke => 9,
+        DrawingCommand::DrawRect(..) => 10,
+        DrawingCommand::DrawCircle(..) => 11,
+        DrawingCommand::DrawText(..) => 12,
+        DrawingCommand::Save => 13,
+        DrawingCommand::Restore => 14,
+        DrawingCommand::Translate(_) => 15,
+        DrawingCommand::Scale(_) => 16,
+        DrawingCommand::Rotate(_) => 17,
+        DrawingCommand::SetGlobalAlpha(_) => 18,
+        DrawingCommand::SetFillGradient(_) => 19,
+        DrawingCommand::SetStrokeGradient(_) => 20,
+        DrawingCommand::SetLineDash(..) => 21,
+        DrawingCommand::SetLineCap(_) => 22,
+        DrawingCommand::SetLineJoin(_) => 23,
+        DrawingCommand::SetMiterLimit(_) => 24,
+        DrawingCommand::Arc(..) => 25,
+        DrawingCommand::Clip => 26,
+        DrawingCommand::DrawRoundedRect(..) => 27,
+        DrawingCommand::DrawEllipse(..) => 28,
+        DrawingCommand::DrawTextBox(_) => 29,
+    }
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+    /// Last point written, in steps.
+    cursor: (i64, i64),
+}
+
+impl Writer {
+    /// Arguments of `command`; the opcode is written by the caller.
+    fn command(&mut self, command: &DrawingCommand) -> Result<(), String> {
+        match command {
+            DrawingCommand::MoveTo(p) | DrawingCommand::LineTo(p) => self.point(*p)?,
+            DrawingCommand::QuadTo(c, p) => {
+                self.point(*c)?;
+                self.point(*p)?;
+            }
+            DrawingCommand::CubicTo(c1, c2, p) => {
+                self.point(*c1)?;
+                self.point(*c2)?;
+                self.point(*p)?;
+            }
+            DrawingCommand::ClosePath
+            | DrawingCommand::Fill
+            | DrawingCommand::Stroke
+            | DrawingCommand::Save
+            | DrawingCommand::Restore
+            | DrawingCommand::Clip => {}
+            DrawingCommand::SetFillColor(color) | DrawingCommand::SetStrokeColor(color) => self.color(*color),
+            DrawingCommand::SetStrokeWidth(value)
+            | DrawingCommand::Rotate(value)
+            | DrawingCommand::SetGlobalAlpha(value)
+            | DrawingCommand::SetMiterLimit(value) => self.f32(*value),
+            DrawingCommand::DrawRect(position, size) => {
+                self.point(*position)?;
+                self.vec2(*size);
+            }
+            DrawingCommand::DrawCircle(center, radius) => {
+                self.point(*center)?;
+                self.f32(*radius);
+            }
+            DrawingCommand::DrawText(text, position, size) => {
+                self.string(text);
+                self.point(*position)?;
+                self.f32(*size);
+            }
+            DrawingCommand::Translate(v) | DrawingCommand::Scale(v) => self.vec2(*v),
+            DrawingCommand::SetFillGradient(gradient) | DrawingCommand::SetStrokeGradient(gradient) => {
+                self.gradient(gradient)?
+            }
+            DrawingCommand::SetLineDash(dashes, offset) => {
+                self.varint(dashes.len() as u64);
+                dashes.iter().for_each(|dash| self.f32(*dash));
+                self.f32(*offset);
+            }
+            DrawingCommand::SetLineCap(cap) => self.bytes.push(*cap as u8),
+            DrawingCommand::SetLineJoin(join) => self.bytes.push(*join as u8),
+            DrawingCommand::Arc(center, radius, start, end) => {
+                self.point(*center)?;
+                self.f32(*radius);
+                self.f32(*start);
+                self.f32(*end);
+            }
+            DrawingCommand::DrawRoundedRect(position, size, radius) => {
+                self.point(*position)?;
+                self.vec2(*size);
+                self.f32(*radius);
+            }
+            DrawingCommand::DrawEllipse(center, radii) => {
+                self.point(*center)?;
+                self.vec2(*radii);
+            }
+            DrawingCommand::DrawTextBox(text_box) => {
+                self.string(&text_box.text);
+                self.point(text_box.position)?;
+                match text_box.width {
+                    Some(width) => {
+                        self.bytes.push(1);
+                        self.f32(width);
+                    }
+                    None => self.bytes.push(0),
+                }
+                let style = &text_box.style;
+                match &style.family {
+                    Some(family) => {
+                        self.bytes.push(1);
+                        self.string(family);
+                    }
+                    None => self.bytes.push(0),
+                }
+                self.f32(style.size);
+                self.bytes.push(style.align as u8);
+                self.f32(style.line_height);
+            }
+        }
+        Ok(())
+    }
+
+    fn gradient(&mut self, gradient: &Gradient) -> Result<(), String> {
+        let stops = match gradient {
+            Gradient::Linear { start, end, stops } => {
+                self.bytes.push(0);
+                self.point(*start)?;
+                self.point(*end)?;
+                stops
+            }
+            Gradient::Radial { center, radius, stops } => {
+                self.bytes.push(1);
+                self.point(*center)?;
+                self.f32(*radius);
+                stops
+            }
+        };
+        self.varint(stops.len() as u64);
+        for stop in stops {
+            self.f32(stop.offset);
+            self.color(stop.color);
+        }
+        Ok(())
+    }
+
+    /// A point as the difference from the last one, in steps.
+    fn point(&mut self, point: Vec2) -> Result<(), String> {
+        let steps = |value: f32| {
+            let steps = (value as f64 * POINT_STEPS_PER_UNIT as f64).round();
+            if steps.is_finite() && steps.abs() <= MAX_POINT_STEPS as f64 {
+                Ok(steps as i64)
+            } else {
+                Err(format!("point {} is out of range", point))
+            }
+        };
+        let (x, y) = (steps(point.x)?, steps(point.y)?);
+        self.varint(zigzag(x - self.cursor.0));
+        self.varint(zigzag(y - self.cursor.1));
+        self.cursor = (x, y);
+        Ok(())
+    }
+
+    fn vec2(&mut self, v: Vec2) {
+        self.f32(v.x);
+        self.f32(v.y);
+    }
+
+    fn color(&mut self, color: [f32; 4]) {
+        color.iter().for_each(|channel| self.f32(*channel));
+    }
+
+    fn f32(&mut self, value: f32) {
+        self.bytes.extend_from_slice(&value.to_le_bytes());
+    }
+
+    fn string(&mut self, text: &str) {
+        self.varint(text.len() as u64);
+        self.bytes.extend_from_slice(text.as_bytes());
+    }
+
+    /// LEB128: seven bits per byte, low bits first, the high bit set on all
+    /// bytes but the last.
+    fn varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.bytes.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        self.bytes.push(value as u8);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    cursor: (i64, i64),
+}
+
+impl Reader<'_> {
+    fn command(&mut self, opcode: u8) -> Result<DrawingCommand, String> {
+        Ok(match opcode {
+            0 => DrawingCommand::MoveTo(self.point()?),
+            1 => DrawingCommand::LineTo(self.point()?),
+            2 => DrawingCommand::QuadTo(self.point()?, self.point()?),
+            3 => DrawingCommand::CubicTo(self.point()?, self.point()?, self.point()?),
+            4 => DrawingCommand::ClosePath,
+            5 => DrawingCommand::SetFillColor(self.color()?),
+            6 => DrawingCommand::SetStrokeColor(self.color()?),
+            7 => DrawingCommand::SetStrokeWidth(self.f32()?),
+            8 => DrawingCommand::Fill,
+            9 => DrawingCommand::Stroke,
+            10 => DrawingCommand::DrawRect(self.point()?, self.vec2()?),
+            11 => DrawingCommand::DrawCircle(self.point()?, self.f32()?),
+            12 => DrawingCommand::DrawText(self.string()?, self.point()?, self.f32()?),
+            13 => DrawingCommand::Save,
+            14 => DrawingCommand::Restore,
+            15 => DrawingCommand::Translate(self.vec2()?),
+            16 => DrawingCommand::Scale(self.vec2()?),
+            17 => DrawingCommand::Rotate(self.f32()?),
+            18 => DrawingCommand::SetGlobalAlpha(self.f32()?),
+            19 => DrawingCommand::SetFillGradient(self.gradient()?),
+            20 => DrawingCommand::SetStrokeGradient(self.gradient()?),
+            21 => {
+                let count = self.varint()?;
+                if count > self.bytes.len() as u64 / 4 {
+                    return Err(format!("has {} dashes in {} bytes", count, self.bytes.len()));
+                }
+                let dashes = (0..count).map(|_| self.f32()).collect::<Result<_, _>>()?;
+                DrawingCommand::SetLineDash(dashes, self.f32()?)
+            }
+            22 => DrawingCommand::SetLineCap(match self.byte()? {
+                0 => LineCap::Butt,
+                1 => LineCap::Round,
+                2 => LineCap::Square,
+                other => return Err(format!("has unknown line cap {}", other)),
+            }),
+            23 => DrawingCommand::SetLineJoin(match self.byte()? {
+                0 => LineJoin::Miter,
+                1 => LineJoin::Round,
+                2 => LineJoin::Bevel,
+                other => return Err(format!("has unknown line join {}", other)),
+            }),
+            24 => DrawingCommand::SetMiterLimit(self.f32()?),
+            25 => DrawingCommand::Arc(self.point()?, self.f32()?, self.f32()?, self.f32()?),
+            26 => DrawingCommand::Clip,
+            27 => DrawingCommand::DrawRoundedRect(self.point()?, self.vec2()?, self.f32()?),
+            28 => DrawingCommand::DrawEllipse(self.point()?, self.vec2()?),
+            29 => {
+                let (text, position) = (self.string()?, self.point()?);
+                let width = if self.flag()? { Some(self.f32()?) } else { None };
+                let family = if self.flag()? { Some(self.string()?) } else { None };
+                let size = self.f32()?;
+                let align = match self.byte()? {
+                    0 => TextAlign::Left,
+                    1 => TextAlign::Center,
+                    2 => TextAlign::Right,
+                    other => return Err(format!("has unknown text alignment {}", other)),
+                };
+                let style = TextStyle { family, size, align, line_height: self.f32()? };
+                DrawingCommand::DrawTextBox(TextBox { text, position, width, style })
+            }
+            other => return Err(format!("has unknown opcode {}", other)),
+        })
+    }
+
+    fn gradient(&mut self) -> Result<Gradient, String> {
+        let kind = self.byte()?;
+        let (first, second) = match kind {
+            0 => (self.point()?, Some(self.point()?)),
+            1 => (self.point()?, None),
+            other => return Err(format!("has unknown gradient kind {}", other)),
+        };
+        let radius = if second.is_none() { self.f32()? } else { 0.0 };
+        let count = self.varint()?;
+        if count > self.bytes.len() as u64 / 20 {
+            return Err(format!("has {} gradient stops in {} bytes", count, self.bytes.len()));
+        }
+        let stops = (0..count)
+            .map(|_| Ok(GradientStop { offset: self.f32()?, color: self.color()? }))
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(match second {
+            Some(end) => Gradient::Linear { start: first, end, stops },
+            None => Gradient::Radial { center: first, radius, stops },
+        })
+    }
+
+    fn point(&mut self) -> Result<Vec2, String> {
+        let (dx, dy) = (unzigzag(self.varint()?), unzigzag(self.varint()?));
+        let x = self.cursor.0.checked_add(dx).filter(|x| x.abs() <= MAX_POINT_STEPS);
+        let y = self.cursor.1.checked_add(dy).filter(|y| y.abs() <= MAX_POINT_STEPS);
+        let (Some(x), Some(y)) = (x, y) else {
+            return Err("has a point out of range".into());
+        };
+        self.cursor = (x, y);
+        // Divided rather than multiplied by the step, so values rounded to
+        // hundredths come back exactly as they were
+        Ok(Vec2::new(x as f32 / POINT_STEPS_PER_UNIT, y as f32 / POINT_STEPS_PER_UNIT))
+    }
+
+    fn vec2(&mut self) -> Result<Vec2, String> {
+        Ok(Vec2::new(self.f32()?, self.f32()?))
+    }
+
+    fn color(&mut self) -> Result<[f32; 4], String> {
+        Ok([self.f32()?, self.f32()?, self.f32()?, self.f32()?])
+    }
+
+    fn f32(&mut self) -> Result<f32, String> {
+        Ok(f32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let length = self.varint()?;
+        if length > self.bytes.len() as u64 {
+            return Err("is cut off in a string".into());
+        }
+        let bytes = self.take(length as usize)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| "has a string that is not UTF-8".to_string())
+    }
+
+    fn flag(&mut self) -> Result<bool, String> {
+        match self.byte()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            other => Err(format!("has {} where a flag belongs", other)),
+        }
+    }
+
+    fn varint(&mut self) -> Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("has a number longer than 64 bits".into())
+    }
+
+    fn byte(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn take(&mut self, count: usize) -> Result<&[u8], String> {
+        if self.bytes.len() < count {
+            return Err("ends early".into());
+        }
+        let (taken, rest) = self.bytes.split_at(count);
+        self.bytes = rest;
+        Ok(taken)
+    }
+}
+
+/// Maps signed to unsigned integers so that small magnitudes stay small:
+/// 0, -1, 1, -2, … become 0, 1, 2, 3, …
+fn zigzag(value: i64) -> u64 {
+    ((value << 1) ^ (value >> 63)) as u64
+}
+
+fn unzigzag(value: u64) -> i64 {
+    (value >> 1) as i64 ^ -((value & 1) as i64)
+}
+
+/// Serializes drawings as commands for human readable formats and as the
+/// binary encoding for the others, see [`TileData`](crate::tiles::TileData).
+pub(crate) mod compact {
+    use std::fmt;
+
+    use serde::de::{self, Deserializer, SeqAccess, Visitor};
+    use serde::ser::{self, Serializer};
+    use serde::{Deserialize, Serialize};
+
+    use super::{decode_commands, encode_commands};
+    use crate::tiles::DrawingCommand;
+
+    pub fn serialize<S: Serializer>(commands: &Vec<DrawingCommand>, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            return commands.serialize(serializer);
+        }
+        let bytes = encode_commands(commands).map_err(ser::Error::custom)?;
+        serializer.serialize_bytes(&bytes)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<DrawingCommand>, D::Error> {
+        if deserializer.is_human_readable() {
+            return Vec::deserialize(deserializer);
+        }
+        deserializer.deserialize_bytes(EncodedVisitor)
+    }
+
+    struct EncodedVisitor;
+
+    impl<'de> Visitor<'de> for EncodedVisitor {
+        type Value = Vec<DrawingCommand>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("encoded drawing commands")
+        }
+
+        fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Self::Value, E> {
+            decode_commands(bytes).map_err(E::custom)
+        }
+
+        // Formats without a byte string type send a sequence of bytes
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+            while let Some(byte) = seq.next_element::<u8>()? {
+                bytes.push(byte);
+            }
+            self.visit_bytes(&bytes)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ink::{InkBrush, InkSample, InkStroke};
+
+    fn every_command() -> Vec<DrawingCommand> {
+        let stops = vec![
+            GradientStop { offset: 0.0, color: [1.0, 0.0, 0.0, 1.0] },
+            GradientStop { offset: 1.0, color: [0.0, 0.0, 1.0, 0.5] },
+        ];
+        vec![
+            DrawingCommand::MoveTo(Vec2::new(1.25, -2.5)),
+            DrawingCommand::LineTo(Vec2::new(10.0, 20.0)),
+            DrawingCommand::LineTo(Vec2::new(-30000.5, 0.01)),
+            DrawingCommand::QuadTo(Vec2::new(1.0, 2.0), Vec2::new(3.0, 4.0)),
+            DrawingCommand::CubicTo(Vec2::new(5.0, 6.0), Vec2::new(7.0, 8.0), Vec2::new(9.0, 10.0)),
+            DrawingCommand::ClosePath,
+            DrawingCommand::SetFillColor([0.1, 0.2, 0.3, 0.4]),
+            DrawingCommand::SetStrokeColor([0.5, 0.6, 0.7, 0.8]),
+            DrawingCommand::SetStrokeWidth(2.5),
+            DrawingCommand::Fill,
+            DrawingCommand::Stroke,
+            DrawingCommand::DrawRect(Vec2::new(1.0, 1.0), Vec2::new(-3.3, 4.4)),
+            DrawingCommand::DrawCircle(Vec2::new(50.0, 50.0), 12.345),
+            DrawingCommand::DrawText("Grüße".into(), Vec2::new(5.0, 5.0), 14.0),
+            DrawingCommand::Save,
+            DrawingCommand::Translate(Vec2::new(0.123_456, 7.0)),
+            DrawingCommand::Scale(Vec2::new(1.5, -1.0)),
+            DrawingCommand::Rotate(0.3),
+            DrawingCommand::SetGlobalAlpha(0.75),
+            DrawingCommand::SetFillGradient(Gradient::Linear {
+                start: Vec2::ZERO,
+                end: Vec2::new(100.0, 0.0),
+                stops: stops.clone(),
+            }),
+            DrawingCommand::SetStrokeGradient(Gradient::Radial { center: Vec2::new(5.0, 5.0), radius: 9.5, stops }),
+            DrawingCommand::SetLineDash(vec![4.0, 2.0, 1.0], 0.5),
+            DrawingCommand::SetLineCap(LineCap::Round),
+            DrawingCommand::SetLineJoin(LineJoin::Bevel),
+            DrawingCommand::SetMiterLimit(10.0),
+            DrawingCommand::Arc(Vec2::new(20.0, 20.0), 5.0, 0.0, std::f32::consts::PI),
+            DrawingCommand::Clip,
+            DrawingCommand::Restore,
+            DrawingCommand::DrawRoundedRect(Vec2::new(0.0, 0.0), Vec2::new(40.0, 20.0), 4.0),
+            DrawingCommand::DrawEllipse(Vec2::new(30.0, 30.0), Vec2::new(10.0, 5.0)),
+            DrawingCommand::DrawTextBox(TextBox {
+                text: "Hello\nworld".into(),
+                position: Vec2::new(2.0, 3.0),
+                width: Some(120.0),
+                style: TextStyle { family: Some("Inter".into()), align: TextAlign::Center, ..TextStyle::sized(18.0) },
+            }),
+            DrawingCommand::DrawTextBox(TextBox {
+                text: String::new(),
+                position: Vec2::ZERO,
+                width: None,
+                style: TextStyle::default(),
+            }),
+        ]
+    }
+
+    fn ink(strokes: usize) -> Vec<DrawingCommand> {
+        let mut commands = Vec::new();
+        for stroke in 0..strokes {
+            let mut ink = InkStroke::new(InkBrush::new([0.1, 0.2, 0.8, 1.0], 4.0)).unwrap();
+            ink.extend((0..200).map(|i| {
+                let t = i as f32 * 0.05 + stroke as f32;
+                let position = Vec2::new(40.0 * t.cos() + 3.0 * t, 40.0 * (1.7 * t).sin() + 7.0 * stroke as f32);
+                InkSample::new(position, 0.5 + 0.4 * (3.0 * t).sin())
+            }));
+            commands.extend(ink.to_commands());
+        }
+        commands
+    }
+
+    #[test]
+    fn every_command_round_trips() {
+        let commands = every_command();
+        let decoded = decode_commands(&encode_commands(&commands).unwrap()).unwrap();
+        assert_eq!(decoded.len(), commands.len());
+        for (decoded, original) in decoded.iter().zip(&commands) {
+            assert_eq!(decoded, original);
+        }
+        assert!(decode_commands(&encode_commands(&[]).unwrap()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn points_are_quantized_to_hundredths() {
+        let commands = [DrawingCommand::MoveTo(Vec2::new(1.004, -2.0061)), DrawingCommand::LineTo(Vec2::new(1e6, 0.006))];
+        let decoded = decode_commands(&encode_commands(&commands).unwrap()).unwrap();
+        assert_eq!(decoded[0], DrawingCommand::MoveTo(Vec2::new(1.0, -2.01)));
+        assert_eq!(decoded[1], DrawingCommand::LineTo(Vec2::new(1e6, 0.01)));
+
+        let error = encode_commands(&[DrawingCommand::LineTo(Vec2::new(f32::NAN, 0.0))]).unwrap_err();
+        assert_eq!(error.code(), "invalid_argument");
+        assert!(encode_commands(&[DrawingCommand::MoveTo(Vec2::new(1e20, 0.0))]).is_err());
+    }
+
+    #[test]
+    fn ink_is_exact_and_far_smaller_than_json() {
+        let commands = ink(20);
+        let encoded = encode_commands(&commands).unwrap();
+        assert_eq!(decode_commands(&encoded).unwrap(), commands);
+
+        let json = serde_json::to_vec(&commands).unwrap();
+        assert!(encoded.len() * 5 < json.len(), "{} bytes against {} of JSON", encoded.len(), json.len());
+    }
+
+    #[test]
+    fn corrupt_data_is_rejected() {
+        let encoded = encode_commands(&every_command()).unwrap();
+        for cut in [0, 3, 5, encoded.len() / 2, encoded.len() - 1] {
+            let error = decode_commands(&encoded[..cut]).unwrap_err();
+            assert_eq!(error.code(), "invalid_document", "cut at {}", cut);
+        }
+        let mut trailing = encoded.clone();
+        trailing.push(0);
+        assert!(decode_commands(&trailing).is_err());
+
+        let mut newer = encoded.clone();
+        newer[4] = ENCODING_VERSION + 1;
+        assert!(decode_commands(&newer).is_err());
+
+        // A huge count must not allocate before failing
+        let mut huge = MAGIC.to_vec();
+        huge.push(ENCODING_VERSION);
+        huge.extend([0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x7f]);
+        assert!(decode_commands(&huge).is_err());
+        assert!(decode_commands(b"DRWC\x01\x01\x7f").is_err());
+    }
+
+    #[test]
+    fn zigzag_keeps_small_magnitudes_small() {
+        for value in [0, 1, -1, 63, -64, 64, i64::MAX / 2, -(1 << 40)] {
+            assert_eq!(unzigzag(zigzag(value)), value);
+        }
+        assert_eq!([zigzag(0), zigzag(-1), zigzag(1), zigzag(-2)], [0, 1, 2, 3]);
+    }
+}
@@ -0,0 +1,169 @@
+use std::fmt::Display;
+use std::path::Path;
+
+use serde::ser::SerializeStruct;
+use serde::{Serialize, Serializer};
+use serde_json::json;
+use thiserror::Error;
+
+use crate::tiles::{ConstraintViolation, TileId};
+
+/// Error returned by every canvas command.
+///
+/// Serializes as `{ "code": ..., "message": ..., "context": {...} }`. The
+/// `code` strings are stable and meant to be matched on by the frontend; the
+/// `message` is for humans and may change.
+#[derive(Debug, Error)]
+pub enum CanvasError {
+    #[error("invalid tile id `{value}`")]
+    InvalidTileId { value: String },
+
+    #[error("tile {tile_id} not found")]
+    TileNotFound { tile_id: TileId },
+
+    #[error("tile {tile_id} already exists")]
+    TileAlreadyExists { tile_id: TileId },
+
+    #[error("tile {tile_id}: {violation}")]
+    ConstraintViolated {
+        tile_id: TileId,
+        violation: ConstraintViolation,
+    },
+
+    #[error("invalid {field}: {reason}")]
+    InvalidArgument { field: String, reason: String },
+
+    #[error("operation {index} failed: {source}")]
+    OperationFailed {
+        index: usize,
+        source: Box<CanvasError>,
+    },
+
+    #[error("invalid document: {message}")]
+    InvalidDocument { message: String },
+
+    #[error("unsupported document version {version}")]
+    UnsupportedDocumentVersion { version: u32 },
+
+    #[error("webview for tile {tile_id} failed: {message}")]
+    WebViewFailed { tile_id: TileId, message: String },
+
+    #[error("terminal for tile {tile_id} failed: {message}")]
+    TerminalFailed { tile_id: TileId, message: String },
+
+    #[error("canvas state unavailable: {message}")]
+    StateUnavailable { message: String },
+
+    #[error("image `{path}` is unavailable: {message}")]
+    ImageUnavailable { path: String, message: String },
+
+    #[error("collaboration with {relay} failed: {message}")]
+    SyncFailed { relay: String, message: String },
+
+    #[error("undo is unavailable: {message}")]
+    UndoUnavailable { message: String },
+
+    #[error("failed to emit `{event}`: {message}")]
+    EventFailed { event: String, message: String },
+
+    #[error("internal error: {message}")]
+    Internal { message: String },
+}
+
+impl CanvasError {
+    pub fn invalid_argument(field: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self::InvalidArgument {
+            field: field.into(),
+            reason: reason.into(),
+        }
+    }
+
+    pub fn constraint(tile_id: TileId, violation: ConstraintViolation) -> Self {
+        Self::ConstraintViolated { tile_id, violation }
+    }
+
+    pub fn webview(tile_id: TileId, error: impl Display) -> Self {
+        Self::WebViewFailed {
+            tile_id,
+            message: error.to_string(),
+        }
+    }
+
+    pub fn terminal(tile_id: TileId, error: impl Display) -> Self {
+        Self::TerminalFailed {
+            tile_id,
+            message: error.to_string(),
+        }
+    }
+
+    pub fn image(path: &Path, error: impl Display) -> Self {
+        Self::ImageUnavailable {
+            path: path.display().to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    pub fn event(event: &str, error: impl Display) -> Self {
+        Self::EventFailed {
+            event: event.to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    /// Stable, machine readable identifier of the error kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidTileId { .. } => "invalid_tile_id",
+            Self::TileNotFound { .. } => "tile_not_found",
+            Self::TileAlreadyExists { .. } => "tile_already_exists",
+            Self::ConstraintViolated { .. } => "constraint_violated",
+            Self::InvalidArgument { .. } => "invalid_argument",
+            Self::OperationFailed { .. } => "operation_failed",
+            Self::InvalidDocument { .. } => "invalid_document",
+            Self::UnsupportedDocumentVersion { .. } => "unsupported_document_version",
+            Self::WebViewFailed { .. } => "webview_failed",
+            Self::TerminalFailed { .. } => "terminal_failed",
+            Self::StateUnavailable { .. } => "state_unavailable",
+            Self::ImageUnavailable { .. } => "image_unavailable",
+            Self::SyncFailed { .. } => "sync_failed",
+            Self::UndoUnavailable { .. } => "undo_unavailable",
+            Self::EventFailed { .. } => "event_failed",
+            Self::Internal { .. } => "internal",
+        }
+    }
+
+    fn context(&self) -> serde_json::Value {
+        match self {
+            Self::InvalidTileId { value } => json!({ "value": value }),
+            Self::TileNotFound { tile_id } => json!({ "tile_id": tile_id }),
+            Self::TileAlreadyExists { tile_id } => json!({ "tile_id": tile_id }),
+            Self::ConstraintViolated { tile_id, violation } => {
+                json!({ "tile_id": tile_id, "violation": violation })
+            }
+            Self::InvalidArgument { field, reason } => json!({ "field": field, "reason": reason }),
+            Self::OperationFailed { index, source } => json!({ "index": index, "error": source }),
+            Self::InvalidDocument { .. } => json!({}),
+            Self::UnsupportedDocumentVersion { version } => json!({ "version": version }),
+            Self::WebViewFailed { tile_id, .. } | Self::TerminalFailed { tile_id, .. } => json!({ "tile_id": tile_id }),
+            Self::StateUnavailable { .. } => json!({}),
+            Self::ImageUnavailable { path, .. } => json!({ "path": path }),
+            Self::SyncFailed { relay, .. } => json!({ "relay": relay }),
+            Self::UndoUnavailable { .. } => json!({}),
+            Self::EventFailed { event, .. } => json!({ "event": event }),
+            Self::Internal { .. } => json!({}),
+        }
+    }
+}
+
+impl Serialize for CanvasError {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut state = serializer.serialize_struct("CanvasError", 3)?;
+        state.serialize_field("code", self.code())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.serialize_field("context", &self.context())?;
+        state.end()
+    }
+}
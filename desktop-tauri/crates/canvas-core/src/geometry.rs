@@ -0,0 +1,70 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+/// Axis aligned rectangle in world coordinates.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Rect {
+    pub min: Vec2,
+    pub max: Vec2,
+}
+
+impl Rect {
+    pub fn new(min: Vec2, max: Vec2) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    pub fn from_center_size(center: Vec2, size: Vec2) -> Self {
+        let half_size = size * 0.5;
+        Self::new(center - half_size, center + half_size)
+    }
+
+    pub fn center(&self) -> Vec2 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec2 {
+        self.max - self.min
+    }
+
+    pub fn contains(&self, point: Vec2) -> bool {
+        point.x >= self.min.x && point.x <= self.max.x && point.y >= self.min.y && point.y <= self.max.y
+    }
+
+    pub fn intersects(&self, other: &Rect) -> bool {
+        self.min.x <= other.max.x
+            && self.max.x >= other.min.x
+            && self.min.y <= other.max.y
+            && self.max.y >= other.min.y
+    }
+
+    pub fn union(&self, other: &Rect) -> Rect {
+        Rect {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn new_normalizes_corners() {
+        let rect = Rect::new(Vec2::new(10.0, -5.0), Vec2::new(-10.0, 5.0));
+        assert_eq!(rect.min, Vec2::new(-10.0, -5.0));
+        assert_eq!(rect.max, Vec2::new(10.0, 5.0));
+    }
+
+    #[test]
+    fn intersects_touching_edges() {
+        let a = Rect::new(Vec2::ZERO, Vec2::splat(10.0));
+        let b = Rect::new(Vec2::new(10.0, 0.0), Vec2::new(20.0, 10.0));
+        let c = Rect::new(Vec2::new(11.0, 0.0), Vec2::new(20.0, 10.0));
+        assert!(a.intersects(&b));
+        assert!(!a.intersects(&c));
+    }
+}
@@ -0,0 +1,226 @@
+//! Decoded image files for image tiles.
+//!
+//! Files are decoded once into a [`MipChain`]: the full resolution image
+//! plus successively halved copies, so drawing a tile at low zoom never
+//! has to scale down a huge bitmap. [`ImageCache`] keeps the chains of
+//! recently drawn files and the PNG encoded thumbnails handed to the
+//! frontend, and reloads a file when its modification time changes.
+
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use image::imageops::{self, FilterType};
+use image::{ImageFormat, ImageReader, RgbaImage};
+
+use crate::error::CanvasError;
+
+/// Mip levels stop once the longer side is at most this many pixels.
+pub const MIN_MIP_DIMENSION: u32 = 32;
+
+/// Formats image tiles can show.
+const SUPPORTED_FORMATS: [ImageFormat; 3] = [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP];
+
+/// An image at full resolution and at every halving down to
+/// [`MIN_MIP_DIMENSION`].
+#[derive(Debug, Clone)]
+pub struct MipChain {
+    levels: Vec<RgbaImage>,
+}
+
+impl MipChain {
+    pub fn decode(path: &Path) -> Result<Self, CanvasError> {
+        let reader = open(path)?;
+        let image = reader.decode().map_err(|e| CanvasError::image(path, e))?;
+        Ok(Self::new(image.to_rgba8()))
+    }
+
+    pub fn new(image: RgbaImage) -> Self {
+        let mut levels = vec![image];
+        loop {
+            let last = &levels[levels.len() - 1];
+            let (width, height) = last.dimensions();
+            if width.max(height) <= MIN_MIP_DIMENSION {
+                break;
+            }
+            let next = imageops::resize(last, (width / 2).max(1), (height / 2).max(1), FilterType::Triangle);
+            levels.push(next);
+        }
+        Self { levels }
+    }
+
+    /// Width and height of the full resolution image in pixels.
+    pub fn natural_size(&self) -> [u32; 2] {
+        let (width, height) = self.levels[0].dimensions();
+        [width, height]
+    }
+
+    /// Level 0 is the full resolution image, every further level is half
+    /// the size of the one before.
+    pub fn levels(&self) -> &[RgbaImage] {
+        &self.levels
+    }
+
+    /// Index of the smallest level that still has at least `pixels` along
+    /// its longer side, or the full resolution one.
+    pub fn level_for(&self, pixels: f32) -> usize {
+        self.levels
+            .iter()
+            .rposition(|level| level.width().max(level.height()) as f32 >= pixels)
+            .unwrap_or(0)
+    }
+}
+
+/// Reads the natural size of an image from its header without decoding it.
+pub fn probe(path: &Path) -> Result<[u32; 2], CanvasError> {
+    let (width, height) = open(path)?.into_dimensions().map_err(|e| CanvasError::image(path, e))?;
+    Ok([width, height])
+}
+
+pub(crate) fn encode_png(image: &RgbaImage) -> Result<Vec<u8>, CanvasError> {
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, ImageFormat::Png)
+        .map_err(|e| CanvasError::Internal { message: format!("failed to encode PNG: {}", e) })?;
+    Ok(bytes.into_inner())
+}
+
+fn open(path: &Path) -> Result<ImageReader<std::io::BufReader<std::fs::File>>, CanvasError> {
+    let reader = ImageReader::open(path)
+        .and_then(|reader| reader.with_guessed_format())
+        .map_err(|e| CanvasError::image(path, e))?;
+    match reader.format() {
+        Some(format) if SUPPORTED_FORMATS.contains(&format) => Ok(reader),
+        Some(format) => Err(CanvasError::image(path, format!("{:?} images are not supported", format))),
+        None => Err(CanvasError::image(path, "not a PNG, JPEG or WebP image")),
+    }
+}
+
+#[derive(Debug)]
+struct CacheEntry {
+    modified: Option<SystemTime>,
+    chain: Arc<MipChain>,
+    /// PNG encoded levels, by level index.
+    thumbnails: HashMap<usize, Arc<Vec<u8>>>,
+}
+
+/// Decoded images by path.
+#[derive(Debug, Default)]
+pub struct ImageCache {
+    entries: HashMap<PathBuf, CacheEntry>,
+}
+
+impl ImageCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The mip chain of the image at `path`, decoding it on first use or
+    /// when the file changed since.
+    pub fn get(&mut self, path: &Path) -> Result<Arc<MipChain>, CanvasError> {
+        Ok(self.entry(path)?.chain.clone())
+    }
+
+    /// PNG encoded copy of the image at `path` that is at least
+    /// `max_dimension` pixels along its longer side, or as large as the
+    /// image is.
+    pub fn thumbnail(&mut self, path: &Path, max_dimension: u32) -> Result<Arc<Vec<u8>>, CanvasError> {
+        let entry = self.entry(path)?;
+        let level = entry.chain.level_for(max_dimension as f32);
+        if let Some(thumbnail) = entry.thumbnails.get(&level) {
+            return Ok(thumbnail.clone());
+        }
+        let thumbnail = Arc::new(encode_png(&entry.chain.levels()[level])?);
+        entry.thumbnails.insert(level, thumbnail.clone());
+        Ok(thumbnail)
+    }
+
+    /// Drops every image whose path `keep` rejects, e.g. after the last tile
+    /// showing it was removed.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Path) -> bool) {
+        self.entries.retain(|path, _| keep(path));
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    fn entry(&mut self, path: &Path) -> Result<&mut CacheEntry, CanvasError> {
+        let modified = std::fs::metadata(path)
+            .map_err(|e| {
+                // The file is gone; so is anything cached for it
+                self.entries.remove(path);
+                CanvasError::image(path, e)
+            })?
+            .modified()
+            .ok();
+
+        let stale = !matches!(self.entries.get(path), Some(entry) if entry.modified == modified);
+        if stale {
+            let chain = Arc::new(MipChain::decode(path)?);
+            self.entries.insert(path.to_path_buf(), CacheEntry { modified, chain, thumbnails: HashMap::new() });
+        }
+        Ok(self.entries.get_mut(path).expect("entry was just checked or inserted"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    /// Writes a `width` x `height` PNG into the temp directory.
+    fn write_png(name: &str, width: u32, height: u32) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("canvas-core-{}-{}.png", std::process::id(), name));
+        RgbaImage::from_pixel(width, height, Rgba([200, 40, 40, 255])).save(&path).unwrap();
+        path
+    }
+
+    #[test]
+    fn mip_levels_halve_down_to_the_minimum() {
+        let chain = MipChain::new(RgbaImage::new(300, 100));
+        let sizes: Vec<_> = chain.levels().iter().map(|level| level.dimensions()).collect();
+        assert_eq!(sizes, [(300, 100), (150, 50), (75, 25), (37, 12), (18, 6)]);
+        assert_eq!(chain.natural_size(), [300, 100]);
+
+        assert_eq!(chain.level_for(1000.0), 0);
+        assert_eq!(chain.level_for(150.0), 1);
+        assert_eq!(chain.level_for(100.0), 1);
+        assert_eq!(chain.level_for(1.0), 4);
+    }
+
+    #[test]
+    fn files_are_probed_decoded_and_cached() {
+        let path = write_png("cached", 64, 48);
+        assert_eq!(probe(&path).unwrap(), [64, 48]);
+
+        let mut cache = ImageCache::new();
+        let chain = cache.get(&path).unwrap();
+        assert!(Arc::ptr_eq(&chain, &cache.get(&path).unwrap()));
+
+        let small = cache.thumbnail(&path, 20).unwrap();
+        assert_eq!(&small[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(image::load_from_memory(&small).unwrap().width(), 32);
+        assert!(Arc::ptr_eq(&small, &cache.thumbnail(&path, 30).unwrap()));
+
+        std::fs::remove_file(&path).unwrap();
+        let error = cache.get(&path).unwrap_err();
+        assert_eq!(error.code(), "image_unavailable");
+        assert!(cache.is_empty());
+    }
+
+    #[test]
+    fn unsupported_files_report_why() {
+        let path = std::env::temp_dir().join(format!("canvas-core-{}-notes.txt", std::process::id()));
+        std::fs::write(&path, "not an image").unwrap();
+        let error = probe(&path).unwrap_err();
+        std::fs::remove_file(&path).unwrap();
+        assert!(error.to_string().contains("not a PNG, JPEG or WebP image"), "{}", error);
+    }
+}
@@ -0,0 +1,357 @@
+//! Freehand ink.
+//!
+//! An [`InkStroke`] collects the raw pointer samples of one pen or finger
+//! stroke. [`InkStroke::to_commands`] smooths them, drops the points that
+//! don't change the shape (Ramer–Douglas–Peucker, with the stroke width as
+//! a third dimension so pressure changes survive), and turns what is left
+//! into one filled outline of `CubicTo` curves whose width follows the
+//! pressure. A stroke is stored as that outline, wrapped in `Save` and
+//! `Restore` so its color doesn't leak into later commands.
+
+use glam::{Vec2, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::error::CanvasError;
+use crate::tiles::DrawingCommand;
+
+/// Pressure of samples from devices that don't report any, the value
+/// browsers use for a pressed mouse button.
+pub const DEFAULT_PRESSURE: f32 = 0.5;
+/// Width of a stroke at zero pressure, as a fraction of the brush width.
+pub const MIN_WIDTH_RATIO: f32 = 0.3;
+/// How far the simplified outline may stray from the smoothed samples, in
+/// world units.
+pub const SIMPLIFY_TOLERANCE: f32 = 0.4;
+/// Samples a stroke keeps at most; later ones are dropped, which bounds
+/// the memory a stroke nobody ends can take.
+pub const MAX_STROKE_SAMPLES: usize = 20_000;
+/// Samples closer than this to the previous one are dropped.
+const MIN_SAMPLE_DISTANCE: f32 = 0.2;
+/// Passes of neighbour averaging at full smoothing.
+const SMOOTHING_PASSES: usize = 3;
+/// Stored coordinates are rounded to this many steps per world unit.
+const PRECISION: f32 = 100.0;
+
+fn default_pressure() -> f32 {
+    DEFAULT_PRESSURE
+}
+
+fn default_smoothing() -> f32 {
+    0.5
+}
+
+/// One pointer position, in the coordinates of the tile's drawing.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct InkSample {
+    pub position: Vec2,
+    /// 0 to 1.
+    #[serde(default = "default_pressure")]
+    pub pressure: f32,
+}
+
+impl InkSample {
+    pub fn new(position: Vec2, pressure: f32) -> Self {
+        Self { position, pressure }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InkBrush {
+    pub color: [f32; 4],
+    /// Width at full pressure.
+    pub width: f32,
+    /// 0 keeps the samples as they are, 1 smooths the most.
+    #[serde(default = "default_smoothing")]
+    pub smoothing: f32,
+}
+
+impl InkBrush {
+    pub fn new(color: [f32; 4], width: f32) -> Self {
+        Self { color, width, smoothing: default_smoothing() }
+    }
+
+    pub fn validate(&self) -> Result<(), CanvasError> {
+        if !self.color.iter().all(|c| (0.0..=1.0).contains(c)) {
+            return Err(CanvasError::invalid_argument("color", "color components must be between 0 and 1"));
+        }
+        if !self.width.is_finite() || self.width <= 0.0 {
+            return Err(CanvasError::invalid_argument("width", "must be positive"));
+        }
+        if !(0.0..=1.0).contains(&self.smoothing) {
+            return Err(CanvasError::invalid_argument("smoothing", "must be between 0 and 1"));
+        }
+        Ok(())
+    }
+
+    /// Half the stroke width at `pressure`.
+    fn radius(&self, pressure: f32) -> f32 {
+        self.width / 2.0 * (MIN_WIDTH_RATIO + (1.0 - MIN_WIDTH_RATIO) * pressure)
+    }
+}
+
+/// A stroke being drawn.
+#[derive(Debug, Clone)]
+pub struct InkStroke {
+    brush: InkBrush,
+    samples: Vec<InkSample>,
+}
+
+impl InkStroke {
+    pub fn new(brush: InkBrush) -> Result<Self, CanvasError> {
+        brush.validate()?;
+        Ok(Self { brush, samples: Vec::new() })
+    }
+
+    pub fn brush(&self) -> &InkBrush {
+        &self.brush
+    }
+
+    /// The samples kept so far.
+    pub fn samples(&self) -> &[InkSample] {
+        &self.samples
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    /// Adds a sample. Samples at non-finite positions or beyond
+    /// [`MAX_STROKE_SAMPLES`] are ignored and the pressure is clamped to
+    /// 0..=1. A sample right on top of the previous one only raises its
+    /// pressure.
+    pub fn push(&mut self, sample: InkSample) {
+        if !sample.position.is_finite() {
+            return;
+        }
+        let pressure = if sample.pressure.is_nan() { DEFAULT_PRESSURE } else { sample.pressure.clamp(0.0, 1.0) };
+        if let Some(last) = self.samples.last_mut() {
+            if last.position.distance(sample.position) < MIN_SAMPLE_DISTANCE {
+                last.pressure = last.pressure.max(pressure);
+                return;
+            }
+        }
+        if self.samples.len() >= MAX_STROKE_SAMPLES {
+            return;
+        }
+        self.samples.push(InkSample { position: sample.position, pressure });
+    }
+
+    pub fn extend(&mut self, samples: impl IntoIterator<Item = InkSample>) {
+        for sample in samples {
+            self.push(sample);
+        }
+    }
+
+    /// The finished stroke as drawing commands, empty if there are no
+    /// samples. A stroke that never moved becomes a dot.
+    pub fn to_commands(&self) -> Vec<DrawingCommand> {
+        let Some(first) = self.samples.first() else {
+            return Vec::new();
+        };
+        let mut commands = vec![DrawingCommand::Save, DrawingCommand::SetFillColor(self.brush.color)];
+        if self.samples.len() == 1 {
+            commands.push(DrawingCommand::DrawCircle(round(first.position), round_length(self.brush.radius(first.pressure))));
+        } else {
+            let points = simplify(&smooth(&self.samples, self.brush.smoothing), &self.brush);
+            outline(&points, &self.brush, &mut commands);
+            commands.push(DrawingCommand::Fill);
+        }
+        commands.push(DrawingCommand::Restore);
+        commands
+    }
+}
+
+/// Pulls every sample but the ends towards the middle of its neighbours.
+fn smooth(samples: &[InkSample], smoothing: f32) -> Vec<InkSample> {
+    let mut points = samples.to_vec();
+    if smoothing <= 0.0 || points.len() < 3 {
+        return points;
+    }
+    for _ in 0..SMOOTHING_PASSES {
+        let previous = points.clone();
+        for i in 1..points.len() - 1 {
+            let position = (previous[i - 1].position + previous[i + 1].position) / 2.0;
+            let pressure = (previous[i - 1].pressure + previous[i + 1].pressure) / 2.0;
+            points[i].position = previous[i].position.lerp(position, smoothing);
+            points[i].pressure += (pressure - previous[i].pressure) * smoothing;
+        }
+    }
+    points
+}
+
+/// The samples Ramer–Douglas–Peucker keeps, treating the outline radius as
+/// a third coordinate.
+fn simplify(samples: &[InkSample], brush: &InkBrush) -> Vec<InkSample> {
+    let points: Vec<Vec3> = samples.iter()
+        .map(|sample| sample.position.extend(brush.radius(sample.pressure)))
+        .collect();
+    let mut keep = vec![false; points.len()];
+    keep[0] = true;
+    keep[points.len() - 1] = true;
+
+    // Ranges still to split, as a stack instead of recursion so long
+    // strokes can't overflow it
+    let mut ranges = vec![(0, points.len() - 1)];
+    while let Some((start, end)) = ranges.pop() {
+        let farthest = (start + 1..end)
+            .map(|i| (i, distance_to_segment(points[i], points[start], points[end])))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        if let Some((index, distance)) = farthest {
+            if distance > SIMPLIFY_TOLERANCE {
+                keep[index] = true;
+                ranges.push((start, index));
+                ranges.push((index, end));
+            }
+        }
+    }
+
+    samples.iter().zip(keep).filter(|(_, keep)| *keep).map(|(sample, _)| *sample).collect()
+}
+
+fn distance_to_segment(point: Vec3, start: Vec3, end: Vec3) -> f32 {
+    let segment = end - start;
+    let length = segment.length_squared();
+    if length == 0.0 {
+        return point.distance(start);
+    }
+    let t = ((point - start).dot(segment) / length).clamp(0.0, 1.0);
+    point.distance(start + segment * t)
+}
+
+/// Appends the closed outline of the stroke through `points`: along its
+/// left side, around a round end cap, back along the right side and around
+/// a round start cap.
+fn outline(points: &[InkSample], brush: &InkBrush, commands: &mut Vec<DrawingCommand>) {
+    let last = points.len() - 1;
+    let direction = |i: usize| {
+        let (before, after) = (points[i.saturating_sub(1)].position, points[(i + 1).min(last)].position);
+        (after - before).try_normalize().unwrap_or(Vec2::X)
+    };
+    let radii: Vec<f32> = points.iter().map(|point| brush.radius(point.pressure)).collect();
+    let left: Vec<Vec2> = (0..=last).map(|i| points[i].position + direction(i).perp() * radii[i]).collect();
+    let right: Vec<Vec2> = (0..=last).rev().map(|i| points[i].position - direction(i).perp() * radii[i]).collect();
+
+    commands.push(DrawingCommand::MoveTo(round(left[0])));
+    curve_through(&left, commands);
+    let end = direction(last).to_angle();
+    commands.push(DrawingCommand::Arc(
+        round(points[last].position),
+        round_length(radii[last]),
+        end + std::f32::consts::FRAC_PI_2,
+        end - std::f32::consts::FRAC_PI_2,
+    ));
+    curve_through(&right, commands);
+    let start = direction(0).to_angle();
+    commands.push(DrawingCommand::Arc(
+        round(points[0].position),
+        round_length(radii[0]),
+        start - std::f32::consts::FRAC_PI_2,
+        start - 3.0 * std::f32::consts::FRAC_PI_2,
+    ));
+    commands.push(DrawingCommand::ClosePath);
+}
+
+/// Appends a Catmull–Rom spline through `points` as `CubicTo` curves,
+/// starting at the first point.
+fn curve_through(points: &[Vec2], commands: &mut Vec<DrawingCommand>) {
+    let last = points.len() - 1;
+    for i in 0..last {
+        let before = points[i.saturating_sub(1)];
+        let after = points[(i + 2).min(last)];
+        let (from, to) = (points[i], points[i + 1]);
+        commands.push(DrawingCommand::CubicTo(
+            round(from + (to - before) / 6.0),
+            round(to - (after - from) / 6.0),
+            round(to),
+        ));
+    }
+}
+
+fn round(point: Vec2) -> Vec2 {
+    (point * PRECISION).round() / PRECISION
+}
+
+fn round_length(length: f32) -> f32 {
+    (length * PRECISION).round() / PRECISION
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::skia::render_commands;
+    use crate::tiles::SkiaTile;
+
+    fn stroke(samples: impl IntoIterator<Item = (f32, f32, f32)>) -> InkStroke {
+        let mut stroke = InkStroke::new(InkBrush::new([0.0, 0.0, 0.0, 1.0], 6.0)).unwrap();
+        stroke.extend(samples.into_iter().map(|(x, y, pressure)| InkSample::new(Vec2::new(x, y), pressure)));
+        stroke
+    }
+
+    fn curves(commands: &[DrawingCommand]) -> usize {
+        commands.iter().filter(|command| matches!(command, DrawingCommand::CubicTo(..))).count()
+    }
+
+    #[test]
+    fn straight_strokes_simplify_to_their_ends() {
+        // A jittery straight line at constant pressure
+        let line = stroke((0..200).map(|i| (i as f32 * 0.5, if i % 2 == 0 { 0.05 } else { -0.05 }, 0.5)));
+        assert_eq!(line.samples().len(), 200);
+        let commands = line.to_commands();
+        // One curve along each side
+        assert_eq!(curves(&commands), 2);
+        assert!(SkiaTile::validate_commands(&commands).is_ok());
+        assert!(matches!(commands[..2], [DrawingCommand::Save, DrawingCommand::SetFillColor(_)]));
+        assert!(matches!(commands[commands.len() - 2..], [DrawingCommand::Fill, DrawingCommand::Restore]));
+
+        // A pressure change is kept even though the path is straight
+        let swelling = stroke((0..200).map(|i| (i as f32 * 0.5, 0.0, if i < 100 { 0.1 } else { 1.0 })));
+        assert!(curves(&swelling.to_commands()) > 2);
+    }
+
+    #[test]
+    fn curves_keep_enough_points_to_stay_close() {
+        let circle = stroke((0..=360).map(|degrees| {
+            let (sin, cos) = (degrees as f32).to_radians().sin_cos();
+            (50.0 + 40.0 * cos, 50.0 + 40.0 * sin, 0.5)
+        }));
+        let commands = circle.to_commands();
+        let count = curves(&commands);
+        assert!(count > 8 && count < 120, "{}", count);
+
+        let pixmap = render_commands(&commands, Vec2::splat(100.0), 1.0).unwrap();
+        let alpha = |x: u32, y: u32| pixmap.pixel(x, y).unwrap().alpha();
+        // On the ring, not in its middle or outside
+        assert_eq!(alpha(90, 50), 255);
+        assert_eq!(alpha(50, 10), 255);
+        assert_eq!(alpha(50, 50), 0);
+        assert_eq!(alpha(2, 2), 0);
+    }
+
+    #[test]
+    fn pressure_sets_the_width() {
+        let light = stroke([(10.0, 20.0, 0.0), (90.0, 20.0, 0.0)]).to_commands();
+        let heavy = stroke([(10.0, 20.0, 1.0), (90.0, 20.0, 1.0)]).to_commands();
+        let height = |commands: &[DrawingCommand]| {
+            let pixmap = render_commands(commands, Vec2::splat(100.0), 1.0).unwrap();
+            (0..100).filter(|y| pixmap.pixel(50, *y).unwrap().alpha() > 128).count()
+        };
+        assert_eq!(height(&light), 2);
+        assert_eq!(height(&heavy), 6);
+    }
+
+    #[test]
+    fn taps_become_dots_and_bad_samples_are_ignored() {
+        let tap = stroke([(5.0, 5.0, 0.2), (5.05, 5.0, 0.9), (f32::NAN, 1.0, 0.5)]);
+        assert_eq!(tap.samples(), [InkSample::new(Vec2::new(5.0, 5.0), 0.9)]);
+        assert!(matches!(tap.to_commands()[2], DrawingCommand::DrawCircle(_, _)));
+        assert!(stroke([]).to_commands().is_empty());
+        let endless = stroke((0..MAX_STROKE_SAMPLES + 10).map(|i| (i as f32, 0.0, 0.5)));
+        assert_eq!(endless.samples().len(), MAX_STROKE_SAMPLES);
+
+        let mut brush = InkBrush::new([0.0; 4], 0.0);
+        assert!(InkStroke::new(brush.clone()).is_err());
+        brush.width = 2.0;
+        brush.smoothing = 2.0;
+        assert!(InkStroke::new(brush).is_err());
+    }
+}
@@ -0,0 +1,50 @@
+//! Document model of the infinite canvas, independent of Tauri.
+//!
+//! The crate covers the three things a frontend or a headless tool needs:
+//! the document itself ([`InfiniteCanvas`] and its [`tiles`]), batched
+//! [`CanvasOperation`]s with undo history, and read-only [`query`]s. The
+//! desktop app is a thin adapter that forwards its commands to this API.
+
+pub mod canvas;
+pub mod document;
+pub mod drawing_codec;
+pub mod error;
+pub mod geometry;
+pub mod image_cache;
+pub mod ink;
+pub mod markdown;
+pub mod path_ops;
+pub mod query;
+pub mod render;
+pub mod render_cache;
+pub mod search;
+pub mod shapes;
+pub mod svg_import;
+pub mod sync;
+pub mod text;
+pub mod tiles;
+pub mod widgets;
+
+pub use glam;
+
+pub use canvas::{AppliedBatch, Camera, CanvasOperation, History, InfiniteCanvas, NewTile};
+pub use drawing_codec::{decode_commands, encode_commands};
+pub use error::CanvasError;
+pub use geometry::Rect;
+pub use image_cache::{ImageCache, MipChain};
+pub use ink::{InkBrush, InkSample, InkStroke};
+pub use markdown::{NoteDocument, NoteLine};
+pub use path_ops::{PathOp, Region};
+pub use query::TileQuery;
+pub use render_cache::{RenderCache, RenderCacheStats};
+pub use search::{SearchField, SearchHit, SearchIndex, SearchMatch};
+pub use shapes::DrawingShape;
+pub use svg_import::{import_svg, SvgImport};
+pub use sync::{OpId, ReplicaId, SyncDocument, SyncOp};
+pub use text::{TextAlign, TextLayout, TextLine, TextStyle};
+pub use tiles::{
+    ConstraintViolation, DrawingCommand, DropShadow, EguiTile, Gradient, GradientStop, ImageTile, LineCap, LineJoin,
+    NoteTile, PropertyValue, SizeConstraints, SkiaTile, TerminalTile, TextBox, Tile, TileData, TileId, TileStyle, TileType,
+    WebViewTile,
+};
+pub use widgets::{WidgetConfig, WidgetKind, WidgetRegistry, WidgetType};
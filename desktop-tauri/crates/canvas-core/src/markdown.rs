@@ -0,0 +1,626 @@
+//! Markdown notes.
+//!
+//! [`NoteDocument::parse`] turns the source of a note tile into a small
+//! block model, and [`NoteDocument::lines`] lays that out as a column of
+//! lines with fixed metrics. The frontend and the exporters both draw the
+//! lines, so text, list markers and checkboxes end up in the same place
+//! everywhere.
+
+use std::ops::Range;
+
+use pulldown_cmark::{CodeBlockKind, Event, HeadingLevel, Options, Parser, Tag};
+use serde::{Deserialize, Serialize};
+
+use crate::error::CanvasError;
+
+/// Font size of body text in world units.
+pub const FONT_SIZE: f32 = 14.0;
+/// Font size of code blocks and inline code.
+pub const CODE_FONT_SIZE: f32 = 13.0;
+/// Line height as a multiple of the font size.
+pub const LINE_HEIGHT: f32 = 1.4;
+/// Horizontal space taken by one level of list nesting. The marker of an
+/// item is drawn in the last such step before the item's text.
+pub const LIST_INDENT: f32 = 22.0;
+/// Horizontal space taken by one level of block quotes.
+pub const QUOTE_INDENT: f32 = 14.0;
+/// Width of the bar drawn left of quoted lines.
+pub const QUOTE_BAR_WIDTH: f32 = 3.0;
+/// Side length of a checkbox marker.
+pub const CHECKBOX_SIZE: f32 = 11.0;
+
+/// Vertical space between blocks.
+const BLOCK_SPACING: f32 = 8.0;
+/// Height of a horizontal rule line.
+const RULE_HEIGHT: f32 = 16.0;
+const HEADING_SIZES: [f32; 6] = [26.0, 22.0, 18.0, 16.0, 14.0, 14.0];
+
+/// Parsed Markdown source of a note.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct NoteDocument {
+    pub blocks: Vec<Block>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Block {
+    Heading { level: u8, spans: Vec<Span> },
+    Paragraph { spans: Vec<Span> },
+    List {
+        ordered: bool,
+        /// Number of the first item of an ordered list.
+        start: u64,
+        items: Vec<ListItem>,
+    },
+    CodeBlock { language: Option<String>, code: String },
+    Quote { blocks: Vec<Block> },
+    Rule,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ListItem {
+    pub checkbox: Option<Checkbox>,
+    pub blocks: Vec<Block>,
+}
+
+/// A task list marker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Checkbox {
+    /// Position among all checkboxes of the note, in document order. This
+    /// is what toggle commands address.
+    pub index: usize,
+    pub checked: bool,
+}
+
+/// A run of inline text with one style. A hard line break is a span whose
+/// text is `"\n"`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Span {
+    pub text: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub strikethrough: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub code: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link: Option<String>,
+}
+
+/// One laid out line of a note, relative to the top left of the note's
+/// content area. Lines are not wrapped.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NoteLine {
+    pub kind: LineKind,
+    pub spans: Vec<Span>,
+    /// Marker of the list item this line starts.
+    pub marker: Option<Marker>,
+    /// Left edge of the text.
+    pub indent: f32,
+    /// Left edges of the bars of the block quotes around the line.
+    pub quote_bars: Vec<f32>,
+    pub top: f32,
+    pub height: f32,
+    pub font_size: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LineKind {
+    Text,
+    Heading { level: u8 },
+    /// A line of a code block, drawn in a monospace font on a shaded
+    /// background.
+    Code,
+    Rule,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Marker {
+    Bullet,
+    Number { value: u64 },
+    Checkbox { index: usize, checked: bool },
+}
+
+impl NoteLine {
+    /// Text of the line without styling.
+    pub fn text(&self) -> String {
+        self.spans.iter().map(|span| span.text.as_str()).collect()
+    }
+
+    /// Where the marker is drawn: the `[x, y]` top left and side length of
+    /// a square left of the text.
+    pub fn marker_box(&self) -> Option<([f32; 2], f32)> {
+        self.marker?;
+        let x = self.indent - LIST_INDENT + (LIST_INDENT - CHECKBOX_SIZE) * 0.5;
+        let y = self.top + (self.height - CHECKBOX_SIZE) * 0.5;
+        Some(([x, y], CHECKBOX_SIZE))
+    }
+}
+
+impl NoteDocument {
+    pub fn parse(markdown: &str) -> Self {
+        let mut builder = Builder::default();
+        for event in Parser::new_ext(markdown, options()) {
+            builder.event(event);
+        }
+        builder.finish()
+    }
+
+    /// Lays the blocks out top to bottom.
+    pub fn lines(&self) -> Vec<NoteLine> {
+        let mut layout = Layout::default();
+        layout.blocks(&self.blocks, 0.0, true);
+        layout.lines
+    }
+}
+
+/// Sets checkbox `index` of `markdown` to `checked`, or flips it when
+/// `checked` is `None`. Returns the new state.
+pub fn set_checkbox(markdown: &mut String, index: usize, checked: Option<bool>) -> Result<bool, CanvasError> {
+    let markers = checkbox_markers(markdown);
+    let Some((range, current)) = markers.get(index).cloned() else {
+        return Err(CanvasError::invalid_argument(
+            "index",
+            format!("note has {} checkboxes", markers.len()),
+        ));
+    };
+    let checked = checked.unwrap_or(!current);
+    markdown.replace_range(range, if checked { "[x]" } else { "[ ]" });
+    Ok(checked)
+}
+
+/// Byte ranges of the `[ ]`/`[x]` task markers with their state.
+fn checkbox_markers(markdown: &str) -> Vec<(Range<usize>, bool)> {
+    Parser::new_ext(markdown, options())
+        .into_offset_iter()
+        .filter_map(|(event, range)| match event {
+            Event::TaskListMarker(checked) => Some((range, checked)),
+            _ => None,
+        })
+        .collect()
+}
+
+fn options() -> Options {
+    Options::ENABLE_TASKLISTS | Options::ENABLE_STRIKETHROUGH
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+enum Container {
+    Root(Vec<Block>),
+    Quote(Vec<Block>),
+    List { ordered: bool, start: u64, items: Vec<ListItem> },
+    Item(ListItem),
+}
+
+/// Inline content being collected for the innermost block.
+struct Inline {
+    heading: Option<u8>,
+    spans: Vec<Span>,
+}
+
+#[derive(Default)]
+struct Builder {
+    containers: Vec<Container>,
+    inline: Option<Inline>,
+    code: Option<(Option<String>, String)>,
+    bold: usize,
+    italic: usize,
+    strikethrough: usize,
+    links: Vec<String>,
+    checkboxes: usize,
+}
+
+impl Builder {
+    fn event(&mut self, event: Event) {
+        if self.containers.is_empty() {
+            self.containers.push(Container::Root(Vec::new()));
+        }
+        match event {
+            Event::Start(tag) => self.start(tag),
+            Event::End(tag) => self.end(tag),
+            Event::Text(text) => {
+                if let Some((_, code)) = &mut self.code {
+                    code.push_str(&text);
+                } else {
+                    self.push_span(&text, false);
+                }
+            }
+            Event::Code(text) => self.push_span(&text, true),
+            Event::SoftBreak => self.push_span(" ", false),
+            Event::HardBreak => self.push_span("\n", false),
+            Event::Rule => {
+                self.flush_inline();
+                self.push_block(Block::Rule);
+            }
+            Event::TaskListMarker(checked) => {
+                if let Some(Container::Item(item)) = self.containers.last_mut() {
+                    item.checkbox = Some(Checkbox { index: self.checkboxes, checked });
+                }
+                self.checkboxes += 1;
+            }
+            // Raw HTML and footnotes are not rendered
+            Event::Html(_) | Event::FootnoteReference(_) => {}
+        }
+    }
+
+    fn start(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph => {
+                self.flush_inline();
+                self.inline = Some(Inline { heading: None, spans: Vec::new() });
+            }
+            Tag::Heading(level, ..) => {
+                self.flush_inline();
+                self.inline = Some(Inline { heading: Some(heading_level(level)), spans: Vec::new() });
+            }
+            Tag::BlockQuote => {
+                self.flush_inline();
+                self.containers.push(Container::Quote(Vec::new()));
+            }
+            Tag::CodeBlock(kind) => {
+                self.flush_inline();
+                let language = match kind {
+                    CodeBlockKind::Fenced(info) => {
+                        info.split_whitespace().next().map(str::to_string)
+                    }
+                    CodeBlockKind::Indented => None,
+                };
+                self.code = Some((language, String::new()));
+            }
+            Tag::List(start) => {
+                self.flush_inline();
+                self.containers.push(Container::List {
+                    ordered: start.is_some(),
+                    start: start.unwrap_or(1),
+                    items: Vec::new(),
+                });
+            }
+            Tag::Item => {
+                self.flush_inline();
+                self.containers.push(Container::Item(ListItem { checkbox: None, blocks: Vec::new() }));
+            }
+            Tag::Emphasis => self.italic += 1,
+            Tag::Strong => self.bold += 1,
+            Tag::Strikethrough => self.strikethrough += 1,
+            Tag::Link(_, url, _) => self.links.push(url.to_string()),
+            // Images show their alt text; tables and footnotes are off
+            Tag::Image(..)
+            | Tag::FootnoteDefinition(_)
+            | Tag::Table(_)
+            | Tag::TableHead
+            | Tag::TableRow
+            | Tag::TableCell => {}
+        }
+    }
+
+    fn end(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::Heading(..) => self.flush_inline(),
+            Tag::CodeBlock(_) => {
+                if let Some((language, mut code)) = self.code.take() {
+                    if code.ends_with('\n') {
+                        code.pop();
+                    }
+                    self.push_block(Block::CodeBlock { language, code });
+                }
+            }
+            Tag::BlockQuote | Tag::List(_) | Tag::Item => {
+                self.flush_inline();
+                let block = match self.containers.pop() {
+                    Some(Container::Quote(blocks)) => Block::Quote { blocks },
+                    Some(Container::List { ordered, start, items }) => Block::List { ordered, start, items },
+                    Some(Container::Item(item)) => {
+                        if let Some(Container::List { items, .. }) = self.containers.last_mut() {
+                            items.push(item);
+                        }
+                        return;
+                    }
+                    Some(root @ Container::Root(_)) => {
+                        self.containers.push(root);
+                        return;
+                    }
+                    None => return,
+                };
+                self.push_block(block);
+            }
+            Tag::Emphasis => self.italic = self.italic.saturating_sub(1),
+            Tag::Strong => self.bold = self.bold.saturating_sub(1),
+            Tag::Strikethrough => self.strikethrough = self.strikethrough.saturating_sub(1),
+            Tag::Link(..) => {
+                self.links.pop();
+            }
+            _ => {}
+        }
+    }
+
+    fn push_span(&mut self, text: &str, code: bool) {
+        // Tight list items have no paragraph around their text
+        let inline = self.inline.get_or_insert_with(|| Inline { heading: None, spans: Vec::new() });
+        let span = Span {
+            text: text.to_string(),
+            bold: self.bold > 0,
+            italic: self.italic > 0,
+            strikethrough: self.strikethrough > 0,
+            code,
+            link: self.links.last().cloned(),
+        };
+        match inline.spans.last_mut() {
+            Some(last) if same_style(last, &span) && last.text != "\n" && text != "\n" => {
+                last.text.push_str(text);
+            }
+            _ => inline.spans.push(span),
+        }
+    }
+
+    fn flush_inline(&mut self) {
+        if let Some(inline) = self.inline.take() {
+            let block = match inline.heading {
+                Some(level) => Block::Heading { level, spans: inline.spans },
+                None => Block::Paragraph { spans: inline.spans },
+            };
+            self.push_block(block);
+        }
+    }
+
+    fn push_block(&mut self, block: Block) {
+        match self.containers.last_mut() {
+            Some(Container::Root(blocks) | Container::Quote(blocks)) => blocks.push(block),
+            Some(Container::Item(item)) => item.blocks.push(block),
+            // Lists only contain items
+            Some(Container::List { .. }) | None => {}
+        }
+    }
+
+    fn finish(mut self) -> NoteDocument {
+        self.flush_inline();
+        // Unwind containers left open by truncated input
+        while self.containers.len() > 1 {
+            let end = match self.containers.last() {
+                Some(Container::Quote(_)) => Tag::BlockQuote,
+                Some(Container::List { .. }) => Tag::List(None),
+                _ => Tag::Item,
+            };
+            self.end(end);
+        }
+        match self.containers.pop() {
+            Some(Container::Root(blocks)) => NoteDocument { blocks },
+            _ => NoteDocument::default(),
+        }
+    }
+}
+
+fn same_style(a: &Span, b: &Span) -> bool {
+    a.bold == b.bold
+        && a.italic == b.italic
+        && a.strikethrough == b.strikethrough
+        && a.code == b.code
+        && a.link == b.link
+}
+
+fn heading_level(level: HeadingLevel) -> u8 {
+    match level {
+        HeadingLevel::H1 => 1,
+        HeadingLevel::H2 => 2,
+        HeadingLevel::H3 => 3,
+        HeadingLevel::H4 => 4,
+        HeadingLevel::H5 => 5,
+        HeadingLevel::H6 => 6,
+    }
+}
+
+#[derive(Default)]
+struct Layout {
+    lines: Vec<NoteLine>,
+    top: f32,
+    /// Marker waiting for the first line of its list item.
+    marker: Option<Marker>,
+    quote_bars: Vec<f32>,
+}
+
+impl Layout {
+    fn blocks(&mut self, blocks: &[Block], indent: f32, spaced: bool) {
+        for block in blocks {
+            self.block(block, indent);
+            if spaced {
+                self.top += BLOCK_SPACING;
+            }
+        }
+    }
+
+    fn block(&mut self, block: &Block, indent: f32) {
+        match block {
+            Block::Heading { level, spans } => {
+                let font_size = HEADING_SIZES[(*level as usize).clamp(1, 6) - 1];
+                self.text(LineKind::Heading { level: *level }, spans, indent, font_size);
+            }
+            Block::Paragraph { spans } => self.text(LineKind::Text, spans, indent, FONT_SIZE),
+            Block::CodeBlock { code, .. } => {
+                for line in code.split('\n') {
+                    let span = Span { text: line.to_string(), code: true, ..Default::default() };
+                    self.line(LineKind::Code, vec![span], indent, CODE_FONT_SIZE);
+                }
+            }
+            Block::Quote { blocks } => {
+                self.quote_bars.push(indent);
+                self.blocks(blocks, indent + QUOTE_INDENT, true);
+                self.quote_bars.pop();
+            }
+            Block::List { ordered, start, items } => {
+                for (i, item) in items.iter().enumerate() {
+                    self.marker = Some(match (item.checkbox, ordered) {
+                        (Some(Checkbox { index, checked }), _) => Marker::Checkbox { index, checked },
+                        (None, true) => Marker::Number { value: start + i as u64 },
+                        (None, false) => Marker::Bullet,
+                    });
+                    if item.blocks.is_empty() {
+                        self.line(LineKind::Text, Vec::new(), indent + LIST_INDENT, FONT_SIZE);
+                    }
+                    self.blocks(&item.blocks, indent + LIST_INDENT, false);
+                }
+            }
+            Block::Rule => {
+                let marker = self.marker.take();
+                self.lines.push(NoteLine {
+                    kind: LineKind::Rule,
+                    spans: Vec::new(),
+                    marker,
+                    indent,
+                    quote_bars: self.quote_bars.clone(),
+                    top: self.top,
+                    height: RULE_HEIGHT,
+                    font_size: FONT_SIZE,
+                });
+                self.top += RULE_HEIGHT;
+            }
+        }
+    }
+
+    /// Splits inline content at hard breaks.
+    fn text(&mut self, kind: LineKind, spans: &[Span], indent: f32, font_size: f32) {
+        let mut line = Vec::new();
+        for span in spans {
+            if span.text == "\n" {
+                self.line(kind, std::mem::take(&mut line), indent, font_size);
+            } else {
+                line.push(span.clone());
+            }
+        }
+        self.line(kind, line, indent, font_size);
+    }
+
+    fn line(&mut self, kind: LineKind, spans: Vec<Span>, indent: f32, font_size: f32) {
+        let height = font_size * LINE_HEIGHT;
+        self.lines.push(NoteLine {
+            kind,
+            spans,
+            marker: self.marker.take(),
+            indent,
+            quote_bars: self.quote_bars.clone(),
+            top: self.top,
+            height,
+            font_size,
+        });
+        self.top += height;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{add_tile, CanvasOperation, InfiniteCanvas, NewTile};
+    use crate::tiles::TileData;
+
+    fn plain(text: &str) -> Span {
+        Span { text: text.to_string(), ..Default::default() }
+    }
+
+    #[test]
+    fn parses_blocks_and_inline_styles() {
+        let doc = NoteDocument::parse(
+            "# Plan *now*\n\nSee [docs](https://example.com) and `cargo`.\n\n```rust\nfn main() {}\n```\n\n> quoted\n\n---\n",
+        );
+        assert_eq!(
+            doc.blocks[0],
+            Block::Heading {
+                level: 1,
+                spans: vec![plain("Plan "), Span { text: "now".into(), italic: true, ..Default::default() }],
+            }
+        );
+        let Block::Paragraph { spans } = &doc.blocks[1] else { panic!("{:?}", doc.blocks[1]) };
+        assert_eq!(spans[1].link.as_deref(), Some("https://example.com"));
+        assert!(spans[3].code);
+        assert_eq!(
+            doc.blocks[2],
+            Block::CodeBlock { language: Some("rust".into()), code: "fn main() {}".into() }
+        );
+        assert_eq!(doc.blocks[3], Block::Quote { blocks: vec![Block::Paragraph { spans: vec![plain("quoted")] }] });
+        assert_eq!(doc.blocks[4], Block::Rule);
+    }
+
+    #[test]
+    fn task_lists_number_their_checkboxes() {
+        let doc = NoteDocument::parse("- [ ] one\n- plain\n  - [x] nested\n\n3. three\n");
+        let Block::List { ordered: false, items, .. } = &doc.blocks[0] else { panic!("{:?}", doc.blocks) };
+        assert_eq!(items[0].checkbox, Some(Checkbox { index: 0, checked: false }));
+        assert_eq!(items[0].blocks, vec![Block::Paragraph { spans: vec![plain("one")] }]);
+        assert_eq!(items[1].checkbox, None);
+        let Block::List { items: nested, .. } = &items[1].blocks[1] else { panic!("{:?}", items[1]) };
+        assert_eq!(nested[0].checkbox, Some(Checkbox { index: 1, checked: true }));
+        assert!(matches!(doc.blocks[1], Block::List { ordered: true, start: 3, .. }));
+
+        let markers: Vec<_> = doc.lines().iter().map(|line| line.marker).collect();
+        assert_eq!(
+            markers,
+            vec![
+                Some(Marker::Checkbox { index: 0, checked: false }),
+                Some(Marker::Bullet),
+                Some(Marker::Checkbox { index: 1, checked: true }),
+                Some(Marker::Number { value: 3 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn lines_stack_and_indent() {
+        let lines = NoteDocument::parse("# Title\n\ntext  \nbreak\n\n- item\n").lines();
+        assert_eq!(lines.iter().map(NoteLine::text).collect::<Vec<_>>(), ["Title", "text", "break", "item"]);
+        assert_eq!(lines[0].font_size, 26.0);
+        assert_eq!(lines[1].top, 26.0 * LINE_HEIGHT + BLOCK_SPACING);
+        assert_eq!(lines[2].top, lines[1].top + lines[1].height);
+        assert_eq!(lines[3].indent, LIST_INDENT);
+        let ([x, _], size) = lines[3].marker_box().unwrap();
+        assert!(x >= 0.0 && x + size <= LIST_INDENT);
+    }
+
+    #[test]
+    fn set_checkbox_edits_the_source() {
+        let mut markdown = String::from("- [ ] one\n- [X] two\n");
+        assert!(set_checkbox(&mut markdown, 0, None).unwrap());
+        assert_eq!(markdown, "- [x] one\n- [X] two\n");
+        assert!(!set_checkbox(&mut markdown, 1, Some(false)).unwrap());
+        assert_eq!(markdown, "- [x] one\n- [ ] two\n");
+        assert!(set_checkbox(&mut markdown, 0, Some(true)).unwrap());
+        assert_eq!(markdown, "- [x] one\n- [ ] two\n");
+        assert!(set_checkbox(&mut markdown, 2, None).is_err());
+    }
+
+    #[test]
+    fn note_text_edits_and_checkbox_toggles() {
+        let mut canvas = InfiniteCanvas::default();
+        let id = add_tile(&mut canvas, NewTile::Note { markdown: "# Tödo\n- [ ] milk\n".into() });
+        let markdown = |canvas: &InfiniteCanvas| match &canvas.get_tile(id).unwrap().data {
+            TileData::Note { markdown } => markdown.clone(),
+            other => panic!("{:?}", other),
+        };
+
+        canvas.apply_operations(vec![
+            // Character offsets, past the multi-byte `ö`
+            CanvasOperation::ReplaceNoteText { tile_id: id, start: 2, end: 6, text: "Shopping".into() },
+            CanvasOperation::ToggleNoteCheckbox { tile_id: id, index: 0, checked: None },
+        ]).unwrap();
+        assert_eq!(markdown(&canvas), "# Shopping\n- [x] milk\n");
+
+        assert!(canvas.apply_operations(vec![
+            CanvasOperation::ReplaceNoteText { tile_id: id, start: 5, end: 100, text: String::new() },
+        ]).is_err());
+        assert!(canvas.apply_operations(vec![
+            CanvasOperation::ToggleNoteCheckbox { tile_id: id, index: 1, checked: Some(true) },
+        ]).is_err());
+        let sketch = add_tile(&mut canvas, NewTile::Skia);
+        assert!(canvas.apply_operations(vec![
+            CanvasOperation::SetNoteText { tile_id: sketch, markdown: "text".into() },
+        ]).is_err());
+
+        canvas.undo();
+        canvas.undo();
+        assert_eq!(markdown(&canvas), "# Tödo\n- [ ] milk\n");
+    }
+
+}
@@ -0,0 +1,487 @@
+//! Boolean operations, offsets and flattening of what drawings paint.
+//!
+//! A [`Region`] is an area in content area coordinates, as polygons filled
+//! by the nonzero rule like everything the renderer fills. Curves are
+//! flattened into chords within a tolerance before areas are combined, so
+//! results are polygons whose corners lie within the tolerance of the exact
+//! outline. Outer boundaries and holes wind in opposite directions, and the
+//! contours never cross, so results fill the same under either fill rule.
+
+use glam::Vec2;
+use i_overlay::core::fill_rule::FillRule;
+use i_overlay::core::overlay_rule::OverlayRule;
+use i_overlay::float::simplify::SimplifyShape;
+use i_overlay::float::single::SingleFloatOverlay;
+use i_overlay::mesh::float::outline::offset::OutlineOffset;
+use i_overlay::mesh::float::style::{LineJoin, OutlineStyle};
+use serde::{Deserialize, Serialize};
+use tiny_skia::{Path, PathSegment, Stroke, Transform};
+
+use crate::error::CanvasError;
+use crate::render::skia::{is_path_command, run_commands, DrawState, Painter};
+use crate::tiles::DrawingCommand;
+
+/// Largest distance between a curve and the chords replacing it, in
+/// content area units, when none is given.
+pub const DEFAULT_TOLERANCE: f32 = 0.1;
+
+/// Chords per curve segment at most, however fine the tolerance.
+const MAX_CHORDS: usize = 1024;
+
+/// Smallest area of a contour worth keeping, in square content area units.
+/// Overlays leave slivers where edges touch.
+const MIN_CONTOUR_AREA: f64 = 1e-6;
+
+/// Largest angle of the chords of offset corners, in radians.
+const MAX_CORNER_STEP: f32 = 0.5;
+
+/// How two areas are combined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PathOp {
+    /// Inside either.
+    Union,
+    /// Inside both.
+    Intersection,
+    /// Inside the first and outside the second.
+    Difference,
+    /// Inside exactly one.
+    Xor,
+}
+
+impl PathOp {
+    fn rule(self) -> OverlayRule {
+        match self {
+            PathOp::Union => OverlayRule::Union,
+            PathOp::Intersection => OverlayRule::Intersect,
+            PathOp::Difference => OverlayRule::Difference,
+            PathOp::Xor => OverlayRule::Xor,
+        }
+    }
+}
+
+/// An area as closed polygons in content area coordinates.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Region {
+    contours: Vec<Vec<Vec2>>,
+}
+
+impl Region {
+    /// The area `commands` paint, strokes at their full width. A path left
+    /// unpainted at the end counts as filled, so a sequence of path commands
+    /// alone gives the area it encloses. Curves are flattened within
+    /// `tolerance`; clips are ignored.
+    pub fn from_commands(commands: &[DrawingCommand], tolerance: f32) -> Result<Self, CanvasError> {
+        let tolerance = validate_tolerance(tolerance)?;
+        let consumes_path = |command: &DrawingCommand| {
+            matches!(command, DrawingCommand::Fill | DrawingCommand::Stroke | DrawingCommand::Clip)
+        };
+        let unpainted = match commands.iter().rposition(is_path_command) {
+            Some(last) => !commands[last..].iter().any(consumes_path),
+            None => false,
+        };
+
+        let mut areas = Areas { tolerance, contours: Vec::new() };
+        if unpainted {
+            let closed: Vec<DrawingCommand> = commands.iter().cloned().chain([DrawingCommand::Fill]).collect();
+            run_commands(&closed, &mut areas);
+        } else {
+            run_commands(commands, &mut areas);
+        }
+        Ok(Self::simplified(areas.contours))
+    }
+
+    /// The area of paths in command coordinates, each with the transform to
+    /// the content area and the pen if it is stroked.
+    pub(crate) fn from_paths<'a>(
+        paths: impl IntoIterator<Item = (&'a Path, Transform, Option<&'a Stroke>)>,
+        tolerance: f32,
+    ) -> Self {
+        let mut contours = Vec::new();
+        for (shape, transform, stroke) in paths {
+            add_area(&mut contours, shape, transform, stroke, tolerance);
+        }
+        Self::simplified(contours)
+    }
+
+    /// Closed polygons of the region. Holes wind against the boundaries
+    /// around them.
+    pub fn contours(&self) -> &[Vec<Vec2>] {
+        &self.contours
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.contours.is_empty()
+    }
+
+    /// Size of the area, holes excluded.
+    pub fn area(&self) -> f32 {
+        let signed: f64 = self.contours.iter().map(|contour| signed_area(contour)).sum();
+        signed.abs() as f32
+    }
+
+    /// This region combined with `other` by `op`.
+    pub fn combine(&self, other: &Region, op: PathOp) -> Region {
+        let shapes = self.points().overlay(&other.points(), op.rule(), FillRule::NonZero);
+        Self::from_shapes(shapes)
+    }
+
+    /// The region grown by `distance`, or shrunk for a negative one, with
+    /// round corners whose chords keep within `tolerance`.
+    pub fn offset(&self, distance: f32, tolerance: f32) -> Result<Region, CanvasError> {
+        if !distance.is_finite() {
+            return Err(CanvasError::invalid_argument("distance", "must be finite"));
+        }
+        let tolerance = validate_tolerance(tolerance)?;
+        if distance == 0.0 || self.is_empty() {
+            return Ok(self.clone());
+        }
+        // A chord over an angle a strays r·(1 - cos(a/2)) ≈ r·a²/8 from the arc
+        let step = (8.0 * tolerance / distance.abs()).sqrt().min(MAX_CORNER_STEP);
+        let style = OutlineStyle::new(distance as f64).line_join(LineJoin::Round(step as f64));
+        Ok(Self::from_shapes(self.points().outline(&style)))
+    }
+
+    /// Path commands that enclose the region, one closed subpath per
+    /// contour.
+    pub fn to_commands(&self) -> Vec<DrawingCommand> {
+        self.to_path_commands(Transform::identity())
+    }
+
+    /// Path commands that enclose the region after `transform`.
+    pub(crate) fn to_path_commands(&self, transform: Transform) -> Vec<DrawingCommand> {
+        let map = |p: Vec2| {
+            let mut point = tiny_skia::Point::from_xy(p.x, p.y);
+            transform.map_point(&mut point);
+            Vec2::new(point.x, point.y)
+        };
+        let polylines: Vec<(Vec<Vec2>, bool)> =
+            self.contours.iter().map(|contour| (contour.iter().copied().map(map).collect(), true)).collect();
+        polyline_commands(&polylines)
+    }
+
+    /// A region of contours that may overlap and wind either way.
+    fn simplified(contours: Vec<Vec<[f64; 2]>>) -> Self {
+        let contours: Vec<Vec<[f64; 2]>> = contours.into_iter().filter(|contour| contour.len() >= 3).collect();
+        if contours.is_empty() {
+            return Self::default();
+        }
+        Self::from_shapes(contours.simplify_shape(FillRule::NonZero))
+    }
+
+    fn from_shapes(shapes: Vec<Vec<Vec<[f64; 2]>>>) -> Self {
+        let contours = shapes
+            .into_iter()
+            .flatten()
+            .map(|contour| contour.into_iter().map(|[x, y]| Vec2::new(x as f32, y as f32)).collect::<Vec<_>>())
+            .filter(|contour| contour.len() >= 3 && signed_area(contour).abs() >= MIN_CONTOUR_AREA)
+            .collect();
+        Self { contours }
+    }
+
+    fn points(&self) -> Vec<Vec<[f64; 2]>> {
+        self.contours.iter().map(|contour| contour.iter().map(|p| [p.x as f64, p.y as f64]).collect()).collect()
+    }
+}
+
+/// Collects the areas commands paint as contours.
+struct Areas {
+    tolerance: f32,
+    contours: Vec<Vec<[f64; 2]>>,
+}
+
+impl Painter for Areas {
+    fn fill(&mut self, _index: usize, state: &DrawState, shape: &Path) {
+        add_area(&mut self.contours, shape, state.transform, None, self.tolerance);
+    }
+
+    fn stroke(&mut self, _index: usize, state: &DrawState, shape: &Path) {
+        add_area(&mut self.contours, shape, state.transform, Some(&state.stroke), self.tolerance);
+    }
+}
+
+fn add_area(
+    contours: &mut Vec<Vec<[f64; 2]>>,
+    shape: &Path,
+    transform: Transform,
+    stroke: Option<&Stroke>,
+    tolerance: f32,
+) {
+    let outline = match stroke {
+        Some(stroke) => stroke_outline(shape, stroke, transform, tolerance),
+        None => Some(shape.clone()),
+    };
+    let Some(outline) = outline.and_then(|outline| outline.transform(transform)) else { return };
+    for (polyline, _) in flatten_path(&outline, tolerance) {
+        contours.push(polyline.into_iter().map(|p| [p.x as f64, p.y as f64]).collect());
+    }
+}
+
+/// The path that fills what `shape` stroked with `stroke` paints, both in
+/// command coordinates, with curves that keep within `tolerance` of the
+/// exact outline after `transform`.
+pub(crate) fn stroke_outline(shape: &Path, stroke: &Stroke, transform: Transform, tolerance: f32) -> Option<Path> {
+    let stretch = max_stretch(transform);
+    if stretch <= 0.0 {
+        return None;
+    }
+    // The stroker keeps its curves within a quarter of the inverse of its
+    // resolution scale
+    let scale = stretch / (4.0 * tolerance);
+    // Stroking leaves dashes to the caller, as the rasterizer does
+    match &stroke.dash {
+        Some(dash) => shape.dash(dash, scale)?.stroke(stroke, scale),
+        None => shape.stroke(stroke, scale),
+    }
+}
+
+/// Drawing commands that build `path`.
+pub(crate) fn path_commands(path: &Path) -> Vec<DrawingCommand> {
+    let v = |p: tiny_skia::Point| Vec2::new(p.x, p.y);
+    path.segments()
+        .map(|segment| match segment {
+            PathSegment::MoveTo(p) => DrawingCommand::MoveTo(v(p)),
+            PathSegment::LineTo(p) => DrawingCommand::LineTo(v(p)),
+            PathSegment::QuadTo(c, p) => DrawingCommand::QuadTo(v(c), v(p)),
+            PathSegment::CubicTo(c1, c2, p) => DrawingCommand::CubicTo(v(c1), v(c2), v(p)),
+            PathSegment::Close => DrawingCommand::ClosePath,
+        })
+        .collect()
+}
+
+/// Drawing commands that build `polylines`, leaving out single points.
+pub(crate) fn polyline_commands(polylines: &[(Vec<Vec2>, bool)]) -> Vec<DrawingCommand> {
+    let mut commands = Vec::new();
+    for (points, closed) in polylines.iter().filter(|(points, _)| points.len() > 1) {
+        commands.push(DrawingCommand::MoveTo(points[0]));
+        commands.extend(points[1..].iter().map(|p| DrawingCommand::LineTo(*p)));
+        if *closed {
+            commands.push(DrawingCommand::ClosePath);
+        }
+    }
+    commands
+}
+
+/// Subpaths of `path` as polylines whose chords keep within `tolerance` of
+/// its curves, and whether each is closed. A closed polyline does not
+/// repeat its first point.
+pub(crate) fn flatten_path(path: &Path, tolerance: f32) -> Vec<(Vec<Vec2>, bool)> {
+    let mut polylines: Vec<(Vec<Vec2>, bool)> = Vec::new();
+    let mut current = Vec2::ZERO;
+    let v = |p: tiny_skia::Point| Vec2::new(p.x, p.y);
+    for segment in path.segments() {
+        let mut points = Vec::new();
+        match segment {
+            PathSegment::MoveTo(p) => {
+                current = v(p);
+                polylines.push((vec![current], false));
+                continue;
+            }
+            PathSegment::LineTo(p) => points.push(v(p)),
+            PathSegment::QuadTo(c, p) => {
+                let (p0, c, p1) = (current, v(c), v(p));
+                let n = chords(2.0 * (p0 - 2.0 * c + p1).length(), tolerance);
+                points.extend((1..=n).map(|i| {
+                    let t = i as f32 / n as f32;
+                    p0.lerp(c, t).lerp(c.lerp(p1, t), t)
+                }));
+            }
+            PathSegment::CubicTo(c1, c2, p) => {
+                let (p0, c1, c2, p1) = (current, v(c1), v(c2), v(p));
+                let bend = 6.0 * (p0 - 2.0 * c1 + c2).length().max((c1 - 2.0 * c2 + p1).length());
+                let n = chords(bend, tolerance);
+                points.extend((1..=n).map(|i| {
+                    let t = i as f32 / n as f32;
+                    let (a, b, c) = (p0.lerp(c1, t), c1.lerp(c2, t), c2.lerp(p1, t));
+                    a.lerp(b, t).lerp(b.lerp(c, t), t)
+                }));
+            }
+            PathSegment::Close => {
+                let Some((polyline, closed)) = polylines.last_mut() else { continue };
+                if polyline.len() > 1 && polyline.first() == polyline.last() {
+                    polyline.pop();
+                }
+                *closed = true;
+                current = polyline[0];
+                continue;
+            }
+        }
+        if let Some(last) = points.last() {
+            current = *last;
+        }
+        if let Some((polyline, _)) = polylines.last_mut() {
+            polyline.extend(points);
+        }
+    }
+    polylines
+}
+
+/// Chords that keep within `tolerance` of a curve whose second derivative
+/// is at most `bend`: over a parameter span h a chord strays at most
+/// bend·h²/8.
+fn chords(bend: f32, tolerance: f32) -> usize {
+    ((bend / (8.0 * tolerance)).sqrt().ceil() as usize).clamp(1, MAX_CHORDS)
+}
+
+/// How much `transform` stretches lengths in the direction it stretches
+/// most.
+pub(crate) fn max_stretch(transform: Transform) -> f32 {
+    let Transform { sx, ky, kx, sy, .. } = transform;
+    let sum = sx * sx + ky * ky + kx * kx + sy * sy;
+    let spread = ((sx * sx + ky * ky - kx * kx - sy * sy).powi(2) + 4.0 * (sx * kx + ky * sy).powi(2)).sqrt();
+    ((sum + spread) / 2.0).sqrt()
+}
+
+pub(crate) fn validate_tolerance(tolerance: f32) -> Result<f32, CanvasError> {
+    if !tolerance.is_finite() || tolerance <= 0.0 {
+        return Err(CanvasError::invalid_argument("tolerance", "must be positive"));
+    }
+    Ok(tolerance)
+}
+
+fn signed_area(contour: &[Vec2]) -> f64 {
+    let twice: f64 = contour
+        .iter()
+        .zip(contour.iter().cycle().skip(1))
+        .map(|(a, b)| a.x as f64 * b.y as f64 - b.x as f64 * a.y as f64)
+        .sum();
+    twice / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    const TOLERANCE: f32 = 0.05;
+    const RADIUS: f32 = 40.0;
+
+    fn circle(center: Vec2) -> Vec<DrawingCommand> {
+        vec![DrawingCommand::Arc(center, RADIUS, 0.0, 2.0 * PI), DrawingCommand::ClosePath]
+    }
+
+    /// Area both of two circles of `RADIUS` whose centers are `d` apart cover.
+    fn lens_area(d: f32) -> f32 {
+        let r = RADIUS;
+        2.0 * r * r * (d / (2.0 * r)).acos() - d / 2.0 * (4.0 * r * r - d * d).sqrt()
+    }
+
+    /// Checks the area against `exact` and every corner against the signed
+    /// distance to the exact outline: chords cut inside curves by at most
+    /// the tolerance, and never outside them.
+    fn assert_precise(region: &Region, exact: f32, distance: impl Fn(Vec2) -> f32) {
+        let perimeter: f32 = region
+            .contours()
+            .iter()
+            .map(|contour| contour.iter().zip(contour.iter().cycle().skip(1)).map(|(a, b)| a.distance(*b)).sum::<f32>())
+            .sum();
+        let area = region.area();
+        assert!((area - exact).abs() <= perimeter * TOLERANCE, "area {} is not {}", area, exact);
+        for point in region.contours().iter().flatten() {
+            let off = distance(*point);
+            assert!(off.abs() <= TOLERANCE + 1e-3, "{:?} is {} off the outline", point, off);
+        }
+    }
+
+    #[test]
+    fn boolean_operations_on_circles_are_precise() {
+        let (a, b) = (Vec2::new(50.0, 50.0), Vec2::new(90.0, 50.0));
+        let first = Region::from_commands(&circle(a), TOLERANCE).unwrap();
+        let second = Region::from_commands(&circle(b), TOLERANCE).unwrap();
+        // Signed distances to each circle, negative inside
+        let da = move |p: Vec2| p.distance(a) - RADIUS;
+        let db = move |p: Vec2| p.distance(b) - RADIUS;
+        let disc = PI * RADIUS * RADIUS;
+        let lens = lens_area(a.distance(b));
+
+        assert_precise(&first.combine(&second, PathOp::Union), 2.0 * disc - lens, |p| da(p).min(db(p)));
+        assert_precise(&first.combine(&second, PathOp::Intersection), lens, |p| da(p).max(db(p)));
+        assert_precise(&first.combine(&second, PathOp::Difference), disc - lens, |p| da(p).max(-db(p)));
+        assert_precise(&first.combine(&second, PathOp::Xor), 2.0 * (disc - lens), |p| {
+            if da(p).abs() < db(p).abs() {
+                da(p)
+            } else {
+                db(p)
+            }
+        });
+
+        let apart = Region::from_commands(&circle(Vec2::new(200.0, 50.0)), TOLERANCE).unwrap();
+        assert!(first.combine(&apart, PathOp::Intersection).is_empty());
+        assert_eq!(first.combine(&apart, PathOp::Union).contours().len(), 2);
+    }
+
+    #[test]
+    fn offsets_grow_and_shrink_curves() {
+        let center = Vec2::new(100.0, 100.0);
+        let disc = Region::from_commands(&circle(center), TOLERANCE).unwrap();
+
+        let grown = disc.offset(10.0, TOLERANCE).unwrap();
+        assert_precise(&grown, PI * 50.0 * 50.0, |p| p.distance(center) - 50.0);
+        let shrunk = disc.offset(-10.0, TOLERANCE).unwrap();
+        assert_precise(&shrunk, PI * 30.0 * 30.0, |p| p.distance(center) - 30.0);
+        assert!(disc.offset(-RADIUS - 1.0, TOLERANCE).unwrap().is_empty());
+        assert!(disc.offset(f32::NAN, TOLERANCE).is_err());
+    }
+
+    #[test]
+    fn strokes_paint_their_outline_and_open_paths_are_closed() {
+        // A ring: a circle stroked 10 wide
+        let center = Vec2::new(60.0, 60.0);
+        let mut ring = vec![DrawingCommand::SetStrokeWidth(10.0)];
+        ring.extend(circle(center));
+        ring.push(DrawingCommand::Stroke);
+        let region = Region::from_commands(&ring, TOLERANCE).unwrap();
+        assert_eq!(region.contours().len(), 2);
+        assert_precise(&region, PI * (45.0 * 45.0 - 35.0 * 35.0), |p| (p.distance(center) - RADIUS).abs() - 5.0);
+
+        // A quarter arc and its chord, left unpainted
+        let sector = [DrawingCommand::MoveTo(center), DrawingCommand::Arc(center, RADIUS, 0.0, PI / 2.0)];
+        let region = Region::from_commands(&sector, TOLERANCE).unwrap();
+        assert_precise(&region, PI * RADIUS * RADIUS / 4.0, |p| {
+            let local = p - center;
+            (local.length() - RADIUS).max(-local.x).max(-local.y)
+        });
+    }
+
+    #[test]
+    fn flattened_curves_stay_within_tolerance() {
+        let mut builder = tiny_skia::PathBuilder::new();
+        builder.move_to(0.0, 0.0);
+        builder.cubic_to(0.0, 80.0, 120.0, -40.0, 100.0, 60.0);
+        builder.quad_to(50.0, 120.0, 0.0, 60.0);
+        let path = builder.finish().unwrap();
+
+        for tolerance in [1.0, 0.1, 0.01] {
+            let polylines = flatten_path(&path, tolerance);
+            assert_eq!(polylines.len(), 1);
+            let (points, closed) = &polylines[0];
+            assert!(!closed);
+            // Every curve point lies within the tolerance of some chord
+            let cubic = |t: f32| {
+                let (p0, c1, c2, p1) = (Vec2::ZERO, Vec2::new(0.0, 80.0), Vec2::new(120.0, -40.0), Vec2::new(100.0, 60.0));
+                let (a, b, c) = (p0.lerp(c1, t), c1.lerp(c2, t), c2.lerp(p1, t));
+                a.lerp(b, t).lerp(b.lerp(c, t), t)
+            };
+            let quad = |t: f32| {
+                let (p0, c, p1) = (Vec2::new(100.0, 60.0), Vec2::new(50.0, 120.0), Vec2::new(0.0, 60.0));
+                p0.lerp(c, t).lerp(c.lerp(p1, t), t)
+            };
+            let to_chords = |p: Vec2| {
+                points
+                    .windows(2)
+                    .map(|pair| {
+                        let along = pair[1] - pair[0];
+                        let t = ((p - pair[0]).dot(along) / along.length_squared()).clamp(0.0, 1.0);
+                        p.distance(pair[0] + along * t)
+                    })
+                    .fold(f32::INFINITY, f32::min)
+            };
+            for i in 0..=1000 {
+                let t = i as f32 / 1000.0;
+                assert!(to_chords(cubic(t)) <= tolerance * 1.01 + 1e-4);
+                assert!(to_chords(quad(t)) <= tolerance * 1.01 + 1e-4);
+            }
+        }
+        assert!(Region::from_commands(&[], 0.0).is_err());
+    }
+}
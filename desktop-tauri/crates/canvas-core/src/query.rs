@@ -0,0 +1,161 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::canvas::InfiniteCanvas;
+use crate::geometry::Rect;
+use crate::tiles::{PropertyValue, Tile, TileId, TileType};
+
+/// Filter for [`InfiniteCanvas::query`]. Unset fields match every tile.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileQuery {
+    pub tile_type: Option<TileType>,
+    pub visible: Option<bool>,
+    /// Only tiles whose bounds intersect this rectangle.
+    pub intersecting: Option<Rect>,
+    /// Case insensitive substring of the tile title.
+    pub title_contains: Option<String>,
+    /// Only tiles carrying all of these tags.
+    pub tags: Vec<String>,
+    /// Only tiles whose properties have exactly these values.
+    pub properties: BTreeMap<String, PropertyValue>,
+}
+
+impl TileQuery {
+    pub fn matches(&self, tile: &Tile) -> bool {
+        if let Some(tile_type) = self.tile_type {
+            if tile.tile_type != tile_type {
+                return false;
+            }
+        }
+        if let Some(visible) = self.visible {
+            if tile.visible != visible {
+                return false;
+            }
+        }
+        if let Some(rect) = &self.intersecting {
+            if !tile.bounds().intersects(rect) {
+                return false;
+            }
+        }
+        if let Some(needle) = &self.title_contains {
+            if !tile.title.to_lowercase().contains(&needle.to_lowercase()) {
+                return false;
+            }
+        }
+        if !self.tags.iter().all(|tag| tile.tags.contains(tag.trim())) {
+            return false;
+        }
+        if !self.properties.iter().all(|(key, value)| tile.properties.get(key) == Some(value)) {
+            return false;
+        }
+        true
+    }
+}
+
+impl InfiniteCanvas {
+    /// Tiles matching `query`, bottom to top in z-order.
+    pub fn query(&self, query: &TileQuery) -> Vec<(TileId, &Tile)> {
+        let mut tiles: Vec<(TileId, &Tile)> = self.tiles.iter()
+            .filter(|(_, tile)| query.matches(tile))
+            .map(|(id, tile)| (*id, tile))
+            .collect();
+
+        // Ties are broken by id so results don't depend on HashMap order
+        tiles.sort_by(|(a_id, a), (b_id, b)| {
+            a.z_index.total_cmp(&b.z_index).then_with(|| a_id.0.cmp(&b_id.0))
+        });
+        tiles
+    }
+
+    pub fn tiles_in_rect(&self, rect: Rect) -> Vec<TileId> {
+        let query = TileQuery {
+            intersecting: Some(rect),
+            ..Default::default()
+        };
+        self.query(&query).into_iter().map(|(id, _)| id).collect()
+    }
+
+    /// Bounds enclosing every tile, or `None` for an empty canvas.
+    pub fn content_bounds(&self) -> Option<Rect> {
+        self.tiles.values()
+            .map(Tile::bounds)
+            .reduce(|bounds, tile_bounds| bounds.union(&tile_bounds))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+
+    use super::*;
+    use crate::tiles::{SkiaTile, WebViewTile};
+
+    fn canvas_with_tiles() -> (InfiniteCanvas, TileId, TileId) {
+        let mut canvas = InfiniteCanvas::default();
+        let web = canvas.add_tile(WebViewTile::new("https://example.com".into()).to_tile(
+            Vec2::ZERO,
+            Vec2::new(100.0, 100.0),
+            "Docs".into(),
+        ));
+        let mut sketch = SkiaTile::new().to_tile(
+            Vec2::new(500.0, 0.0),
+            Vec2::new(100.0, 100.0),
+            "Sketch".into(),
+        );
+        sketch.visible = false;
+        let sketch = canvas.add_tile(sketch);
+        (canvas, web, sketch)
+    }
+
+    #[test]
+    fn query_filters_by_type_and_visibility() {
+        let (canvas, web, sketch) = canvas_with_tiles();
+
+        let skia = TileQuery { tile_type: Some(TileType::Skia), ..Default::default() };
+        assert_eq!(canvas.query(&skia).iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![sketch]);
+
+        let visible = TileQuery { visible: Some(true), ..Default::default() };
+        assert_eq!(canvas.query(&visible).iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![web]);
+    }
+
+    #[test]
+    fn query_filters_by_tags_and_properties() {
+        let (mut canvas, web, sketch) = canvas_with_tiles();
+        for id in [web, sketch] {
+            canvas.get_tile_mut(id).unwrap().add_tags(&["research"]).unwrap();
+        }
+        let docs = canvas.get_tile_mut(web).unwrap();
+        docs.add_tags(&["ana"]).unwrap();
+        docs.set_property("priority", PropertyValue::Number(2.0)).unwrap();
+
+        let research = TileQuery { tags: vec!["research".into()], ..Default::default() };
+        assert_eq!(canvas.query(&research).len(), 2);
+
+        let mine = TileQuery { tags: vec!["research".into(), " ana ".into()], ..Default::default() };
+        assert_eq!(canvas.query(&mine).iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![web]);
+
+        let urgent: TileQuery = serde_json::from_str(r#"{ "properties": { "priority": 2 } }"#).unwrap();
+        assert_eq!(canvas.query(&urgent).iter().map(|(id, _)| *id).collect::<Vec<_>>(), vec![web]);
+
+        let other: TileQuery = serde_json::from_str(r#"{ "properties": { "priority": "2" } }"#).unwrap();
+        assert!(canvas.query(&other).is_empty());
+    }
+
+    #[test]
+    fn tiles_in_rect_uses_bounds() {
+        let (canvas, web, _) = canvas_with_tiles();
+        let rect = Rect::new(Vec2::new(40.0, 40.0), Vec2::new(60.0, 60.0));
+        assert_eq!(canvas.tiles_in_rect(rect), vec![web]);
+    }
+
+    #[test]
+    fn content_bounds_cover_all_tiles() {
+        let (canvas, _, _) = canvas_with_tiles();
+        let bounds = canvas.content_bounds().unwrap();
+        assert_eq!(bounds.min, Vec2::new(-50.0, -50.0));
+        assert_eq!(bounds.max, Vec2::new(550.0, 50.0));
+        assert!(InfiniteCanvas::default().content_bounds().is_none());
+    }
+}
@@ -0,0 +1,179 @@
+//! Static renderings of a whole board, e.g. for exporting or sharing.
+//!
+//! Both renderers draw the visible tiles in z-order with their
+//! [`TileStyle`](crate::tiles::TileStyle): background, border, corner radius,
+//! opacity, drop shadow and title bar. Note tiles draw their
+//! [`NoteLine`](crate::markdown::NoteLine)s inside the frame, in the title
+//! color, image tiles their file, scaled to fit, or a placeholder when it
+//! can't be read, and Skia tiles their drawing commands, see [`skia`].
+//! Terminal screens live in the app, not the document, so terminal tiles
+//! export as an empty frame. The output covers every tile plus
+//! [`ExportOptions::padding`].
+
+pub mod png;
+pub mod skia;
+pub mod svg;
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::canvas::InfiniteCanvas;
+use crate::error::CanvasError;
+use crate::geometry::Rect;
+use crate::query::TileQuery;
+use crate::tiles::{Tile, TileId};
+
+/// Largest width or height of an exported image, in pixels.
+pub const MAX_EXPORT_DIMENSION: f32 = 16384.0;
+
+/// Inset of note content from the frame and title bar, in world units.
+pub const NOTE_PADDING: f32 = 12.0;
+
+/// Font size of tile titles in world units.
+pub(crate) const TITLE_FONT_SIZE: f32 = 14.0;
+/// Distance of tile titles from the left edge of the title bar.
+pub(crate) const TITLE_INSET: f32 = 10.0;
+
+/// Color of links in notes.
+pub(crate) const LINK_COLOR: [f32; 4] = [0.302, 0.639, 1.0, 1.0];
+/// Shading behind code in notes, over the tile background.
+pub(crate) const CODE_BACKGROUND: [f32; 4] = [1.0, 1.0, 1.0, 0.08];
+/// Fill of image tiles whose file can't be read.
+pub(crate) const PLACEHOLDER_COLOR: [f32; 4] = [0.5, 0.5, 0.5, 0.25];
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ExportOptions {
+    /// Output pixels per world unit.
+    pub scale: f32,
+    /// Margin around the tiles in world units.
+    pub padding: f32,
+    /// Fill the output with the canvas background color.
+    pub background: bool,
+}
+
+impl Default for ExportOptions {
+    fn default() -> Self {
+        Self {
+            scale: 1.0,
+            padding: 32.0,
+            background: true,
+        }
+    }
+}
+
+/// What to draw and where: the visible tiles bottom to top, and the world
+/// area covered by the output.
+pub(crate) struct Scene<'a> {
+    pub tiles: Vec<(TileId, &'a Tile)>,
+    pub area: Rect,
+    /// Output size in pixels.
+    pub size: Vec2,
+}
+
+impl<'a> Scene<'a> {
+    pub fn new(canvas: &'a InfiniteCanvas, options: &ExportOptions) -> Result<Self, CanvasError> {
+        if !options.scale.is_finite() || options.scale <= 0.0 {
+            return Err(CanvasError::invalid_argument("scale", "must be positive"));
+        }
+        if !options.padding.is_finite() || options.padding < 0.0 {
+            return Err(CanvasError::invalid_argument("padding", "must be finite and not negative"));
+        }
+
+        let tiles = canvas.query(&TileQuery { visible: Some(true), ..Default::default() });
+        let content = tiles.iter()
+            .map(|(_, tile)| painted_bounds(tile))
+            .reduce(|a, b| a.union(&b))
+            .unwrap_or(Rect::new(Vec2::ZERO, Vec2::ZERO));
+        let padding = Vec2::splat(options.padding);
+        let area = Rect::new(content.min - padding, content.max + padding);
+
+        let size = (area.size() * options.scale).ceil().max(Vec2::ONE);
+        if size.max_element() > MAX_EXPORT_DIMENSION {
+            return Err(CanvasError::invalid_argument(
+                "scale",
+                format!("output of {}x{} pixels is too large", size.x, size.y),
+            ));
+        }
+
+        Ok(Self { tiles, area, size })
+    }
+}
+
+/// Bounds of everything drawn for `tile`, including rotation, border and
+/// shadow.
+pub(crate) fn painted_bounds(tile: &Tile) -> Rect {
+    let (sin, cos) = tile.rotation.sin_cos();
+    let half = tile.size * 0.5;
+    let extent = Vec2::new(
+        cos.abs() * half.x + sin.abs() * half.y,
+        sin.abs() * half.x + cos.abs() * half.y,
+    ) + Vec2::splat(tile.style.border_width * 0.5);
+    let frame = Rect::new(tile.position - extent, tile.position + extent);
+
+    match &tile.style.shadow {
+        Some(shadow) => {
+            let blur = Vec2::splat(shadow.blur);
+            let center = tile.position + shadow.offset;
+            frame.union(&Rect::new(center - extent - blur, center + extent + blur))
+        }
+        None => frame,
+    }
+}
+
+/// Top-left corner and size of `tile` before rotation.
+pub(crate) fn frame(tile: &Tile) -> (Vec2, Vec2) {
+    (tile.position - tile.size * 0.5, tile.size)
+}
+
+/// Top-left corner and width of the area note lines are laid out in.
+pub(crate) fn note_area(tile: &Tile) -> (Vec2, f32) {
+    let (origin, size) = frame(tile);
+    let top = tile.style.title_bar_height(size) + NOTE_PADDING;
+    (origin + Vec2::new(NOTE_PADDING, top), (size.x - 2.0 * NOTE_PADDING).max(0.0))
+}
+
+/// Top-left corner and size of the part of `tile` below its title bar.
+pub(crate) fn content_area(tile: &Tile) -> (Vec2, Vec2) {
+    let (origin, size) = frame(tile);
+    let title_bar = tile.style.title_bar_height(size);
+    (origin + Vec2::new(0.0, title_bar), size - Vec2::new(0.0, title_bar))
+}
+
+/// Where an image of `natural_size` pixels is drawn in `tile`: scaled to
+/// fit below the title bar, centered, keeping its aspect ratio.
+pub(crate) fn image_rect(tile: &Tile, natural_size: [u32; 2]) -> (Vec2, Vec2) {
+    let (origin, size) = content_area(tile);
+    let natural = Vec2::new(natural_size[0] as f32, natural_size[1] as f32).max(Vec2::ONE);
+    let fitted = natural * (size / natural).min_element();
+    (origin + (size - fitted) * 0.5, fitted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::{DropShadow, SkiaTile};
+
+    #[test]
+    fn painted_bounds_cover_rotation_and_shadow() {
+        let mut tile = SkiaTile::new().to_tile(Vec2::ZERO, Vec2::new(100.0, 50.0), "Sketch".into());
+        tile.style.border_width = 0.0;
+        tile.rotation = std::f32::consts::FRAC_PI_2;
+        let rotated = painted_bounds(&tile);
+        assert!((rotated.size() - Vec2::new(50.0, 100.0)).length() < 1e-3);
+
+        tile.rotation = 0.0;
+        tile.style.shadow = Some(DropShadow { offset: Vec2::new(10.0, 10.0), blur: 5.0, color: [0.0, 0.0, 0.0, 0.5] });
+        let shadowed = painted_bounds(&tile);
+        assert_eq!(shadowed.min, Vec2::new(-50.0, -25.0));
+        assert_eq!(shadowed.max, Vec2::new(65.0, 40.0));
+    }
+
+    #[test]
+    fn scene_rejects_huge_outputs() {
+        let mut canvas = InfiniteCanvas::default();
+        canvas.add_tile(SkiaTile::new().to_tile(Vec2::ZERO, Vec2::splat(100.0), "Sketch".into()));
+        let options = ExportOptions { scale: 1000.0, ..Default::default() };
+        assert_eq!(Scene::new(&canvas, &options).err().unwrap().code(), "invalid_argument");
+    }
+}
@@ -0,0 +1,465 @@
+//! Raster export of a board with tiny-skia.
+//!
+//! Text is laid out by [`crate::text`] in the default family, so it lines
+//! up with the frontend, which draws with the same font. Note text has no
+//! bold, italic or monospace faces here and is drawn in the regular one.
+
+use glam::Vec2;
+use image::RgbaImage;
+use tiny_skia::{
+    Color, FillRule, FilterQuality, IntSize, Mask, Paint, Path, PathBuilder, Pixmap, PixmapPaint, Rect,
+    Stroke, Transform,
+};
+
+use crate::canvas::InfiniteCanvas;
+use crate::error::CanvasError;
+use crate::image_cache::ImageCache;
+use crate::markdown::{LineKind, Marker, NoteDocument, QUOTE_BAR_WIDTH};
+use crate::render::{
+    content_area, frame, image_rect, note_area, skia, ExportOptions, Scene, CODE_BACKGROUND, LINK_COLOR,
+    PLACEHOLDER_COLOR, TITLE_FONT_SIZE, TITLE_INSET,
+};
+use crate::text::{self, TextStyle};
+use crate::tiles::{Tile, TileData};
+
+/// Number of layers used to approximate a blurred shadow.
+const SHADOW_STEPS: usize = 4;
+
+/// Control point distance for approximating a quarter circle with a cubic.
+const KAPPA: f32 = 0.552_284_8;
+
+/// Renders the visible tiles of `canvas` and encodes them as PNG.
+pub fn to_png(canvas: &InfiniteCanvas, options: &ExportOptions) -> Result<Vec<u8>, CanvasError> {
+    render(canvas, options)?
+        .encode_png()
+        .map_err(|e| CanvasError::Internal { message: format!("failed to encode PNG: {}", e) })
+}
+
+/// Renders the visible tiles of `canvas` into a pixmap.
+pub fn render(canvas: &InfiniteCanvas, options: &ExportOptions) -> Result<Pixmap, CanvasError> {
+    let scene = Scene::new(canvas, options)?;
+    let mut pixmap = Pixmap::new(scene.size.x as u32, scene.size.y as u32)
+        .ok_or_else(|| CanvasError::invalid_argument("scale", "output is empty"))?;
+
+    if options.background {
+        pixmap.fill(color(canvas.background_color));
+    }
+
+    let view = Transform::from_scale(options.scale, options.scale)
+        .pre_translate(-scene.area.min.x, -scene.area.min.y);
+    let mut images = ImageCache::new();
+    for (_, tile) in &scene.tiles {
+        draw_tile(&mut pixmap, view, tile, &mut images, options.scale);
+    }
+    Ok(pixmap)
+}
+
+fn draw_tile(pixmap: &mut Pixmap, view: Transform, tile: &Tile, images: &mut ImageCache, scale: f32) {
+    let transform = view.pre_concat(Transform::from_rotate_at(
+        tile.rotation.to_degrees(),
+        tile.position.x,
+        tile.position.y,
+    ));
+
+    if tile.style.opacity >= 1.0 {
+        draw_frame(pixmap, transform, tile, images, scale);
+        return;
+    }
+
+    // Composite the tile on its own layer so overlapping parts don't show
+    // through each other
+    let Some(mut layer) = Pixmap::new(pixmap.width(), pixmap.height()) else {
+        return;
+    };
+    draw_frame(&mut layer, transform, tile, images, scale);
+    let paint = PixmapPaint {
+        opacity: tile.style.opacity,
+        ..Default::default()
+    };
+    pixmap.draw_pixmap(0, 0, layer.as_ref(), &paint, Transform::identity(), None);
+}
+
+fn draw_frame(pixmap: &mut Pixmap, transform: Transform, tile: &Tile, images: &mut ImageCache, scale: f32) {
+    let style = &tile.style;
+    let (origin, size) = frame(tile);
+    let radius = style.effective_radius(size);
+
+    if let Some(shadow) = &style.shadow {
+        // Stacked, increasingly larger translucent copies stand in for a blur
+        let [r, g, b, a] = shadow.color;
+        let step_color = color([r, g, b, a / SHADOW_STEPS as f32]);
+        for step in 1..=SHADOW_STEPS {
+            let grow = shadow.blur * (step as f32 / SHADOW_STEPS as f32 - 0.5);
+            let path = rounded_rect(
+                origin + shadow.offset - Vec2::splat(grow),
+                size + Vec2::splat(2.0 * grow),
+                radius + grow.max(0.0),
+            );
+            if let Some(path) = path {
+                pixmap.fill_path(&path, &solid(step_color), FillRule::Winding, transform, None);
+            }
+        }
+    }
+
+    let Some(outline) = rounded_rect(origin, size, radius) else {
+        return;
+    };
+    pixmap.fill_path(&outline, &solid(color(style.background)), FillRule::Winding, transform, None);
+
+    if matches!(tile.data, TileData::Note { .. } | TileData::Image { .. } | TileData::Skia { .. }) {
+        // Content stays inside the rounded frame
+        if let Some(mut clip) = Mask::new(pixmap.width(), pixmap.height()) {
+            clip.fill_path(&outline, FillRule::Winding, true, transform);
+            match &tile.data {
+                TileData::Note { markdown } => draw_note(pixmap, transform, &clip, tile, markdown),
+                TileData::Image { path, .. } => draw_image(pixmap, transform, &clip, tile, path, images, scale),
+                TileData::Skia { drawing_commands } => {
+                    let (origin, _) = content_area(tile);
+                    let placement = transform.pre_translate(origin.x, origin.y);
+                    skia::draw_commands(pixmap, placement, Some(&clip), drawing_commands);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    let title_bar = style.title_bar_height(size);
+    if title_bar > 0.0 {
+        if let Some(path) = top_rounded_rect(origin, Vec2::new(size.x, title_bar), radius.min(title_bar)) {
+            pixmap.fill_path(&path, &solid(color(style.title_bar_color)), FillRule::Winding, transform, None);
+        }
+        let title = TextStyle::sized(TITLE_FONT_SIZE);
+        let (left, middle) = (origin.x + TITLE_INSET, origin.y + title_bar * 0.5);
+        fill_text(pixmap, transform, None, &tile.title, &title, Vec2::new(left, middle), &solid(color(style.title_color)));
+    }
+
+    if style.border_width > 0.0 {
+        let stroke = Stroke {
+            width: style.border_width,
+            ..Default::default()
+        };
+        pixmap.stroke_path(&outline, &solid(color(style.border_color)), &stroke, transform, None);
+    }
+}
+
+fn draw_note(pixmap: &mut Pixmap, transform: Transform, clip: &Mask, tile: &Tile, markdown: &str) {
+    let (origin, width) = note_area(tile);
+    let ink = solid(color(tile.style.title_color));
+    let bar = solid(color(tile.style.border_color));
+    let fill_rect = |pixmap: &mut Pixmap, x: f32, y: f32, w: f32, h: f32, paint: &Paint| {
+        if let Some(rect) = Rect::from_xywh(x, y, w, h) {
+            pixmap.fill_rect(rect, paint, transform, Some(clip));
+        }
+    };
+
+    for line in NoteDocument::parse(markdown).lines() {
+        let top = origin.y + line.top;
+        for x in &line.quote_bars {
+            fill_rect(pixmap, origin.x + x, top, QUOTE_BAR_WIDTH, line.height, &bar);
+        }
+        match line.kind {
+            LineKind::Code => {
+                let shade = solid(color(CODE_BACKGROUND));
+                fill_rect(pixmap, origin.x + line.indent, top, width - line.indent, line.height, &shade);
+            }
+            LineKind::Rule => {
+                let middle = top + line.height * 0.5;
+                fill_rect(pixmap, origin.x + line.indent, middle - 0.5, width - line.indent, 1.0, &bar);
+            }
+            LineKind::Text | LineKind::Heading { .. } => {}
+        }
+
+        let style = TextStyle::sized(line.font_size);
+        let middle = top + line.height * 0.5;
+        let mut x = origin.x + line.indent;
+        for span in &line.spans {
+            let paint = if span.link.is_some() { solid(color(LINK_COLOR)) } else { ink.clone() };
+            fill_text(pixmap, transform, Some(clip), &span.text, &style, Vec2::new(x, middle), &paint);
+            let advance = text::advance(&span.text, &style);
+            // Underlines and strikethroughs at the offsets the frontend uses
+            let underline = span.link.is_some().then_some(line.font_size * 0.45);
+            let strike = span.strikethrough.then_some(0.0);
+            for offset in underline.into_iter().chain(strike) {
+                fill_rect(pixmap, x, middle + offset, advance, 1.0, &paint);
+            }
+            x += advance;
+        }
+
+        let (Some(marker), Some(([left, marker_top], size))) = (line.marker, line.marker_box()) else {
+            continue;
+        };
+        let (left, marker_top) = (origin.x + left, origin.y + marker_top);
+        match marker {
+            Marker::Bullet => {
+                let center = (left + size * 0.5, marker_top + size * 0.5);
+                if let Some(dot) = PathBuilder::from_circle(center.0, center.1, size * 0.2) {
+                    pixmap.fill_path(&dot, &ink, FillRule::Winding, transform, Some(clip));
+                }
+            }
+            Marker::Number { value } => {
+                let label = format!("{}.", value);
+                let style = TextStyle::sized(line.font_size);
+                let start = Vec2::new(left + size - text::advance(&label, &style), top + line.height * 0.5);
+                fill_text(pixmap, transform, Some(clip), &label, &style, start, &ink);
+            }
+            Marker::Checkbox { checked, .. } => {
+                let stroke = Stroke { width: 1.5, ..Default::default() };
+                if let Some(outline) = rounded_rect(Vec2::new(left, marker_top), Vec2::splat(size), 2.0) {
+                    pixmap.stroke_path(&outline, &ink, &stroke, transform, Some(clip));
+                }
+                if checked {
+                    let mut tick = PathBuilder::new();
+                    tick.move_to(left + size * 0.2, marker_top + size * 0.5);
+                    tick.line_to(left + size * 0.42, marker_top + size * 0.75);
+                    tick.line_to(left + size * 0.8, marker_top + size * 0.25);
+                    if let Some(tick) = tick.finish() {
+                        pixmap.stroke_path(&tick, &ink, &stroke, transform, Some(clip));
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Fills one line of `content` in the default family, starting at
+/// `start` and centered vertically on it.
+fn fill_text(
+    pixmap: &mut Pixmap,
+    transform: Transform,
+    clip: Option<&Mask>,
+    content: &str,
+    style: &TextStyle,
+    start: Vec2,
+    paint: &Paint,
+) {
+    let baseline = start.y + text::middle_to_baseline(style);
+    if let Some(glyphs) = text::outline(content, Vec2::new(start.x, baseline), style.size) {
+        pixmap.fill_path(&glyphs, paint, FillRule::Winding, transform, clip);
+    }
+}
+
+/// Draws the image file at `path`, from the mip level closest to its size in
+/// the output, or a crossed out placeholder if it can't be read.
+fn draw_image(
+    pixmap: &mut Pixmap,
+    transform: Transform,
+    clip: &Mask,
+    tile: &Tile,
+    path: &str,
+    images: &mut ImageCache,
+    scale: f32,
+) {
+    let chain = match images.get(std::path::Path::new(path)) {
+        Ok(chain) => chain,
+        Err(_) => return draw_placeholder(pixmap, transform, clip, tile),
+    };
+    let (origin, size) = image_rect(tile, chain.natural_size());
+    let level = &chain.levels()[chain.level_for(size.max_element() * scale)];
+    let Some(bitmap) = to_pixmap(level) else {
+        return;
+    };
+    let paint = PixmapPaint {
+        quality: FilterQuality::Bilinear,
+        ..Default::default()
+    };
+    let placement = transform
+        .pre_translate(origin.x, origin.y)
+        .pre_scale(size.x / bitmap.width() as f32, size.y / bitmap.height() as f32);
+    pixmap.draw_pixmap(0, 0, bitmap.as_ref(), &paint, placement, Some(clip));
+}
+
+fn draw_placeholder(pixmap: &mut Pixmap, transform: Transform, clip: &Mask, tile: &Tile) {
+    let (origin, size) = frame(tile);
+    let top = origin.y + tile.style.title_bar_height(size);
+    let (left, right, bottom) = (origin.x, origin.x + size.x, origin.y + size.y);
+    if let Some(rect) = Rect::from_ltrb(left, top, right, bottom) {
+        pixmap.fill_rect(rect, &solid(color(PLACEHOLDER_COLOR)), transform, Some(clip));
+    }
+
+    let mut cross = PathBuilder::new();
+    cross.move_to(left, top);
+    cross.line_to(right, bottom);
+    cross.move_to(right, top);
+    cross.line_to(left, bottom);
+    if let Some(cross) = cross.finish() {
+        let stroke = Stroke { width: 1.5, ..Default::default() };
+        pixmap.stroke_path(&cross, &solid(color(tile.style.border_color)), &stroke, transform, Some(clip));
+    }
+}
+
+/// Copies an image into a pixmap, which stores premultiplied alpha.
+fn to_pixmap(image: &RgbaImage) -> Option<Pixmap> {
+    let mut data = image.as_raw().clone();
+    for pixel in data.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u16;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u16 * alpha + 127) / 255) as u8;
+        }
+    }
+    Pixmap::from_vec(data, IntSize::from_wh(image.width(), image.height())?)
+}
+
+fn rounded_rect(origin: Vec2, size: Vec2, radius: f32) -> Option<Path> {
+    let radius = radius.min(size.min_element() * 0.5).max(0.0);
+    let (left, top) = (origin.x, origin.y);
+    let (right, bottom) = (origin.x + size.x, origin.y + size.y);
+    let k = radius * (1.0 - KAPPA);
+
+    let mut path = PathBuilder::new();
+    path.move_to(left + radius, top);
+    path.line_to(right - radius, top);
+    path.cubic_to(right - k, top, right, top + k, right, top + radius);
+    path.line_to(right, bottom - radius);
+    path.cubic_to(right, bottom - k, right - k, bottom, right - radius, bottom);
+    path.line_to(left + radius, bottom);
+    path.cubic_to(left + k, bottom, left, bottom - k, left, bottom - radius);
+    path.line_to(left, top + radius);
+    path.cubic_to(left, top + k, left + k, top, left + radius, top);
+    path.close();
+    path.finish()
+}
+
+/// Rectangle with only its top corners rounded, for title bars.
+fn top_rounded_rect(origin: Vec2, size: Vec2, radius: f32) -> Option<Path> {
+    let (left, top) = (origin.x, origin.y);
+    let (right, bottom) = (origin.x + size.x, origin.y + size.y);
+    let k = radius * (1.0 - KAPPA);
+
+    let mut path = PathBuilder::new();
+    path.move_to(left + radius, top);
+    path.line_to(right - radius, top);
+    path.cubic_to(right - k, top, right, top + k, right, top + radius);
+    path.line_to(right, bottom);
+    path.line_to(left, bottom);
+    path.line_to(left, top + radius);
+    path.cubic_to(left, top + k, left + k, top, left + radius, top);
+    path.close();
+    path.finish()
+}
+
+pub(crate) fn solid(color: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    paint.set_color(color);
+    paint.anti_alias = true;
+    paint
+}
+
+pub(crate) fn color([r, g, b, a]: [f32; 4]) -> Color {
+    Color::from_rgba(r.clamp(0.0, 1.0), g.clamp(0.0, 1.0), b.clamp(0.0, 1.0), a.clamp(0.0, 1.0))
+        .unwrap_or(Color::BLACK)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::{ImageTile, NoteTile, SkiaTile, TITLE_BAR_HEIGHT};
+
+    fn pixel(pixmap: &Pixmap, x: u32, y: u32) -> [u8; 4] {
+        let p = pixmap.pixel(x, y).unwrap().demultiply();
+        [p.red(), p.green(), p.blue(), p.alpha()]
+    }
+
+    fn canvas_with(tile: Tile) -> InfiniteCanvas {
+        let mut canvas = InfiniteCanvas {
+            background_color: [0.0, 0.0, 1.0, 1.0],
+            ..Default::default()
+        };
+        canvas.add_tile(tile);
+        canvas
+    }
+
+    #[test]
+    fn draws_background_title_bar_and_body() {
+        let mut tile = SkiaTile::new().to_tile(Vec2::ZERO, Vec2::new(200.0, 100.0), "Sketch".into());
+        tile.style.background = [1.0, 0.0, 0.0, 1.0];
+        tile.style.title_bar_color = [0.0, 1.0, 0.0, 1.0];
+        tile.style.title_color = [1.0, 1.0, 1.0, 1.0];
+        let canvas = canvas_with(tile);
+
+        let pixmap = render(&canvas, &ExportOptions { padding: 10.0, ..Default::default() }).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (222, 122));
+        // Padding shows the canvas background
+        assert_eq!(pixel(&pixmap, 2, 2), [0, 0, 255, 255]);
+        // Title bar at the top of the tile, body below it
+        assert_eq!(pixel(&pixmap, 111, 30), [0, 255, 0, 255]);
+        assert_eq!(pixel(&pixmap, 111, 90), [255, 0, 0, 255]);
+        // The title is drawn from its inset, in the title color, which is
+        // the only one with any red
+        let title_bar = 10..10 + TITLE_BAR_HEIGHT as u32;
+        let inked = |x: std::ops::Range<u32>| {
+            x.flat_map(|x| title_bar.clone().map(move |y| (x, y)))
+                .filter(|&(x, y)| pixel(&pixmap, x, y)[0] > 128)
+                .count()
+        };
+        assert!(inked(10 + TITLE_INSET as u32..70) > 20);
+        assert_eq!((inked(12..18), inked(100..200)), (0, 0));
+
+        let png = to_png(&canvas, &ExportOptions::default()).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+    }
+
+    #[test]
+    fn opacity_blends_with_the_background() {
+        let mut tile = SkiaTile::new().to_tile(Vec2::ZERO, Vec2::new(200.0, 100.0), "Sketch".into());
+        tile.style.background = [1.0, 0.0, 0.0, 1.0];
+        tile.style.show_title_bar = false;
+        tile.style.opacity = 0.5;
+
+        let pixmap = render(&canvas_with(tile), &ExportOptions { padding: 10.0, ..Default::default() }).unwrap();
+        let [r, g, b, a] = pixel(&pixmap, 111, 61);
+        assert!((r as i32 - 128).abs() <= 1 && g == 0 && (b as i32 - 128).abs() <= 1 && a == 255, "{:?}", [r, g, b, a]);
+    }
+
+    #[test]
+    fn scale_multiplies_the_output_size() {
+        let tile = SkiaTile::new().to_tile(Vec2::ZERO, Vec2::new(100.0, 100.0), "Sketch".into());
+        let options = ExportOptions { scale: 2.0, padding: 0.0, background: false };
+        let pixmap = render(&canvas_with(tile), &options).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (204, 204));
+        // Outside the rounded corner
+        assert_eq!(pixel(&pixmap, 0, 0)[3], 0);
+    }
+
+    #[test]
+    fn notes_draw_checkboxes_and_code_shading() {
+        let mut tile = NoteTile::new("- [x] done\n\n```\ncode\n```\n".into())
+            .to_tile(Vec2::ZERO, Vec2::new(200.0, 200.0), "Note".into());
+        tile.style.background = [0.0, 0.0, 0.0, 1.0];
+        tile.style.title_color = [1.0, 1.0, 1.0, 1.0];
+        tile.style.show_title_bar = false;
+        let options = ExportOptions { padding: 0.0, background: false, ..Default::default() };
+        let pixmap = render(&canvas_with(tile.clone()), &options).unwrap();
+
+        let (origin, _) = note_area(&tile);
+        let line = &NoteDocument::parse("- [x] done").lines()[0];
+        let ([x, y], size) = line.marker_box().unwrap();
+        // Pixel coordinates of the checkbox's left edge, halfway down
+        let (px, py) = (origin.x + x + 1.0 - frame(&tile).0.x, origin.y + y + size * 0.5 - frame(&tile).0.y);
+        assert!(pixel(&pixmap, px as u32, py as u32)[0] > 128);
+        // Code shading lightens the black background
+        let code_top = origin.y - frame(&tile).0.y + line.height + 8.0;
+        let [r, ..] = pixel(&pixmap, 150, (code_top + 5.0) as u32);
+        assert!(r > 0 && r < 64, "{}", r);
+    }
+
+    #[test]
+    fn images_fill_their_tile_and_missing_files_show_a_placeholder() {
+        let path = std::env::temp_dir().join(format!("canvas-core-{}-export.png", std::process::id()));
+        RgbaImage::from_pixel(400, 200, image::Rgba([0, 255, 0, 255])).save(&path).unwrap();
+        let tile = ImageTile::new(path.display().to_string())
+            .to_tile(Vec2::ZERO, Vec2::new(100.0, 100.0), "Photo".into());
+        // Fitted to the natural aspect ratio
+        assert_eq!(tile.size, Vec2::new(100.0, 50.0));
+        let mut canvas = canvas_with(tile);
+        let options = ExportOptions { padding: 10.0, ..Default::default() };
+
+        let pixmap = render(&canvas, &options).unwrap();
+        assert_eq!(pixel(&pixmap, 61, 36), [0, 255, 0, 255]);
+
+        std::fs::remove_file(&path).unwrap();
+        canvas.tiles.values_mut().next().unwrap().style.background = [0.0, 0.0, 0.0, 1.0];
+        let pixmap = render(&canvas, &options).unwrap();
+        let [r, g, b, _] = pixel(&pixmap, 61, 28);
+        assert!(r == g && g == b && r > 0, "{:?}", [r, g, b]);
+    }
+}
@@ -0,0 +1,530 @@
+//! Rasterizes the drawing commands of Skia tiles.
+//!
+//! Commands run like a small 2D canvas API. Paints, stroke settings, the
+//! transform, the clip and the global alpha are state that applies to
+//! everything drawn after they are set; `Save` and `Restore` push and pop
+//! all of it. Path commands build up a path that `Fill`, `Stroke` and
+//! `Clip` use, and the first path command after one of those starts a new
+//! path. Path points are mapped through the transform current when they are
+//! added, while strokes are drawn with the pen of the transform current
+//! when `Stroke` runs, as in an HTML canvas. `DrawRect`, `DrawRoundedRect`,
+//! `DrawCircle`, `DrawEllipse`, `DrawText` and `DrawTextBox` are filled with
+//! the fill paint right away and leave the path alone. Coordinates are world
+//! units from the top-left corner of the tile's content area, below its
+//! title bar. `DrawText` is positioned by the left end of its first
+//! baseline, `DrawTextBox` by the top-left of its box; both are laid out by
+//! [`crate::text`].
+
+use std::f32::consts::{FRAC_PI_2, TAU};
+
+use glam::Vec2;
+use tiny_skia::{
+    FillRule, LinearGradient, Mask, Paint, Path, PathBuilder, Pixmap, Point, RadialGradient, Rect, SpreadMode,
+    Stroke, StrokeDash, Transform,
+};
+
+use crate::error::CanvasError;
+use crate::render::png::{color, solid};
+use crate::render::{content_area, MAX_EXPORT_DIMENSION};
+use crate::text;
+use crate::tiles::{DrawingCommand, Gradient, GradientStop, LineCap, LineJoin, Tile, TileData};
+
+pub const DEFAULT_FILL_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+pub const DEFAULT_STROKE_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+pub const DEFAULT_STROKE_WIDTH: f32 = 1.0;
+pub const DEFAULT_MITER_LIMIT: f32 = 4.0;
+
+/// Renders the drawing of a Skia tile at `scale` output pixels per world
+/// unit, on a transparent background the size of its content area.
+pub fn render_tile(tile: &Tile, scale: f32) -> Result<Pixmap, CanvasError> {
+    let TileData::Skia { drawing_commands } = &tile.data else {
+        return Err(CanvasError::invalid_argument("tile_id", "is not a Skia tile"));
+    };
+    let (_, size) = content_area(tile);
+    render_commands(drawing_commands, size, scale)
+}
+
+/// [`render_tile`], encoded as PNG.
+pub fn to_png(tile: &Tile, scale: f32) -> Result<Vec<u8>, CanvasError> {
+    render_tile(tile, scale)?
+        .encode_png()
+        .map_err(|e| CanvasError::Internal { message: format!("failed to encode PNG: {}", e) })
+}
+
+/// Renders `commands` into a transparent pixmap of `size` world units at
+/// `scale` output pixels per unit.
+pub fn render_commands(commands: &[DrawingCommand], size: Vec2, scale: f32) -> Result<Pixmap, CanvasError> {
+    if !scale.is_finite() || scale <= 0.0 {
+        return Err(CanvasError::invalid_argument("scale", "must be positive"));
+    }
+    let pixels = (size * scale).ceil().max(Vec2::ONE);
+    if !pixels.is_finite() || pixels.max_element() > MAX_EXPORT_DIMENSION {
+        return Err(CanvasError::invalid_argument(
+            "scale",
+            format!("output of {}x{} pixels is too large", pixels.x, pixels.y),
+        ));
+    }
+
+    let mut pixmap = Pixmap::new(pixels.x as u32, pixels.y as u32)
+        .ok_or_else(|| CanvasError::invalid_argument("scale", "output is empty"))?;
+    draw_commands(&mut pixmap, Transform::from_scale(scale, scale), None, commands);
+    Ok(pixmap)
+}
+
+/// Fill or stroke paint, before the global alpha is applied.
+#[derive(Clone, PartialEq)]
+pub(crate) enum Brush {
+    Solid([f32; 4]),
+    Gradient(Gradient),
+}
+
+impl Brush {
+    fn paint(&self, alpha: f32) -> Option<Paint<'static>> {
+        let faded = |[r, g, b, a]: [f32; 4]| color([r, g, b, a * alpha]);
+        let stops = |stops: &[GradientStop]| {
+            stops.iter().map(|stop| tiny_skia::GradientStop::new(stop.offset, faded(stop.color))).collect()
+        };
+        let shader = match self {
+            Brush::Solid(rgba) => return Some(solid(faded(*rgba))),
+            Brush::Gradient(Gradient::Linear { start, end, stops: list }) => {
+                LinearGradient::new(point(*start), point(*end), stops(list), SpreadMode::Pad, Transform::identity())
+            }
+            Brush::Gradient(Gradient::Radial { center, radius, stops: list }) => {
+                RadialGradient::new(point(*center), point(*center), *radius, stops(list), SpreadMode::Pad, Transform::identity())
+            }
+        }?;
+        Some(Paint { shader, anti_alias: true, ..Default::default() })
+    }
+}
+
+/// What `Save` pushes.
+#[derive(Clone)]
+pub(crate) struct DrawState {
+    pub(crate) fill: Brush,
+    pub(crate) stroke_brush: Brush,
+    pub(crate) stroke: Stroke,
+    /// Maps command coordinates to the content area.
+    pub(crate) transform: Transform,
+    /// Intersection of the `Clip` commands so far with the caller's clip,
+    /// `None` before the first.
+    clip: Option<Mask>,
+    pub(crate) alpha: f32,
+}
+
+impl Default for DrawState {
+    fn default() -> Self {
+        Self {
+            fill: Brush::Solid(DEFAULT_FILL_COLOR),
+            stroke_brush: Brush::Solid(DEFAULT_STROKE_COLOR),
+            stroke: Stroke { width: DEFAULT_STROKE_WIDTH, miter_limit: DEFAULT_MITER_LIMIT, ..Default::default() },
+            transform: Transform::identity(),
+            clip: None,
+            alpha: 1.0,
+        }
+    }
+}
+
+/// Receives what drawing commands paint, in order. Shapes are in command
+/// coordinates and `state.transform` maps them to the content area;
+/// `index` is the command that painted them.
+pub(crate) trait Painter {
+    /// Called before each command runs.
+    fn command(&mut self, _index: usize, _state: &DrawState) {}
+    fn fill(&mut self, index: usize, state: &DrawState, shape: &Path);
+    fn stroke(&mut self, index: usize, state: &DrawState, shape: &Path);
+    /// Narrows the clip of `state` to `shape`, given in content area
+    /// coordinates; without a shape nothing is left inside.
+    fn clip(&mut self, _state: &mut DrawState, _shape: Option<&Path>) {}
+}
+
+/// Paints into a pixmap, with `base` mapping the content area to pixels.
+struct Rasterizer<'a> {
+    pixmap: &'a mut Pixmap,
+    base: Transform,
+    clip: Option<&'a Mask>,
+}
+
+impl Rasterizer<'_> {
+    fn paint(&mut self, state: &DrawState, shape: &Path, stroke: bool) {
+        let brush = if stroke { &state.stroke_brush } else { &state.fill };
+        let Some(paint) = brush.paint(state.alpha) else { return };
+        let transform = self.base.pre_concat(state.transform);
+        let clip = state.clip.as_ref().or(self.clip);
+        if stroke {
+            self.pixmap.stroke_path(shape, &paint, &state.stroke, transform, clip);
+        } else {
+            self.pixmap.fill_path(shape, &paint, FillRule::Winding, transform, clip);
+        }
+    }
+}
+
+impl Painter for Rasterizer<'_> {
+    fn fill(&mut self, _index: usize, state: &DrawState, shape: &Path) {
+        self.paint(state, shape, false);
+    }
+
+    fn stroke(&mut self, _index: usize, state: &DrawState, shape: &Path) {
+        self.paint(state, shape, true);
+    }
+
+    fn clip(&mut self, state: &mut DrawState, shape: Option<&Path>) {
+        let mut mask = match state.clip.take().or_else(|| self.clip.cloned()) {
+            Some(mask) => mask,
+            None => {
+                let Some(mut mask) = Mask::new(self.pixmap.width(), self.pixmap.height()) else { return };
+                mask.data_mut().fill(u8::MAX);
+                mask
+            }
+        };
+        match shape {
+            Some(shape) => mask.intersect_path(shape, FillRule::Winding, true, self.base),
+            // Nothing is inside an empty path
+            None => mask.clear(),
+        }
+        state.clip = Some(mask);
+    }
+}
+
+/// Runs `commands` with `base` mapping the content area to pixels.
+pub(crate) fn draw_commands(pixmap: &mut Pixmap, base: Transform, clip: Option<&Mask>, commands: &[DrawingCommand]) {
+    run_commands(commands, &mut Rasterizer { pixmap, base, clip });
+}
+
+/// Runs `commands`, handing what they paint to `painter`.
+pub(crate) fn run_commands(commands: &[DrawingCommand], painter: &mut impl Painter) {
+    let mut state = DrawState::default();
+    let mut saved: Vec<DrawState> = Vec::new();
+    let mut path = PathBuilder::new();
+    // The path was painted, so the next path command starts a new one
+    let mut painted = false;
+
+    for (index, command) in commands.iter().enumerate() {
+        painter.command(index, &state);
+        if painted && is_path_command(command) {
+            path.clear();
+            painted = false;
+        }
+        let at = |p: Vec2| {
+            let mut p = point(p);
+            state.transform.map_point(&mut p);
+            (p.x, p.y)
+        };
+        match command {
+            DrawingCommand::MoveTo(p) => {
+                let (x, y) = at(*p);
+                path.move_to(x, y);
+            }
+            DrawingCommand::LineTo(p) => {
+                let (x, y) = at(*p);
+                path.line_to(x, y);
+            }
+            DrawingCommand::QuadTo(c, p) => {
+                let ((x1, y1), (x, y)) = (at(*c), at(*p));
+                path.quad_to(x1, y1, x, y);
+            }
+            DrawingCommand::CubicTo(c1, c2, p) => {
+                let ((x1, y1), (x2, y2), (x, y)) = (at(*c1), at(*c2), at(*p));
+                path.cubic_to(x1, y1, x2, y2, x, y);
+            }
+            DrawingCommand::Arc(center, radius, start, end) => {
+                if radius.is_finite() && *radius >= 0.0 && start.is_finite() && end.is_finite() {
+                    arc(&mut path, at, *center, *radius, *start, *end);
+                }
+            }
+            DrawingCommand::ClosePath => path.close(),
+            DrawingCommand::SetFillColor(rgba) => state.fill = Brush::Solid(*rgba),
+            DrawingCommand::SetStrokeColor(rgba) => state.stroke_brush = Brush::Solid(*rgba),
+            DrawingCommand::SetFillGradient(gradient) => state.fill = Brush::Gradient(gradient.clone()),
+            DrawingCommand::SetStrokeGradient(gradient) => state.stroke_brush = Brush::Gradient(gradient.clone()),
+            DrawingCommand::SetStrokeWidth(width) => {
+                if width.is_finite() && *width >= 0.0 {
+                    state.stroke.width = *width;
+                }
+            }
+            DrawingCommand::SetLineDash(dashes, offset) => {
+                let mut dashes = dashes.clone();
+                if dashes.len() % 2 == 1 {
+                    dashes.extend_from_within(..);
+                }
+                // Empty and all-zero patterns are rejected here, both mean
+                // solid lines
+                state.stroke.dash = StrokeDash::new(dashes, *offset);
+            }
+            DrawingCommand::SetLineCap(cap) => {
+                state.stroke.line_cap = match cap {
+                    LineCap::Butt => tiny_skia::LineCap::Butt,
+                    LineCap::Round => tiny_skia::LineCap::Round,
+                    LineCap::Square => tiny_skia::LineCap::Square,
+                };
+            }
+            DrawingCommand::SetLineJoin(join) => {
+                state.stroke.line_join = match join {
+                    LineJoin::Miter => tiny_skia::LineJoin::Miter,
+                    LineJoin::Round => tiny_skia::LineJoin::Round,
+                    LineJoin::Bevel => tiny_skia::LineJoin::Bevel,
+                };
+            }
+            DrawingCommand::SetMiterLimit(limit) => {
+                if limit.is_finite() && *limit >= 0.0 {
+                    state.stroke.miter_limit = *limit;
+                }
+            }
+            DrawingCommand::SetGlobalAlpha(alpha) => {
+                if (0.0..=1.0).contains(alpha) {
+                    state.alpha = *alpha;
+                }
+            }
+            DrawingCommand::Save => saved.push(state.clone()),
+            DrawingCommand::Restore => {
+                if let Some(previous) = saved.pop() {
+                    state = previous;
+                }
+            }
+            DrawingCommand::Translate(offset) => state.transform = state.transform.pre_translate(offset.x, offset.y),
+            DrawingCommand::Scale(factor) => state.transform = state.transform.pre_scale(factor.x, factor.y),
+            DrawingCommand::Rotate(angle) => state.transform = state.transform.pre_rotate(angle.to_degrees()),
+            DrawingCommand::Fill | DrawingCommand::Stroke => {
+                // Back to command coordinates, so strokes get the pen of the
+                // current transform; a collapsed one has no pen at all
+                let local = path.clone().finish()
+                    .and_then(|shape| state.transform.invert().and_then(|inverse| shape.transform(inverse)));
+                if let Some(local) = local {
+                    if matches!(command, DrawingCommand::Stroke) {
+                        painter.stroke(index, &state, &local);
+                    } else {
+                        painter.fill(index, &state, &local);
+                    }
+                }
+                painted = true;
+            }
+            DrawingCommand::Clip => {
+                painter.clip(&mut state, path.clone().finish().as_ref());
+                painted = true;
+            }
+            DrawingCommand::DrawRect(position, size) => {
+                let (min, max) = (position.min(*position + *size), position.max(*position + *size));
+                if let Some(rect) = Rect::from_ltrb(min.x, min.y, max.x, max.y) {
+                    painter.fill(index, &state, &PathBuilder::from_rect(rect));
+                }
+            }
+            DrawingCommand::DrawRoundedRect(position, size, radius) => {
+                let (min, max) = (position.min(*position + *size), position.max(*position + *size));
+                if let Some(shape) = rounded_rect(min, max, *radius) {
+                    painter.fill(index, &state, &shape);
+                }
+            }
+            DrawingCommand::DrawCircle(center, radius) => {
+                if let Some(circle) = PathBuilder::from_circle(center.x, center.y, *radius) {
+                    painter.fill(index, &state, &circle);
+                }
+            }
+            DrawingCommand::DrawEllipse(center, radii) => {
+                let oval = Rect::from_xywh(center.x - radii.x, center.y - radii.y, 2.0 * radii.x, 2.0 * radii.y);
+                if let Some(ellipse) = oval.and_then(PathBuilder::from_oval) {
+                    painter.fill(index, &state, &ellipse);
+                }
+            }
+            DrawingCommand::DrawText(content, position, size) => {
+                if size.is_finite() && *size > 0.0 {
+                    if let Some(glyphs) = text::outline(content, *position, *size) {
+                        painter.fill(index, &state, &glyphs);
+                    }
+                }
+            }
+            DrawingCommand::DrawTextBox(text_box) => {
+                if text_box.style.validate().is_ok() {
+                    let glyphs = text::outline_box(&text_box.text, &text_box.style, text_box.position, text_box.width);
+                    if let Some(glyphs) = glyphs {
+                        painter.fill(index, &state, &glyphs);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Adds a circular arc from `start` to `end` radians as cubic curves of at
+/// most a quarter turn each, joined to the path by a line.
+fn arc(path: &mut PathBuilder, at: impl Fn(Vec2) -> (f32, f32), center: Vec2, radius: f32, start: f32, end: f32) {
+    let sweep = (end - start).clamp(-TAU, TAU);
+    let segments = (sweep.abs() / FRAC_PI_2).ceil().max(1.0);
+    let step = sweep / segments;
+    // Distance of the control points along the tangents, with the sign of
+    // the sweep
+    let handle = radius * 4.0 / 3.0 * (step / 4.0).tan();
+    let on_circle = |angle: f32| center + radius * Vec2::from_angle(angle);
+    let tangent = |angle: f32| Vec2::from_angle(angle).perp();
+
+    let (x, y) = at(on_circle(start));
+    if path.is_empty() {
+        path.move_to(x, y);
+    } else {
+        path.line_to(x, y);
+    }
+    for segment in 0..segments as usize {
+        let a0 = start + step * segment as f32;
+        let a1 = a0 + step;
+        let ((x1, y1), (x2, y2), (x, y)) = (
+            at(on_circle(a0) + handle * tangent(a0)),
+            at(on_circle(a1) - handle * tangent(a1)),
+            at(on_circle(a1)),
+        );
+        path.cubic_to(x1, y1, x2, y2, x, y);
+    }
+}
+
+/// Rectangle from `min` to `max` with corners rounded by `radius`, which is
+/// reduced to fit.
+fn rounded_rect(min: Vec2, max: Vec2, radius: f32) -> Option<Path> {
+    let rect = Rect::from_ltrb(min.x, min.y, max.x, max.y)?;
+    let radius = radius.min(rect.width() / 2.0).min(rect.height() / 2.0);
+    if radius <= 0.0 {
+        return Some(PathBuilder::from_rect(rect));
+    }
+    // Control point distance of a quarter circle drawn as one cubic curve
+    let handle = radius * (1.0 - 0.552_284_8);
+    let (l, t, r, b) = (rect.left(), rect.top(), rect.right(), rect.bottom());
+    let mut path = PathBuilder::new();
+    path.move_to(l + radius, t);
+    path.line_to(r - radius, t);
+    path.cubic_to(r - handle, t, r, t + handle, r, t + radius);
+    path.line_to(r, b - radius);
+    path.cubic_to(r, b - handle, r - handle, b, r - radius, b);
+    path.line_to(l + radius, b);
+    path.cubic_to(l + handle, b, l, b - handle, l, b - radius);
+    path.line_to(l, t + radius);
+    path.cubic_to(l, t + handle, l + handle, t, l + radius, t);
+    path.close();
+    path.finish()
+}
+
+fn point(p: Vec2) -> Point {
+    Point::from_xy(p.x, p.y)
+}
+
+pub(crate) fn is_path_command(command: &DrawingCommand) -> bool {
+    matches!(
+        command,
+        DrawingCommand::MoveTo(_)
+            | DrawingCommand::LineTo(_)
+            | DrawingCommand::QuadTo(..)
+            | DrawingCommand::CubicTo(..)
+            | DrawingCommand::Arc(..)
+            | DrawingCommand::ClosePath
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiles::SkiaTile;
+
+    fn alpha(pixmap: &Pixmap, x: u32, y: u32) -> u8 {
+        pixmap.pixel(x, y).unwrap().alpha()
+    }
+
+    #[test]
+    fn output_covers_the_content_area_at_scale() {
+        let tile = SkiaTile::new().to_tile(Vec2::ZERO, Vec2::new(100.0, 80.0), "Sketch".into());
+        let title_bar = tile.style.title_bar_height(tile.size);
+        let pixmap = render_tile(&tile, 2.0).unwrap();
+        assert_eq!((pixmap.width(), pixmap.height()), (200, (2.0 * (80.0 - title_bar)) as u32));
+
+        assert_eq!(render_tile(&tile, 0.0).unwrap_err().code(), "invalid_argument");
+        assert_eq!(render_tile(&tile, 1000.0).unwrap_err().code(), "invalid_argument");
+    }
+
+    #[test]
+    fn painting_keeps_the_path_until_the_next_path_command() {
+        let commands = vec![
+            DrawingCommand::MoveTo(Vec2::new(0.0, 0.0)),
+            DrawingCommand::LineTo(Vec2::new(10.0, 0.0)),
+            DrawingCommand::LineTo(Vec2::new(10.0, 10.0)),
+            DrawingCommand::LineTo(Vec2::new(0.0, 10.0)),
+            DrawingCommand::Fill,
+            // Moves the path away; the first square must not be filled again
+            DrawingCommand::SetFillColor([1.0, 0.0, 0.0, 1.0]),
+            DrawingCommand::MoveTo(Vec2::new(20.0, 0.0)),
+            DrawingCommand::LineTo(Vec2::new(30.0, 0.0)),
+            DrawingCommand::LineTo(Vec2::new(30.0, 10.0)),
+            DrawingCommand::LineTo(Vec2::new(20.0, 10.0)),
+            DrawingCommand::Fill,
+        ];
+        let pixmap = render_commands(&commands, Vec2::new(40.0, 20.0), 1.0).unwrap();
+        let first = pixmap.pixel(5, 5).unwrap();
+        assert_eq!((first.red(), first.alpha()), (0, 255));
+        let second = pixmap.pixel(25, 5).unwrap();
+        assert_eq!((second.red(), second.alpha()), (255, 255));
+        assert_eq!(alpha(&pixmap, 15, 5), 0);
+    }
+
+    #[test]
+    fn invalid_values_are_skipped() {
+        let commands = vec![
+            DrawingCommand::SetStrokeWidth(f32::NAN),
+            DrawingCommand::DrawCircle(Vec2::new(5.0, 5.0), -1.0),
+            DrawingCommand::DrawText("x".into(), Vec2::new(0.0, 10.0), f32::INFINITY),
+            DrawingCommand::MoveTo(Vec2::new(f32::NAN, 0.0)),
+            DrawingCommand::Stroke,
+        ];
+        let pixmap = render_commands(&commands, Vec2::splat(10.0), 1.0).unwrap();
+        assert!(pixmap.pixels().iter().all(|pixel| pixel.alpha() == 0));
+    }
+
+    #[test]
+    fn restore_brings_back_transform_paint_and_clip() {
+        let commands = vec![
+            DrawingCommand::Save,
+            DrawingCommand::Translate(Vec2::new(20.0, 0.0)),
+            DrawingCommand::SetFillColor([1.0, 0.0, 0.0, 1.0]),
+            DrawingCommand::MoveTo(Vec2::ZERO),
+            DrawingCommand::LineTo(Vec2::new(10.0, 0.0)),
+            DrawingCommand::LineTo(Vec2::new(10.0, 10.0)),
+            DrawingCommand::LineTo(Vec2::new(0.0, 10.0)),
+            DrawingCommand::Clip,
+            // Only the translated square survives the clip
+            DrawingCommand::DrawRect(Vec2::new(-20.0, 0.0), Vec2::new(40.0, 10.0)),
+            DrawingCommand::Restore,
+            DrawingCommand::DrawRect(Vec2::new(0.0, 10.0), Vec2::new(10.0, 10.0)),
+            DrawingCommand::Restore,
+        ];
+        let pixmap = render_commands(&commands, Vec2::new(40.0, 20.0), 1.0).unwrap();
+        assert_eq!(alpha(&pixmap, 5, 5), 0);
+        assert_eq!(pixmap.pixel(25, 5).unwrap().red(), 255);
+        let restored = pixmap.pixel(5, 15).unwrap();
+        assert_eq!((restored.red(), restored.alpha()), (0, 255));
+    }
+
+    #[test]
+    fn path_points_use_the_transform_they_were_added_with() {
+        let commands = vec![
+            DrawingCommand::Scale(Vec2::splat(2.0)),
+            DrawingCommand::MoveTo(Vec2::ZERO),
+            DrawingCommand::LineTo(Vec2::new(5.0, 0.0)),
+            DrawingCommand::LineTo(Vec2::new(5.0, 5.0)),
+            DrawingCommand::Scale(Vec2::splat(0.5)),
+            DrawingCommand::LineTo(Vec2::new(0.0, 10.0)),
+            DrawingCommand::SetGlobalAlpha(0.5),
+            DrawingCommand::Fill,
+        ];
+        let pixmap = render_commands(&commands, Vec2::splat(20.0), 1.0).unwrap();
+        assert!((126..=129).contains(&alpha(&pixmap, 5, 5)));
+        assert_eq!(alpha(&pixmap, 15, 5), 0);
+    }
+
+    #[test]
+    fn existing_documents_still_deserialize() {
+        let json = r#"[{"MoveTo":[1.0,2.0]},"ClosePath",{"SetStrokeWidth":2.0},{"DrawText":["hi",[0.0,0.0],12.0]}]"#;
+        let commands: Vec<DrawingCommand> = serde_json::from_str(json).unwrap();
+        assert_eq!(commands.len(), 4);
+
+        let gradient = DrawingCommand::SetFillGradient(Gradient::Radial {
+            center: Vec2::ZERO,
+            radius: 5.0,
+            stops: vec![GradientStop { offset: 0.0, color: [1.0; 4] }],
+        });
+        let json = serde_json::to_value(&gradient).unwrap();
+        assert_eq!(json["SetFillGradient"]["kind"], "radial");
+        assert!(matches!(serde_json::from_value(json).unwrap(), DrawingCommand::SetFillGradient(_)));
+    }
+}
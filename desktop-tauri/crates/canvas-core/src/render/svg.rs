@@ -0,0 +1,443 @@
+//! SVG export of a board.
+//!
+//! Skia drawings are embedded as images rasterized at the export scale.
+
+use std::fmt::Write;
+
+use crate::canvas::InfiniteCanvas;
+use crate::error::CanvasError;
+use crate::image_cache::{encode_png, ImageCache};
+use crate::markdown::{LineKind, Marker, NoteDocument, NoteLine, QUOTE_BAR_WIDTH};
+use crate::render::{
+    content_area, frame, image_rect, note_area, skia, ExportOptions, Scene, CODE_BACKGROUND, LINK_COLOR,
+    PLACEHOLDER_COLOR, TITLE_FONT_SIZE, TITLE_INSET,
+};
+use crate::tiles::{DrawingCommand, Tile, TileData};
+
+/// Renders the visible tiles of `canvas` as a standalone SVG document.
+pub fn to_svg(canvas: &InfiniteCanvas, options: &ExportOptions) -> Result<String, CanvasError> {
+    let scene = Scene::new(canvas, options)?;
+    let area = scene.area;
+    let mut svg = String::new();
+
+    // Writing into a String cannot fail
+    let _ = writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}">"#,
+        scene.size.x,
+        scene.size.y,
+        area.min.x,
+        area.min.y,
+        area.size().x,
+        area.size().y,
+    );
+
+    if options.background {
+        let _ = writeln!(
+            svg,
+            r#"  <rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+            area.min.x,
+            area.min.y,
+            area.size().x,
+            area.size().y,
+            paint("fill", canvas.background_color),
+        );
+    }
+
+    let mut images = ImageCache::new();
+    for (index, (_, tile)) in scene.tiles.iter().enumerate() {
+        write_tile(&mut svg, index, tile, &mut images, options.scale)?;
+    }
+
+    svg.push_str("</svg>\n");
+    Ok(svg)
+}
+
+fn write_tile(
+    svg: &mut String,
+    index: usize,
+    tile: &Tile,
+    images: &mut ImageCache,
+    scale: f32,
+) -> Result<(), CanvasError> {
+    let style = &tile.style;
+    let (origin, size) = frame(tile);
+    let radius = style.effective_radius(size);
+    let rect = format!(
+        r#"x="{}" y="{}" width="{}" height="{}" rx="{}""#,
+        origin.x, origin.y, size.x, size.y, radius,
+    );
+
+    let _ = write!(
+        svg,
+        r#"  <g transform="rotate({} {} {})""#,
+        tile.rotation.to_degrees(),
+        tile.position.x,
+        tile.position.y,
+    );
+    if style.opacity < 1.0 {
+        let _ = write!(svg, r#" opacity="{}""#, style.opacity);
+    }
+    svg.push_str(">\n");
+
+    if let Some(shadow) = &style.shadow {
+        let id = format!("shadow-{}", index);
+        let _ = writeln!(
+            svg,
+            r#"    <filter id="{}" x="-50%" y="-50%" width="200%" height="200%"><feGaussianBlur stdDeviation="{}"/></filter>"#,
+            id,
+            shadow.blur * 0.5,
+        );
+        let _ = writeln!(
+            svg,
+            r#"    <rect {} transform="translate({} {})" {} filter="url(#{})"/>"#,
+            rect,
+            shadow.offset.x,
+            shadow.offset.y,
+            paint("fill", shadow.color),
+            id,
+        );
+    }
+
+    let _ = writeln!(svg, r#"    <rect {} {}/>"#, rect, paint("fill", style.background));
+
+    if matches!(tile.data, TileData::Note { .. } | TileData::Image { .. })
+        || matches!(&tile.data, TileData::Skia { drawing_commands } if !drawing_commands.is_empty())
+    {
+        // Content stays inside the rounded frame
+        let _ = writeln!(svg, r#"    <clipPath id="clip-{}"><rect {}/></clipPath>"#, index, rect);
+        let _ = writeln!(svg, r#"    <g clip-path="url(#clip-{})">"#, index);
+        match &tile.data {
+            TileData::Note { markdown } => write_note(svg, tile, markdown),
+            TileData::Image { path, .. } => write_image(svg, tile, path, images, scale)?,
+            TileData::Skia { drawing_commands } => write_drawing(svg, tile, drawing_commands, scale)?,
+            _ => {}
+        }
+        svg.push_str("    </g>\n");
+    }
+
+    let title_bar = style.title_bar_height(size);
+    if title_bar > 0.0 {
+        let r = radius.min(title_bar);
+        let (x, y, right) = (origin.x, origin.y, origin.x + size.x);
+        let _ = writeln!(
+            svg,
+            r#"    <path d="M {} {} H {} A {r} {r} 0 0 1 {} {} V {} H {} V {} A {r} {r} 0 0 1 {} {} Z" {}/>"#,
+            x + r, y, right - r, right, y + r, y + title_bar, x, y + r, x + r, y,
+            paint("fill", style.title_bar_color),
+        );
+        let _ = writeln!(
+            svg,
+            r#"    <text x="{}" y="{}" font-family="sans-serif" font-size="{}" dominant-baseline="middle" {}>{}</text>"#,
+            x + TITLE_INSET,
+            y + title_bar * 0.5,
+            TITLE_FONT_SIZE,
+            paint("fill", style.title_color),
+            escape(&tile.title),
+        );
+    }
+
+    if style.border_width > 0.0 {
+        let _ = writeln!(
+            svg,
+            r#"    <rect {} fill="none" {} stroke-width="{}"/>"#,
+            rect,
+            paint("stroke", style.border_color),
+            style.border_width,
+        );
+    }
+
+    svg.push_str("  </g>\n");
+    Ok(())
+}
+
+/// Embeds the mip level of the image file closest to its size in the
+/// output, or draws a crossed out placeholder naming the file.
+fn write_image(
+    svg: &mut String,
+    tile: &Tile,
+    path: &str,
+    images: &mut ImageCache,
+    scale: f32,
+) -> Result<(), CanvasError> {
+    let chain = match images.get(std::path::Path::new(path)) {
+        Ok(chain) => chain,
+        Err(_) => {
+            write_placeholder(svg, tile, path);
+            return Ok(());
+        }
+    };
+    let (origin, size) = image_rect(tile, chain.natural_size());
+    let level = &chain.levels()[chain.level_for(size.max_element() * scale)];
+    let _ = writeln!(
+        svg,
+        r#"      <image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" href="data:image/png;base64,{}"/>"#,
+        origin.x, origin.y, size.x, size.y, base64(&encode_png(level)?),
+    );
+    Ok(())
+}
+
+fn write_drawing(svg: &mut String, tile: &Tile, commands: &[DrawingCommand], scale: f32) -> Result<(), CanvasError> {
+    let (origin, size) = content_area(tile);
+    let png = skia::render_commands(commands, size, scale)?
+        .encode_png()
+        .map_err(|e| CanvasError::Internal { message: format!("failed to encode PNG: {}", e) })?;
+    let _ = writeln!(
+        svg,
+        r#"      <image x="{}" y="{}" width="{}" height="{}" preserveAspectRatio="none" href="data:image/png;base64,{}"/>"#,
+        origin.x, origin.y, size.x, size.y, base64(&png),
+    );
+    Ok(())
+}
+
+fn write_placeholder(svg: &mut String, tile: &Tile, path: &str) {
+    let (origin, size) = frame(tile);
+    let top = origin.y + tile.style.title_bar_height(size);
+    let (left, right, bottom) = (origin.x, origin.x + size.x, origin.y + size.y);
+    let _ = writeln!(
+        svg,
+        r#"      <rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+        left, top, size.x, bottom - top, paint("fill", PLACEHOLDER_COLOR),
+    );
+    let _ = writeln!(
+        svg,
+        r#"      <path d="M {} {} L {} {} M {} {} L {} {}" {} stroke-width="1.5"/>"#,
+        left, top, right, bottom, right, top, left, bottom, paint("stroke", tile.style.border_color),
+    );
+    let name = std::path::Path::new(path).file_name().map_or(path.into(), |name| name.to_string_lossy());
+    let _ = writeln!(
+        svg,
+        r#"      <text x="{}" y="{}" font-family="sans-serif" font-size="12" text-anchor="middle" dominant-baseline="middle" {}>Missing image: {}</text>"#,
+        (left + right) * 0.5, (top + bottom) * 0.5, paint("fill", tile.style.title_color), escape(&name),
+    );
+}
+
+fn write_note(svg: &mut String, tile: &Tile, markdown: &str) {
+    let (origin, width) = note_area(tile);
+    let ink = tile.style.title_color;
+    for line in NoteDocument::parse(markdown).lines() {
+        let top = origin.y + line.top;
+        let middle = top + line.height * 0.5;
+        for bar in &line.quote_bars {
+            let _ = writeln!(
+                svg,
+                r#"      <rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+                origin.x + bar, top, QUOTE_BAR_WIDTH, line.height, paint("fill", tile.style.border_color),
+            );
+        }
+        match line.kind {
+            LineKind::Code => {
+                let _ = writeln!(
+                    svg,
+                    r#"      <rect x="{}" y="{}" width="{}" height="{}" {}/>"#,
+                    origin.x + line.indent, top, (width - line.indent).max(0.0), line.height,
+                    paint("fill", CODE_BACKGROUND),
+                );
+            }
+            LineKind::Rule => {
+                let _ = writeln!(
+                    svg,
+                    r#"      <line x1="{}" y1="{}" x2="{}" y2="{}" {} stroke-width="1"/>"#,
+                    origin.x + line.indent, middle, origin.x + width, middle, paint("stroke", tile.style.border_color),
+                );
+            }
+            LineKind::Text | LineKind::Heading { .. } => {}
+        }
+        write_marker(svg, &line, origin.x, origin.y, ink);
+
+        if line.spans.is_empty() {
+            continue;
+        }
+        let weight = if matches!(line.kind, LineKind::Heading { .. }) { r#" font-weight="bold""# } else { "" };
+        let _ = write!(
+            svg,
+            r#"      <text x="{}" y="{}" font-family="sans-serif" font-size="{}" dominant-baseline="middle" xml:space="preserve"{} {}>"#,
+            origin.x + line.indent, middle, line.font_size, weight, paint("fill", ink),
+        );
+        for span in &line.spans {
+            let mut attributes = String::new();
+            if span.bold {
+                attributes.push_str(r#" font-weight="bold""#);
+            }
+            if span.italic {
+                attributes.push_str(r#" font-style="italic""#);
+            }
+            if span.code {
+                attributes.push_str(r#" font-family="monospace""#);
+            }
+            let decoration = match (span.link.is_some(), span.strikethrough) {
+                (true, true) => Some("underline line-through"),
+                (true, false) => Some("underline"),
+                (false, true) => Some("line-through"),
+                (false, false) => None,
+            };
+            if let Some(decoration) = decoration {
+                let _ = write!(attributes, r#" text-decoration="{}""#, decoration);
+            }
+            if span.link.is_some() {
+                let _ = write!(attributes, " {}", paint("fill", LINK_COLOR));
+            }
+            let _ = write!(svg, "<tspan{}>{}</tspan>", attributes, escape(&span.text));
+        }
+        svg.push_str("</text>\n");
+    }
+}
+
+fn write_marker(svg: &mut String, line: &NoteLine, x: f32, y: f32, ink: [f32; 4]) {
+    let (Some(marker), Some(([left, top], size))) = (line.marker, line.marker_box()) else {
+        return;
+    };
+    let (left, top) = (x + left, y + top);
+    match marker {
+        Marker::Bullet => {
+            let _ = writeln!(
+                svg,
+                r#"      <circle cx="{}" cy="{}" r="{}" {}/>"#,
+                left + size * 0.5, top + size * 0.5, size * 0.2, paint("fill", ink),
+            );
+        }
+        Marker::Number { value } => {
+            let _ = writeln!(
+                svg,
+                r#"      <text x="{}" y="{}" font-family="sans-serif" font-size="{}" text-anchor="end" dominant-baseline="middle" {}>{}.</text>"#,
+                left + size, top + size * 0.5, line.font_size, paint("fill", ink), value,
+            );
+        }
+        Marker::Checkbox { checked, .. } => {
+            let _ = writeln!(
+                svg,
+                r#"      <rect x="{}" y="{}" width="{}" height="{}" rx="2" fill="none" {} stroke-width="1.5"/>"#,
+                left, top, size, size, paint("stroke", ink),
+            );
+            if checked {
+                let _ = writeln!(
+                    svg,
+                    r#"      <path d="M {} {} L {} {} L {} {}" fill="none" {} stroke-width="1.5"/>"#,
+                    left + size * 0.2, top + size * 0.5,
+                    left + size * 0.42, top + size * 0.75,
+                    left + size * 0.8, top + size * 0.25,
+                    paint("stroke", ink),
+                );
+            }
+        }
+    }
+}
+
+/// `fill`/`stroke` attributes for an RGBA color.
+fn paint(attribute: &str, [r, g, b, a]: [f32; 4]) -> String {
+    let channel = |c: f32| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let mut paint = format!(
+        r#"{}="rgb({},{},{})""#,
+        attribute,
+        channel(r),
+        channel(g),
+        channel(b),
+    );
+    if a < 1.0 {
+        let _ = write!(paint, r#" {}-opacity="{}""#, attribute, a);
+    }
+    paint
+}
+
+fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut encoded = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let triple = chunk.iter().enumerate().fold(0u32, |acc, (i, byte)| acc | (*byte as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                encoded.push(ALPHABET[(triple >> (18 - 6 * i) & 0x3f) as usize] as char);
+            } else {
+                encoded.push('=');
+            }
+        }
+    }
+    encoded
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+
+    use super::*;
+    use crate::tiles::{DropShadow, ImageTile, NoteTile, SkiaTile};
+
+    fn canvas_with(tile: Tile) -> InfiniteCanvas {
+        let mut canvas = InfiniteCanvas::default();
+        canvas.add_tile(tile);
+        canvas
+    }
+
+    #[test]
+    fn tiles_use_their_style() {
+        let mut tile = SkiaTile::new().to_tile(Vec2::ZERO, Vec2::new(200.0, 100.0), "Q&A <draft>".into());
+        tile.style.corner_radius = 12.0;
+        tile.style.opacity = 0.5;
+        tile.style.background = [1.0, 0.0, 0.0, 1.0];
+        tile.style.shadow = Some(DropShadow { offset: Vec2::new(4.0, 4.0), blur: 8.0, color: [0.0, 0.0, 0.0, 0.25] });
+
+        let svg = to_svg(&canvas_with(tile), &ExportOptions::default()).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains(r#"viewBox="-137 -87 282 182""#), "{}", svg);
+        assert!(svg.contains(r#"rx="12""#));
+        assert!(svg.contains(r#"opacity="0.5""#));
+        assert!(svg.contains(r#"fill="rgb(255,0,0)""#));
+        assert!(svg.contains(r#"<feGaussianBlur stdDeviation="4"/>"#));
+        assert!(svg.contains(r#"fill-opacity="0.25""#));
+        assert!(svg.contains("Q&amp;A &lt;draft&gt;"));
+    }
+
+    #[test]
+    fn hidden_title_bar_and_border_are_skipped() {
+        let mut tile = SkiaTile::new().to_tile(Vec2::ZERO, Vec2::new(200.0, 100.0), "Plain".into());
+        tile.style.show_title_bar = false;
+        tile.style.border_width = 0.0;
+
+        let svg = to_svg(&canvas_with(tile), &ExportOptions { background: false, ..Default::default() }).unwrap();
+        assert!(!svg.contains("<text"));
+        assert!(!svg.contains("stroke"));
+        assert_eq!(svg.matches("<rect").count(), 1);
+    }
+
+    #[test]
+    fn notes_draw_their_lines() {
+        let markdown = "# Plan\n\n- [x] **ship** it\n- [ ] see [docs](https://a.example?b&c)\n\n```\ncargo test\n```\n";
+        let tile = NoteTile::new(markdown.into()).to_tile(Vec2::ZERO, Vec2::new(300.0, 300.0), "Note".into());
+
+        let svg = to_svg(&canvas_with(tile), &ExportOptions::default()).unwrap();
+        assert!(svg.contains(r#"<clipPath id="clip-0">"#));
+        assert!(svg.contains(r#"font-size="26" dominant-baseline="middle" xml:space="preserve" font-weight="bold" fill="rgb(246,246,246)"><tspan>Plan</tspan>"#), "{}", svg);
+        assert!(svg.contains(r#"<tspan font-weight="bold">ship</tspan><tspan> it</tspan>"#));
+        assert!(svg.contains(r#"<tspan text-decoration="underline" fill="rgb(77,163,255)">docs</tspan>"#));
+        assert!(svg.contains(r#"<tspan font-family="monospace">cargo test</tspan>"#));
+        // Two checkboxes, one of them ticked
+        assert_eq!(svg.matches(r#"rx="2" fill="none""#).count(), 2);
+        assert_eq!(svg.matches("<path d=\"M").count(), 2);
+    }
+
+    #[test]
+    fn base64_pads_partial_chunks() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+
+    #[test]
+    fn missing_images_name_the_file() {
+        let tile = ImageTile::new("/no/such/dir/holiday <1>.jpg".into())
+            .to_tile(Vec2::ZERO, Vec2::new(200.0, 100.0), "Photo".into());
+        assert!(tile.constraints.aspect_ratio.is_none());
+
+        let svg = to_svg(&canvas_with(tile), &ExportOptions::default()).unwrap();
+        assert!(svg.contains("Missing image: holiday &lt;1&gt;.jpg"), "{}", svg);
+        assert!(!svg.contains("<image"));
+    }
+}
@@ -0,0 +1,492 @@
+//! Rendered drawings of Skia tiles, kept between frames.
+//!
+//! Drawings are rendered at mip levels, power of two scales: a request gets
+//! the smallest level at least as sharp as it asked for, so zooming only
+//! renders again when it crosses a level. A [`RenderCache`] entry is the
+//! drawing of one tile at one level and knows the content revision it
+//! shows. When the commands of a tile change, the cache compares what the
+//! old and the new commands paint and marks only the area where they differ
+//! as dirty; each cached level repaints that area in place the next time it
+//! is used. Entries beyond the memory budget are evicted, least recently
+//! used first.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use tiny_skia::{BlendMode, Path, Pixmap, PixmapPaint, Stroke, Transform};
+
+use crate::error::CanvasError;
+use crate::geometry::Rect;
+use crate::render::skia::{draw_commands, render_commands, run_commands, Brush, DrawState, Painter};
+use crate::render::{content_area, MAX_EXPORT_DIMENSION};
+use crate::shapes::outline_bounds;
+use crate::tiles::{DrawingCommand, Tile, TileData, TileId};
+
+/// Memory budget of a new cache, in bytes of pixels and encoded PNGs.
+pub const DEFAULT_RENDER_BUDGET: usize = 256 * 1024 * 1024;
+/// Mip level of the lowest scale drawings are rendered at, 1/8.
+pub const MIN_MIP_LEVEL: i32 = -3;
+/// Mip level of the highest scale drawings are rendered at, 16.
+pub const MAX_MIP_LEVEL: i32 = 4;
+
+/// The level of the smallest power of two scale that is at least `scale`,
+/// within [`MIN_MIP_LEVEL`] and [`MAX_MIP_LEVEL`].
+pub fn mip_level(scale: f32) -> i32 {
+    (scale.log2().ceil() as i32).clamp(MIN_MIP_LEVEL, MAX_MIP_LEVEL)
+}
+
+/// Output pixels per world unit at mip `level`.
+pub fn level_scale(level: i32) -> f32 {
+    2f32.powi(level)
+}
+
+/// What a [`RenderCache`] holds and how well it has been doing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RenderCacheStats {
+    /// Tiles whose commands are known.
+    pub tiles: usize,
+    /// Rendered tile and mip level pairs.
+    pub entries: usize,
+    pub bytes: usize,
+    pub budget: usize,
+    /// Requests served from an up to date entry.
+    pub hits: u64,
+    /// Requests that rendered a whole drawing.
+    pub misses: u64,
+    /// Requests served from an entry after repainting its dirty area.
+    pub repaints: u64,
+    pub evictions: u64,
+}
+
+/// The commands of a tile as last seen, and what they paint.
+struct TileRecord {
+    commands: Vec<DrawingCommand>,
+    size: Vec2,
+    revision: u64,
+    ops: Vec<PaintOp>,
+}
+
+struct Entry {
+    /// Revision of the tile the pixels show, apart from `dirty`.
+    revision: u64,
+    pixmap: Pixmap,
+    png: Option<Arc<Vec<u8>>>,
+    /// Area in world units that changed since the pixels were painted.
+    dirty: Option<Rect>,
+    /// Value of the cache's clock when the entry was last used.
+    last_used: u64,
+}
+
+impl Entry {
+    fn bytes(&self) -> usize {
+        self.pixmap.data().len() + self.png.as_ref().map_or(0, |png| png.len())
+    }
+}
+
+/// Rendered drawings by tile and mip level.
+pub struct RenderCache {
+    tiles: HashMap<TileId, TileRecord>,
+    entries: HashMap<(TileId, i32), Entry>,
+    budget: usize,
+    /// Counts requests, to find the least recently used entry.
+    clock: u64,
+    hits: u64,
+    misses: u64,
+    repaints: u64,
+    evictions: u64,
+}
+
+impl Default for RenderCache {
+    fn default() -> Self {
+        Self::with_budget(DEFAULT_RENDER_BUDGET)
+    }
+}
+
+impl RenderCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// An empty cache that keeps at most `budget` bytes, apart from the
+    /// entry used last, which is kept even if it is larger.
+    pub fn with_budget(budget: usize) -> Self {
+        Self {
+            tiles: HashMap::new(),
+            entries: HashMap::new(),
+            budget,
+            clock: 0,
+            hits: 0,
+            misses: 0,
+            repaints: 0,
+            evictions: 0,
+        }
+    }
+
+    /// The drawing of the Skia tile `tile` at the mip level for `scale`,
+    /// or a lower one if that would be larger than
+    /// [`MAX_EXPORT_DIMENSION`]. Its scale is the width of the pixmap over
+    /// the width of the content area.
+    pub fn render(&mut self, tile_id: TileId, tile: &Tile, scale: f32) -> Result<&Pixmap, CanvasError> {
+        let key = self.update(tile_id, tile, scale)?;
+        Ok(&self.entries[&key].pixmap)
+    }
+
+    /// [`RenderCache::render`], encoded as PNG. The encoding is cached
+    /// along with the pixels.
+    pub fn png(&mut self, tile_id: TileId, tile: &Tile, scale: f32) -> Result<Arc<Vec<u8>>, CanvasError> {
+        let key = self.update(tile_id, tile, scale)?;
+        let entry = self.entries.get_mut(&key).expect("entry was just rendered");
+        if let Some(png) = &entry.png {
+            return Ok(png.clone());
+        }
+        let png = entry.pixmap
+            .encode_png()
+            .map_err(|e| CanvasError::Internal { message: format!("failed to encode PNG: {}", e) })?;
+        let png = Arc::new(png);
+        entry.png = Some(png.clone());
+        self.evict(key);
+        Ok(png)
+    }
+
+    /// Forgets everything about `tile_id`, e.g. after it was removed.
+    pub fn remove(&mut self, tile_id: TileId) {
+        self.tiles.remove(&tile_id);
+        self.entries.retain(|(id, _), _| *id != tile_id);
+    }
+
+    /// Forgets every tile `keep` rejects.
+    pub fn retain(&mut self, mut keep: impl FnMut(TileId) -> bool) {
+        self.tiles.retain(|tile_id, _| keep(*tile_id));
+        let tiles = &self.tiles;
+        self.entries.retain(|(tile_id, _), _| tiles.contains_key(tile_id));
+    }
+
+    /// Changes the memory budget, evicting entries until they fit.
+    pub fn set_budget(&mut self, budget: usize) {
+        self.budget = budget;
+        self.evict_all_but(None);
+    }
+
+    pub fn stats(&self) -> RenderCacheStats {
+        RenderCacheStats {
+            tiles: self.tiles.len(),
+            entries: self.entries.len(),
+            bytes: self.bytes(),
+            budget: self.budget,
+            hits: self.hits,
+            misses: self.misses,
+            repaints: self.repaints,
+            evictions: self.evictions,
+        }
+    }
+
+    fn bytes(&self) -> usize {
+        self.entries.values().map(Entry::bytes).sum()
+    }
+
+    /// Brings the entry for `tile` at the level for `scale` up to date and
+    /// returns its key.
+    fn update(&mut self, tile_id: TileId, tile: &Tile, scale: f32) -> Result<(TileId, i32), CanvasError> {
+        let TileData::Skia { drawing_commands } = &tile.data else {
+            return Err(CanvasError::invalid_argument("tile_id", "is not a Skia tile"));
+        };
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(CanvasError::invalid_argument("scale", "must be positive"));
+        }
+        let (_, size) = content_area(tile);
+        self.refresh(tile_id, drawing_commands, size);
+        let record = &self.tiles[&tile_id];
+
+        let mut level = mip_level(scale);
+        while level > MIN_MIP_LEVEL && (size * level_scale(level)).max_element() > MAX_EXPORT_DIMENSION {
+            level -= 1;
+        }
+        let key = (tile_id, level);
+        self.clock += 1;
+        match self.entries.get_mut(&key) {
+            Some(entry) if entry.revision == record.revision => self.hits += 1,
+            Some(entry) => {
+                if let Some(dirty) = entry.dirty.take() {
+                    repaint(&mut entry.pixmap, &record.commands, level_scale(level), dirty);
+                    entry.png = None;
+                }
+                entry.revision = record.revision;
+                self.repaints += 1;
+            }
+            None => {
+                let pixmap = render_commands(&record.commands, record.size, level_scale(level))?;
+                let entry = Entry { revision: record.revision, pixmap, png: None, dirty: None, last_used: 0 };
+                self.entries.insert(key, entry);
+                self.misses += 1;
+            }
+        }
+        if let Some(entry) = self.entries.get_mut(&key) {
+            entry.last_used = self.clock;
+        }
+        self.evict(key);
+        Ok(key)
+    }
+
+    /// Records the current commands and size of a tile. Changed commands
+    /// mark what they paint differently as dirty in every entry of the
+    /// tile, a changed size drops its entries.
+    fn refresh(&mut self, tile_id: TileId, commands: &[DrawingCommand], size: Vec2) {
+        match self.tiles.get_mut(&tile_id) {
+            Some(record) if record.size == size && record.commands == commands => {}
+            Some(record) if record.size == size => {
+                let ops = paint_ops(commands);
+                let changed = changed_area(&record.ops, &ops);
+                record.commands = commands.to_vec();
+                record.ops = ops;
+                record.revision += 1;
+                if let Some(changed) = changed {
+                    for ((_, _), entry) in self.entries.iter_mut().filter(|((id, _), _)| *id == tile_id) {
+                        entry.dirty = Some(entry.dirty.map_or(changed, |dirty| dirty.union(&changed)));
+                    }
+                }
+            }
+            previous => {
+                let revision = previous.map_or(0, |record| record.revision + 1);
+                self.entries.retain(|(id, _), _| *id != tile_id);
+                let record = TileRecord { commands: commands.to_vec(), size, revision, ops: paint_ops(commands) };
+                self.tiles.insert(tile_id, record);
+            }
+        }
+    }
+
+    fn evict(&mut self, keep: (TileId, i32)) {
+        self.evict_all_but(Some(keep));
+    }
+
+    /// Evicts the least recently used entries other than `keep` until the
+    /// rest fit into the budget.
+    fn evict_all_but(&mut self, keep: Option<(TileId, i32)>) {
+        let mut bytes = self.bytes();
+        while bytes > self.budget {
+            let oldest = self.entries
+                .iter()
+                .filter(|(key, _)| Some(**key) != keep)
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key);
+            let Some(oldest) = oldest else { break };
+            if let Some(entry) = self.entries.remove(&oldest) {
+                bytes -= entry.bytes();
+                self.evictions += 1;
+            }
+        }
+    }
+}
+
+/// Something a drawing paints, with everything that decides its pixels.
+#[derive(Clone, PartialEq)]
+struct PaintOp {
+    shape: Path,
+    transform: Transform,
+    brush: Brush,
+    alpha: f32,
+    stroke: Option<Stroke>,
+    /// The `Clip` paths in effect, in the content area.
+    clips: Arc<Vec<Option<Path>>>,
+}
+
+impl PaintOp {
+    fn bounds(&self) -> Option<Rect> {
+        outline_bounds(&self.shape, self.transform, self.stroke.as_ref())
+    }
+}
+
+/// Collects the [`PaintOp`]s of a drawing.
+struct Recorder<'a> {
+    commands: &'a [DrawingCommand],
+    ops: Vec<PaintOp>,
+    clips: Arc<Vec<Option<Path>>>,
+    saved: Vec<Arc<Vec<Option<Path>>>>,
+}
+
+impl Recorder<'_> {
+    fn push(&mut self, state: &DrawState, shape: &Path, stroke: bool) {
+        self.ops.push(PaintOp {
+            shape: shape.clone(),
+            transform: state.transform,
+            brush: if stroke { state.stroke_brush.clone() } else { state.fill.clone() },
+            alpha: state.alpha,
+            stroke: stroke.then(|| state.stroke.clone()),
+            clips: self.clips.clone(),
+        });
+    }
+}
+
+impl Painter for Recorder<'_> {
+    fn command(&mut self, index: usize, _state: &DrawState) {
+        // The state keeps the clip as a mask, which only rasterizing makes
+        match self.commands[index] {
+            DrawingCommand::Save => self.saved.push(self.clips.clone()),
+            DrawingCommand::Restore => {
+                if let Some(clips) = self.saved.pop() {
+                    self.clips = clips;
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn fill(&mut self, _index: usize, state: &DrawState, shape: &Path) {
+        self.push(state, shape, false);
+    }
+
+    fn stroke(&mut self, _index: usize, state: &DrawState, shape: &Path) {
+        self.push(state, shape, true);
+    }
+
+    fn clip(&mut self, _state: &mut DrawState, shape: Option<&Path>) {
+        Arc::make_mut(&mut self.clips).push(shape.cloned());
+    }
+}
+
+fn paint_ops(commands: &[DrawingCommand]) -> Vec<PaintOp> {
+    let mut recorder = Recorder { commands, ops: Vec::new(), clips: Arc::default(), saved: Vec::new() };
+    run_commands(commands, &mut recorder);
+    recorder.ops
+}
+
+/// Where pixels of `old` and `new` may differ: around everything painted
+/// between their common start and their common end. Outside of it, both
+/// paint the same things in the same order.
+fn changed_area(old: &[PaintOp], new: &[PaintOp]) -> Option<Rect> {
+    let prefix = old.iter().zip(new).take_while(|(old, new)| old == new).count();
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let suffix = old.iter().rev().zip(new.iter().rev()).take_while(|(old, new)| old == new).count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+    old.iter().chain(new).filter_map(PaintOp::bounds).reduce(|area, bounds| area.union(&bounds))
+}
+
+/// Paints `commands` again inside `area`, in world units, with a pixel to
+/// spare for anti-aliasing.
+fn repaint(pixmap: &mut Pixmap, commands: &[DrawingCommand], scale: f32, area: Rect) {
+    let left = ((area.min.x * scale).floor() - 1.0).max(0.0) as i32;
+    let top = ((area.min.y * scale).floor() - 1.0).max(0.0) as i32;
+    let right = ((area.max.x * scale).ceil() + 1.0).min(pixmap.width() as f32) as i32;
+    let bottom = ((area.max.y * scale).ceil() + 1.0).min(pixmap.height() as f32) as i32;
+    if right <= left || bottom <= top {
+        return;
+    }
+    let Some(mut patch) = Pixmap::new((right - left) as u32, (bottom - top) as u32) else { return };
+    let base = Transform::from_scale(scale, scale).post_translate(-left as f32, -top as f32);
+    draw_commands(&mut patch, base, None, commands);
+    let paint = PixmapPaint { blend_mode: BlendMode::Source, ..PixmapPaint::default() };
+    pixmap.draw_pixmap(left, top, patch.as_ref(), &paint, Transform::identity(), None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    use crate::tiles::SkiaTile;
+
+    fn skia_tile(commands: Vec<DrawingCommand>) -> Tile {
+        let mut tile = SkiaTile { drawing_commands: commands }.to_tile(Vec2::ZERO, Vec2::new(100.0, 100.0), String::new());
+        tile.style.show_title_bar = false;
+        tile
+    }
+
+    fn dot(center: Vec2, color: [f32; 4]) -> Vec<DrawingCommand> {
+        vec![
+            DrawingCommand::Save,
+            DrawingCommand::SetFillColor(color),
+            DrawingCommand::DrawCircle(center, 10.0),
+            DrawingCommand::Restore,
+        ]
+    }
+
+    fn assert_area(area: Option<Rect>, min: [f32; 2], max: [f32; 2]) {
+        let area = area.unwrap();
+        assert!(area.min.abs_diff_eq(min.into(), 1e-3) && area.max.abs_diff_eq(max.into(), 1e-3), "{:?}", area);
+    }
+
+    fn set_commands(tile: &mut Tile, commands: Vec<DrawingCommand>) {
+        tile.data = TileData::Skia { drawing_commands: commands };
+    }
+
+    #[test]
+    fn scales_round_up_to_mip_levels() {
+        assert_eq!(mip_level(1.0), 0);
+        assert_eq!(mip_level(1.01), 1);
+        assert_eq!(mip_level(0.5), -1);
+        assert_eq!(mip_level(0.3), -1);
+        assert_eq!(mip_level(0.001), MIN_MIP_LEVEL);
+        assert_eq!(mip_level(1000.0), MAX_MIP_LEVEL);
+        assert_eq!(level_scale(-2), 0.25);
+
+        let id = TileId(Uuid::new_v4());
+        let tile = skia_tile(dot(Vec2::splat(50.0), [1.0, 0.0, 0.0, 1.0]));
+        let mut cache = RenderCache::new();
+        assert_eq!(cache.render(id, &tile, 1.5).unwrap().width(), 200);
+        assert_eq!(cache.render(id, &tile, 1.2).unwrap().width(), 200);
+        assert_eq!(cache.render(id, &tile, 0.7).unwrap().width(), 100);
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.misses, stats.hits), (2, 2, 1));
+        assert!(cache.render(id, &tile, 0.0).is_err());
+    }
+
+    #[test]
+    fn changed_commands_repaint_only_what_they_changed() {
+        let id = TileId(Uuid::new_v4());
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let mut commands = dot(Vec2::new(20.0, 20.0), red);
+        commands.extend(dot(Vec2::new(80.0, 80.0), [0.0, 0.0, 1.0, 1.0]));
+        let mut tile = skia_tile(commands.clone());
+        let mut cache = RenderCache::new();
+        cache.render(id, &tile, 2.0).unwrap();
+
+        // Moving the first dot leaves the second one's pixels alone
+        let old = paint_ops(&commands);
+        commands.splice(0..4, dot(Vec2::new(30.0, 20.0), red));
+        assert_area(changed_area(&old, &paint_ops(&commands)), [10.0, 10.0], [40.0, 30.0]);
+
+        set_commands(&mut tile, commands.clone());
+        let repainted = cache.render(id, &tile, 2.0).unwrap().clone();
+        let fresh = render_commands(&commands, Vec2::new(100.0, 100.0), 2.0).unwrap();
+        let differences = repainted.data().iter().zip(fresh.data()).filter(|(a, b)| a.abs_diff(**b) > 1).count();
+        assert_eq!(differences, 0);
+        let stats = cache.stats();
+        assert_eq!((stats.misses, stats.repaints), (1, 1));
+
+        // Appending paints only the new shape; a state change that moves
+        // nothing paints nothing
+        let old = paint_ops(&commands);
+        commands.extend(dot(Vec2::new(50.0, 50.0), red));
+        assert_area(changed_area(&old, &paint_ops(&commands)), [40.0, 40.0], [60.0, 60.0]);
+        let old = paint_ops(&commands);
+        commands.push(DrawingCommand::SetFillColor(red));
+        assert_eq!(changed_area(&old, &paint_ops(&commands)), None);
+    }
+
+    #[test]
+    fn least_recently_used_entries_are_evicted_over_budget() {
+        let tile = skia_tile(dot(Vec2::splat(50.0), [1.0, 0.0, 0.0, 1.0]));
+        let (a, b, c) = (TileId(Uuid::new_v4()), TileId(Uuid::new_v4()), TileId(Uuid::new_v4()));
+        // Room for two 100x100 renderings
+        let mut cache = RenderCache::with_budget(2 * 100 * 100 * 4);
+        cache.render(a, &tile, 1.0).unwrap();
+        cache.render(b, &tile, 1.0).unwrap();
+        cache.render(a, &tile, 1.0).unwrap();
+        cache.render(c, &tile, 1.0).unwrap();
+
+        let stats = cache.stats();
+        assert_eq!((stats.entries, stats.evictions, stats.bytes), (2, 1, 2 * 100 * 100 * 4));
+        assert!(cache.entries.contains_key(&(a, 0)) && !cache.entries.contains_key(&(b, 0)));
+
+        // Resizing starts the tile over, removing forgets it
+        let mut resized = tile.clone();
+        resized.size = Vec2::new(50.0, 50.0);
+        assert_eq!(cache.render(a, &resized, 1.0).unwrap().width(), 50);
+        assert_eq!(cache.stats().misses, 4);
+        cache.remove(c);
+        cache.set_budget(0);
+        let stats = cache.stats();
+        assert_eq!((stats.tiles, stats.entries), (2, 0));
+    }
+}
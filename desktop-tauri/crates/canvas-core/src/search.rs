@@ -0,0 +1,488 @@
+//! Full-text search over the text content of tiles.
+//!
+//! [`SearchIndex`] keeps an inverted index from lowercase words to tiles.
+//! It is kept up to date with the [`AppliedBatch`] of every change, so a
+//! search never rescans the board. Only tiles whose indexed text changed
+//! are re-tokenized, so keeping it next to a canvas is cheap even when most
+//! edits are moves and resizes.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::canvas::{AppliedBatch, Camera, InfiniteCanvas};
+use crate::geometry::Rect;
+use crate::markdown::{NoteDocument, NoteLine};
+use crate::tiles::{DrawingCommand, Tile, TileData, TileId};
+
+/// Screen space margin kept around a result when framing it.
+const FRAME_PADDING: f32 = 48.0;
+
+/// Where in a tile a piece of indexed text comes from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum SearchField {
+    Title,
+    /// URL of a webview tile.
+    Url,
+    /// File path of an image tile, or working directory of a terminal.
+    Path,
+    /// A `DrawText` or `DrawTextBox` command of a Skia tile.
+    Text { command_index: usize },
+    /// A string in the config of an egui tile, addressed by JSON pointer.
+    Config { path: String },
+    /// A laid out line of a note tile, see [`NoteDocument::lines`].
+    Note { line: usize },
+}
+
+impl SearchField {
+    fn weight(&self) -> f32 {
+        match self {
+            SearchField::Title => 4.0,
+            SearchField::Text { .. } | SearchField::Note { .. } => 3.0,
+            SearchField::Url | SearchField::Path => 2.0,
+            SearchField::Config { .. } => 1.0,
+        }
+    }
+}
+
+/// A field of a matching tile with the parts to highlight.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchHit {
+    pub field: SearchField,
+    pub text: String,
+    /// `[start, end)` offsets in characters (Unicode scalar values) into
+    /// `text`.
+    pub ranges: Vec<[usize; 2]>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SearchMatch {
+    pub tile_id: TileId,
+    pub score: f32,
+    pub hits: Vec<SearchHit>,
+    pub bounds: Rect,
+    /// Camera that frames the tile in the canvas viewport, for flying to the
+    /// result.
+    pub camera: Camera,
+}
+
+#[derive(Debug, Clone)]
+struct Word {
+    range: [usize; 2],
+    term: String,
+}
+
+#[derive(Debug, Clone)]
+struct IndexedField {
+    field: SearchField,
+    text: String,
+    words: Vec<Word>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SearchIndex {
+    tiles: HashMap<TileId, Vec<IndexedField>>,
+    terms: BTreeMap<String, HashSet<TileId>>,
+}
+
+impl SearchIndex {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Brings the index up to date with all of `tiles`, e.g. for a board
+    /// that was loaded. Returns how many tiles were (re)indexed or dropped.
+    pub fn refresh(&mut self, tiles: &HashMap<TileId, Tile>) -> usize {
+        let removed: Vec<TileId> = self.tiles.keys()
+            .filter(|id| !tiles.contains_key(id))
+            .copied()
+            .collect();
+        let mut updated = removed.len();
+        for id in removed {
+            self.remove_tile(id);
+        }
+
+        for (id, tile) in tiles {
+            updated += usize::from(self.update_tile(*id, tile));
+        }
+        updated
+    }
+
+    /// Brings the index up to date with the tiles `batch` added, changed or
+    /// removed, as they are now in `tiles`. Returns how many tiles were
+    /// (re)indexed or dropped.
+    pub fn update(&mut self, tiles: &HashMap<TileId, Tile>, batch: &AppliedBatch) -> usize {
+        let mut updated = 0;
+        for id in batch.added.iter().chain(&batch.changed).chain(&batch.removed) {
+            match tiles.get(id) {
+                Some(tile) => updated += usize::from(self.update_tile(*id, tile)),
+                None if self.tiles.contains_key(id) => {
+                    self.remove_tile(*id);
+                    updated += 1;
+                }
+                None => {}
+            }
+        }
+        updated
+    }
+
+    /// Returns up to `limit` tiles of `canvas` containing every word of
+    /// `query`, best match first. The last word also matches as a prefix, so
+    /// results show up while typing.
+    pub fn search(&self, canvas: &InfiniteCanvas, query: &str, limit: usize) -> Vec<SearchMatch> {
+        let query_terms: Vec<String> = words(query).into_iter().map(|word| word.term).collect();
+        let Some(last) = query_terms.len().checked_sub(1) else {
+            return Vec::new();
+        };
+
+        let mut candidates: Option<HashSet<TileId>> = None;
+        for (i, term) in query_terms.iter().enumerate() {
+            let tiles = self.tiles_with(term, i == last);
+            candidates = Some(match candidates {
+                Some(candidates) => candidates.intersection(&tiles).copied().collect(),
+                None => tiles,
+            });
+        }
+
+        let mut matches: Vec<SearchMatch> = candidates.unwrap_or_default().into_iter()
+            .filter_map(|tile_id| {
+                let tile = canvas.tiles.get(&tile_id)?;
+                let (score, hits) = self.score(tile_id, &query_terms);
+                let bounds = tile.bounds();
+                Some(SearchMatch {
+                    tile_id,
+                    score,
+                    hits,
+                    bounds,
+                    camera: Camera::framing(bounds, canvas.canvas_size, FRAME_PADDING),
+                })
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            b.score.total_cmp(&a.score).then_with(|| a.tile_id.0.cmp(&b.tile_id.0))
+        });
+        matches.truncate(limit);
+        matches
+    }
+
+    /// Re-indexes `tile` if its text changed. Returns whether it did.
+    fn update_tile(&mut self, id: TileId, tile: &Tile) -> bool {
+        let fields = indexed_text(tile);
+        let unchanged = self.tiles.get(&id).is_some_and(|indexed| {
+            indexed.len() == fields.len()
+                && indexed.iter().zip(&fields).all(|(a, (field, text))| a.field == *field && a.text == *text)
+        });
+        if !unchanged {
+            self.index_tile(id, fields);
+        }
+        !unchanged
+    }
+
+    fn index_tile(&mut self, id: TileId, fields: Vec<(SearchField, String)>) {
+        self.remove_tile(id);
+        let fields: Vec<IndexedField> = fields.into_iter()
+            .map(|(field, text)| IndexedField { words: words(&text), field, text })
+            .collect();
+        for word in fields.iter().flat_map(|field| &field.words) {
+            self.terms.entry(word.term.clone()).or_default().insert(id);
+        }
+        self.tiles.insert(id, fields);
+    }
+
+    fn remove_tile(&mut self, id: TileId) {
+        let Some(fields) = self.tiles.remove(&id) else {
+            return;
+        };
+        for word in fields.iter().flat_map(|field| &field.words) {
+            if let Some(tiles) = self.terms.get_mut(&word.term) {
+                tiles.remove(&id);
+                if tiles.is_empty() {
+                    self.terms.remove(&word.term);
+                }
+            }
+        }
+    }
+
+    fn tiles_with(&self, term: &str, prefix: bool) -> HashSet<TileId> {
+        if !prefix {
+            return self.terms.get(term).cloned().unwrap_or_default();
+        }
+        self.terms.range(term.to_string()..)
+            .take_while(|(indexed, _)| indexed.starts_with(term))
+            .flat_map(|(_, tiles)| tiles.iter().copied())
+            .collect()
+    }
+
+    /// Sums, per query term, the best weighted match over all fields. Exact
+    /// word matches count twice as much as prefix matches.
+    fn score(&self, id: TileId, query_terms: &[String]) -> (f32, Vec<SearchHit>) {
+        let Some(fields) = self.tiles.get(&id) else {
+            return (0.0, Vec::new());
+        };
+        let last = query_terms.len() - 1;
+        let mut best = vec![0.0f32; query_terms.len()];
+        let mut hits = Vec::new();
+
+        for field in fields {
+            let mut ranges = Vec::new();
+            for word in &field.words {
+                let mut matched = false;
+                for (i, term) in query_terms.iter().enumerate() {
+                    let quality = if word.term == *term {
+                        1.0
+                    } else if i == last && word.term.starts_with(term.as_str()) {
+                        0.5
+                    } else {
+                        continue;
+                    };
+                    best[i] = best[i].max(quality * field.field.weight());
+                    matched = true;
+                }
+                if matched {
+                    ranges.push(word.range);
+                }
+            }
+            if !ranges.is_empty() {
+                hits.push(SearchHit {
+                    field: field.field.clone(),
+                    text: field.text.clone(),
+                    ranges,
+                });
+            }
+        }
+        (best.iter().sum(), hits)
+    }
+}
+
+/// The searchable text of `tile`, in a stable order.
+fn indexed_text(tile: &Tile) -> Vec<(SearchField, String)> {
+    let mut fields = vec![(SearchField::Title, tile.title.clone())];
+    match &tile.data {
+        TileData::WebView { url, .. } => fields.push((SearchField::Url, url.clone())),
+        TileData::Image { path, .. } | TileData::Terminal { cwd: Some(path) } => {
+            fields.push((SearchField::Path, path.clone()))
+        }
+        TileData::Terminal { cwd: None } => {}
+        TileData::Skia { drawing_commands } => {
+            for (command_index, command) in drawing_commands.iter().enumerate() {
+                match command {
+                    DrawingCommand::DrawText(text, ..) => {
+                        fields.push((SearchField::Text { command_index }, text.clone()))
+                    }
+                    DrawingCommand::DrawTextBox(text_box) => {
+                        fields.push((SearchField::Text { command_index }, text_box.text.clone()))
+                    }
+                    _ => {}
+                }
+            }
+        }
+        TileData::Egui { config, .. } => collect_strings(config, &mut String::new(), &mut fields),
+        TileData::Note { markdown } => {
+            for (line, text) in NoteDocument::parse(markdown).lines().iter().map(NoteLine::text).enumerate() {
+                if !text.is_empty() {
+                    fields.push((SearchField::Note { line }, text));
+                }
+            }
+        }
+    }
+    fields
+}
+
+fn collect_strings(value: &Value, path: &mut String, fields: &mut Vec<(SearchField, String)>) {
+    let len = path.len();
+    match value {
+        Value::String(text) => fields.push((SearchField::Config { path: path.clone() }, text.clone())),
+        Value::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                path.push_str(&format!("/{}", i));
+                collect_strings(item, path, fields);
+                path.truncate(len);
+            }
+        }
+        Value::Object(entries) => {
+            for (key, item) in entries {
+                // JSON pointer escaping
+                path.push('/');
+                path.push_str(&key.replace('~', "~0").replace('/', "~1"));
+                collect_strings(item, path, fields);
+                path.truncate(len);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Splits `text` into runs of alphanumeric characters.
+fn words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut start = None;
+    let mut term = String::new();
+    let mut count = 0;
+
+    for (i, c) in text.chars().enumerate() {
+        count = i + 1;
+        if c.is_alphanumeric() {
+            start.get_or_insert(i);
+            term.extend(c.to_lowercase());
+        } else if let Some(word_start) = start.take() {
+            words.push(Word { range: [word_start, i], term: std::mem::take(&mut term) });
+        }
+    }
+    if let Some(word_start) = start {
+        words.push(Word { range: [word_start, count], term });
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+
+    use super::*;
+    use crate::canvas::{CanvasOperation, NewTile};
+    use crate::tiles::{EguiTile, NoteTile, SkiaTile, WebViewTile};
+
+    fn canvas() -> InfiniteCanvas {
+        InfiniteCanvas::new(Vec2::new(800.0, 600.0))
+    }
+
+    fn indexed(canvas: &InfiniteCanvas) -> SearchIndex {
+        let mut index = SearchIndex::new();
+        index.refresh(&canvas.tiles);
+        index
+    }
+
+    fn ids(matches: &[SearchMatch]) -> Vec<TileId> {
+        matches.iter().map(|m| m.tile_id).collect()
+    }
+
+    #[test]
+    fn indexes_titles_urls_text_and_config() {
+        let mut canvas = canvas();
+        let web = canvas.add_tile(WebViewTile::new("https://docs.rs/glam".into())
+            .to_tile(Vec2::ZERO, Vec2::splat(100.0), "Docs".into()));
+        let mut sketch = SkiaTile::new().to_tile(Vec2::ZERO, Vec2::splat(100.0), "Sketch".into());
+        sketch.data = TileData::Skia {
+            drawing_commands: vec![
+                DrawingCommand::MoveTo(Vec2::ZERO),
+                DrawingCommand::DrawText("Roadmap for Q3".into(), Vec2::ZERO, 12.0),
+            ],
+        };
+        let sketch = canvas.add_tile(sketch);
+        let slider = canvas.add_tile(EguiTile::new("slider".into(), serde_json::json!({
+            "label": "Volume",
+            "presets": [{ "name": "roadmap review" }],
+        })).to_tile(Vec2::ZERO, Vec2::splat(100.0), "Controls".into()));
+
+        let index = indexed(&canvas);
+        assert_eq!(ids(&index.search(&canvas, "glam", 10)), vec![web]);
+
+        let roadmap = index.search(&canvas, "roadmap", 10);
+        // Drawn text outranks a nested config string
+        assert_eq!(ids(&roadmap), vec![sketch, slider]);
+        assert_eq!(roadmap[0].hits[0].field, SearchField::Text { command_index: 1 });
+        assert_eq!(roadmap[1].hits[0].field, SearchField::Config { path: "/presets/0/name".into() });
+    }
+
+    #[test]
+    fn notes_are_indexed_by_rendered_line() {
+        let mut canvas = canvas();
+        let note = canvas.add_tile(NoteTile::new("# Groceries\n\n- [ ] **oat** milk\n".into())
+            .to_tile(Vec2::ZERO, Vec2::splat(100.0), "List".into()));
+
+        let index = indexed(&canvas);
+        let found = index.search(&canvas, "oat milk", 10);
+        assert_eq!(ids(&found), vec![note]);
+        // Markup is not part of the indexed text
+        assert_eq!(found[0].hits[0].field, SearchField::Note { line: 1 });
+        assert_eq!(found[0].hits[0].text, "oat milk");
+        assert_eq!(found[0].hits[0].ranges, vec![[0, 3], [4, 8]]);
+    }
+
+    #[test]
+    fn all_words_must_match_and_last_is_a_prefix() {
+        let mut canvas = canvas();
+        let both = canvas.add_tile(SkiaTile::new().to_tile(Vec2::ZERO, Vec2::splat(100.0), "Team planning".into()));
+        canvas.add_tile(SkiaTile::new().to_tile(Vec2::ZERO, Vec2::splat(100.0), "Team lunch".into()));
+
+        let index = indexed(&canvas);
+        assert_eq!(ids(&index.search(&canvas, "team plan", 10)), vec![both]);
+        assert!(index.search(&canvas, "plan team", 10).is_empty());
+        assert!(index.search(&canvas, "  ", 10).is_empty());
+    }
+
+    #[test]
+    fn highlight_ranges_are_character_offsets() {
+        let mut canvas = canvas();
+        canvas.add_tile(SkiaTile::new().to_tile(Vec2::ZERO, Vec2::splat(100.0), "Größe der Übersicht".into()));
+
+        let index = indexed(&canvas);
+        let found = index.search(&canvas, "übersicht", 10);
+        assert_eq!(found[0].hits[0].ranges, vec![[10, 19]]);
+    }
+
+    #[test]
+    fn refresh_only_reindexes_changed_text() {
+        let mut canvas = canvas();
+        let a = canvas.add_tile(SkiaTile::new().to_tile(Vec2::ZERO, Vec2::splat(100.0), "Alpha".into()));
+        let b = canvas.add_tile(SkiaTile::new().to_tile(Vec2::ZERO, Vec2::splat(100.0), "Beta".into()));
+
+        let mut index = SearchIndex::new();
+        assert_eq!(index.refresh(&canvas.tiles), 2);
+
+        canvas.get_tile_mut(a).unwrap().position = Vec2::splat(500.0);
+        assert_eq!(index.refresh(&canvas.tiles), 0);
+
+        canvas.get_tile_mut(a).unwrap().title = "Gamma".into();
+        canvas.remove_tile(b);
+        assert_eq!(index.refresh(&canvas.tiles), 2);
+        assert!(index.search(&canvas, "alpha", 10).is_empty());
+        assert!(index.search(&canvas, "beta", 10).is_empty());
+        assert_eq!(ids(&index.search(&canvas, "gamma", 10)), vec![a]);
+    }
+
+    #[test]
+    fn updates_follow_applied_batches() {
+        let mut canvas = canvas();
+        let mut index = SearchIndex::new();
+        let add = |title: &str| CanvasOperation::Add {
+            tile_id: None,
+            tile: NewTile::Skia,
+            position: [0.0, 0.0],
+            size: [100.0, 100.0],
+            title: title.into(),
+        };
+
+        let batch = canvas.apply_operations(vec![add("Alpha"), add("Beta")]).unwrap();
+        assert_eq!(index.update(&canvas.tiles, &batch), 2);
+        let (a, b) = (batch.added[0], batch.added[1]);
+
+        let batch = canvas.apply_operations(vec![
+            CanvasOperation::Move { tile_id: a, position: [50.0, 0.0] },
+            CanvasOperation::Remove { tile_id: b },
+        ]).unwrap();
+        assert_eq!(index.update(&canvas.tiles, &batch), 1);
+        assert!(index.search(&canvas, "beta", 10).is_empty());
+        assert_eq!(ids(&index.search(&canvas, "alpha", 10)), vec![a]);
+
+        // Undo brings the removed tile back
+        let before = canvas.tiles.clone();
+        canvas.undo();
+        assert_eq!(index.update(&canvas.tiles, &AppliedBatch::diff(&before, &canvas.tiles)), 1);
+        assert_eq!(ids(&index.search(&canvas, "beta", 10)), vec![b]);
+    }
+
+    #[test]
+    fn matches_frame_the_tile() {
+        let mut canvas = canvas();
+        canvas.add_tile(SkiaTile::new().to_tile(Vec2::new(1000.0, 200.0), Vec2::new(352.0, 100.0), "Far".into()));
+
+        let index = indexed(&canvas);
+        let found = index.search(&canvas, "far", 1);
+        assert_eq!(found[0].camera.position, Vec2::new(1000.0, 200.0));
+        assert_eq!(found[0].camera.zoom, 2.0);
+    }
+}
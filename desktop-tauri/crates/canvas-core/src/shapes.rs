@@ -0,0 +1,1190 @@
+//! Shape objects inside the drawings of Skia tiles.
+//!
+//! A drawing is a flat list of commands, but the commands group into
+//! shapes that can be picked, moved, recolored, combined, deleted and
+//! erased. Shapes are addressed by their index in [`shapes`], in painting
+//! order, and found as follows:
+//!
+//! - A `Save`…`Restore` block that paints is one shape, as are ink strokes.
+//! - Outside blocks, each `Fill`, `Stroke` or `Draw*` command ends a shape
+//!   that starts after the previous one. A `Fill` or `Stroke` that paints
+//!   the path of the shape before it joins that shape instead.
+//! - State commands between shapes belong to the shape after them, and
+//!   a `Clip` outside blocks belongs to no shape.
+//!
+//! Edits leave every other shape looking the same. A shape outside a block
+//! is wrapped in one before it is moved or recolored, and the state it set
+//! for the shapes after it is set again after the block.
+
+use std::ops::Range;
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use tiny_skia::{FillRule, Mask, Path, PathSegment, Stroke, Transform};
+
+use crate::error::CanvasError;
+use crate::geometry::Rect;
+use crate::path_ops::{
+    flatten_path, max_stretch, path_commands, polyline_commands, stroke_outline, validate_tolerance, PathOp, Region,
+    DEFAULT_TOLERANCE,
+};
+use crate::render::skia::{is_path_command, run_commands, Brush, DrawState, Painter};
+use crate::tiles::{validate_color, DrawingCommand};
+
+/// Distance between eraser samples as a share of its radius.
+const ERASER_STEP: f32 = 0.5;
+
+/// Samples per segment length of a stroke, relative to the eraser's reach,
+/// when finding where the eraser cuts it.
+const SPLIT_SAMPLES_PER_REACH: f32 = 4.0;
+const MAX_SPLIT_SAMPLES: usize = 256;
+
+/// Bisection steps that place a cut between two samples.
+const CUT_REFINEMENT: usize = 10;
+
+/// A group of drawing commands that is picked and edited as one.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DrawingShape {
+    /// Commands of the shape, `start..end`.
+    pub start: usize,
+    pub end: usize,
+    /// Whether the shape is a `Save`…`Restore` block.
+    pub grouped: bool,
+    /// What the shape paints in content area coordinates, strokes at their
+    /// full width, or `None` if it paints nothing.
+    pub bounds: Option<Rect>,
+}
+
+/// Shapes of a drawing in painting order.
+pub fn shapes(commands: &[DrawingCommand]) -> Vec<DrawingShape> {
+    Traced::new(commands).shapes
+}
+
+/// Index of the topmost shape whose fill or stroke covers `point`, or
+/// comes within `tolerance` of it.
+pub fn hit_test(commands: &[DrawingCommand], point: Vec2, tolerance: f32) -> Option<usize> {
+    let traced = Traced::new(commands);
+    (0..traced.shapes.len()).rev().find(|&shape| traced.hits(shape, point, tolerance.max(0.0)))
+}
+
+/// Removes shapes from a drawing.
+pub fn delete_shapes(commands: &mut Vec<DrawingCommand>, indices: &[usize]) -> Result<(), CanvasError> {
+    let traced = Traced::new(commands);
+    for shape in traced.selected(indices)? {
+        delete(commands, shape);
+    }
+    Ok(())
+}
+
+/// Moves shapes by `offset` in content area units.
+pub fn move_shapes(commands: &mut Vec<DrawingCommand>, indices: &[usize], offset: Vec2) -> Result<(), CanvasError> {
+    if !offset.is_finite() {
+        return Err(CanvasError::invalid_argument("offset", "must be finite"));
+    }
+    let traced = Traced::new(commands);
+    for shape in traced.selected(indices)? {
+        // An offset in the content area, in the coordinates of the block
+        let Some(inverse) = traced.transforms[shape.start].invert() else { continue };
+        let local = Vec2::new(
+            inverse.sx * offset.x + inverse.kx * offset.y,
+            inverse.ky * offset.x + inverse.sy * offset.y,
+        );
+
+        let block = isolate(commands, shape);
+        match &mut commands[block.start + 1] {
+            // Repeated moves add up
+            DrawingCommand::Translate(previous) => *previous += local,
+            _ => commands.insert(block.start + 1, DrawingCommand::Translate(local)),
+        }
+    }
+    Ok(())
+}
+
+/// Sets the fill and stroke colors of shapes, replacing the colors and
+/// gradients they had. `None` keeps that paint as it is.
+pub fn recolor_shapes(
+    commands: &mut Vec<DrawingCommand>,
+    indices: &[usize],
+    fill: Option<[f32; 4]>,
+    stroke: Option<[f32; 4]>,
+) -> Result<(), CanvasError> {
+    for (field, color) in [("fill", fill), ("stroke", stroke)] {
+        if let Some(color) = color {
+            validate_color(field, color)?;
+        }
+    }
+    let traced = Traced::new(commands);
+    for shape in traced.selected(indices)? {
+        let block = isolate(commands, shape);
+        for command in &mut commands[block.clone()] {
+            let sets_fill = matches!(command, DrawingCommand::SetFillColor(_) | DrawingCommand::SetFillGradient(_));
+            let sets_stroke =
+                matches!(command, DrawingCommand::SetStrokeColor(_) | DrawingCommand::SetStrokeGradient(_));
+            match (fill, stroke) {
+                (Some(fill), _) if sets_fill => *command = DrawingCommand::SetFillColor(fill),
+                (_, Some(stroke)) if sets_stroke => *command = DrawingCommand::SetStrokeColor(stroke),
+                _ => {}
+            }
+        }
+        // After a move's translation, so the two stay at the top of the block
+        let mut at = block.start + 1;
+        if matches!(commands[at], DrawingCommand::Translate(_)) {
+            at += 1;
+        }
+        let paints = stroke.map(DrawingCommand::SetStrokeColor).into_iter();
+        commands.splice(at..at, paints.chain(fill.map(DrawingCommand::SetFillColor)));
+    }
+    Ok(())
+}
+
+/// Erases along the polyline `path` with a round eraser of `radius`.
+/// Stroked paths are cut where the eraser crosses them and keep the rest;
+/// any other shape it touches is removed whole. Returns whether anything
+/// was erased.
+pub fn erase(commands: &mut Vec<DrawingCommand>, path: &[Vec2], radius: f32) -> Result<bool, CanvasError> {
+    if path.is_empty() || path.iter().any(|point| !point.is_finite()) {
+        return Err(CanvasError::invalid_argument("path", "must have at least one point, all finite"));
+    }
+    if !radius.is_finite() || radius <= 0.0 {
+        return Err(CanvasError::invalid_argument("radius", "must be positive"));
+    }
+
+    let traced = Traced::new(commands);
+    let samples = sample_polyline(path, radius * ERASER_STEP);
+    let reach = Rect::new(
+        path.iter().copied().reduce(Vec2::min).unwrap() - Vec2::splat(radius),
+        path.iter().copied().reduce(Vec2::max).unwrap() + Vec2::splat(radius),
+    );
+
+    let mut erased = false;
+    // Back to front, so the ranges of the shapes still to do stay put
+    for (index, shape) in traced.shapes.iter().enumerate().rev() {
+        if !shape.bounds.is_some_and(|bounds| bounds.intersects(&reach)) {
+            continue;
+        }
+        if !samples.iter().any(|sample| traced.hits(index, *sample, radius)) {
+            continue;
+        }
+        match traced.stroked_path(commands, index) {
+            Some(stroked) => match cut(stroked, path, radius) {
+                Some(pieces) if !pieces.is_empty() => {
+                    replace_path(commands, shape, stroked.transform, &pieces);
+                    erased = true;
+                }
+                Some(_) => {
+                    delete(commands, shape);
+                    erased = true;
+                }
+                // Touched by coverage but not by the cut, e.g. at a joint
+                None => {}
+            },
+            None => {
+                delete(commands, shape);
+                erased = true;
+            }
+        }
+    }
+    Ok(erased)
+}
+
+/// Combines shapes by `op` into the lowest of them, which keeps its paints
+/// and paints the result; the others are removed. Each shape counts with
+/// the area it fills, or with the area of its strokes if it only strokes,
+/// which the result then fills in the stroke paint. Shapes combine in
+/// painting order, so a difference takes the shapes above from the lowest.
+/// Curves are flattened within `tolerance` in content area units.
+pub fn combine_shapes(
+    commands: &mut Vec<DrawingCommand>,
+    indices: &[usize],
+    op: PathOp,
+    tolerance: f32,
+) -> Result<(), CanvasError> {
+    let tolerance = validate_tolerance(tolerance)?;
+    let traced = Traced::new(commands);
+    let order = traced.checked(indices)?;
+    if order.len() < 2 {
+        return Err(CanvasError::invalid_argument("shapes", "must name at least two shapes"));
+    }
+    let (mut region, outlined) = traced.area(order[0], tolerance);
+    for &index in &order[1..] {
+        region = region.combine(&traced.area(index, tolerance).0, op);
+    }
+    // Top down, so the ranges of the shapes below stay put
+    for &index in order[1..].iter().rev() {
+        delete(commands, &traced.shapes[index]);
+    }
+    paint_region(commands, &traced, order[0], &region, outlined);
+    Ok(())
+}
+
+/// Grows the areas of shapes by `distance` in content area units, or
+/// shrinks them for a negative one, with round corners within `tolerance`.
+/// Areas are taken as by [`combine_shapes`]; shapes shrunk to nothing are
+/// removed.
+pub fn offset_shapes(
+    commands: &mut Vec<DrawingCommand>,
+    indices: &[usize],
+    distance: f32,
+    tolerance: f32,
+) -> Result<(), CanvasError> {
+    if !distance.is_finite() {
+        return Err(CanvasError::invalid_argument("distance", "must be finite"));
+    }
+    let tolerance = validate_tolerance(tolerance)?;
+    let traced = Traced::new(commands);
+    for index in traced.checked(indices)?.into_iter().rev() {
+        let (area, outlined) = traced.area(index, tolerance);
+        paint_region(commands, &traced, index, &area.offset(distance, tolerance)?, outlined);
+    }
+    Ok(())
+}
+
+/// Turns the strokes of shapes into filled outlines in the stroke paint,
+/// dashes, caps and joins included, so they look the same.
+pub fn outline_strokes(commands: &mut Vec<DrawingCommand>, indices: &[usize]) -> Result<(), CanvasError> {
+    let traced = Traced::new(commands);
+    for index in traced.checked(indices)?.into_iter().rev() {
+        repaint(commands, &traced, index, |painted| {
+            let stroke = painted.stroke.as_ref()?;
+            let outline = stroke_outline(&painted.shape, stroke, painted.transform, DEFAULT_TOLERANCE)?;
+            Some(fill_with(&painted.brush, path_commands(&outline)))
+        });
+    }
+    Ok(())
+}
+
+/// Replaces the curves shapes paint, text included, with lines that keep
+/// within `tolerance` of them in content area units.
+pub fn flatten_shapes(commands: &mut Vec<DrawingCommand>, indices: &[usize], tolerance: f32) -> Result<(), CanvasError> {
+    let tolerance = validate_tolerance(tolerance)?;
+    let traced = Traced::new(commands);
+    for index in traced.checked(indices)?.into_iter().rev() {
+        repaint(commands, &traced, index, |painted| {
+            let stretch = max_stretch(painted.transform);
+            if stretch <= 0.0 {
+                return None;
+            }
+            let mut body = polyline_commands(&flatten_path(&painted.shape, tolerance / stretch));
+            body.push(if painted.stroke.is_some() { DrawingCommand::Stroke } else { DrawingCommand::Fill });
+            Some(body)
+        });
+    }
+    Ok(())
+}
+
+/// Something painted: a shape in command coordinates, the transform to the
+/// content area, the paint and the pen if it was stroked.
+struct Painted {
+    index: usize,
+    shape: Path,
+    transform: Transform,
+    brush: Brush,
+    stroke: Option<Stroke>,
+}
+
+/// Collects what commands paint and the transform before each one.
+#[derive(Default)]
+struct Recorder {
+    painted: Vec<Painted>,
+    transforms: Vec<Transform>,
+}
+
+impl Painter for Recorder {
+    fn command(&mut self, _index: usize, state: &DrawState) {
+        self.transforms.push(state.transform);
+    }
+
+    fn fill(&mut self, index: usize, state: &DrawState, shape: &Path) {
+        let brush = state.fill.clone();
+        self.painted.push(Painted { index, shape: shape.clone(), transform: state.transform, brush, stroke: None });
+    }
+
+    fn stroke(&mut self, index: usize, state: &DrawState, shape: &Path) {
+        let (brush, stroke) = (state.stroke_brush.clone(), Some(state.stroke.clone()));
+        self.painted.push(Painted { index, shape: shape.clone(), transform: state.transform, brush, stroke });
+    }
+}
+
+/// A drawing split into shapes, with what each one paints.
+struct Traced {
+    shapes: Vec<DrawingShape>,
+    painted: Vec<Painted>,
+    /// Transform before each command, plus the one after the last.
+    transforms: Vec<Transform>,
+}
+
+impl Traced {
+    fn new(commands: &[DrawingCommand]) -> Self {
+        let mut recorder = Recorder::default();
+        run_commands(commands, &mut recorder);
+        let Recorder { painted, mut transforms } = recorder;
+        transforms.push(transforms.last().copied().unwrap_or_default());
+
+        let mut shapes: Vec<DrawingShape> = segment(commands)
+            .into_iter()
+            .map(|(range, grouped)| DrawingShape { start: range.start, end: range.end, grouped, bounds: None })
+            .collect();
+        for shape in &mut shapes {
+            shape.bounds = painted
+                .iter()
+                .filter(|painted| (shape.start..shape.end).contains(&painted.index))
+                .filter_map(painted_bounds)
+                .reduce(|a, b| a.union(&b));
+        }
+        Self { shapes, painted, transforms }
+    }
+
+    /// Shapes at `indices` from the last, each once.
+    fn selected(&self, indices: &[usize]) -> Result<Vec<&DrawingShape>, CanvasError> {
+        Ok(self.checked(indices)?.into_iter().rev().map(|index| &self.shapes[index]).collect())
+    }
+
+    /// `indices` in painting order, each once, if the drawing has them all.
+    fn checked(&self, indices: &[usize]) -> Result<Vec<usize>, CanvasError> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if let Some(missing) = indices.iter().find(|index| **index >= self.shapes.len()) {
+            return Err(CanvasError::invalid_argument("shapes", format!("drawing has no shape {}", missing)));
+        }
+        Ok(indices)
+    }
+
+    /// The area a shape fills, or the area of its strokes if it only
+    /// strokes, and whether it is the latter.
+    fn area(&self, shape: usize, tolerance: f32) -> (Region, bool) {
+        let fills = self.painted_by(shape).any(|painted| painted.stroke.is_none());
+        let paths = self
+            .painted_by(shape)
+            .filter(|painted| painted.stroke.is_none() == fills)
+            .map(|painted| (&painted.shape, painted.transform, painted.stroke.as_ref()));
+        (Region::from_paths(paths, tolerance), !fills)
+    }
+
+    fn painted_by(&self, shape: usize) -> impl Iterator<Item = &Painted> {
+        let range = self.shapes[shape].start..self.shapes[shape].end;
+        self.painted.iter().filter(move |painted| range.contains(&painted.index))
+    }
+
+    fn hits(&self, shape: usize, point: Vec2, tolerance: f32) -> bool {
+        let near = |bounds: Rect| {
+            Rect::new(bounds.min - Vec2::splat(tolerance), bounds.max + Vec2::splat(tolerance)).contains(point)
+        };
+        self.shapes[shape].bounds.is_some_and(near)
+            && self
+                .painted_by(shape)
+                .any(|painted| painted_bounds(painted).is_some_and(near) && covers(painted, point, tolerance))
+    }
+
+    /// The one path a shape strokes, if that is all it paints.
+    fn stroked_path(&self, commands: &[DrawingCommand], shape: usize) -> Option<&Painted> {
+        let DrawingShape { start, end, .. } = self.shapes[shape];
+        let mut painted = self.painted_by(shape);
+        let first = painted.next()?;
+        if first.stroke.is_none() || painted.any(|other| other.stroke.is_none()) {
+            return None;
+        }
+        // A second path stroked in the same block
+        let single = !commands[first.index..end].iter().any(is_path_command);
+        let from_commands = commands[start..end].iter().any(is_path_command);
+        (single && from_commands).then_some(first)
+    }
+}
+
+/// Ranges of the shapes in `commands`, and whether each is a block.
+fn segment(commands: &[DrawingCommand]) -> Vec<(Range<usize>, bool)> {
+    let mut shapes: Vec<(Range<usize>, bool)> = Vec::new();
+    let mut start = 0;
+    let mut depth = 0;
+    // Shape whose path is current and painted, for a Fill after a Stroke
+    let mut painting_path: Option<usize> = None;
+    for (index, command) in commands.iter().enumerate() {
+        if depth > 0 {
+            match command {
+                DrawingCommand::Save => depth += 1,
+                DrawingCommand::Restore => {
+                    depth -= 1;
+                    if depth == 0 {
+                        if commands[start..index].iter().any(is_paint_command) {
+                            shapes.push((start..index + 1, true));
+                        }
+                        start = index + 1;
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        match command {
+            DrawingCommand::Save => {
+                depth = 1;
+                start = index;
+                painting_path = None;
+            }
+            DrawingCommand::Clip => {
+                start = index + 1;
+                painting_path = None;
+            }
+            DrawingCommand::Fill | DrawingCommand::Stroke => {
+                match painting_path {
+                    Some(shape) => shapes[shape].0.end = index + 1,
+                    None => {
+                        shapes.push((start..index + 1, false));
+                        painting_path = Some(shapes.len() - 1);
+                    }
+                }
+                start = index + 1;
+            }
+            command if is_paint_command(command) => {
+                shapes.push((start..index + 1, false));
+                start = index + 1;
+                painting_path = None;
+            }
+            command if is_path_command(command) => painting_path = None,
+            _ => {}
+        }
+    }
+    // A block left open at the end
+    if depth > 0 && commands[start..].iter().any(is_paint_command) {
+        shapes.push((start..commands.len(), true));
+    }
+    shapes
+}
+
+/// Commands that paint, the current path or a shape of their own.
+fn is_paint_command(command: &DrawingCommand) -> bool {
+    matches!(
+        command,
+        DrawingCommand::Fill
+            | DrawingCommand::Stroke
+            | DrawingCommand::DrawRect(..)
+            | DrawingCommand::DrawRoundedRect(..)
+            | DrawingCommand::DrawCircle(..)
+            | DrawingCommand::DrawEllipse(..)
+            | DrawingCommand::DrawText(..)
+            | DrawingCommand::DrawTextBox(..)
+    )
+}
+
+/// Commands that only change the drawing state, which later shapes see.
+fn is_state_command(command: &DrawingCommand) -> bool {
+    matches!(
+        command,
+        DrawingCommand::SetFillColor(_)
+            | DrawingCommand::SetStrokeColor(_)
+            | DrawingCommand::SetFillGradient(_)
+            | DrawingCommand::SetStrokeGradient(_)
+            | DrawingCommand::SetStrokeWidth(_)
+            | DrawingCommand::SetLineDash(..)
+            | DrawingCommand::SetLineCap(_)
+            | DrawingCommand::SetLineJoin(_)
+            | DrawingCommand::SetMiterLimit(_)
+            | DrawingCommand::SetGlobalAlpha(_)
+            | DrawingCommand::Translate(_)
+            | DrawingCommand::Scale(_)
+            | DrawingCommand::Rotate(_)
+    )
+}
+
+/// Turns `shape` into a `Save`…`Restore` block and returns its range. The
+/// state the shape set for later shapes is set again after the block.
+fn isolate(commands: &mut Vec<DrawingCommand>, shape: &DrawingShape) -> Range<usize> {
+    if shape.grouped {
+        if !matches!(commands[shape.end - 1], DrawingCommand::Restore) {
+            commands.insert(shape.end, DrawingCommand::Restore);
+            return shape.start..shape.end + 1;
+        }
+        return shape.start..shape.end;
+    }
+    let body: Vec<DrawingCommand> = commands[shape.start..shape.end].to_vec();
+    let replay: Vec<DrawingCommand> = body.iter().filter(|command| is_state_command(command)).cloned().collect();
+    let block_end = shape.start + body.len() + 2;
+    let block = std::iter::once(DrawingCommand::Save).chain(body).chain([DrawingCommand::Restore]).chain(replay);
+    commands.splice(shape.start..shape.end, block);
+    shape.start..block_end
+}
+
+fn delete(commands: &mut Vec<DrawingCommand>, shape: &DrawingShape) {
+    if shape.grouped {
+        commands.drain(shape.start..shape.end);
+    } else {
+        let kept: Vec<DrawingCommand> =
+            commands[shape.start..shape.end].iter().filter(|command| is_state_command(command)).cloned().collect();
+        commands.splice(shape.start..shape.end, kept);
+    }
+}
+
+/// Rebuilds `shape` with what it paints replaced: `replace` gives the
+/// commands that paint instead of each painted path, or `None` to paint it
+/// as before. Paths are built again right before the command that paints
+/// them, in its coordinates; the path commands of the shape go, except
+/// those that build a clip or are never painted.
+fn repaint(
+    commands: &mut Vec<DrawingCommand>,
+    traced: &Traced,
+    shape: usize,
+    mut replace: impl FnMut(&Painted) -> Option<Vec<DrawingCommand>>,
+) {
+    let DrawingShape { start, end, .. } = traced.shapes[shape];
+    let mut kept = vec![false; end - start];
+    let mut run = Vec::new();
+    for (index, command) in commands[start..end].iter().enumerate() {
+        match command {
+            DrawingCommand::Fill | DrawingCommand::Stroke => run.clear(),
+            DrawingCommand::Clip => run.drain(..).for_each(|index: usize| kept[index] = true),
+            command if is_path_command(command) => run.push(index),
+            _ => {}
+        }
+    }
+    run.into_iter().for_each(|index| kept[index] = true);
+
+    let mut body = Vec::new();
+    for (index, command) in commands[start..end].iter().enumerate() {
+        if is_path_command(command) {
+            if kept[index] {
+                body.push(command.clone());
+            }
+            continue;
+        }
+        if !is_paint_command(command) {
+            body.push(command.clone());
+            continue;
+        }
+        for painted in traced.painted_by(shape).filter(|painted| painted.index == start + index) {
+            match replace(painted) {
+                Some(replacement) => body.extend(replacement),
+                None if matches!(command, DrawingCommand::Fill | DrawingCommand::Stroke) => {
+                    body.extend(path_commands(&painted.shape));
+                    body.push(command.clone());
+                }
+                None => body.push(command.clone()),
+            }
+        }
+    }
+    commands.splice(start..end, body);
+}
+
+/// Makes `shape` paint `region` instead of its paths, once with each paint
+/// it uses, or fill it in the stroke paint if the region is `outlined`
+/// strokes. An empty region removes the shape.
+fn paint_region(commands: &mut Vec<DrawingCommand>, traced: &Traced, shape: usize, region: &Region, outlined: bool) {
+    if region.is_empty() {
+        delete(commands, &traced.shapes[shape]);
+        return;
+    }
+    let (mut filled, mut stroked) = (false, false);
+    repaint(commands, traced, shape, |painted| {
+        let stroke = painted.stroke.is_some() && !outlined;
+        let done = if stroke { &mut stroked } else { &mut filled };
+        if std::mem::replace(done, true) {
+            return Some(Vec::new());
+        }
+        let path = painted.transform.invert().map(|local| region.to_path_commands(local)).unwrap_or_default();
+        Some(match (outlined, stroke) {
+            (true, _) => fill_with(&painted.brush, path),
+            (false, true) => path.into_iter().chain([DrawingCommand::Stroke]).collect(),
+            (false, false) => path.into_iter().chain([DrawingCommand::Fill]).collect(),
+        })
+    });
+}
+
+/// Commands that fill `path` with `brush` and leave the fill paint as it
+/// was.
+fn fill_with(brush: &Brush, path: Vec<DrawingCommand>) -> Vec<DrawingCommand> {
+    let paint = match brush {
+        Brush::Solid(color) => DrawingCommand::SetFillColor(*color),
+        Brush::Gradient(gradient) => DrawingCommand::SetFillGradient(gradient.clone()),
+    };
+    [DrawingCommand::Save, paint]
+        .into_iter()
+        .chain(path)
+        .chain([DrawingCommand::Fill, DrawingCommand::Restore])
+        .collect()
+}
+
+fn painted_bounds(painted: &Painted) -> Option<Rect> {
+    outline_bounds(&painted.shape, painted.transform, painted.stroke.as_ref())
+}
+
+/// Bounds in the content area of `shape` in command coordinates, filled or
+/// stroked with `stroke`, after `transform`.
+pub(crate) fn outline_bounds(shape: &Path, transform: Transform, stroke: Option<&Stroke>) -> Option<Rect> {
+    let outline = match stroke {
+        Some(stroke) => shape.stroke(stroke, 1.0).unwrap_or_else(|| shape.clone()),
+        None => shape.clone(),
+    };
+    let bounds = outline.transform(transform)?.bounds();
+    Some(Rect::new(Vec2::new(bounds.left(), bounds.top()), Vec2::new(bounds.right(), bounds.bottom())))
+}
+
+/// Whether `painted` covers `point`, grown by `tolerance` on every side.
+fn covers(painted: &Painted, point: Vec2, tolerance: f32) -> bool {
+    let scale = transform_scale(painted.transform);
+    if scale <= 0.0 {
+        return false;
+    }
+    // Pens are in command coordinates, the tolerance in the content area
+    let grow = 2.0 * tolerance / scale;
+    let outline = match &painted.stroke {
+        Some(stroke) => {
+            let pen = Stroke { width: stroke.width + grow, dash: None, ..stroke.clone() };
+            painted.shape.stroke(&pen, 1.0)
+        }
+        None if grow > 0.0 => painted.shape.stroke(&Stroke { width: grow, ..Default::default() }, 1.0),
+        None => None,
+    };
+    let inside = |shape: &Path| {
+        let Some(mut mask) = Mask::new(1, 1) else { return false };
+        // The pixel's center is the point
+        let transform = Transform::from_translate(0.5 - point.x, 0.5 - point.y).pre_concat(painted.transform);
+        mask.fill_path(shape, FillRule::Winding, false, transform);
+        mask.data()[0] > 0
+    };
+    (painted.stroke.is_none() && inside(&painted.shape)) || outline.as_ref().is_some_and(inside)
+}
+
+/// How much `transform` scales lengths, on average.
+fn transform_scale(transform: Transform) -> f32 {
+    (transform.sx * transform.sy - transform.kx * transform.ky).abs().sqrt()
+}
+
+/// Points along `path` at most `step` apart.
+fn sample_polyline(path: &[Vec2], step: f32) -> Vec<Vec2> {
+    let mut samples = vec![path[0]];
+    for pair in path.windows(2) {
+        let steps = (pair[0].distance(pair[1]) / step).ceil().max(1.0) as usize;
+        samples.extend((1..=steps).map(|i| pair[0].lerp(pair[1], i as f32 / steps as f32)));
+    }
+    samples
+}
+
+fn distance_to_polyline(point: Vec2, path: &[Vec2]) -> f32 {
+    if path.len() == 1 {
+        return point.distance(path[0]);
+    }
+    path.windows(2)
+        .map(|pair| {
+            let along = pair[1] - pair[0];
+            let t = if along == Vec2::ZERO {
+                0.0
+            } else {
+                ((point - pair[0]).dot(along) / along.length_squared()).clamp(0.0, 1.0)
+            };
+            point.distance(pair[0] + along * t)
+        })
+        .fold(f32::INFINITY, f32::min)
+}
+
+/// A piece of a path in content area coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Segment {
+    Line(Vec2, Vec2),
+    Quad(Vec2, Vec2, Vec2),
+    Cubic(Vec2, Vec2, Vec2, Vec2),
+}
+
+impl Segment {
+    fn at(&self, t: f32) -> Vec2 {
+        match *self {
+            Segment::Line(p0, p1) => p0.lerp(p1, t),
+            Segment::Quad(p0, c, p1) => p0.lerp(c, t).lerp(c.lerp(p1, t), t),
+            Segment::Cubic(p0, c1, c2, p1) => {
+                let (a, b, c) = (p0.lerp(c1, t), c1.lerp(c2, t), c2.lerp(p1, t));
+                a.lerp(b, t).lerp(b.lerp(c, t), t)
+            }
+        }
+    }
+
+    fn start(&self) -> Vec2 {
+        self.at(0.0)
+    }
+
+    /// Rough length, for choosing how finely to sample.
+    fn length(&self) -> f32 {
+        match *self {
+            Segment::Line(p0, p1) => p0.distance(p1),
+            Segment::Quad(p0, c, p1) => p0.distance(c) + c.distance(p1),
+            Segment::Cubic(p0, c1, c2, p1) => p0.distance(c1) + c1.distance(c2) + c2.distance(p1),
+        }
+    }
+
+    /// The part of the segment from `t0` to `t1`, by de Casteljau.
+    fn part(&self, t0: f32, t1: f32) -> Segment {
+        let after = self.split(t0).1;
+        if t1 >= 1.0 {
+            return after;
+        }
+        let t = if t0 >= 1.0 { 0.0 } else { (t1 - t0) / (1.0 - t0) };
+        after.split(t).0
+    }
+
+    fn split(&self, t: f32) -> (Segment, Segment) {
+        match *self {
+            Segment::Line(p0, p1) => {
+                let m = p0.lerp(p1, t);
+                (Segment::Line(p0, m), Segment::Line(m, p1))
+            }
+            Segment::Quad(p0, c, p1) => {
+                let (a, b) = (p0.lerp(c, t), c.lerp(p1, t));
+                let m = a.lerp(b, t);
+                (Segment::Quad(p0, a, m), Segment::Quad(m, b, p1))
+            }
+            Segment::Cubic(p0, c1, c2, p1) => {
+                let (a, b, c) = (p0.lerp(c1, t), c1.lerp(c2, t), c2.lerp(p1, t));
+                let (d, e) = (a.lerp(b, t), b.lerp(c, t));
+                let m = d.lerp(e, t);
+                (Segment::Cubic(p0, a, d, m), Segment::Cubic(m, e, c, p1))
+            }
+        }
+    }
+}
+
+/// Connected pieces of a path, and whether each is a closed subpath.
+type Pieces = Vec<(Vec<Segment>, bool)>;
+
+/// Subpaths of a path in content area coordinates, and whether each was
+/// closed.
+fn subpaths(path: &Path) -> Pieces {
+    let mut subpaths: Pieces = Vec::new();
+    let (mut start, mut current) = (Vec2::ZERO, Vec2::ZERO);
+    let v = |p: tiny_skia::Point| Vec2::new(p.x, p.y);
+    for segment in path.segments() {
+        let next = match segment {
+            PathSegment::MoveTo(p) => {
+                start = v(p);
+                current = start;
+                subpaths.push((Vec::new(), false));
+                continue;
+            }
+            PathSegment::LineTo(p) => Segment::Line(current, v(p)),
+            PathSegment::QuadTo(c, p) => Segment::Quad(current, v(c), v(p)),
+            PathSegment::CubicTo(c1, c2, p) => Segment::Cubic(current, v(c1), v(c2), v(p)),
+            PathSegment::Close => {
+                let Some((segments, closed)) = subpaths.last_mut() else { continue };
+                if current != start {
+                    segments.push(Segment::Line(current, start));
+                }
+                *closed = true;
+                current = start;
+                continue;
+            }
+        };
+        current = next.at(1.0);
+        if let Some((segments, _)) = subpaths.last_mut() {
+            segments.push(next);
+        }
+    }
+    subpaths
+}
+
+/// What is left of a stroked path after erasing along `eraser`, or `None`
+/// if the eraser misses it.
+fn cut(stroked: &Painted, eraser: &[Vec2], radius: f32) -> Option<Pieces> {
+    let stroke = stroked.stroke.as_ref()?;
+    let content = stroked.shape.clone().transform(stroked.transform)?;
+    // The eraser cuts where it touches the stroke's edge
+    let reach = radius + stroke.width * transform_scale(stroked.transform) / 2.0;
+    let erased = |point: Vec2| distance_to_polyline(point, eraser) <= reach;
+
+    let mut pieces: Pieces = Vec::new();
+    let mut cut_any = false;
+    for (segments, closed) in subpaths(&content) {
+        let mut kept: Vec<Vec<Segment>> = vec![Vec::new()];
+        for segment in segments {
+            let samples = (segment.length() * SPLIT_SAMPLES_PER_REACH / reach).ceil() as usize;
+            let samples = samples.clamp(8, MAX_SPLIT_SAMPLES);
+            let ts: Vec<f32> = (0..=samples).map(|i| i as f32 / samples as f32).collect();
+            let gone: Vec<bool> = ts.iter().map(|t| erased(segment.at(*t))).collect();
+            if !gone.contains(&true) {
+                kept.last_mut().unwrap().push(segment);
+                continue;
+            }
+            cut_any = true;
+            // Kept runs of samples, with their ends moved onto the cut
+            let boundary = |from: f32, to: f32| {
+                let (mut keep, mut drop) = (from, to);
+                for _ in 0..CUT_REFINEMENT {
+                    let middle = (keep + drop) / 2.0;
+                    if erased(segment.at(middle)) {
+                        drop = middle;
+                    } else {
+                        keep = middle;
+                    }
+                }
+                keep
+            };
+            let mut i = 0;
+            while i < ts.len() {
+                if gone[i] {
+                    if !kept.last().unwrap().is_empty() {
+                        kept.push(Vec::new());
+                    }
+                    i += 1;
+                    continue;
+                }
+                let first = i;
+                while i + 1 < ts.len() && !gone[i + 1] {
+                    i += 1;
+                }
+                let t0 = if first == 0 { 0.0 } else { boundary(ts[first], ts[first - 1]) };
+                let t1 = if i == ts.len() - 1 { 1.0 } else { boundary(ts[i], ts[i + 1]) };
+                if t1 > t0 {
+                    kept.last_mut().unwrap().push(segment.part(t0, t1));
+                }
+                i += 1;
+            }
+        }
+        let uncut = kept.len() == 1;
+        for run in kept.into_iter().filter(|run| !run.is_empty()) {
+            pieces.push((run, closed && uncut));
+        }
+    }
+    cut_any.then_some(pieces)
+}
+
+/// Replaces the path a stroked shape paints with `pieces`, mapped back to
+/// the coordinates of the `Stroke` that paints them.
+fn replace_path(commands: &mut Vec<DrawingCommand>, shape: &DrawingShape, transform: Transform, pieces: &Pieces) {
+    let Some(inverse) = transform.invert() else { return };
+    let local = |p: Vec2| {
+        let mut point = tiny_skia::Point::from_xy(p.x, p.y);
+        inverse.map_point(&mut point);
+        Vec2::new(point.x, point.y)
+    };
+    let mut path = Vec::new();
+    for (segments, closed) in pieces {
+        path.push(DrawingCommand::MoveTo(local(segments[0].start())));
+        for segment in segments {
+            path.push(match *segment {
+                Segment::Line(_, p) => DrawingCommand::LineTo(local(p)),
+                Segment::Quad(_, c, p) => DrawingCommand::QuadTo(local(c), local(p)),
+                Segment::Cubic(_, c1, c2, p) => DrawingCommand::CubicTo(local(c1), local(c2), local(p)),
+            });
+        }
+        if *closed {
+            path.push(DrawingCommand::ClosePath);
+        }
+    }
+
+    let mut body = Vec::new();
+    let mut path = Some(path);
+    for command in &commands[shape.start..shape.end] {
+        if is_path_command(command) {
+            continue;
+        }
+        if matches!(command, DrawingCommand::Stroke) {
+            if let Some(path) = path.take() {
+                body.extend(path);
+            }
+        }
+        body.push(command.clone());
+    }
+    commands.splice(shape.start..shape.end, body);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{add_tile, CanvasOperation, InfiniteCanvas, NewTile};
+    use crate::path_ops::PathOp;
+    use crate::tiles::TileData;
+
+    fn line(from: Vec2, to: Vec2) -> Vec<DrawingCommand> {
+        vec![DrawingCommand::MoveTo(from), DrawingCommand::LineTo(to), DrawingCommand::Stroke]
+    }
+
+    fn fill_color_at(commands: &[DrawingCommand], point: Vec2) -> Option<[u8; 4]> {
+        let pixmap = crate::render::skia::render_commands(commands, Vec2::new(100.0, 100.0), 1.0).ok()?;
+        let pixel = pixmap.pixel(point.x as u32, point.y as u32)?.demultiply();
+        Some([pixel.red(), pixel.green(), pixel.blue(), pixel.alpha()])
+    }
+
+    #[test]
+    fn commands_group_into_shapes() {
+        let mut commands = vec![
+            DrawingCommand::SetFillColor([1.0, 0.0, 0.0, 1.0]),
+            DrawingCommand::DrawRect(Vec2::new(10.0, 10.0), Vec2::new(20.0, 20.0)),
+            DrawingCommand::MoveTo(Vec2::new(0.0, 0.0)),
+            DrawingCommand::LineTo(Vec2::new(50.0, 0.0)),
+            DrawingCommand::LineTo(Vec2::new(50.0, 50.0)),
+            DrawingCommand::Fill,
+            DrawingCommand::SetStrokeWidth(4.0),
+            DrawingCommand::Stroke,
+            DrawingCommand::Save,
+            DrawingCommand::Translate(Vec2::new(60.0, 60.0)),
+            DrawingCommand::DrawCircle(Vec2::ZERO, 5.0),
+            DrawingCommand::Restore,
+            // Paints nothing, so no shape
+            DrawingCommand::Save,
+            DrawingCommand::SetFillColor([0.0; 4]),
+            DrawingCommand::Restore,
+        ];
+        commands.extend(line(Vec2::new(0.0, 90.0), Vec2::new(90.0, 90.0)));
+
+        let shapes = shapes(&commands);
+        let ranges: Vec<(usize, usize, bool)> = shapes.iter().map(|s| (s.start, s.end, s.grouped)).collect();
+        assert_eq!(ranges, [(0, 2, false), (2, 8, false), (8, 12, true), (15, 18, false)]);
+        assert_eq!(shapes[0].bounds, Some(Rect::new(Vec2::new(10.0, 10.0), Vec2::new(30.0, 30.0))));
+        assert_eq!(shapes[2].bounds, Some(Rect::new(Vec2::new(55.0, 55.0), Vec2::new(65.0, 65.0))));
+        // The stroke widens the bounds of the filled triangle
+        let triangle = shapes[1].bounds.unwrap();
+        assert!(triangle.min.y < 0.0 && triangle.max.x > 50.0, "{:?}", triangle);
+    }
+
+    #[test]
+    fn points_hit_fills_and_strokes_from_the_top() {
+        let mut commands = vec![DrawingCommand::DrawRect(Vec2::new(10.0, 10.0), Vec2::new(40.0, 40.0))];
+        commands.push(DrawingCommand::SetStrokeWidth(4.0));
+        commands.extend(line(Vec2::new(0.0, 30.0), Vec2::new(100.0, 30.0)));
+
+        assert_eq!(hit_test(&commands, Vec2::new(20.0, 20.0), 0.0), Some(0));
+        // The line is on top of the rectangle
+        assert_eq!(hit_test(&commands, Vec2::new(20.0, 31.0), 0.0), Some(1));
+        assert_eq!(hit_test(&commands, Vec2::new(80.0, 33.0), 0.0), None);
+        assert_eq!(hit_test(&commands, Vec2::new(80.0, 33.0), 2.0), Some(1));
+        assert_eq!(hit_test(&commands, Vec2::new(52.0, 20.0), 0.0), None);
+        assert_eq!(hit_test(&commands, Vec2::new(52.0, 20.0), 3.0), Some(0));
+    }
+
+    #[test]
+    fn edited_shapes_leave_the_others_alone() {
+        let red = [1.0, 0.0, 0.0, 1.0];
+        let commands = vec![
+            DrawingCommand::SetFillColor(red),
+            DrawingCommand::DrawRect(Vec2::new(0.0, 0.0), Vec2::new(20.0, 20.0)),
+            // Inherits red from the first shape
+            DrawingCommand::DrawRect(Vec2::new(50.0, 0.0), Vec2::new(20.0, 20.0)),
+        ];
+
+        let mut moved = commands.clone();
+        move_shapes(&mut moved, &[0], Vec2::new(0.0, 40.0)).unwrap();
+        move_shapes(&mut moved, &[0], Vec2::new(5.0, 0.0)).unwrap();
+        assert_eq!(fill_color_at(&moved, Vec2::new(10.0, 10.0)).unwrap()[3], 0);
+        assert_eq!(fill_color_at(&moved, Vec2::new(15.0, 50.0)), Some([255, 0, 0, 255]));
+        assert_eq!(fill_color_at(&moved, Vec2::new(60.0, 10.0)), Some([255, 0, 0, 255]));
+        assert_eq!(shapes(&moved).len(), 2);
+        assert_eq!(moved.iter().filter(|c| matches!(c, DrawingCommand::Translate(_))).count(), 1);
+
+        let mut recolored = commands.clone();
+        recolor_shapes(&mut recolored, &[0], Some([0.0, 0.0, 1.0, 1.0]), None).unwrap();
+        assert_eq!(fill_color_at(&recolored, Vec2::new(10.0, 10.0)), Some([0, 0, 255, 255]));
+        assert_eq!(fill_color_at(&recolored, Vec2::new(60.0, 10.0)), Some([255, 0, 0, 255]));
+
+        let mut deleted = commands.clone();
+        delete_shapes(&mut deleted, &[0]).unwrap();
+        assert_eq!(fill_color_at(&deleted, Vec2::new(10.0, 10.0)).unwrap()[3], 0);
+        assert_eq!(fill_color_at(&deleted, Vec2::new(60.0, 10.0)), Some([255, 0, 0, 255]));
+
+        assert!(delete_shapes(&mut deleted, &[5]).is_err());
+    }
+
+    #[test]
+    fn the_eraser_cuts_strokes_and_removes_other_shapes() {
+        let mut commands = vec![DrawingCommand::SetStrokeWidth(2.0)];
+        commands.extend(line(Vec2::new(0.0, 20.0), Vec2::new(100.0, 20.0)));
+        commands.push(DrawingCommand::DrawCircle(Vec2::new(50.0, 70.0), 10.0));
+
+        // A vertical swipe through the middle of both
+        let eraser = [Vec2::new(50.0, 0.0), Vec2::new(50.0, 100.0)];
+        assert!(erase(&mut commands, &eraser, 5.0).unwrap());
+
+        let shapes = shapes(&commands);
+        assert_eq!(shapes.len(), 1, "the circle is gone: {:?}", commands);
+        let pieces: Vec<Vec2> = commands.iter().filter_map(|c| match c {
+            DrawingCommand::MoveTo(p) | DrawingCommand::LineTo(p) => Some(*p),
+            _ => None,
+        }).collect();
+        assert_eq!(pieces.len(), 4);
+        // Cut where the eraser reaches the edge of the stroke, 5 + 1 away
+        assert_eq!(pieces[0], Vec2::new(0.0, 20.0));
+        assert!((pieces[1].x - 44.0).abs() < 0.1 && (pieces[2].x - 56.0).abs() < 0.1, "{:?}", pieces);
+        assert_eq!(pieces[3], Vec2::new(100.0, 20.0));
+
+        // Erasing what is left of both ends removes the line
+        let rest = [Vec2::new(0.0, 20.0), Vec2::new(100.0, 20.0)];
+        assert!(erase(&mut commands, &rest, 5.0).unwrap());
+        assert!(super::shapes(&commands).is_empty());
+        assert!(!erase(&mut commands, &rest, 5.0).unwrap());
+    }
+
+    #[test]
+    fn combined_shapes_paint_in_the_lowest_ones_paints() {
+        let commands = vec![
+            DrawingCommand::Save,
+            DrawingCommand::SetFillColor([1.0, 0.0, 0.0, 1.0]),
+            DrawingCommand::Translate(Vec2::new(10.0, 0.0)),
+            DrawingCommand::DrawCircle(Vec2::new(30.0, 50.0), 20.0),
+            DrawingCommand::Restore,
+            DrawingCommand::SetFillColor([0.0, 1.0, 0.0, 1.0]),
+            DrawingCommand::DrawCircle(Vec2::new(60.0, 50.0), 20.0),
+        ];
+        let red = Some([255, 0, 0, 255]);
+        let cases = [
+            (PathOp::Union, [true, true, true]),
+            (PathOp::Intersection, [false, true, false]),
+            (PathOp::Difference, [true, false, false]),
+            (PathOp::Xor, [true, false, true]),
+        ];
+        for (op, covered) in cases {
+            let mut combined = commands.clone();
+            combine_shapes(&mut combined, &[1, 0], op, 0.1).unwrap();
+            assert_eq!(shapes(&combined).len(), 1, "{:?}", op);
+            for (x, covered) in [25.0, 50.0, 75.0].into_iter().zip(covered) {
+                let color = fill_color_at(&combined, Vec2::new(x, 50.0));
+                assert_eq!(color == red, covered, "{:?} at {}: {:?}", op, x, color);
+            }
+        }
+
+        let mut apart = commands.clone();
+        apart.push(DrawingCommand::DrawCircle(Vec2::new(90.0, 90.0), 5.0));
+        combine_shapes(&mut apart, &[0, 2], PathOp::Intersection, 0.1).unwrap();
+        assert_eq!(shapes(&apart).len(), 1, "nothing is left of the two");
+        assert!(combine_shapes(&mut apart, &[0], PathOp::Union, 0.1).is_err());
+        assert!(combine_shapes(&mut apart, &[0, 1], PathOp::Union, 0.1).is_err());
+    }
+
+    #[test]
+    fn offsets_grow_shapes_with_round_corners() {
+        let mut commands = vec![DrawingCommand::DrawRect(Vec2::new(20.0, 20.0), Vec2::new(40.0, 40.0))];
+        offset_shapes(&mut commands, &[0], 5.0, 0.1).unwrap();
+        assert!(fill_color_at(&commands, Vec2::new(16.0, 40.0)).unwrap()[3] > 0);
+        assert_eq!(fill_color_at(&commands, Vec2::new(13.0, 40.0)).unwrap()[3], 0);
+        // The corner is rounded 5 around the old one
+        assert_eq!(fill_color_at(&commands, Vec2::new(15.0, 15.0)).unwrap()[3], 0);
+
+        offset_shapes(&mut commands, &[0], -30.0, 0.1).unwrap();
+        assert!(shapes(&commands).is_empty());
+    }
+
+    #[test]
+    fn outlined_and_flattened_strokes_look_the_same() {
+        let commands = vec![
+            DrawingCommand::SetStrokeWidth(6.0),
+            DrawingCommand::SetStrokeColor([0.0, 0.0, 1.0, 1.0]),
+            DrawingCommand::SetLineCap(crate::tiles::LineCap::Round),
+            DrawingCommand::SetLineDash(vec![20.0, 8.0], 0.0),
+            DrawingCommand::MoveTo(Vec2::new(10.0, 80.0)),
+            DrawingCommand::CubicTo(Vec2::new(20.0, 0.0), Vec2::new(80.0, 0.0), Vec2::new(90.0, 80.0)),
+            DrawingCommand::Stroke,
+            DrawingCommand::DrawCircle(Vec2::new(50.0, 60.0), 15.0),
+        ];
+        let render = |commands: &[DrawingCommand]| {
+            crate::render::skia::render_commands(commands, Vec2::new(100.0, 100.0), 1.0).unwrap()
+        };
+        // Edges may shift by a fraction of a pixel, nothing more
+        let assert_same_look = |edited: &[DrawingCommand]| {
+            let (before, after) = (render(&commands), render(edited));
+            let differences: Vec<u32> = before
+                .pixels()
+                .iter()
+                .zip(after.pixels())
+                .map(|(a, b)| (a.alpha() as i32 - b.alpha() as i32).unsigned_abs())
+                .collect();
+            let mean = differences.iter().sum::<u32>() as f32 / differences.len() as f32;
+            assert!(differences.iter().all(|d| *d <= 96) && mean < 1.0, "{} on average", mean);
+        };
+
+        let mut outlined = commands.clone();
+        outline_strokes(&mut outlined, &[0, 1]).unwrap();
+        assert!(!outlined.contains(&DrawingCommand::Stroke));
+        assert_eq!(shapes(&outlined).len(), 2);
+        assert_eq!(fill_color_at(&outlined, Vec2::new(50.0, 60.0)), Some([0, 0, 0, 255]));
+        assert_same_look(&outlined);
+
+        let mut flattened = commands.clone();
+        flatten_shapes(&mut flattened, &[0, 1], 0.05).unwrap();
+        let curved = |command: &DrawingCommand| {
+            matches!(command, DrawingCommand::CubicTo(..) | DrawingCommand::QuadTo(..) | DrawingCommand::DrawCircle(..))
+        };
+        assert!(!flattened.iter().any(curved));
+        assert_same_look(&flattened);
+    }
+
+    #[test]
+    fn cut_curves_keep_their_shape() {
+        let curve = Segment::Cubic(Vec2::ZERO, Vec2::new(10.0, 30.0), Vec2::new(40.0, 30.0), Vec2::new(50.0, 0.0));
+        let part = curve.part(0.25, 0.75);
+        for t in [0.0, 0.3, 0.5, 1.0] {
+            assert!(part.at(t).distance(curve.at(0.25 + 0.5 * t)) < 1e-4);
+        }
+    }
+
+    #[test]
+    fn drawing_edits_are_validated_and_undoable() {
+        let mut canvas = InfiniteCanvas::default();
+        let id = add_tile(&mut canvas, NewTile::Skia);
+        let widths = |canvas: &InfiniteCanvas| match &canvas.get_tile(id).unwrap().data {
+            TileData::Skia { drawing_commands } => drawing_commands.iter().map(|command| match command {
+                DrawingCommand::SetStrokeWidth(width) => *width,
+                other => panic!("{:?}", other),
+            }).collect::<Vec<_>>(),
+            other => panic!("{:?}", other),
+        };
+        let width = DrawingCommand::SetStrokeWidth;
+
+        let batch = canvas.apply_operations(vec![
+            CanvasOperation::SetDrawing { tile_id: id, commands: vec![width(1.0), width(2.0)] },
+            CanvasOperation::AppendDrawing { tile_id: id, commands: vec![width(5.0)] },
+            CanvasOperation::SpliceDrawing { tile_id: id, start: 2, end: 2, commands: vec![width(3.0), width(4.0)] },
+            CanvasOperation::SpliceDrawing { tile_id: id, start: 0, end: 1, commands: vec![] },
+        ]).unwrap();
+        assert_eq!(batch.changed, [id]);
+        assert_eq!(widths(&canvas), [2.0, 3.0, 4.0, 5.0]);
+
+        for invalid in [
+            CanvasOperation::AppendDrawing { tile_id: id, commands: vec![width(f32::NAN)] },
+            CanvasOperation::SetDrawing { tile_id: id, commands: vec![width(-1.0)] },
+            CanvasOperation::SpliceDrawing { tile_id: id, start: 3, end: 5, commands: vec![] },
+            CanvasOperation::SpliceDrawing { tile_id: id, start: 2, end: 1, commands: vec![] },
+        ] {
+            assert!(canvas.apply_operations(vec![invalid]).is_err());
+        }
+        assert_eq!(widths(&canvas), [2.0, 3.0, 4.0, 5.0]);
+
+        canvas.undo();
+        assert_eq!(widths(&canvas), Vec::<f32>::new());
+    }
+
+    #[test]
+    fn shape_edits_apply_as_one_undoable_batch() {
+        let mut canvas = InfiniteCanvas::default();
+        let id = add_tile(&mut canvas, NewTile::Skia);
+        let drawing = |canvas: &InfiniteCanvas| match &canvas.get_tile(id).unwrap().data {
+            TileData::Skia { drawing_commands } => drawing_commands.clone(),
+            other => panic!("{:?}", other),
+        };
+        canvas.apply_operations(vec![CanvasOperation::SetDrawing {
+            tile_id: id,
+            commands: vec![
+                DrawingCommand::DrawRect(Vec2::new(0.0, 0.0), Vec2::new(10.0, 10.0)),
+                DrawingCommand::DrawCircle(Vec2::new(50.0, 50.0), 5.0),
+            ],
+        }]).unwrap();
+
+        let batch = canvas.apply_operations(vec![
+            CanvasOperation::MoveShapes { tile_id: id, shapes: vec![0], offset: [20.0, 0.0] },
+            CanvasOperation::RecolorShapes { tile_id: id, shapes: vec![0], fill: Some([1.0, 0.0, 0.0, 1.0]), stroke: None },
+            CanvasOperation::DeleteShapes { tile_id: id, shapes: vec![1] },
+        ]).unwrap();
+        assert_eq!(batch.changed, [id]);
+        let found = shapes(&drawing(&canvas));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].bounds.unwrap().min, Vec2::new(20.0, 0.0));
+
+        // Nothing under the eraser leaves the tile unchanged
+        let missed = CanvasOperation::EraseDrawing { tile_id: id, path: vec![[90.0, 90.0]], radius: 2.0 };
+        assert!(canvas.apply_operations(vec![missed]).unwrap().changed.is_empty());
+        for invalid in [
+            CanvasOperation::DeleteShapes { tile_id: id, shapes: vec![1] },
+            CanvasOperation::RecolorShapes { tile_id: id, shapes: vec![0], fill: Some([2.0, 0.0, 0.0, 1.0]), stroke: None },
+            CanvasOperation::EraseDrawing { tile_id: id, path: vec![], radius: 2.0 },
+            CanvasOperation::CombineShapes { tile_id: id, shapes: vec![0], operation: PathOp::Union, tolerance: None },
+            CanvasOperation::FlattenShapes { tile_id: id, shapes: vec![0], tolerance: Some(0.0) },
+        ] {
+            assert!(canvas.apply_operations(vec![invalid]).is_err());
+        }
+
+        canvas.undo();
+        assert_eq!(shapes(&drawing(&canvas)).len(), 2);
+    }
+}
@@ -0,0 +1,919 @@
+//! Import of SVG documents into Skia drawings.
+//!
+//! [`import_svg`] turns paths, basic shapes, transforms, fills, strokes and
+//! linear and radial gradients into [`DrawingCommand`]s in the coordinates
+//! of the document's `viewBox`, moved to the origin. Every element that
+//! paints becomes its own `Save`/`Restore` block with its whole transform
+//! and opacity, so each one is a [`DrawingShape`](crate::DrawingShape) that
+//! can be hit, moved and recolored on its own. What the drawing model can't
+//! express, such as text, images, filters or clip paths, is skipped and
+//! named in [`SvgImport::dropped`].
+
+use std::collections::{BTreeSet, HashMap};
+use std::str::FromStr;
+
+use glam::Vec2;
+use roxmltree::{Document, Node};
+use svgtypes::{Length, LengthListParser, LengthUnit, PointsParser, SimplePathSegment, SimplifyingPathParser};
+use tiny_skia::{NonZeroRect, PathBuilder, Transform};
+
+use crate::error::CanvasError;
+use crate::render::skia::{DEFAULT_MITER_LIMIT, DEFAULT_STROKE_WIDTH};
+use crate::tiles::{DrawingCommand, Gradient, GradientStop, LineCap, LineJoin};
+
+const SVG_NAMESPACE: &str = "http://www.w3.org/2000/svg";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// Size of documents with neither a `viewBox` nor a width and height, as
+/// in browsers.
+pub const DEFAULT_SVG_SIZE: Vec2 = Vec2::new(300.0, 150.0);
+/// Font size `em` and `ex` lengths are relative to.
+const FONT_SIZE: f32 = 16.0;
+/// Distance of the control points of a quarter ellipse from its ends, as a
+/// fraction of the radius.
+const KAPPA: f32 = 0.552_284_8;
+/// How many `href`s a gradient follows to inherit attributes and stops.
+const MAX_GRADIENT_REFERENCES: usize = 8;
+/// Rotations and scales closer than this to doing nothing are left out.
+const TRANSFORM_EPSILON: f32 = 1e-6;
+
+/// An imported SVG document.
+#[derive(Debug, Clone)]
+pub struct SvgImport {
+    pub commands: Vec<DrawingCommand>,
+    /// Size of the `viewBox`, or of the document if it has none.
+    pub size: Vec2,
+    /// Elements and properties that were skipped, sorted and without
+    /// duplicates, e.g. `<text>` or `fill-rule: evenodd`.
+    pub dropped: Vec<String>,
+}
+
+/// Parses an SVG document. Fails only if it isn't well-formed XML or its
+/// root isn't an `<svg>` element; anything inside that can't be imported
+/// is reported in [`SvgImport::dropped`] instead.
+pub fn import_svg(source: &str) -> Result<SvgImport, CanvasError> {
+    let document = Document::parse(source).map_err(|e| CanvasError::invalid_argument("svg", e.to_string()))?;
+    let root = document.root_element();
+    if !is_svg(root) || root.tag_name().name() != "svg" {
+        return Err(CanvasError::invalid_argument("svg", "root element is not <svg>"));
+    }
+
+    let view_box = root
+        .attribute("viewBox")
+        .and_then(|value| svgtypes::ViewBox::from_str(value).ok())
+        .filter(|view_box| view_box.w > 0.0 && view_box.h > 0.0);
+    let (origin, size) = match view_box {
+        Some(view_box) => (
+            Vec2::new(view_box.x as f32, view_box.y as f32),
+            Vec2::new(view_box.w as f32, view_box.h as f32),
+        ),
+        None => {
+            // Percentages are relative to a viewport the document doesn't have
+            let dimension = |name: &str, default: f32| {
+                root.attribute(name)
+                    .filter(|value| !value.trim_end().ends_with('%'))
+                    .and_then(|value| length(value, 0.0))
+                    .filter(|value| *value > 0.0)
+                    .unwrap_or(default)
+            };
+            (Vec2::ZERO, Vec2::new(dimension("width", DEFAULT_SVG_SIZE.x), dimension("height", DEFAULT_SVG_SIZE.y)))
+        }
+    };
+
+    let mut importer = Importer {
+        ids: document
+            .descendants()
+            .filter_map(|node| Some((node.attribute("id")?, node)))
+            .collect(),
+        viewport: size,
+        commands: Vec::new(),
+        dropped: BTreeSet::new(),
+    };
+    importer.element(root, &Style::default(), Transform::from_translate(-origin.x, -origin.y), 1.0);
+
+    Ok(SvgImport {
+        commands: importer.commands,
+        size,
+        dropped: importer.dropped.into_iter().collect(),
+    })
+}
+
+struct Importer<'a, 'input> {
+    ids: HashMap<&'a str, Node<'a, 'input>>,
+    /// Size percentages of lengths outside gradients are relative to.
+    viewport: Vec2,
+    commands: Vec<DrawingCommand>,
+    dropped: BTreeSet<String>,
+}
+
+impl<'a, 'input> Importer<'a, 'input> {
+    fn drop(&mut self, feature: impl Into<String>) {
+        self.dropped.insert(feature.into());
+    }
+
+    /// Imports `node` and its children. `ctm` maps its user space to the
+    /// drawing and `opacity` is the product of its ancestors' opacities.
+    fn element(&mut self, node: Node<'a, 'input>, parent: &Style, ctm: Transform, opacity: f32) {
+        // Elements of other namespaces are editor metadata, e.g. Inkscape's
+        if !is_svg(node) {
+            return;
+        }
+        let name = node.tag_name().name();
+        match name {
+            // Gradients are imported where they are painted with
+            "title" | "desc" | "metadata" | "defs" | "linearGradient" | "radialGradient" => return,
+            "svg" if node.parent_element().is_some() => {
+                self.drop("nested <svg>");
+                return;
+            }
+            "svg" | "g" | "a" | "path" | "rect" | "circle" | "ellipse" | "line" | "polyline" | "polygon" => {}
+            "style" => {
+                self.drop("<style> sheets");
+                return;
+            }
+            other => {
+                self.drop(format!("<{}>", other));
+                return;
+            }
+        }
+        if property(node, "display") == Some("none") {
+            return;
+        }
+
+        for unsupported in ["clip-path", "mask", "filter", "marker-start", "marker-mid", "marker-end"] {
+            if property(node, unsupported).is_some_and(|value| value != "none") {
+                self.drop(unsupported);
+            }
+        }
+        let style = parent.inherit(node, self.viewport);
+        let ctm = match node.attribute("transform") {
+            Some(transform) => ctm.pre_concat(parse_transform(transform)),
+            None => ctm,
+        };
+        let opacity = opacity * property(node, "opacity").and_then(fraction).unwrap_or(1.0).clamp(0.0, 1.0);
+
+        match name {
+            "svg" | "g" | "a" => {
+                for child in node.children().filter(Node::is_element) {
+                    self.element(child, &style, ctm, opacity);
+                }
+            }
+            _ => {
+                if let Some(path) = shape_path(node, self.viewport) {
+                    self.draw(node, &style, ctm, opacity, path);
+                }
+            }
+        }
+    }
+
+    /// Fills and strokes `path` as one `Save`/`Restore` block.
+    fn draw(&mut self, node: Node, style: &Style, ctm: Transform, opacity: f32, path: Vec<DrawingCommand>) {
+        // Transforms that collapse the shape leave nothing to see
+        if !style.visible || opacity <= 0.0 || ctm.invert().is_none() {
+            return;
+        }
+        let bounds = bounding_box(&path);
+        // A line has no inside
+        let fill = if node.tag_name().name() == "line" {
+            None
+        } else {
+            self.brush(&style.fill, style.color, style.fill_opacity, bounds)
+        };
+        let stroke = if style.stroke_width > 0.0 {
+            self.brush(&style.stroke, style.color, style.stroke_opacity, bounds)
+        } else {
+            None
+        };
+        if fill.is_none() && stroke.is_none() {
+            return;
+        }
+        if fill.is_some() && style.even_odd {
+            self.drop("fill-rule: evenodd");
+        }
+
+        let commands = &mut self.commands;
+        commands.push(DrawingCommand::Save);
+        commands.extend(transform_commands(ctm));
+        if opacity < 1.0 {
+            commands.push(DrawingCommand::SetGlobalAlpha(opacity));
+        }
+        if let Some(stroke) = &stroke {
+            if style.stroke_width != DEFAULT_STROKE_WIDTH {
+                commands.push(DrawingCommand::SetStrokeWidth(style.stroke_width));
+            }
+            if style.line_cap != LineCap::default() {
+                commands.push(DrawingCommand::SetLineCap(style.line_cap));
+            }
+            if style.line_join != LineJoin::default() {
+                commands.push(DrawingCommand::SetLineJoin(style.line_join));
+            }
+            if style.miter_limit != DEFAULT_MITER_LIMIT {
+                commands.push(DrawingCommand::SetMiterLimit(style.miter_limit));
+            }
+            if !style.dashes.is_empty() {
+                commands.push(DrawingCommand::SetLineDash(style.dashes.clone(), style.dash_offset));
+            }
+            commands.push(match stroke {
+                Brush::Solid(color) => DrawingCommand::SetStrokeColor(*color),
+                // Stroke widths follow the transform, so the gradient is
+                // moved into user space instead
+                Brush::Gradient { gradient, space } => DrawingCommand::SetStrokeGradient(map_gradient(gradient, *space)),
+            });
+        }
+        if let Some(Brush::Solid(color)) = fill {
+            commands.push(DrawingCommand::SetFillColor(color));
+        }
+        commands.extend(path);
+        match fill {
+            Some(Brush::Solid(_)) => commands.push(DrawingCommand::Fill),
+            // Filling in the gradient's own space keeps it exact, e.g. the
+            // ellipse of a radial gradient across a wide bounding box
+            Some(Brush::Gradient { gradient, space }) => {
+                commands.push(DrawingCommand::Save);
+                commands.extend(transform_commands(space));
+                commands.push(DrawingCommand::SetFillGradient(gradient));
+                commands.push(DrawingCommand::Fill);
+                commands.push(DrawingCommand::Restore);
+            }
+            None => {}
+        }
+        if stroke.is_some() {
+            commands.push(DrawingCommand::Stroke);
+        }
+        commands.push(DrawingCommand::Restore);
+    }
+
+    /// What `paint` paints with, faded by `opacity`, or `None` if nothing.
+    fn brush(&mut self, paint: &Paint, current_color: [f32; 4], opacity: f32, bounds: Option<NonZeroRect>) -> Option<Brush> {
+        match paint {
+            Paint::None => None,
+            Paint::Color(color) => Some(Brush::Solid(faded(*color, opacity))),
+            Paint::CurrentColor => Some(Brush::Solid(faded(current_color, opacity))),
+            Paint::Server(id, fallback) => match self.ids.get(id.as_str()).copied() {
+                Some(node) if is_gradient(node) => self.gradient(node, opacity, bounds),
+                Some(node) => {
+                    self.drop(format!("<{}>", node.tag_name().name()));
+                    self.brush(fallback, current_color, opacity, bounds)
+                }
+                None => self.brush(fallback, current_color, opacity, bounds),
+            },
+        }
+    }
+
+    fn gradient(&mut self, node: Node, opacity: f32, bounds: Option<NonZeroRect>) -> Option<Brush> {
+        // Attributes and stops missing on a gradient come from the one its
+        // href points at
+        let mut chain = vec![node];
+        while chain.len() < MAX_GRADIENT_REFERENCES {
+            let last = chain[chain.len() - 1];
+            let next = last
+                .attribute((XLINK_NAMESPACE, "href"))
+                .or_else(|| last.attribute("href"))
+                .and_then(|href| href.trim().strip_prefix('#'))
+                .and_then(|id| self.ids.get(id).copied());
+            match next {
+                Some(next) if is_gradient(next) && !chain.contains(&next) => chain.push(next),
+                _ => break,
+            }
+        }
+        let attribute = |name: &str| chain.iter().find_map(|node| node.attribute(name));
+
+        let stops = chain.iter().map(|node| gradient_stops(*node, opacity)).find(|stops| !stops.is_empty())?;
+        let last = stops[stops.len() - 1].color;
+        if stops.len() == 1 {
+            return Some(Brush::Solid(last));
+        }
+        if matches!(attribute("spreadMethod"), Some("reflect" | "repeat")) {
+            self.drop("spreadMethod");
+        }
+
+        // Coordinates are fractions of the bounding box by default, which
+        // an empty one doesn't have
+        let bounding_box = attribute("gradientUnits") != Some("userSpaceOnUse");
+        let mut space = if bounding_box { Transform::from_bbox(bounds?) } else { Transform::identity() };
+        if let Some(transform) = attribute("gradientTransform") {
+            space = space.pre_concat(parse_transform(transform));
+        }
+        space.invert()?;
+        let viewport = self.viewport;
+        let coordinate = |name: &str, default: &str, reference: f32| {
+            let parse = |value: &str| if bounding_box { fraction(value) } else { length(value, reference) };
+            attribute(name).and_then(parse).or_else(|| parse(default)).unwrap_or(0.0)
+        };
+
+        let gradient = if node.tag_name().name() == "linearGradient" {
+            let start = Vec2::new(coordinate("x1", "0%", viewport.x), coordinate("y1", "0%", viewport.y));
+            let end = Vec2::new(coordinate("x2", "100%", viewport.x), coordinate("y2", "0%", viewport.y));
+            if start == end {
+                return Some(Brush::Solid(last));
+            }
+            Gradient::Linear { start, end, stops }
+        } else {
+            let center = Vec2::new(coordinate("cx", "50%", viewport.x), coordinate("cy", "50%", viewport.y));
+            let radius = coordinate("r", "50%", diagonal(viewport));
+            let focus = Vec2::new(
+                attribute("fx").map_or(center.x, |_| coordinate("fx", "50%", viewport.x)),
+                attribute("fy").map_or(center.y, |_| coordinate("fy", "50%", viewport.y)),
+            );
+            if focus != center {
+                self.drop("focal point of <radialGradient>");
+            }
+            if radius <= 0.0 {
+                return Some(Brush::Solid(last));
+            }
+            Gradient::Radial { center, radius, stops }
+        };
+        Some(Brush::Gradient { gradient, space })
+    }
+}
+
+/// A fill or stroke as given by the document.
+#[derive(Debug, Clone, PartialEq)]
+enum Paint {
+    None,
+    Color([f32; 4]),
+    CurrentColor,
+    /// A reference to a gradient or pattern by id, and what to paint if it
+    /// can't be used.
+    Server(String, Box<Paint>),
+}
+
+/// A resolved paint. Gradient coordinates are in `space`, which maps them
+/// to user space.
+enum Brush {
+    Solid([f32; 4]),
+    Gradient { gradient: Gradient, space: Transform },
+}
+
+/// The inherited properties, with the initial values of the SVG spec.
+#[derive(Debug, Clone)]
+struct Style {
+    fill: Paint,
+    stroke: Paint,
+    fill_opacity: f32,
+    stroke_opacity: f32,
+    even_odd: bool,
+    stroke_width: f32,
+    line_cap: LineCap,
+    line_join: LineJoin,
+    miter_limit: f32,
+    dashes: Vec<f32>,
+    dash_offset: f32,
+    /// What `currentColor` stands for.
+    color: [f32; 4],
+    visible: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Self {
+            fill: Paint::Color([0.0, 0.0, 0.0, 1.0]),
+            stroke: Paint::None,
+            fill_opacity: 1.0,
+            stroke_opacity: 1.0,
+            even_odd: false,
+            stroke_width: 1.0,
+            line_cap: LineCap::Butt,
+            line_join: LineJoin::Miter,
+            miter_limit: 4.0,
+            dashes: Vec::new(),
+            dash_offset: 0.0,
+            color: [0.0, 0.0, 0.0, 1.0],
+            visible: true,
+        }
+    }
+}
+
+impl Style {
+    /// This style with the properties `node` sets. Invalid values are
+    /// ignored, as if they weren't there.
+    fn inherit(&self, node: Node, viewport: Vec2) -> Style {
+        let mut style = self.clone();
+        let get = |name: &str| property(node, name);
+        if let Some(color) = get("color").and_then(parse_color) {
+            style.color = color;
+        }
+        if let Some(fill) = get("fill").and_then(parse_paint) {
+            style.fill = fill;
+        }
+        if let Some(stroke) = get("stroke").and_then(parse_paint) {
+            style.stroke = stroke;
+        }
+        if let Some(opacity) = get("fill-opacity").and_then(fraction) {
+            style.fill_opacity = opacity.clamp(0.0, 1.0);
+        }
+        if let Some(opacity) = get("stroke-opacity").and_then(fraction) {
+            style.stroke_opacity = opacity.clamp(0.0, 1.0);
+        }
+        match get("fill-rule") {
+            Some("evenodd") => style.even_odd = true,
+            Some("nonzero") => style.even_odd = false,
+            _ => {}
+        }
+        if let Some(width) = get("stroke-width").and_then(|value| length(value, diagonal(viewport))) {
+            if width >= 0.0 {
+                style.stroke_width = width;
+            }
+        }
+        match get("stroke-linecap") {
+            Some("butt") => style.line_cap = LineCap::Butt,
+            Some("round") => style.line_cap = LineCap::Round,
+            Some("square") => style.line_cap = LineCap::Square,
+            _ => {}
+        }
+        match get("stroke-linejoin") {
+            Some("miter" | "miter-clip" | "arcs") => style.line_join = LineJoin::Miter,
+            Some("round") => style.line_join = LineJoin::Round,
+            Some("bevel") => style.line_join = LineJoin::Bevel,
+            _ => {}
+        }
+        if let Some(limit) = get("stroke-miterlimit").and_then(|value| value.parse::<f32>().ok()) {
+            if limit >= 1.0 {
+                style.miter_limit = limit;
+            }
+        }
+        if let Some(dashes) = get("stroke-dasharray") {
+            // Negative lengths turn dashing off, as does a pattern that is
+            // all gaps
+            let lengths = LengthListParser::from(dashes)
+                .map(|dash| dash.ok().map(|dash| absolute(dash, diagonal(viewport))))
+                .collect::<Option<Vec<f32>>>()
+                .filter(|dashes| dashes.iter().all(|dash| *dash >= 0.0) && dashes.iter().any(|dash| *dash > 0.0));
+            style.dashes = lengths.unwrap_or_default();
+        }
+        if let Some(offset) = get("stroke-dashoffset").and_then(|value| length(value, diagonal(viewport))) {
+            style.dash_offset = offset;
+        }
+        match get("visibility") {
+            Some("visible") => style.visible = true,
+            Some("hidden" | "collapse") => style.visible = false,
+            _ => {}
+        }
+        style
+    }
+}
+
+/// The outline of a path or basic shape in user space, or `None` if it
+/// doesn't render, e.g. a rectangle without a width.
+fn shape_path(node: Node, viewport: Vec2) -> Option<Vec<DrawingCommand>> {
+    let get = |name: &str, reference: f32| node.attribute(name).and_then(|value| length(value, reference));
+    let point = |x: f64, y: f64| Vec2::new(x as f32, y as f32);
+    let mut path = Vec::new();
+    match node.tag_name().name() {
+        "path" => {
+            // Like browsers, draw the path up to the first error in it
+            for segment in SimplifyingPathParser::from(node.attribute("d")?).map_while(Result::ok) {
+                path.push(match segment {
+                    SimplePathSegment::MoveTo { x, y } => DrawingCommand::MoveTo(point(x, y)),
+                    SimplePathSegment::LineTo { x, y } => DrawingCommand::LineTo(point(x, y)),
+                    SimplePathSegment::CurveTo { x1, y1, x2, y2, x, y } => {
+                        DrawingCommand::CubicTo(point(x1, y1), point(x2, y2), point(x, y))
+                    }
+                    SimplePathSegment::Quadratic { x1, y1, x, y } => DrawingCommand::QuadTo(point(x1, y1), point(x, y)),
+                    SimplePathSegment::ClosePath => DrawingCommand::ClosePath,
+                });
+            }
+        }
+        "rect" => {
+            let position = Vec2::new(get("x", viewport.x).unwrap_or(0.0), get("y", viewport.y).unwrap_or(0.0));
+            let size = Vec2::new(get("width", viewport.x)?, get("height", viewport.y)?);
+            if size.x <= 0.0 || size.y <= 0.0 {
+                return None;
+            }
+            // A missing corner radius is the other one
+            let radii = match (get("rx", viewport.x).filter(|r| *r >= 0.0), get("ry", viewport.y).filter(|r| *r >= 0.0)) {
+                (Some(rx), Some(ry)) => Vec2::new(rx, ry),
+                (Some(r), None) | (None, Some(r)) => Vec2::splat(r),
+                (None, None) => Vec2::ZERO,
+            };
+            rounded_rect(&mut path, position, size, radii.min(size / 2.0));
+        }
+        "circle" => {
+            let center = Vec2::new(get("cx", viewport.x).unwrap_or(0.0), get("cy", viewport.y).unwrap_or(0.0));
+            let radius = get("r", diagonal(viewport)).filter(|r| *r > 0.0)?;
+            ellipse(&mut path, center, Vec2::splat(radius));
+        }
+        "ellipse" => {
+            let center = Vec2::new(get("cx", viewport.x).unwrap_or(0.0), get("cy", viewport.y).unwrap_or(0.0));
+            let radii = Vec2::new(get("rx", viewport.x)?, get("ry", viewport.y)?);
+            if radii.x <= 0.0 || radii.y <= 0.0 {
+                return None;
+            }
+            ellipse(&mut path, center, radii);
+        }
+        "line" => {
+            let coordinate = |name: &str, reference: f32| get(name, reference).unwrap_or(0.0);
+            path.push(DrawingCommand::MoveTo(Vec2::new(coordinate("x1", viewport.x), coordinate("y1", viewport.y))));
+            path.push(DrawingCommand::LineTo(Vec2::new(coordinate("x2", viewport.x), coordinate("y2", viewport.y))));
+        }
+        "polyline" | "polygon" => {
+            for (index, (x, y)) in PointsParser::from(node.attribute("points")?).enumerate() {
+                path.push(if index == 0 { DrawingCommand::MoveTo(point(x, y)) } else { DrawingCommand::LineTo(point(x, y)) });
+            }
+            if path.len() < 2 {
+                return None;
+            }
+            if node.tag_name().name() == "polygon" {
+                path.push(DrawingCommand::ClosePath);
+            }
+        }
+        _ => return None,
+    }
+    (!path.is_empty()).then_some(path)
+}
+
+fn rounded_rect(path: &mut Vec<DrawingCommand>, position: Vec2, size: Vec2, radii: Vec2) {
+    let (min, max) = (position, position + size);
+    if radii.x <= 0.0 || radii.y <= 0.0 {
+        path.extend([
+            DrawingCommand::MoveTo(min),
+            DrawingCommand::LineTo(Vec2::new(max.x, min.y)),
+            DrawingCommand::LineTo(max),
+            DrawingCommand::LineTo(Vec2::new(min.x, max.y)),
+            DrawingCommand::ClosePath,
+        ]);
+        return;
+    }
+    let k = radii * (1.0 - KAPPA);
+    path.extend([
+        DrawingCommand::MoveTo(Vec2::new(min.x + radii.x, min.y)),
+        DrawingCommand::LineTo(Vec2::new(max.x - radii.x, min.y)),
+        DrawingCommand::CubicTo(
+            Vec2::new(max.x - k.x, min.y),
+            Vec2::new(max.x, min.y + k.y),
+            Vec2::new(max.x, min.y + radii.y),
+        ),
+        DrawingCommand::LineTo(Vec2::new(max.x, max.y - radii.y)),
+        DrawingCommand::CubicTo(
+            Vec2::new(max.x, max.y - k.y),
+            Vec2::new(max.x - k.x, max.y),
+            Vec2::new(max.x - radii.x, max.y),
+        ),
+        DrawingCommand::LineTo(Vec2::new(min.x + radii.x, max.y)),
+        DrawingCommand::CubicTo(
+            Vec2::new(min.x + k.x, max.y),
+            Vec2::new(min.x, max.y - k.y),
+            Vec2::new(min.x, max.y - radii.y),
+        ),
+        DrawingCommand::LineTo(Vec2::new(min.x, min.y + radii.y)),
+        DrawingCommand::CubicTo(
+            Vec2::new(min.x, min.y + k.y),
+            Vec2::new(min.x + k.x, min.y),
+            Vec2::new(min.x + radii.x, min.y),
+        ),
+        DrawingCommand::ClosePath,
+    ]);
+}
+
+/// An ellipse as four cubic quarters, clockwise from the right.
+fn ellipse(path: &mut Vec<DrawingCommand>, center: Vec2, radii: Vec2) {
+    let k = radii * KAPPA;
+    let at = |x: f32, y: f32| center + Vec2::new(x, y);
+    path.extend([
+        DrawingCommand::MoveTo(at(radii.x, 0.0)),
+        DrawingCommand::CubicTo(at(radii.x, k.y), at(k.x, radii.y), at(0.0, radii.y)),
+        DrawingCommand::CubicTo(at(-k.x, radii.y), at(-radii.x, k.y), at(-radii.x, 0.0)),
+        DrawingCommand::CubicTo(at(-radii.x, -k.y), at(-k.x, -radii.y), at(0.0, -radii.y)),
+        DrawingCommand::CubicTo(at(k.x, -radii.y), at(radii.x, -k.y), at(radii.x, 0.0)),
+        DrawingCommand::ClosePath,
+    ]);
+}
+
+/// Bounds of the geometry of `path`, without its stroke, or `None` if it
+/// has no area.
+fn bounding_box(path: &[DrawingCommand]) -> Option<NonZeroRect> {
+    let mut builder = PathBuilder::new();
+    for command in path {
+        match command {
+            DrawingCommand::MoveTo(p) => builder.move_to(p.x, p.y),
+            DrawingCommand::LineTo(p) => builder.line_to(p.x, p.y),
+            DrawingCommand::QuadTo(c, p) => builder.quad_to(c.x, c.y, p.x, p.y),
+            DrawingCommand::CubicTo(c1, c2, p) => builder.cubic_to(c1.x, c1.y, c2.x, c2.y, p.x, p.y),
+            DrawingCommand::ClosePath => builder.close(),
+            _ => {}
+        }
+    }
+    builder.finish()?.compute_tight_bounds()?.to_non_zero_rect()
+}
+
+/// `transform` as `Translate`, `Rotate`, `Scale` and `Rotate` commands,
+/// leaving out the ones that do nothing. Skews and mirroring are covered
+/// by scaling between the two rotations.
+fn transform_commands(transform: Transform) -> Vec<DrawingCommand> {
+    let mut commands = Vec::new();
+    if transform.tx != 0.0 || transform.ty != 0.0 {
+        commands.push(DrawingCommand::Translate(Vec2::new(transform.tx, transform.ty)));
+    }
+
+    // Singular value decomposition of the linear part, which maps the x
+    // axis to (sx, ky) and the y axis to (kx, sy)
+    let e = (transform.sx + transform.sy) / 2.0;
+    let f = (transform.sx - transform.sy) / 2.0;
+    let g = (transform.ky + transform.kx) / 2.0;
+    let h = (transform.ky - transform.kx) / 2.0;
+    let (q, r) = (e.hypot(h), f.hypot(g));
+    let (a1, a2) = (g.atan2(f), h.atan2(e));
+    let (first, second) = ((a2 + a1) / 2.0, (a2 - a1) / 2.0);
+    let scale = Vec2::new(q + r, q - r);
+
+    if first.abs() > TRANSFORM_EPSILON {
+        commands.push(DrawingCommand::Rotate(first));
+    }
+    if (scale - Vec2::ONE).abs().max_element() > TRANSFORM_EPSILON {
+        commands.push(DrawingCommand::Scale(scale));
+    }
+    if second.abs() > TRANSFORM_EPSILON {
+        commands.push(DrawingCommand::Rotate(second));
+    }
+    commands
+}
+
+/// `gradient` with its coordinates mapped by `space`. A radial gradient
+/// stays a circle, scaled by the mean of the scale factors.
+fn map_gradient(gradient: &Gradient, space: Transform) -> Gradient {
+    let map = |p: Vec2| {
+        let mut p = tiny_skia::Point::from_xy(p.x, p.y);
+        space.map_point(&mut p);
+        Vec2::new(p.x, p.y)
+    };
+    match gradient {
+        Gradient::Linear { start, end, stops } => Gradient::Linear { start: map(*start), end: map(*end), stops: stops.clone() },
+        Gradient::Radial { center, radius, stops } => {
+            let scale = (space.sx * space.sy - space.kx * space.ky).abs().sqrt();
+            Gradient::Radial { center: map(*center), radius: radius * scale, stops: stops.clone() }
+        }
+    }
+}
+
+/// The `<stop>`s of a gradient. Offsets are clamped to 0..1 and made to
+/// never decrease, as the spec asks.
+fn gradient_stops(node: Node, opacity: f32) -> Vec<GradientStop> {
+    let mut stops: Vec<GradientStop> = Vec::new();
+    for stop in node.children().filter(|child| is_svg(*child) && child.tag_name().name() == "stop") {
+        let previous = stops.last().map_or(0.0, |stop| stop.offset);
+        let offset = stop.attribute("offset").and_then(fraction).unwrap_or(0.0).clamp(previous, 1.0);
+        let color = property(stop, "stop-color").and_then(parse_color).unwrap_or([0.0, 0.0, 0.0, 1.0]);
+        let alpha = property(stop, "stop-opacity").and_then(fraction).unwrap_or(1.0).clamp(0.0, 1.0);
+        stops.push(GradientStop { offset, color: faded(color, alpha * opacity) });
+    }
+    stops
+}
+
+fn is_svg(node: Node) -> bool {
+    node.is_element() && node.tag_name().namespace() == Some(SVG_NAMESPACE)
+}
+
+fn is_gradient(node: Node) -> bool {
+    is_svg(node) && matches!(node.tag_name().name(), "linearGradient" | "radialGradient")
+}
+
+/// The value of a presentation property, from the `style` attribute if it
+/// is declared there, otherwise from the attribute of the same name.
+/// `inherit` counts as not set.
+fn property<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    let declared = node.attribute("style").and_then(|style| {
+        style
+            .split(';')
+            .filter_map(|declaration| declaration.split_once(':'))
+            .rfind(|(key, _)| key.trim() == name)
+            .map(|(_, value)| value.trim_end_matches("!important"))
+    });
+    declared
+        .or_else(|| node.attribute(name))
+        .map(str::trim)
+        .filter(|value| !value.is_empty() && *value != "inherit")
+}
+
+fn parse_transform(value: &str) -> Transform {
+    match svgtypes::Transform::from_str(value) {
+        Ok(t) => Transform::from_row(t.a as f32, t.b as f32, t.c as f32, t.d as f32, t.e as f32, t.f as f32),
+        // An invalid transform disables the element in browsers
+        Err(_) => Transform::from_scale(0.0, 0.0),
+    }
+}
+
+fn parse_color(value: &str) -> Option<[f32; 4]> {
+    svgtypes::Color::from_str(value).ok().map(channels)
+}
+
+fn parse_paint(value: &str) -> Option<Paint> {
+    Some(match svgtypes::Paint::from_str(value).ok()? {
+        svgtypes::Paint::None => Paint::None,
+        svgtypes::Paint::CurrentColor => Paint::CurrentColor,
+        svgtypes::Paint::Color(c) => Paint::Color(channels(c)),
+        svgtypes::Paint::FuncIRI(id, fallback) => {
+            let fallback = match fallback {
+                Some(svgtypes::PaintFallback::CurrentColor) => Paint::CurrentColor,
+                Some(svgtypes::PaintFallback::Color(c)) => Paint::Color(channels(c)),
+                Some(svgtypes::PaintFallback::None) | None => Paint::None,
+            };
+            Paint::Server(id.to_string(), Box::new(fallback))
+        }
+        // `inherit` keeps the parent's paint, context paints only make
+        // sense for markers
+        _ => return None,
+    })
+}
+
+fn channels(color: svgtypes::Color) -> [f32; 4] {
+    [color.red, color.green, color.blue, color.alpha].map(|channel| channel as f32 / 255.0)
+}
+
+fn faded([r, g, b, a]: [f32; 4], opacity: f32) -> [f32; 4] {
+    [r, g, b, a * opacity]
+}
+
+/// A number, or a percentage as a fraction.
+fn fraction(value: &str) -> Option<f32> {
+    let length = Length::from_str(value).ok()?;
+    match length.unit {
+        LengthUnit::None => Some(length.number as f32),
+        LengthUnit::Percent => Some(length.number as f32 / 100.0),
+        _ => None,
+    }
+}
+
+/// A length in user units, with percentages of `reference`.
+fn length(value: &str, reference: f32) -> Option<f32> {
+    let length = Length::from_str(value).ok()?;
+    Some(absolute(length, reference)).filter(|length| length.is_finite())
+}
+
+fn absolute(length: Length, reference: f32) -> f32 {
+    let number = length.number as f32;
+    match length.unit {
+        LengthUnit::None | LengthUnit::Px => number,
+        LengthUnit::Em => number * FONT_SIZE,
+        LengthUnit::Ex => number * FONT_SIZE / 2.0,
+        LengthUnit::In => number * 96.0,
+        LengthUnit::Cm => number * 96.0 / 2.54,
+        LengthUnit::Mm => number * 96.0 / 25.4,
+        LengthUnit::Pt => number * 4.0 / 3.0,
+        LengthUnit::Pc => number * 16.0,
+        LengthUnit::Percent => number / 100.0 * reference,
+    }
+}
+
+/// What percentages of lengths that are neither horizontal nor vertical,
+/// such as radii and stroke widths, are relative to.
+fn diagonal(viewport: Vec2) -> f32 {
+    (viewport.length_squared() / 2.0).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::skia::render_commands;
+    use crate::tiles::SkiaTile;
+
+    fn import(body: &str) -> SvgImport {
+        let source = format!(r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="0 0 100 100">{}</svg>"#, body);
+        let svg = import_svg(&source).unwrap();
+        SkiaTile::validate_commands(&svg.commands).unwrap();
+        svg
+    }
+
+    fn pixel(svg: &SvgImport, x: u32, y: u32) -> [u8; 4] {
+        let pixmap = render_commands(&svg.commands, svg.size, 1.0).unwrap();
+        let color = pixmap.pixel(x, y).unwrap().demultiply();
+        [color.red(), color.green(), color.blue(), color.alpha()]
+    }
+
+    #[test]
+    fn shapes_are_drawn_in_view_box_coordinates() {
+        let svg = import_svg(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="400" viewBox="50 50 200 100">
+                <rect x="50" y="50" width="100" height="100" rx="10" fill="red"/>
+                <circle cx="200" cy="100" r="40" fill="none" stroke="blue" stroke-width="4"/>
+                <path d="M 160 60 h 80 v 80 z" fill="lime" fill-opacity="0.5"/>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!(svg.size, Vec2::new(200.0, 100.0));
+        assert!(svg.dropped.is_empty());
+        let blocks = svg.commands.iter().filter(|command| matches!(command, DrawingCommand::Save)).count();
+        assert_eq!(blocks, 3);
+
+        assert_eq!(pixel(&svg, 50, 50), [255, 0, 0, 255]);
+        // The corner is rounded off
+        assert_eq!(pixel(&svg, 0, 0)[3], 0);
+        // Only the ring of the circle is painted
+        assert_eq!(pixel(&svg, 110, 50)[2], 255);
+        assert_eq!(pixel(&svg, 135, 50), [0, 0, 0, 0]);
+        assert_eq!(pixel(&svg, 188, 14), [0, 255, 0, 128]);
+    }
+
+    #[test]
+    fn transforms_become_rotations_and_scales() {
+        for matrix in [
+            [1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            [2.0, 0.0, 0.0, 3.0, 5.0, -7.0],
+            [-1.0, 0.0, 0.0, 1.0, 0.0, 0.0],
+            [0.0, 1.0, -1.0, 0.0, 10.0, 0.0],
+            [1.0, 0.0, 0.5, 1.0, 0.0, 0.0],
+            [0.3, -1.2, 2.5, 0.7, 1.0, 2.0],
+            [-2.0, 0.0, 0.0, -2.0, 0.0, 0.0],
+        ] {
+            let [sx, ky, kx, sy, tx, ty] = matrix;
+            let expected = Transform::from_row(sx, ky, kx, sy, tx, ty);
+            let mut actual = Transform::identity();
+            for command in transform_commands(expected) {
+                actual = match command {
+                    DrawingCommand::Translate(offset) => actual.pre_translate(offset.x, offset.y),
+                    DrawingCommand::Rotate(angle) => actual.pre_rotate(angle.to_degrees()),
+                    DrawingCommand::Scale(factor) => actual.pre_scale(factor.x, factor.y),
+                    other => panic!("unexpected {:?}", other),
+                };
+            }
+            let difference = [
+                actual.sx - expected.sx,
+                actual.ky - expected.ky,
+                actual.kx - expected.kx,
+                actual.sy - expected.sy,
+                actual.tx - expected.tx,
+                actual.ty - expected.ty,
+            ];
+            assert!(difference.iter().all(|d| d.abs() < 1e-4), "{:?} became {:?}", expected, actual);
+        }
+        assert!(transform_commands(Transform::identity()).is_empty());
+
+        let svg = import(r#"<g transform="translate(50 0)"><rect width="10" height="10" transform="scale(2) rotate(90)"/></g>"#);
+        assert_eq!(pixel(&svg, 40, 10)[3], 255);
+        assert_eq!(pixel(&svg, 60, 10)[3], 0);
+    }
+
+    #[test]
+    fn styles_are_inherited_and_overridden() {
+        let svg = import(
+            r#"<g fill="red" stroke="blue" stroke-width="0" opacity="0.5" style="color: lime">
+                <rect width="50" height="50" style="fill: currentColor"/>
+                <rect x="50" width="50" height="50"/>
+                <rect y="50" width="50" height="50" display="none"/>
+                <g visibility="hidden"><rect x="50" y="50" width="50" height="50"/></g>
+            </g>"#,
+        );
+        assert_eq!(pixel(&svg, 25, 25), [0, 255, 0, 128]);
+        assert_eq!(pixel(&svg, 75, 25), [255, 0, 0, 128]);
+        assert_eq!(pixel(&svg, 25, 75)[3], 0);
+        assert_eq!(pixel(&svg, 75, 75)[3], 0);
+        assert!(svg.commands.iter().any(|command| matches!(command, DrawingCommand::SetGlobalAlpha(alpha) if *alpha == 0.5)));
+        assert!(!svg.commands.iter().any(|command| matches!(command, DrawingCommand::Stroke)));
+    }
+
+    #[test]
+    fn gradients_follow_the_bounding_box_and_inherit_stops() {
+        let svg = import(
+            r##"<defs>
+                <linearGradient id="stops"><stop offset="0" stop-color="red"/><stop offset="1" stop-color="blue"/></linearGradient>
+                <linearGradient id="across" href="#stops"/>
+                <radialGradient id="round" xlink:href="#stops" xmlns:xlink="http://www.w3.org/1999/xlink"/>
+            </defs>
+            <rect x="20" width="60" height="50" fill="url(#across)"/>
+            <rect y="50" width="100" height="50" fill="url(#round)"/>"##,
+        );
+        assert!(svg.dropped.is_empty());
+        let left = pixel(&svg, 21, 25);
+        let right = pixel(&svg, 78, 25);
+        assert!(left[0] > 240 && left[2] < 15, "{:?}", left);
+        assert!(right[2] > 240 && right[0] < 15, "{:?}", right);
+
+        // The radial gradient is stretched to an ellipse across the box
+        let center = pixel(&svg, 50, 75);
+        let side = pixel(&svg, 2, 75);
+        assert!(center[0] > 240, "{:?}", center);
+        assert!(side[2] > 230, "{:?}", side);
+    }
+
+    #[test]
+    fn unsupported_features_are_reported() {
+        let svg = import(
+            r#"<title>Logo</title>
+            <style>rect { fill: red }</style>
+            <pattern id="dots"/>
+            <text>Hello</text>
+            <image href="logo.png"/>
+            <rect width="10" height="10" filter="url(#blur)" fill="url(#dots) green"/>
+            <path d="M 0 0 L 10 0 L 0 10 z" fill-rule="evenodd"/>
+            <linearGradient id="reflected" spreadMethod="reflect"><stop stop-color="red"/><stop offset="1"/></linearGradient>
+            <circle r="5" fill="url(#reflected)"/>"#,
+        );
+        assert_eq!(
+            svg.dropped,
+            ["<image>", "<pattern>", "<style> sheets", "<text>", "fill-rule: evenodd", "filter", "spreadMethod"],
+        );
+        // The fallback color stands in for the pattern
+        let green = [0.0, 128.0 / 255.0, 0.0, 1.0];
+        assert!(svg.commands.iter().any(|command| matches!(command, DrawingCommand::SetFillColor(color) if *color == green)));
+    }
+
+    #[test]
+    fn documents_without_a_view_box_use_their_size() {
+        let svg = import_svg(r#"<svg xmlns="http://www.w3.org/2000/svg" width="2in" height="50%"/>"#).unwrap();
+        assert_eq!(svg.size, Vec2::new(192.0, DEFAULT_SVG_SIZE.y));
+        assert!(svg.commands.is_empty());
+
+        for bad in ["<svg", r#"<html xmlns="http://www.w3.org/1999/xhtml"/>"#, "<svg/>"] {
+            assert!(matches!(import_svg(bad), Err(CanvasError::InvalidArgument { .. })), "{}", bad);
+        }
+    }
+}
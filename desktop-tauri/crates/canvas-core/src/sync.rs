@@ -0,0 +1,737 @@
+//! Conflict free replication of the tile map between several editors.
+//!
+//! Every tile is replicated as a set of last-writer-wins registers plus an
+//! `alive` flag: one register per top level [`Tile`] field, and one per
+//! tag (`tags/<tag>`), property (`properties/<key>`) and drawing command
+//! (`drawing_commands/<id>`), where `null` marks a removed element.
+//! Registers are stamped with an [`OpId`] (Lamport time, replica id), so
+//! applying the same [`SyncOp`]s in any order and any number of times yields
+//! the same tiles on every replica:
+//!
+//! * concurrent adds of different tiles all survive,
+//! * concurrent edits of different fields, tags, properties or drawing
+//!   commands of one tile are all kept,
+//! * concurrent edits of the same field or element resolve to the later
+//!   [`OpId`],
+//! * a remove beats concurrent edits; only a later add or upsert brings a
+//!   removed tile back.
+//!
+//! Drawing commands are ordered by dense position keys. Commands added
+//! together share a random position prefix, so strokes drawn concurrently
+//! on two replicas both survive without interleaving.
+//!
+//! Local changes are not recorded one by one. Instead [`SyncDocument::commit`]
+//! diffs the local tile map against the last replicated state, so every
+//! mutation path (commands, batches, undo) is covered without hooking it.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use uuid::Uuid;
+
+use crate::tiles::{Tile, TileId};
+
+/// Prefixes of the registers of single elements, followed by `/` and the
+/// element's key.
+const TAGS: &str = "tags";
+const PROPERTIES: &str = "properties";
+const DRAWING_COMMANDS: &str = "drawing_commands";
+
+/// Gap between the positions of commands appended at the end, which keeps
+/// the positions of commands inserted between them short.
+const POSITION_STEP: u64 = 1 << 16;
+
+/// Identifies one editor taking part in a session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct ReplicaId(pub Uuid);
+
+impl ReplicaId {
+    pub fn new() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+impl Default for ReplicaId {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Totally ordered timestamp of an operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct OpId {
+    pub lamport: u64,
+    pub replica: ReplicaId,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum OpKind {
+    /// Creates or revives the tile and sets all of its fields.
+    Create { fields: BTreeMap<String, Value> },
+    /// Sets some fields of the tile without reviving it.
+    Set { fields: BTreeMap<String, Value> },
+    Remove,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SyncOp {
+    pub id: OpId,
+    pub tile_id: TileId,
+    #[serde(flatten)]
+    pub kind: OpKind,
+}
+
+#[derive(Debug, Clone)]
+struct Register<T> {
+    id: OpId,
+    value: T,
+}
+
+impl<T> Register<T> {
+    fn set(&mut self, id: OpId, value: T) -> bool {
+        if id > self.id {
+            self.id = id;
+            self.value = value;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TileState {
+    alive: Register<bool>,
+    fields: BTreeMap<String, Register<Value>>,
+}
+
+impl TileState {
+    fn new(id: OpId) -> Self {
+        Self {
+            alive: Register { id, value: false },
+            fields: BTreeMap::new(),
+        }
+    }
+
+    fn set_fields(&mut self, id: OpId, fields: &BTreeMap<String, Value>) -> bool {
+        let mut changed = false;
+        for (name, value) in fields {
+            match self.fields.get_mut(name) {
+                Some(register) => changed |= register.set(id, value.clone()),
+                None => {
+                    self.fields.insert(name.clone(), Register { id, value: value.clone() });
+                    changed = true;
+                }
+            }
+        }
+        changed
+    }
+
+    /// Values of the registers that aren't `null`.
+    fn values(&self) -> BTreeMap<String, Value> {
+        self.fields.iter()
+            .filter(|(_, register)| !register.value.is_null())
+            .map(|(name, register)| (name.clone(), register.value.clone()))
+            .collect()
+    }
+}
+
+/// A drawing command with its place among the others.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Element {
+    position: Vec<u32>,
+    command: Value,
+}
+
+/// Replicated state of the tiles of one canvas.
+#[derive(Debug, Clone)]
+pub struct SyncDocument {
+    replica: ReplicaId,
+    clock: u64,
+    tiles: HashMap<TileId, TileState>,
+    /// Register values of the tiles that are alive and complete, as of the
+    /// last commit or remote apply.
+    view: HashMap<TileId, BTreeMap<String, Value>>,
+}
+
+impl SyncDocument {
+    pub fn new(replica: ReplicaId) -> Self {
+        Self {
+            replica,
+            clock: 0,
+            tiles: HashMap::new(),
+            view: HashMap::new(),
+        }
+    }
+
+    pub fn replica(&self) -> ReplicaId {
+        self.replica
+    }
+
+    /// Records local changes of `tiles` since the previous commit and
+    /// returns the operations to send to the other replicas.
+    pub fn commit(&mut self, tiles: &HashMap<TileId, Tile>) -> Vec<SyncOp> {
+        let mut ops = Vec::new();
+
+        for (tile_id, tile) in tiles {
+            let previous = self.view.get(tile_id);
+            let exists = previous.is_some();
+            let fields = replicated_fields(tile, previous);
+            let mut changed: BTreeMap<String, Value> = fields.iter()
+                .filter(|(name, value)| previous.and_then(|previous| previous.get(*name)) != Some(*value))
+                .map(|(name, value)| (name.clone(), value.clone()))
+                .collect();
+            // Elements the tile no longer has, or had before it was removed
+            if let Some(state) = self.tiles.get(tile_id) {
+                for name in state.values().into_keys() {
+                    if !fields.contains_key(&name) {
+                        changed.insert(name, Value::Null);
+                    }
+                }
+            }
+
+            if !exists {
+                ops.push(self.next_op(*tile_id, OpKind::Create { fields: changed }));
+            } else if !changed.is_empty() {
+                ops.push(self.next_op(*tile_id, OpKind::Set { fields: changed }));
+            }
+        }
+
+        let removed: Vec<TileId> = self.view.keys()
+            .filter(|tile_id| !tiles.contains_key(tile_id))
+            .copied()
+            .collect();
+        for tile_id in removed {
+            ops.push(self.next_op(tile_id, OpKind::Remove));
+        }
+
+        for op in &ops {
+            self.integrate(op);
+        }
+        ops
+    }
+
+    /// Applies operations from other replicas and updates `tiles` to match.
+    /// Returns the ids of tiles that were added, changed or removed.
+    ///
+    /// Local-only state of tiles that stay, such as the native webview id,
+    /// is kept.
+    pub fn apply_remote(&mut self, ops: &[SyncOp], tiles: &mut HashMap<TileId, Tile>) -> Vec<TileId> {
+        self.apply_remote_checked(ops, tiles, |_, _| true)
+    }
+
+    /// Like [`SyncDocument::apply_remote`], but every added or changed tile
+    /// first goes through `accept`, which may also normalize it. A rejected
+    /// tile keeps its local version, or stays away if it is new, and isn't
+    /// published back either: later commits only send what changes locally.
+    pub fn apply_remote_checked(
+        &mut self,
+        ops: &[SyncOp],
+        tiles: &mut HashMap<TileId, Tile>,
+        mut accept: impl FnMut(TileId, &mut Tile) -> bool,
+    ) -> Vec<TileId> {
+        let mut touched = Vec::new();
+        // The replicated state of each touched tile as the local one matches it
+        let mut before: HashMap<TileId, Option<BTreeMap<String, Value>>> = HashMap::new();
+        for op in ops {
+            self.clock = self.clock.max(op.id.lamport);
+            before.entry(op.tile_id).or_insert_with(|| self.view.get(&op.tile_id).cloned());
+            if self.integrate(op) && !touched.contains(&op.tile_id) {
+                touched.push(op.tile_id);
+            }
+        }
+
+        let mut applied = Vec::new();
+        for tile_id in touched {
+            let Some(fields) = self.view.get(&tile_id) else {
+                tiles.remove(&tile_id);
+                applied.push(tile_id);
+                continue;
+            };
+            let Some(mut tile) = materialize(fields) else {
+                continue;
+            };
+            if let Some(existing) = tiles.get(&tile_id) {
+                tile.keep_local_state(existing);
+            }
+            if accept(tile_id, &mut tile) {
+                tiles.insert(tile_id, tile);
+                applied.push(tile_id);
+            } else {
+                match before.remove(&tile_id).flatten() {
+                    Some(fields) => self.view.insert(tile_id, fields),
+                    None => self.view.remove(&tile_id),
+                };
+            }
+        }
+        applied
+    }
+
+    /// Operations that rebuild the current state from scratch, e.g. for a
+    /// replica joining late. Every register keeps its original [`OpId`], so
+    /// the snapshot merges correctly with operations it has not seen.
+    pub fn snapshot(&self) -> Vec<SyncOp> {
+        let mut ops = Vec::new();
+        for (tile_id, state) in &self.tiles {
+            let mut by_id: BTreeMap<OpId, BTreeMap<String, Value>> = BTreeMap::new();
+            for (name, register) in &state.fields {
+                by_id.entry(register.id).or_default().insert(name.clone(), register.value.clone());
+            }
+            for (id, fields) in by_id {
+                ops.push(SyncOp { id, tile_id: *tile_id, kind: OpKind::Set { fields } });
+            }
+
+            let kind = if state.alive.value {
+                OpKind::Create { fields: BTreeMap::new() }
+            } else {
+                OpKind::Remove
+            };
+            ops.push(SyncOp { id: state.alive.id, tile_id: *tile_id, kind });
+        }
+        ops.sort_by_key(|op| op.id);
+        ops
+    }
+
+    fn next_op(&mut self, tile_id: TileId, kind: OpKind) -> SyncOp {
+        self.clock += 1;
+        SyncOp {
+            id: OpId {
+                lamport: self.clock,
+                replica: self.replica,
+            },
+            tile_id,
+            kind,
+        }
+    }
+
+    /// Merges one operation into the registers. Returns whether anything
+    /// changed.
+    fn integrate(&mut self, op: &SyncOp) -> bool {
+        let state = self.tiles.entry(op.tile_id).or_insert_with(|| TileState::new(OpId {
+            lamport: 0,
+            replica: ReplicaId(Uuid::nil()),
+        }));
+
+        let changed = match &op.kind {
+            OpKind::Create { fields } => {
+                let revived = state.alive.set(op.id, true);
+                state.set_fields(op.id, fields) || revived
+            }
+            OpKind::Set { fields } => state.set_fields(op.id, fields),
+            OpKind::Remove => state.alive.set(op.id, false),
+        };
+
+        let values = state.values();
+        if state.alive.value && materialize(&values).is_some() {
+            self.view.insert(op.tile_id, values);
+        } else {
+            self.view.remove(&op.tile_id);
+        }
+        changed
+    }
+}
+
+/// The registers `tile` is replicated as, without local-only state. Drawing
+/// commands that `previous` already has keep their registers.
+fn replicated_fields(tile: &Tile, previous: Option<&BTreeMap<String, Value>>) -> BTreeMap<String, Value> {
+    let mut tile = tile.clone();
+    tile.clear_local_state();
+    let Ok(Value::Object(object)) = serde_json::to_value(&tile) else {
+        return BTreeMap::new();
+    };
+
+    let mut fields = BTreeMap::new();
+    for (name, mut value) in object {
+        match (name.as_str(), &mut value) {
+            (TAGS, Value::Array(tags)) => {
+                for tag in tags.iter().filter_map(Value::as_str) {
+                    fields.insert(format!("{}/{}", TAGS, tag), Value::Bool(true));
+                }
+            }
+            (PROPERTIES, Value::Object(properties)) => {
+                for (key, value) in std::mem::take(properties) {
+                    fields.insert(format!("{}/{}", PROPERTIES, key), value);
+                }
+            }
+            _ => {
+                if let Some(Value::Array(commands)) = value.pointer_mut("/Skia/drawing_commands") {
+                    place_commands(std::mem::take(commands), previous, &mut fields);
+                }
+                fields.insert(name, value);
+            }
+        }
+    }
+    fields
+}
+
+/// Adds the registers of a drawing's `commands` to `fields`. Commands that
+/// didn't change around the edited part keep the registers they had in
+/// `previous`, the others get new ones positioned between them.
+fn place_commands(commands: Vec<Value>, previous: Option<&BTreeMap<String, Value>>, fields: &mut BTreeMap<String, Value>) {
+    let old = previous.map(elements).unwrap_or_default();
+    let prefix = old.iter()
+        .zip(&commands)
+        .take_while(|((_, element), command)| element.command == **command)
+        .count();
+    let suffix = old[prefix..].iter().rev()
+        .zip(commands[prefix..].iter().rev())
+        .take_while(|((_, element), command)| element.command == **command)
+        .count();
+
+    let kept = old[..prefix].iter().chain(&old[old.len() - suffix..]);
+    for (name, element) in kept {
+        fields.insert(name.clone(), serde_json::to_value(element).unwrap_or_default());
+    }
+
+    let inserted = &commands[prefix..commands.len() - suffix];
+    if inserted.is_empty() {
+        return;
+    }
+    let lo = prefix.checked_sub(1).map_or(&[][..], |i| &old[i].1.position);
+    let hi = old.get(old.len() - suffix).map(|(_, element)| element.position.as_slice());
+    let mut run = position_between(lo, hi);
+    let nonce = Uuid::new_v4().as_u64_pair().0;
+    run.extend([(nonce >> 32) as u32, nonce as u32]);
+    for (i, command) in inserted.iter().enumerate() {
+        let mut position = run.clone();
+        position.push(i as u32 + 1);
+        let element = Element { position, command: command.clone() };
+        fields.insert(
+            format!("{}/{}", DRAWING_COMMANDS, Uuid::new_v4()),
+            serde_json::to_value(element).unwrap_or_default(),
+        );
+    }
+}
+
+/// The drawing commands among `fields`, in drawing order.
+fn elements(fields: &BTreeMap<String, Value>) -> Vec<(String, Element)> {
+    let mut elements: Vec<(String, Element)> = fields.iter()
+        .filter(|(name, _)| name.split_once('/').is_some_and(|(prefix, _)| prefix == DRAWING_COMMANDS))
+        .filter_map(|(name, value)| Some((name.clone(), Element::deserialize(value).ok()?)))
+        .collect();
+    elements.sort_by(|(a_name, a), (b_name, b)| (&a.position, a_name).cmp(&(&b.position, b_name)));
+    elements
+}
+
+/// A position ordered after `lo` and before `hi`, or anywhere after `lo`
+/// without `hi`. Positions compare like words, digit by digit, and the
+/// last digit is never zero, so there is always room between two of them.
+fn position_between(lo: &[u32], hi: Option<&[u32]>) -> Vec<u32> {
+    let mut hi = hi;
+    let mut position = Vec::new();
+    for depth in 0.. {
+        let l = lo.get(depth).map_or(0, |&digit| digit as u64);
+        let h = hi.and_then(|hi| hi.get(depth)).map_or(1 << 32, |&digit| digit as u64);
+        if h > l + 1 {
+            position.push((l + ((h - l) / 2).min(POSITION_STEP)) as u32);
+            break;
+        }
+        position.push(l as u32);
+        if l != h {
+            // Below `hi` from here on
+            hi = None;
+        }
+    }
+    position
+}
+
+/// The tile described by the values of its registers, if they are complete.
+fn materialize(fields: &BTreeMap<String, Value>) -> Option<Tile> {
+    let mut object = serde_json::Map::new();
+    let mut tags = Vec::new();
+    let mut properties = serde_json::Map::new();
+    for (name, value) in fields {
+        match name.split_once('/') {
+            None => {
+                object.insert(name.clone(), value.clone());
+            }
+            Some((TAGS, tag)) => tags.push(Value::String(tag.to_string())),
+            Some((PROPERTIES, key)) => {
+                properties.insert(key.to_string(), value.clone());
+            }
+            Some(_) => {}
+        }
+    }
+    object.insert(TAGS.into(), Value::Array(tags));
+    object.insert(PROPERTIES.into(), Value::Object(properties));
+    if let Some(commands) = object.get_mut("data").and_then(|data| data.pointer_mut("/Skia/drawing_commands")) {
+        *commands = Value::Array(elements(fields).into_iter().map(|(_, element)| element.command).collect());
+    }
+    serde_json::from_value(Value::Object(object)).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::Vec2;
+
+    use super::*;
+    use crate::tiles::{DrawingCommand, PropertyValue, SkiaTile, TileData, WebViewTile};
+
+    fn replica(n: u128) -> SyncDocument {
+        SyncDocument::new(ReplicaId(Uuid::from_u128(n)))
+    }
+
+    fn sketch(x: f32) -> Tile {
+        SkiaTile::new().to_tile(Vec2::new(x, 0.0), Vec2::splat(100.0), "Sketch".into())
+    }
+
+    #[test]
+    fn concurrent_moves_converge_to_the_later_op() {
+        let tile_id = TileId(Uuid::new_v4());
+        let (mut a, mut b) = (replica(1), replica(2));
+        let (mut a_tiles, mut b_tiles) = (HashMap::new(), HashMap::new());
+
+        a_tiles.insert(tile_id, sketch(0.0));
+        let create = a.commit(&a_tiles);
+        b.apply_remote(&create, &mut b_tiles);
+
+        a_tiles.get_mut(&tile_id).unwrap().position = Vec2::new(10.0, 0.0);
+        b_tiles.get_mut(&tile_id).unwrap().position = Vec2::new(20.0, 0.0);
+        let from_a = a.commit(&a_tiles);
+        let from_b = b.commit(&b_tiles);
+
+        a.apply_remote(&from_b, &mut a_tiles);
+        b.apply_remote(&from_a, &mut b_tiles);
+
+        // Same Lamport time: the higher replica id wins on both sides
+        assert_eq!(a_tiles[&tile_id].position, Vec2::new(20.0, 0.0));
+        assert_eq!(b_tiles[&tile_id].position, Vec2::new(20.0, 0.0));
+    }
+
+    #[test]
+    fn rejected_remote_changes_are_dropped_and_not_published() {
+        let (mut a, mut b) = (replica(1), replica(2));
+        let (mut a_tiles, mut b_tiles) = (HashMap::new(), HashMap::new());
+        let valid = |tile_id: TileId, tile: &mut Tile| tile.validate(tile_id).is_ok();
+
+        let kept = TileId(Uuid::new_v4());
+        a_tiles.insert(kept, sketch(0.0));
+        b.apply_remote_checked(&a.commit(&a_tiles), &mut b_tiles, valid);
+
+        // An invalid new tile and an invalid edit of an existing one
+        let broken = TileId(Uuid::new_v4());
+        let mut tile = sketch(0.0);
+        tile.size = Vec2::new(-1.0, 10.0);
+        a_tiles.insert(broken, tile);
+        a_tiles.get_mut(&kept).unwrap().size = Vec2::ZERO;
+        let applied = b.apply_remote_checked(&a.commit(&a_tiles), &mut b_tiles, valid);
+
+        assert!(applied.is_empty());
+        assert!(!b_tiles.contains_key(&broken));
+        assert_eq!(b_tiles[&kept].size, Vec2::splat(100.0));
+        assert!(b.commit(&b_tiles).is_empty());
+
+        // Later commits only send what changed locally
+        b_tiles.get_mut(&kept).unwrap().title = "Renamed".into();
+        let ops = b.commit(&b_tiles);
+        assert!(matches!(&ops[..], [SyncOp { kind: OpKind::Set { fields }, .. }] if fields.keys().eq(["title"])));
+    }
+
+    #[test]
+    fn edits_of_different_fields_are_both_kept() {
+        let tile_id = TileId(Uuid::new_v4());
+        let (mut a, mut b) = (replica(1), replica(2));
+        let (mut a_tiles, mut b_tiles) = (HashMap::new(), HashMap::new());
+
+        a_tiles.insert(tile_id, sketch(0.0));
+        let create = a.commit(&a_tiles);
+        b.apply_remote(&create, &mut b_tiles);
+
+        a_tiles.get_mut(&tile_id).unwrap().title = "Renamed".into();
+        b_tiles.get_mut(&tile_id).unwrap().size = Vec2::splat(300.0);
+        let from_a = a.commit(&a_tiles);
+        let from_b = b.commit(&b_tiles);
+        a.apply_remote(&from_b, &mut a_tiles);
+        b.apply_remote(&from_a, &mut b_tiles);
+
+        for tiles in [&a_tiles, &b_tiles] {
+            assert_eq!(tiles[&tile_id].title, "Renamed");
+            assert_eq!(tiles[&tile_id].size, Vec2::splat(300.0));
+        }
+    }
+
+    fn commands(tile: &mut Tile) -> &mut Vec<DrawingCommand> {
+        match &mut tile.data {
+            TileData::Skia { drawing_commands } => drawing_commands,
+            _ => unreachable!(),
+        }
+    }
+
+    fn stroke(from: f32) -> [DrawingCommand; 3] {
+        [
+            DrawingCommand::MoveTo(Vec2::splat(from)),
+            DrawingCommand::LineTo(Vec2::splat(from + 10.0)),
+            DrawingCommand::Stroke,
+        ]
+    }
+
+    #[test]
+    fn concurrent_strokes_are_both_kept_whole() {
+        let tile_id = TileId(Uuid::new_v4());
+        let (mut a, mut b) = (replica(1), replica(2));
+        let (mut a_tiles, mut b_tiles) = (HashMap::new(), HashMap::new());
+
+        let mut tile = sketch(0.0);
+        commands(&mut tile).extend(stroke(0.0));
+        a_tiles.insert(tile_id, tile);
+        b.apply_remote(&a.commit(&a_tiles), &mut b_tiles);
+
+        commands(a_tiles.get_mut(&tile_id).unwrap()).extend(stroke(100.0));
+        commands(b_tiles.get_mut(&tile_id).unwrap()).extend(stroke(200.0));
+        // And one in front of the first stroke on b
+        commands(b_tiles.get_mut(&tile_id).unwrap()).splice(0..0, stroke(300.0));
+        let from_a = a.commit(&a_tiles);
+        let from_b = b.commit(&b_tiles);
+        a.apply_remote(&from_b, &mut a_tiles);
+        b.apply_remote(&from_a, &mut b_tiles);
+
+        let merged = commands(a_tiles.get_mut(&tile_id).unwrap()).clone();
+        assert_eq!(&merged, commands(b_tiles.get_mut(&tile_id).unwrap()));
+        assert_eq!(merged.len(), 12);
+        assert_eq!(merged[..6], [stroke(300.0), stroke(0.0)].concat());
+        let ends = [&merged[6..9], &merged[9..]];
+        assert!(ends.contains(&&stroke(100.0)[..]) && ends.contains(&&stroke(200.0)[..]));
+        assert!(a.commit(&a_tiles).is_empty());
+
+        // Deleting a stroke removes just its commands
+        commands(b_tiles.get_mut(&tile_id).unwrap()).drain(0..3);
+        a.apply_remote(&b.commit(&b_tiles), &mut a_tiles);
+        assert_eq!(commands(a_tiles.get_mut(&tile_id).unwrap())[..3], stroke(0.0));
+        assert_eq!(commands(a_tiles.get_mut(&tile_id).unwrap()).len(), 9);
+    }
+
+    #[test]
+    fn concurrent_tags_and_properties_merge() {
+        let tile_id = TileId(Uuid::new_v4());
+        let (mut a, mut b) = (replica(1), replica(2));
+        let (mut a_tiles, mut b_tiles) = (HashMap::new(), HashMap::new());
+
+        let mut tile = sketch(0.0);
+        tile.add_tags(&["draft"]).unwrap();
+        a_tiles.insert(tile_id, tile);
+        b.apply_remote(&a.commit(&a_tiles), &mut b_tiles);
+
+        let on_a = a_tiles.get_mut(&tile_id).unwrap();
+        on_a.add_tags(&["urgent"]).unwrap();
+        on_a.set_property("owner", PropertyValue::Text("ana".into())).unwrap();
+        let on_b = b_tiles.get_mut(&tile_id).unwrap();
+        on_b.remove_tags(&["draft"]);
+        on_b.add_tags(&["review"]).unwrap();
+        on_b.set_property("priority", PropertyValue::Number(2.0)).unwrap();
+        let from_a = a.commit(&a_tiles);
+        let from_b = b.commit(&b_tiles);
+        a.apply_remote(&from_b, &mut a_tiles);
+        b.apply_remote(&from_a, &mut b_tiles);
+
+        for tiles in [&a_tiles, &b_tiles] {
+            let tags: Vec<&str> = tiles[&tile_id].tags.iter().map(String::as_str).collect();
+            assert_eq!(tags, ["review", "urgent"]);
+            assert_eq!(tiles[&tile_id].properties.len(), 2);
+        }
+    }
+
+    #[test]
+    fn positions_fit_between_any_two() {
+        let first = position_between(&[], None);
+        let last = position_between(&first, None);
+        let mut positions = vec![first, last];
+        // Always inserting right after the first one
+        for _ in 0..100 {
+            let next = position_between(&positions[0], Some(&positions[1]));
+            positions.insert(1, next);
+        }
+        assert!(positions.windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(positions.iter().all(|position| position.last() != Some(&0)));
+    }
+
+    #[test]
+    fn remove_beats_concurrent_move() {
+        let tile_id = TileId(Uuid::new_v4());
+        let (mut a, mut b) = (replica(1), replica(2));
+        let (mut a_tiles, mut b_tiles) = (HashMap::new(), HashMap::new());
+
+        a_tiles.insert(tile_id, sketch(0.0));
+        let create = a.commit(&a_tiles);
+        b.apply_remote(&create, &mut b_tiles);
+
+        a_tiles.remove(&tile_id);
+        b_tiles.get_mut(&tile_id).unwrap().position = Vec2::splat(99.0);
+        let from_a = a.commit(&a_tiles);
+        let from_b = b.commit(&b_tiles);
+        a.apply_remote(&from_b, &mut a_tiles);
+        b.apply_remote(&from_a, &mut b_tiles);
+
+        assert!(a_tiles.is_empty());
+        assert!(b_tiles.is_empty());
+    }
+
+    #[test]
+    fn ops_are_idempotent_and_order_independent() {
+        let (mut a, mut b) = (replica(1), replica(2));
+        let mut a_tiles = HashMap::new();
+        let first = TileId(Uuid::new_v4());
+        let second = TileId(Uuid::new_v4());
+
+        a_tiles.insert(first, sketch(0.0));
+        let mut ops = a.commit(&a_tiles);
+        a_tiles.insert(second, sketch(200.0));
+        a_tiles.get_mut(&first).unwrap().visible = false;
+        ops.extend(a.commit(&a_tiles));
+
+        let mut b_tiles = HashMap::new();
+        let mut reversed = ops.clone();
+        reversed.reverse();
+        b.apply_remote(&reversed, &mut b_tiles);
+        b.apply_remote(&ops, &mut b_tiles);
+
+        assert_eq!(b_tiles.len(), 2);
+        assert!(!b_tiles[&first].visible);
+        assert!(b.commit(&b_tiles).is_empty());
+    }
+
+    #[test]
+    fn webview_ids_stay_local() {
+        let tile_id = TileId(Uuid::new_v4());
+        let (mut a, mut b) = (replica(1), replica(2));
+        let mut a_tiles = HashMap::new();
+        let mut b_tiles = HashMap::new();
+
+        let mut web = WebViewTile::new("https://example.com".into());
+        web.set_webview_id("window-a".into());
+        a_tiles.insert(tile_id, web.to_tile(Vec2::ZERO, Vec2::splat(100.0), "Docs".into()));
+        b.apply_remote(&a.commit(&a_tiles), &mut b_tiles);
+        assert!(matches!(&b_tiles[&tile_id].data, TileData::WebView { webview_id: None, .. }));
+
+        if let TileData::WebView { webview_id, .. } = &mut b_tiles.get_mut(&tile_id).unwrap().data {
+            *webview_id = Some("window-b".into());
+        }
+        assert!(b.commit(&b_tiles).is_empty());
+
+        a_tiles.get_mut(&tile_id).unwrap().position = Vec2::splat(5.0);
+        b.apply_remote(&a.commit(&a_tiles), &mut b_tiles);
+        assert!(matches!(
+            &b_tiles[&tile_id].data,
+            TileData::WebView { webview_id: Some(id), .. } if id == "window-b"
+        ));
+    }
+
+    #[test]
+    fn snapshot_rebuilds_state() {
+        let mut a = replica(1);
+        let mut a_tiles = HashMap::new();
+        let kept = TileId(Uuid::new_v4());
+        let removed = TileId(Uuid::new_v4());
+        a_tiles.insert(kept, sketch(0.0));
+        a_tiles.insert(removed, sketch(1.0));
+        a.commit(&a_tiles);
+        a_tiles.remove(&removed);
+        a.commit(&a_tiles);
+
+        let mut b = replica(2);
+        let mut b_tiles = HashMap::new();
+        b.apply_remote(&a.snapshot(), &mut b_tiles);
+        assert_eq!(b_tiles.keys().copied().collect::<Vec<_>>(), vec![kept]);
+    }
+}
@@ -0,0 +1,481 @@
+//! Fonts, shaping and line layout.
+//!
+//! Text is shaped with rustybuzz and broken into lines here, both for the
+//! rasterizer and, through the app's `measure_text` command, for the
+//! frontend, so lines break in the same places on screen and in exports.
+//! DejaVu Sans is bundled as the default family; other fonts are loaded
+//! from files with [`load_font_file`]. Text in a family that isn't loaded
+//! is set in the default one. Lines run left to right.
+
+use std::path::Path;
+use std::sync::{Arc, OnceLock, RwLock};
+
+use glam::Vec2;
+use rustybuzz::ttf_parser::{name_id, GlyphId, OutlineBuilder};
+use rustybuzz::{Direction, Face, UnicodeBuffer};
+use serde::{Deserialize, Serialize};
+use tiny_skia::PathBuilder;
+
+use crate::error::CanvasError;
+
+/// Family of the bundled font, used when no other family is asked for.
+pub const DEFAULT_FAMILY: &str = "DejaVu Sans";
+
+/// Em size of text without an explicit size, in world units.
+pub const DEFAULT_FONT_SIZE: f32 = 14.0;
+
+/// Distance between baselines without an explicit line height, in ems.
+pub const DEFAULT_LINE_HEIGHT: f32 = 1.2;
+
+/// DejaVu Sans, see `fonts/LICENSE-DejaVu`.
+static BUNDLED_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+
+struct LoadedFont {
+    family: String,
+    data: Arc<[u8]>,
+}
+
+/// Loaded fonts, the bundled one first.
+fn fonts() -> &'static RwLock<Vec<LoadedFont>> {
+    static FONTS: OnceLock<RwLock<Vec<LoadedFont>>> = OnceLock::new();
+    FONTS.get_or_init(|| {
+        RwLock::new(vec![LoadedFont { family: DEFAULT_FAMILY.to_string(), data: Arc::from(BUNDLED_FONT) }])
+    })
+}
+
+/// Loads the font file at `path` and returns its family name. A font of a
+/// family that is already loaded replaces it.
+pub fn load_font_file(path: &Path) -> Result<String, CanvasError> {
+    let data = std::fs::read(path).map_err(|e| CanvasError::invalid_argument("path", format!("can't be read: {}", e)))?;
+    load_font_data(data)
+}
+
+/// Loads a TrueType or OpenType font from its bytes and returns its family
+/// name, as [`load_font_file`].
+pub fn load_font_data(data: Vec<u8>) -> Result<String, CanvasError> {
+    let face = Face::from_slice(&data, 0).ok_or_else(|| CanvasError::invalid_argument("font", "is not a TrueType or OpenType font"))?;
+    let family = family_name(&face).ok_or_else(|| CanvasError::invalid_argument("font", "has no family name"))?;
+    drop(face);
+
+    let mut fonts = fonts().write().unwrap_or_else(|e| e.into_inner());
+    let data: Arc<[u8]> = Arc::from(data);
+    match fonts.iter_mut().find(|font| font.family == family) {
+        Some(font) => font.data = data,
+        None => fonts.push(LoadedFont { family: family.clone(), data }),
+    }
+    Ok(family)
+}
+
+/// Family names of the loaded fonts, the default first.
+pub fn families() -> Vec<String> {
+    fonts().read().unwrap_or_else(|e| e.into_inner()).iter().map(|font| font.family.clone()).collect()
+}
+
+/// Bytes of the font file loaded for `family`, e.g. to hand to a browser.
+pub fn font_data(family: &str) -> Option<Arc<[u8]>> {
+    let fonts = fonts().read().unwrap_or_else(|e| e.into_inner());
+    fonts.iter().find(|font| font.family == family).map(|font| font.data.clone())
+}
+
+/// Font for `family`, matched ignoring case, or the default font.
+fn resolve(family: Option<&str>) -> Arc<[u8]> {
+    let fonts = fonts().read().unwrap_or_else(|e| e.into_inner());
+    family
+        .and_then(|family| fonts.iter().find(|font| font.family.eq_ignore_ascii_case(family)))
+        .unwrap_or(&fonts[0])
+        .data
+        .clone()
+}
+
+/// Typographic family of `face`, or its legacy family when it has none.
+fn family_name(face: &Face) -> Option<String> {
+    let names = face.names();
+    let find = |id: u16| names.into_iter().filter(|name| name.name_id == id).find_map(|name| name.to_string());
+    find(name_id::TYPOGRAPHIC_FAMILY).or_else(|| find(name_id::FAMILY))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TextAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TextStyle {
+    /// Font family, the default family when `None` or not loaded.
+    pub family: Option<String>,
+    /// Em size in world units.
+    pub size: f32,
+    pub align: TextAlign,
+    /// Distance between baselines, in ems.
+    pub line_height: f32,
+}
+
+impl Default for TextStyle {
+    fn default() -> Self {
+        Self { family: None, size: DEFAULT_FONT_SIZE, align: TextAlign::Left, line_height: DEFAULT_LINE_HEIGHT }
+    }
+}
+
+impl TextStyle {
+    /// Default family and alignment at `size`.
+    pub fn sized(size: f32) -> Self {
+        Self { size, ..Self::default() }
+    }
+
+    pub fn validate(&self) -> Result<(), CanvasError> {
+        if !self.size.is_finite() || self.size <= 0.0 {
+            return Err(CanvasError::invalid_argument("size", "must be a positive number"));
+        }
+        if !self.line_height.is_finite() || self.line_height <= 0.0 {
+            return Err(CanvasError::invalid_argument("line_height", "must be a positive number"));
+        }
+        Ok(())
+    }
+}
+
+/// One line of a [`TextLayout`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextLine {
+    /// The line's text, without the whitespace it was broken at.
+    pub text: String,
+    /// Left end of the line, from the left of the box.
+    pub x: f32,
+    /// Baseline, from the top of the box.
+    pub baseline: f32,
+    /// Advance of the line, not counting trailing whitespace.
+    pub width: f32,
+}
+
+/// Text broken into lines and aligned inside a box. Without a box width
+/// the lines align around the left of the box instead, so centered text
+/// extends to both sides of it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextLayout {
+    pub lines: Vec<TextLine>,
+    /// Widest line.
+    pub width: f32,
+    /// Distance between baselines times the number of lines.
+    pub height: f32,
+}
+
+/// Lays out `text` with `style`, wrapping lines to `max_width` if given.
+/// Lines break at newlines and, to fit, after whitespace, or inside words
+/// that don't fit on a line of their own.
+pub fn layout(text: &str, style: &TextStyle, max_width: Option<f32>) -> TextLayout {
+    shape(text, style, max_width).layout
+}
+
+/// Outline of `text` laid out in a box with its top-left at `position`, see
+/// [`layout`]. `None` if nothing would be visible.
+pub fn outline_box(text: &str, style: &TextStyle, position: Vec2, max_width: Option<f32>) -> Option<tiny_skia::Path> {
+    let shaped = shape(text, style, max_width);
+    let face = Face::from_slice(&shaped.font, 0)?;
+    let scale = style.size / face.units_per_em() as f32;
+
+    let mut builder = GlyphOutline { path: PathBuilder::new(), origin: Vec2::ZERO, scale };
+    for (line, glyphs) in shaped.layout.lines.iter().zip(&shaped.glyphs) {
+        for glyph in glyphs {
+            builder.origin = position + Vec2::new(line.x + glyph.x, line.baseline + glyph.y);
+            face.outline_glyph(glyph.id, &mut builder);
+        }
+    }
+    builder.path.finish()
+}
+
+/// Outline of `text` in the default family at `size`, with the left end
+/// of its first baseline at `origin`.
+pub fn outline(text: &str, origin: Vec2, size: f32) -> Option<tiny_skia::Path> {
+    let style = TextStyle::sized(size);
+    let top = first_baseline(&style);
+    outline_box(text, &style, origin - Vec2::new(0.0, top), None)
+}
+
+/// Baseline of the first line of text set in `style`, from the top of its
+/// box.
+pub fn first_baseline(style: &TextStyle) -> f32 {
+    let metrics = Metrics::of(&resolve(style.family.as_deref()), style);
+    metrics.half_leading + metrics.ascent
+}
+
+/// Offset from the vertical middle of a line of text in `style` to its
+/// baseline, for centering text on a point.
+pub fn middle_to_baseline(style: &TextStyle) -> f32 {
+    let metrics = Metrics::of(&resolve(style.family.as_deref()), style);
+    (metrics.ascent + metrics.descent) / 2.0
+}
+
+/// Advance of `text` on one line, trailing whitespace included, e.g. to
+/// place the next run of text after it.
+pub fn advance(text: &str, style: &TextStyle) -> f32 {
+    let font = resolve(style.family.as_deref());
+    let Some(face) = Face::from_slice(&font, 0) else { return 0.0 };
+    let scale = style.size / face.units_per_em() as f32;
+    shape_paragraph(&face, text).glyph_positions().iter().map(|p| p.x_advance as f32 * scale).sum()
+}
+
+struct Metrics {
+    ascent: f32,
+    /// Negative below the baseline.
+    descent: f32,
+    /// Space above the ascent and below the descent on each line.
+    half_leading: f32,
+}
+
+impl Metrics {
+    fn of(font: &[u8], style: &TextStyle) -> Self {
+        let Some(face) = Face::from_slice(font, 0) else {
+            return Self { ascent: style.size, descent: 0.0, half_leading: 0.0 };
+        };
+        let scale = style.size / face.units_per_em() as f32;
+        let ascent = face.ascender() as f32 * scale;
+        let descent = face.descender() as f32 * scale;
+        let half_leading = (style.size * style.line_height - (ascent - descent)) / 2.0;
+        Self { ascent, descent, half_leading }
+    }
+}
+
+struct PlacedGlyph {
+    id: GlyphId,
+    /// From the left end of the line.
+    x: f32,
+    /// Down from the baseline.
+    y: f32,
+}
+
+struct Shaped {
+    layout: TextLayout,
+    /// Glyphs of each line.
+    glyphs: Vec<Vec<PlacedGlyph>>,
+    font: Arc<[u8]>,
+}
+
+/// Glyphs that map to the same run of text and can't be broken apart.
+struct Cluster {
+    /// Byte range in the paragraph.
+    start: usize,
+    end: usize,
+    glyphs: std::ops::Range<usize>,
+    advance: f32,
+    whitespace: bool,
+}
+
+fn shape_paragraph(face: &Face, text: &str) -> rustybuzz::GlyphBuffer {
+    let mut buffer = UnicodeBuffer::new();
+    buffer.push_str(text);
+    buffer.guess_segment_properties();
+    buffer.set_direction(Direction::LeftToRight);
+    rustybuzz::shape(face, &[], buffer)
+}
+
+fn shape(text: &str, style: &TextStyle, max_width: Option<f32>) -> Shaped {
+    let font = resolve(style.family.as_deref());
+    let mut layout = TextLayout { lines: Vec::new(), width: 0.0, height: 0.0 };
+    let mut glyphs = Vec::new();
+    let Some(face) = Face::from_slice(&font, 0) else {
+        return Shaped { layout, glyphs, font: font.clone() };
+    };
+    let scale = style.size / face.units_per_em() as f32;
+    let metrics = Metrics::of(&font, style);
+    let line_advance = style.size * style.line_height;
+
+    for paragraph in text.split('\n') {
+        let paragraph = paragraph.strip_suffix('\r').unwrap_or(paragraph);
+        let shaped = shape_paragraph(&face, paragraph);
+        let (infos, positions) = (shaped.glyph_infos(), shaped.glyph_positions());
+
+        let mut clusters: Vec<Cluster> = Vec::new();
+        for (index, (info, position)) in infos.iter().zip(positions).enumerate() {
+            let start = info.cluster as usize;
+            match clusters.last_mut() {
+                Some(cluster) if cluster.start == start => {
+                    cluster.glyphs.end = index + 1;
+                    cluster.advance += position.x_advance as f32 * scale;
+                }
+                _ => clusters.push(Cluster {
+                    start,
+                    end: paragraph.len(),
+                    glyphs: index..index + 1,
+                    advance: position.x_advance as f32 * scale,
+                    whitespace: false,
+                }),
+            }
+        }
+        for index in 0..clusters.len() {
+            if let Some(next) = clusters.get(index + 1).map(|c| c.start) {
+                clusters[index].end = next;
+            }
+            let cluster = &mut clusters[index];
+            cluster.whitespace = paragraph[cluster.start..cluster.end].chars().all(char::is_whitespace);
+        }
+
+        // Greedy breaking: a line ends before the first cluster that
+        // overflows it, after the last whitespace if there was any
+        let mut ranges = Vec::new();
+        let mut line_start = 0;
+        let mut break_after_space = None;
+        for index in 0..clusters.len() {
+            let cluster = &clusters[index];
+            let overflows =
+                max_width.is_some_and(|max| line_width(&clusters[line_start..=index]) > max && index > line_start);
+            if overflows && !cluster.whitespace {
+                let end = break_after_space.filter(|end| *end > line_start).unwrap_or(index);
+                ranges.push(line_start..end);
+                line_start = end;
+                break_after_space = None;
+            }
+            if cluster.whitespace {
+                break_after_space = Some(index + 1);
+            }
+        }
+        ranges.push(line_start..clusters.len());
+
+        for range in ranges {
+            let line_clusters = &clusters[range];
+            let width = line_width(line_clusters);
+            let (start, end) = match (line_clusters.first(), line_clusters.last()) {
+                (Some(first), Some(last)) => (first.start, last.end),
+                _ => (0, 0),
+            };
+
+            let mut pen = 0.0;
+            let mut placed = Vec::new();
+            for cluster in line_clusters {
+                for index in cluster.glyphs.clone() {
+                    let position = &positions[index];
+                    placed.push(PlacedGlyph {
+                        id: GlyphId(infos[index].glyph_id as u16),
+                        x: pen + position.x_offset as f32 * scale,
+                        y: -position.y_offset as f32 * scale,
+                    });
+                    pen += position.x_advance as f32 * scale;
+                }
+            }
+
+            let free = max_width.unwrap_or(0.0) - width;
+            let x = match style.align {
+                TextAlign::Left => 0.0,
+                TextAlign::Center => free / 2.0,
+                TextAlign::Right => free,
+            };
+            let baseline = layout.lines.len() as f32 * line_advance + metrics.half_leading + metrics.ascent;
+            layout.width = layout.width.max(width);
+            layout.lines.push(TextLine { text: paragraph[start..end].trim_end().to_string(), x, baseline, width });
+            glyphs.push(placed);
+        }
+    }
+
+    layout.height = layout.lines.len() as f32 * line_advance;
+    Shaped { layout, glyphs, font }
+}
+
+/// Advance of a line of clusters, not counting trailing whitespace.
+fn line_width(clusters: &[Cluster]) -> f32 {
+    let visible = clusters.iter().rposition(|c| !c.whitespace).map_or(0, |last| last + 1);
+    clusters[..visible].iter().map(|c| c.advance).sum()
+}
+
+/// Appends glyph outlines, which point up in font units, to a path.
+struct GlyphOutline {
+    path: PathBuilder,
+    origin: Vec2,
+    scale: f32,
+}
+
+impl GlyphOutline {
+    fn point(&self, x: f32, y: f32) -> (f32, f32) {
+        (self.origin.x + x * self.scale, self.origin.y - y * self.scale)
+    }
+}
+
+impl OutlineBuilder for GlyphOutline {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.path.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.point(x, y);
+        self.path.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let ((x1, y1), (x, y)) = (self.point(x1, y1), self.point(x, y));
+        self.path.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let ((x1, y1), (x2, y2), (x, y)) = (self.point(x1, y1), self.point(x2, y2), self.point(x, y));
+        self.path.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.path.close();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_is_outlined_on_its_baseline() {
+        let bounds = outline("Hello", Vec2::new(10.0, 100.0), 16.0).unwrap().bounds();
+        assert!(bounds.left() >= 10.0 && bounds.right() > 40.0 && bounds.right() < 60.0, "{:?}", bounds);
+        let double = outline("Hello", Vec2::new(10.0, 100.0), 32.0).unwrap().bounds();
+        assert!((double.width() - 2.0 * bounds.width()).abs() < 0.1);
+        // Capitals rise above the baseline and nothing in "Hello" descends
+        assert!(bounds.top() < 90.0 && bounds.bottom() <= 100.5, "{:?}", bounds);
+        assert!(outline("   ", Vec2::ZERO, 16.0).is_none());
+    }
+
+    #[test]
+    fn lines_wrap_at_whitespace_and_split_long_words() {
+        let style = TextStyle::sized(16.0);
+        let one_line = layout("the quick brown fox", &style, None);
+        assert_eq!(one_line.lines.len(), 1);
+
+        let wrapped = layout("the quick brown fox", &style, Some(one_line.width * 0.6));
+        let texts: Vec<&str> = wrapped.lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, ["the quick", "brown fox"]);
+        assert!(wrapped.lines.iter().all(|line| line.width <= one_line.width * 0.6));
+        assert_eq!(wrapped.height, 2.0 * 16.0 * DEFAULT_LINE_HEIGHT);
+        assert!((wrapped.lines[1].baseline - wrapped.lines[0].baseline - 16.0 * DEFAULT_LINE_HEIGHT).abs() < 1e-4);
+
+        let word = layout("abcdefghij", &style, Some(advance("abcd", &style) + 1.0));
+        let texts: Vec<&str> = word.lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, ["abcd", "efgh", "ij"]);
+
+        // Newlines always break, and empty paragraphs still take a line
+        assert_eq!(layout("a\n\nb", &style, None).lines.len(), 3);
+    }
+
+    #[test]
+    fn lines_align_inside_the_box_or_around_its_left() {
+        let centered = TextStyle { align: TextAlign::Center, ..TextStyle::sized(12.0) };
+        let line = &layout("centered", &centered, Some(200.0)).lines[0];
+        assert!((line.x - (200.0 - line.width) / 2.0).abs() < 1e-4);
+
+        let right = TextStyle { align: TextAlign::Right, ..TextStyle::sized(12.0) };
+        let line = &layout("right  ", &right, Some(200.0)).lines[0];
+        assert!((line.x + line.width - 200.0).abs() < 1e-4, "trailing spaces don't count");
+        let line = &layout("right", &right, None).lines[0];
+        assert!((line.x + line.width).abs() < 1e-4);
+    }
+
+    #[test]
+    fn unknown_families_fall_back_to_the_default_font() {
+        let fallback = TextStyle { family: Some("No Such Family".into()), ..TextStyle::sized(14.0) };
+        assert_eq!(layout("Hello", &fallback, None), layout("Hello", &TextStyle::sized(14.0), None));
+        assert!(families().contains(&DEFAULT_FAMILY.to_string()));
+        assert!(load_font_data(b"not a font".to_vec()).is_err());
+
+        // Loading the bundled font again keeps one entry for its family
+        assert_eq!(load_font_data(BUNDLED_FONT.to_vec()).unwrap(), DEFAULT_FAMILY);
+        assert_eq!(families().iter().filter(|family| *family == DEFAULT_FAMILY).count(), 1);
+    }
+}
@@ -0,0 +1,200 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Smallest size a tile can have unless its constraints say otherwise.
+pub const DEFAULT_MIN_SIZE: Vec2 = Vec2::new(50.0, 50.0);
+
+/// Relative tolerance when checking a size against a locked aspect ratio.
+const ASPECT_TOLERANCE: f32 = 1e-3;
+
+/// Limits on the size of a tile.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SizeConstraints {
+    pub min_size: Vec2,
+    /// An infinite axis is unbounded, and is written as `null`, e.g.
+    /// `[400, null]` limits only the width.
+    #[serde(with = "max_size")]
+    pub max_size: Option<Vec2>,
+    /// Locked width / height ratio.
+    pub aspect_ratio: Option<f32>,
+}
+
+impl Default for SizeConstraints {
+    fn default() -> Self {
+        Self {
+            min_size: DEFAULT_MIN_SIZE,
+            max_size: None,
+            aspect_ratio: None,
+        }
+    }
+}
+
+impl SizeConstraints {
+    /// Checks that the constraints are usable, i.e. that some size
+    /// satisfies them.
+    pub fn validate(&self) -> Result<(), ConstraintViolation> {
+        let invalid = |reason: &str| Err(ConstraintViolation::InvalidConstraints { reason: reason.to_string() });
+        if !self.min_size.is_finite() || self.min_size.min_element() < 0.0 {
+            return invalid("min_size must be finite and not negative");
+        }
+        if let Some(max_size) = self.max_size {
+            if max_size.is_nan() || max_size.cmplt(self.min_size).any() {
+                return invalid("max_size must not be smaller than min_size");
+            }
+        }
+        if let Some(ratio) = self.aspect_ratio {
+            if !ratio.is_finite() || ratio <= 0.0 {
+                return invalid("aspect_ratio must be positive");
+            }
+            if let Some(max_size) = self.max_size {
+                let (low, high) = (self.min_size.x.max(self.min_size.y * ratio), max_size.x.min(max_size.y * ratio));
+                if low > high {
+                    return invalid("no size within min_size and max_size has this aspect_ratio");
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the first constraint `size` breaks.
+    pub fn check(&self, size: Vec2) -> Result<(), ConstraintViolation> {
+        if size.cmplt(self.min_size).any() {
+            return Err(ConstraintViolation::TooSmall { min_size: self.min_size.into() });
+        }
+        if let Some(max_size) = self.max_size {
+            if size.cmpgt(max_size).any() {
+                return Err(ConstraintViolation::TooLarge { max_size: max_size.into() });
+            }
+        }
+        if let Some(ratio) = self.aspect_ratio {
+            let actual = size.x / size.y;
+            if ((actual - ratio) / ratio).abs() > ASPECT_TOLERANCE {
+                return Err(ConstraintViolation::AspectRatio { aspect_ratio: ratio });
+            }
+        }
+        Ok(())
+    }
+
+    /// The size closest to `size` that satisfies the constraints. With a
+    /// locked aspect ratio the width wins, unless that breaks the limits.
+    pub fn fit(&self, size: Vec2) -> Vec2 {
+        let max_size = self.max_size.unwrap_or(Vec2::splat(f32::INFINITY));
+        let size = size.clamp(self.min_size, max_size);
+        let Some(ratio) = self.aspect_ratio else {
+            return size;
+        };
+
+        // Widths that keep both dimensions within their limits
+        let low = self.min_size.x.max(self.min_size.y * ratio);
+        let high = max_size.x.min(max_size.y * ratio);
+        let width = if low <= high { size.x.clamp(low, high) } else { low };
+        Vec2::new(width, width / ratio)
+    }
+}
+
+/// Serializes `max_size` with `null` for unbounded axes, which JSON can't
+/// write as numbers.
+mod max_size {
+    use glam::Vec2;
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    pub fn serialize<S: Serializer>(max_size: &Option<Vec2>, serializer: S) -> Result<S::Ok, S::Error> {
+        let axis = |value: f32| (value != f32::INFINITY).then_some(value);
+        max_size.map(|max_size| [axis(max_size.x), axis(max_size.y)]).serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec2>, D::Error> {
+        let axes = Option::<[Option<f32>; 2]>::deserialize(deserializer)?;
+        let axis = |value: Option<f32>| value.unwrap_or(f32::INFINITY);
+        Ok(axes.map(|[x, y]| Vec2::new(axis(x), axis(y))))
+    }
+}
+
+/// Why a tile refused a change.
+#[derive(Debug, Clone, PartialEq, Error, Serialize)]
+#[serde(tag = "constraint", rename_all = "snake_case")]
+pub enum ConstraintViolation {
+    #[error("tile is locked against moving")]
+    NotMovable,
+    #[error("tile is locked against resizing")]
+    NotResizable,
+    #[error("size is below the minimum of {min_size:?}")]
+    TooSmall { min_size: [f32; 2] },
+    #[error("size is above the maximum of {max_size:?}")]
+    TooLarge { max_size: [f32; 2] },
+    #[error("size does not keep the locked aspect ratio {aspect_ratio}")]
+    AspectRatio { aspect_ratio: f32 },
+    #[error("invalid constraints: {reason}")]
+    InvalidConstraints { reason: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fit_clamps_and_keeps_aspect_ratio() {
+        let constraints = SizeConstraints {
+            min_size: Vec2::new(10.0, 10.0),
+            max_size: Some(Vec2::new(400.0, 300.0)),
+            aspect_ratio: Some(2.0),
+        };
+        assert_eq!(constraints.fit(Vec2::new(100.0, 10.0)), Vec2::new(100.0, 50.0));
+        // Height limit caps the width
+        assert_eq!(constraints.fit(Vec2::new(1000.0, 1000.0)), Vec2::new(400.0, 200.0));
+        // Height minimum raises the width
+        assert_eq!(constraints.fit(Vec2::new(12.0, 1.0)), Vec2::new(20.0, 10.0));
+        for size in [Vec2::new(100.0, 10.0), Vec2::new(5.0, 5000.0)] {
+            assert_eq!(constraints.check(constraints.fit(size)), Ok(()));
+        }
+    }
+
+    #[test]
+    fn check_reports_the_broken_constraint() {
+        let constraints = SizeConstraints {
+            max_size: Some(Vec2::new(200.0, 200.0)),
+            aspect_ratio: Some(1.0),
+            ..Default::default()
+        };
+        assert!(matches!(constraints.check(Vec2::new(10.0, 100.0)), Err(ConstraintViolation::TooSmall { .. })));
+        assert!(matches!(constraints.check(Vec2::new(300.0, 300.0)), Err(ConstraintViolation::TooLarge { .. })));
+        assert!(matches!(constraints.check(Vec2::new(100.0, 120.0)), Err(ConstraintViolation::AspectRatio { .. })));
+        assert_eq!(constraints.check(Vec2::new(120.0, 120.0)), Ok(()));
+    }
+
+    #[test]
+    fn unbounded_axes_round_trip_through_json() {
+        let constraints = SizeConstraints {
+            max_size: Some(Vec2::new(400.0, f32::INFINITY)),
+            ..Default::default()
+        };
+        let json = serde_json::to_value(constraints).unwrap();
+        assert_eq!(json["max_size"], serde_json::json!([400.0, null]));
+        let read: SizeConstraints = serde_json::from_value(json).unwrap();
+        assert_eq!(read, constraints);
+        assert!(matches!(read.check(Vec2::new(500.0, 100.0)), Err(ConstraintViolation::TooLarge { .. })));
+        assert_eq!(read.check(Vec2::new(100.0, 5000.0)), Ok(()));
+
+        let unlimited: SizeConstraints = serde_json::from_str(r#"{"max_size": null}"#).unwrap();
+        assert_eq!(unlimited.max_size, None);
+    }
+
+    #[test]
+    fn validate_rejects_impossible_constraints() {
+        let inverted = SizeConstraints {
+            max_size: Some(Vec2::new(10.0, 10.0)),
+            ..Default::default()
+        };
+        assert!(inverted.validate().is_err());
+        let flat = SizeConstraints { aspect_ratio: Some(0.0), ..Default::default() };
+        assert!(flat.validate().is_err());
+        let narrow = SizeConstraints {
+            max_size: Some(Vec2::new(60.0, 100.0)),
+            aspect_ratio: Some(10.0),
+            ..Default::default()
+        };
+        assert!(narrow.validate().is_err());
+    }
+}
@@ -0,0 +1,63 @@
+use std::path::Path;
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use super::{Tile, TileData, TileType};
+use crate::error::CanvasError;
+use crate::image_cache;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ImageTile {
+    pub path: String,
+    /// Pixel size of the file, unknown while it can't be read.
+    pub natural_size: Option<[u32; 2]>,
+}
+
+impl ImageTile {
+    /// Reads the natural size from the file if possible. A missing file
+    /// still makes a tile, which shows a placeholder until it appears.
+    pub fn new(path: String) -> Self {
+        let natural_size = image_cache::probe(Path::new(&path)).ok();
+        Self { path, natural_size }
+    }
+
+    /// Like [`ImageTile::new`], but fails if the file can't be read.
+    pub fn open(path: String) -> Result<Self, CanvasError> {
+        let natural_size = image_cache::probe(Path::new(&path))?;
+        Ok(Self { path, natural_size: Some(natural_size) })
+    }
+
+    /// Image tiles have no title bar and, while the natural size is known,
+    /// keep its aspect ratio; `size` is fitted to it.
+    pub fn to_tile(self, position: Vec2, size: Vec2, title: String) -> Tile {
+        let aspect_ratio = self.natural_size
+            .filter(|[width, height]| *width > 0 && *height > 0)
+            .map(|[width, height]| width as f32 / height as f32);
+        let mut tile = Tile::new(
+            position,
+            size,
+            TileType::Image,
+            TileData::Image {
+                path: self.path,
+                natural_size: self.natural_size,
+            },
+            title,
+        );
+        tile.style.show_title_bar = false;
+        tile.constraints.aspect_ratio = aspect_ratio;
+        tile.size = tile.constraints.fit(size);
+        tile
+    }
+
+    pub fn from_tile(tile: &Tile) -> Option<Self> {
+        if let TileData::Image { path, natural_size } = &tile.data {
+            Some(Self {
+                path: path.clone(),
+                natural_size: *natural_size,
+            })
+        } else {
+            None
+        }
+    }
+}
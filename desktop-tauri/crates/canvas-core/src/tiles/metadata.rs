@@ -0,0 +1,87 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::CanvasError;
+
+/// Value of a custom tile property.
+///
+/// Serialized as the plain JSON value, so `{"owner": "ana", "priority": 2,
+/// "done": false}` is a valid property map.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum PropertyValue {
+    Bool(bool),
+    Number(f64),
+    Text(String),
+}
+
+impl fmt::Display for PropertyValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PropertyValue::Bool(value) => value.fmt(f),
+            PropertyValue::Number(value) => value.fmt(f),
+            PropertyValue::Text(value) => value.fmt(f),
+        }
+    }
+}
+
+/// Trims `tag` and rejects empty tags.
+pub(crate) fn normalize_tag(tag: &str) -> Result<String, CanvasError> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(CanvasError::invalid_argument("tag", "must not be empty"));
+    }
+    Ok(tag.to_string())
+}
+
+pub(crate) fn validate_property(key: &str, value: &PropertyValue) -> Result<(), CanvasError> {
+    if key.trim().is_empty() {
+        return Err(CanvasError::invalid_argument("key", "must not be empty"));
+    }
+    if let PropertyValue::Number(number) = value {
+        if !number.is_finite() {
+            return Err(CanvasError::invalid_argument(key, "must be a finite number"));
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{add_tile, CanvasOperation, InfiniteCanvas, NewTile};
+
+    #[test]
+    fn metadata_operations_are_validated_and_undoable() {
+        let mut canvas = InfiniteCanvas::default();
+        let id = add_tile(&mut canvas, NewTile::Skia);
+
+        let batch = canvas.apply_operations(vec![
+            CanvasOperation::AddTags { tile_id: id, tags: vec!["draft".into(), "ana".into()] },
+            CanvasOperation::SetProperty { tile_id: id, key: "status".into(), value: PropertyValue::Text("open".into()) },
+        ]).unwrap();
+        assert_eq!(batch.changed, vec![id]);
+
+        // Removing a tag that isn't there changes nothing
+        let noop = canvas.apply_operations(vec![
+            CanvasOperation::RemoveTags { tile_id: id, tags: vec!["missing".into()] },
+        ]).unwrap();
+        assert!(noop.is_empty());
+
+        let invalid = canvas.apply_operations(vec![
+            CanvasOperation::RemoveTags { tile_id: id, tags: vec!["draft".into()] },
+            CanvasOperation::AddTags { tile_id: id, tags: vec!["  ".into()] },
+        ]).unwrap_err();
+        assert_eq!(invalid.code(), "operation_failed");
+        assert!(canvas.get_tile(id).unwrap().tags.contains("draft"));
+
+        canvas.apply_operations(vec![
+            CanvasOperation::RemoveProperty { tile_id: id, key: "status".into() },
+        ]).unwrap();
+        assert!(canvas.get_tile(id).unwrap().properties.is_empty());
+        assert!(canvas.undo());
+        assert_eq!(canvas.get_tile(id).unwrap().properties["status"], PropertyValue::Text("open".into()));
+    }
+
+}
@@ -0,0 +1,22 @@
+mod constraints;
+mod metadata;
+mod style;
+mod tile;
+mod webview_tile;
+mod egui_tile;
+mod skia_tile;
+mod note_tile;
+mod image_tile;
+mod terminal_tile;
+
+pub use constraints::{ConstraintViolation, SizeConstraints, DEFAULT_MIN_SIZE};
+pub use metadata::PropertyValue;
+pub use style::{DropShadow, TileStyle, TITLE_BAR_HEIGHT};
+pub(crate) use style::validate_color;
+pub use tile::{DrawingCommand, Gradient, GradientStop, LineCap, LineJoin, TextBox, Tile, TileId, TileType, TileData};
+pub use webview_tile::WebViewTile;
+pub use egui_tile::EguiTile;
+pub use skia_tile::SkiaTile;
+pub use note_tile::NoteTile;
+pub use image_tile::ImageTile;
+pub use terminal_tile::{TerminalTile, TERMINAL_CELL_SIZE, TERMINAL_FONT_SIZE, TERMINAL_PADDING};
\ No newline at end of file
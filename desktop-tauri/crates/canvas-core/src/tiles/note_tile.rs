@@ -0,0 +1,42 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use super::{Tile, TileData, TileType};
+use crate::markdown::NoteDocument;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct NoteTile {
+    pub markdown: String,
+}
+
+impl NoteTile {
+    pub fn new(markdown: String) -> Self {
+        Self { markdown }
+    }
+
+    pub fn to_tile(self, position: Vec2, size: Vec2, title: String) -> Tile {
+        Tile::new(
+            position,
+            size,
+            TileType::Note,
+            TileData::Note {
+                markdown: self.markdown,
+            },
+            title,
+        )
+    }
+
+    pub fn from_tile(tile: &Tile) -> Option<Self> {
+        if let TileData::Note { markdown } = &tile.data {
+            Some(Self {
+                markdown: markdown.clone(),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn document(&self) -> NoteDocument {
+        NoteDocument::parse(&self.markdown)
+    }
+}
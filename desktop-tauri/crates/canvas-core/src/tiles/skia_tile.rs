@@ -0,0 +1,239 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use super::style::{validate_color, validate_non_negative};
+use super::{DrawingCommand, Gradient, Tile, TileData, TileType};
+use crate::error::CanvasError;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SkiaTile {
+    pub drawing_commands: Vec<DrawingCommand>,
+}
+
+impl SkiaTile {
+    pub fn new() -> Self {
+        Self {
+            drawing_commands: Vec::new(),
+        }
+    }
+
+    pub fn to_tile(self, position: Vec2, size: Vec2, title: String) -> Tile {
+        Tile::new(
+            position,
+            size,
+            TileType::Skia,
+            TileData::Skia {
+                drawing_commands: self.drawing_commands,
+            },
+            title,
+        )
+    }
+
+    pub fn from_tile(tile: &Tile) -> Option<Self> {
+        if let TileData::Skia { drawing_commands } = &tile.data {
+            Some(Self {
+                drawing_commands: drawing_commands.clone(),
+            })
+        } else {
+            None
+        }
+    }
+
+    pub fn add_command(&mut self, command: DrawingCommand) {
+        self.drawing_commands.push(command);
+    }
+
+    pub fn clear_commands(&mut self) {
+        self.drawing_commands.clear();
+    }
+
+    /// Validates every command, naming the first bad one by its index, e.g.
+    /// `commands[3].stroke_width`.
+    pub fn validate_commands(commands: &[DrawingCommand]) -> Result<(), CanvasError> {
+        for (index, command) in commands.iter().enumerate() {
+            command.validate().map_err(|e| match e {
+                CanvasError::InvalidArgument { field, reason } => {
+                    CanvasError::invalid_argument(format!("commands[{}].{}", index, field), reason)
+                }
+                other => other,
+            })?;
+        }
+        Ok(())
+    }
+}
+
+impl DrawingCommand {
+    /// Rejects numbers the renderer could not draw: non-finite coordinates,
+    /// colors and alphas outside 0..=1, negative widths, lengths and radii,
+    /// and font sizes, line heights and text box widths that are not
+    /// positive. Rectangles may have negative sizes, they extend left or up
+    /// from their position.
+    pub fn validate(&self) -> Result<(), CanvasError> {
+        let point = |field: &str, point: Vec2| validate_finite(field, point.is_finite());
+        match self {
+            DrawingCommand::MoveTo(p) | DrawingCommand::LineTo(p) => point("point", *p),
+            DrawingCommand::QuadTo(c, p) => point("control", *c).and(point("point", *p)),
+            DrawingCommand::CubicTo(c1, c2, p) => {
+                point("control1", *c1).and(point("control2", *c2)).and(point("point", *p))
+            }
+            DrawingCommand::ClosePath | DrawingCommand::Fill | DrawingCommand::Stroke => Ok(()),
+            DrawingCommand::SetFillColor(color) | DrawingCommand::SetStrokeColor(color) => {
+                validate_color("color", *color)
+            }
+            DrawingCommand::SetStrokeWidth(width) => validate_non_negative("stroke_width", *width),
+            DrawingCommand::DrawRect(position, size) => point("position", *position).and(point("size", *size)),
+            DrawingCommand::DrawCircle(center, radius) => {
+                point("center", *center).and(validate_non_negative("radius", *radius))
+            }
+            DrawingCommand::DrawText(_, position, size) => {
+                point("position", *position)?;
+                if size.is_finite() && *size > 0.0 {
+                    Ok(())
+                } else {
+                    Err(CanvasError::invalid_argument("font_size", "must be positive"))
+                }
+            }
+            DrawingCommand::Save | DrawingCommand::Restore | DrawingCommand::Clip => Ok(()),
+            DrawingCommand::Translate(offset) => point("offset", *offset),
+            DrawingCommand::Scale(factor) => point("factor", *factor),
+            DrawingCommand::Rotate(angle) => validate_finite("angle", angle.is_finite()),
+            DrawingCommand::SetGlobalAlpha(alpha) => {
+                if (0.0..=1.0).contains(alpha) {
+                    Ok(())
+                } else {
+                    Err(CanvasError::invalid_argument("alpha", "must be between 0 and 1"))
+                }
+            }
+            DrawingCommand::SetFillGradient(gradient) | DrawingCommand::SetStrokeGradient(gradient) => {
+                gradient.validate()
+            }
+            DrawingCommand::SetLineDash(dashes, offset) => {
+                for dash in dashes {
+                    validate_non_negative("dashes", *dash)?;
+                }
+                validate_finite("offset", offset.is_finite())
+            }
+            DrawingCommand::SetLineCap(_) | DrawingCommand::SetLineJoin(_) => Ok(()),
+            DrawingCommand::SetMiterLimit(limit) => validate_non_negative("miter_limit", *limit),
+            DrawingCommand::Arc(center, radius, start, end) => {
+                point("center", *center)?;
+                validate_non_negative("radius", *radius)?;
+                validate_finite("angle", start.is_finite() && end.is_finite())
+            }
+            DrawingCommand::DrawRoundedRect(position, size, radius) => {
+                point("position", *position)?;
+                point("size", *size)?;
+                validate_non_negative("radius", *radius)
+            }
+            DrawingCommand::DrawEllipse(center, radii) => {
+                point("center", *center)?;
+                validate_non_negative("radii", radii.x)?;
+                validate_non_negative("radii", radii.y)
+            }
+            DrawingCommand::DrawTextBox(text_box) => {
+                point("position", text_box.position)?;
+                if let Some(width) = text_box.width {
+                    if !width.is_finite() || width <= 0.0 {
+                        return Err(CanvasError::invalid_argument("width", "must be positive"));
+                    }
+                }
+                text_box.style.validate()
+            }
+        }
+    }
+}
+
+impl Gradient {
+    pub fn validate(&self) -> Result<(), CanvasError> {
+        let stops = match self {
+            Gradient::Linear { start, end, stops } => {
+                validate_finite("start", start.is_finite())?;
+                validate_finite("end", end.is_finite())?;
+                stops
+            }
+            Gradient::Radial { center, radius, stops } => {
+                validate_finite("center", center.is_finite())?;
+                validate_non_negative("radius", *radius)?;
+                stops
+            }
+        };
+        if stops.is_empty() {
+            return Err(CanvasError::invalid_argument("stops", "must not be empty"));
+        }
+        for (index, stop) in stops.iter().enumerate() {
+            if !(0.0..=1.0).contains(&stop.offset) {
+                return Err(CanvasError::invalid_argument(format!("stops[{}].offset", index), "must be between 0 and 1"));
+            }
+            validate_color(&format!("stops[{}].color", index), stop.color)?;
+        }
+        Ok(())
+    }
+}
+
+fn validate_finite(field: &str, finite: bool) -> Result<(), CanvasError> {
+    if finite {
+        Ok(())
+    } else {
+        Err(CanvasError::invalid_argument(field, "must be finite"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::text::TextStyle;
+    use crate::tiles::{GradientStop, TextBox};
+
+    #[test]
+    fn invalid_commands_are_named_by_index() {
+        let good = vec![
+            DrawingCommand::SetStrokeWidth(0.0),
+            DrawingCommand::DrawRect(Vec2::new(10.0, 10.0), Vec2::new(-5.0, 5.0)),
+            DrawingCommand::DrawText("hi".into(), Vec2::ZERO, 12.0),
+        ];
+        assert!(SkiaTile::validate_commands(&good).is_ok());
+
+        for (bad, field) in [
+            (DrawingCommand::SetStrokeWidth(f32::NAN), "commands[1].stroke_width"),
+            (DrawingCommand::SetStrokeWidth(-1.0), "commands[1].stroke_width"),
+            (DrawingCommand::SetFillColor([0.0, 0.0, 1.5, 1.0]), "commands[1].color"),
+            (DrawingCommand::CubicTo(Vec2::ZERO, Vec2::INFINITY, Vec2::ZERO), "commands[1].control2"),
+            (DrawingCommand::DrawCircle(Vec2::ZERO, -2.0), "commands[1].radius"),
+            (DrawingCommand::DrawText("hi".into(), Vec2::ZERO, 0.0), "commands[1].font_size"),
+            (DrawingCommand::SetGlobalAlpha(1.5), "commands[1].alpha"),
+            (
+                DrawingCommand::DrawTextBox(TextBox {
+                    text: "hi".into(),
+                    position: Vec2::ZERO,
+                    width: Some(0.0),
+                    style: TextStyle::default(),
+                }),
+                "commands[1].width",
+            ),
+            (
+                DrawingCommand::DrawTextBox(TextBox {
+                    text: "hi".into(),
+                    position: Vec2::ZERO,
+                    width: None,
+                    style: TextStyle { line_height: -1.0, ..TextStyle::default() },
+                }),
+                "commands[1].line_height",
+            ),
+            (DrawingCommand::SetLineDash(vec![4.0, -1.0], 0.0), "commands[1].dashes"),
+            (DrawingCommand::Rotate(f32::INFINITY), "commands[1].angle"),
+            (
+                DrawingCommand::SetFillGradient(Gradient::Linear {
+                    start: Vec2::ZERO,
+                    end: Vec2::X,
+                    stops: vec![GradientStop { offset: 2.0, color: [0.0; 4] }],
+                }),
+                "commands[1].stops[0].offset",
+            ),
+        ] {
+            match SkiaTile::validate_commands(&[DrawingCommand::Fill, bad]) {
+                Err(CanvasError::InvalidArgument { field: actual, .. }) => assert_eq!(actual, field),
+                other => panic!("{:?}", other),
+            }
+        }
+    }
+}
\ No newline at end of file
@@ -0,0 +1,143 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use crate::error::CanvasError;
+
+/// Height of the title bar in world units.
+pub const TITLE_BAR_HEIGHT: f32 = 30.0;
+
+/// How a tile's frame is drawn. Colors are RGBA with components in `0..=1`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TileStyle {
+    pub border_color: [f32; 4],
+    pub border_width: f32,
+    pub background: [f32; 4],
+    pub corner_radius: f32,
+    /// Opacity of the whole tile, applied after its parts are composited.
+    pub opacity: f32,
+    pub shadow: Option<DropShadow>,
+    pub show_title_bar: bool,
+    pub title_bar_color: [f32; 4],
+    pub title_color: [f32; 4],
+}
+
+impl Default for TileStyle {
+    fn default() -> Self {
+        Self {
+            border_color: rgb(0x3a3a3a),
+            border_width: 2.0,
+            background: rgb(0x2a2a2a),
+            corner_radius: 6.0,
+            opacity: 1.0,
+            shadow: None,
+            show_title_bar: true,
+            title_bar_color: rgb(0x3a3a3a),
+            title_color: rgb(0xf6f6f6),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct DropShadow {
+    pub offset: Vec2,
+    /// Blur radius in world units.
+    pub blur: f32,
+    pub color: [f32; 4],
+}
+
+impl TileStyle {
+    pub fn validate(&self) -> Result<(), CanvasError> {
+        for (field, color) in [
+            ("border_color", self.border_color),
+            ("background", self.background),
+            ("title_bar_color", self.title_bar_color),
+            ("title_color", self.title_color),
+        ] {
+            validate_color(field, color)?;
+        }
+        validate_non_negative("border_width", self.border_width)?;
+        validate_non_negative("corner_radius", self.corner_radius)?;
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(CanvasError::invalid_argument("opacity", "must be between 0 and 1"));
+        }
+        if let Some(shadow) = &self.shadow {
+            if !shadow.offset.is_finite() {
+                return Err(CanvasError::invalid_argument("shadow.offset", "must be finite"));
+            }
+            validate_non_negative("shadow.blur", shadow.blur)?;
+            validate_color("shadow.color", shadow.color)?;
+        }
+        Ok(())
+    }
+
+    /// Corner radius that fits into a tile of `size`.
+    pub fn effective_radius(&self, size: Vec2) -> f32 {
+        self.corner_radius.min(size.min_element() * 0.5).max(0.0)
+    }
+
+    /// Height of the title bar on a tile of `size`, zero when hidden.
+    pub fn title_bar_height(&self, size: Vec2) -> f32 {
+        if self.show_title_bar {
+            TITLE_BAR_HEIGHT.min(size.y)
+        } else {
+            0.0
+        }
+    }
+}
+
+pub(crate) fn validate_color(field: &str, color: [f32; 4]) -> Result<(), CanvasError> {
+    if color.iter().all(|c| (0.0..=1.0).contains(c)) {
+        Ok(())
+    } else {
+        Err(CanvasError::invalid_argument(field, "color components must be between 0 and 1"))
+    }
+}
+
+pub(crate) fn validate_non_negative(field: &str, value: f32) -> Result<(), CanvasError> {
+    if value.is_finite() && value >= 0.0 {
+        Ok(())
+    } else {
+        Err(CanvasError::invalid_argument(field, "must be finite and not negative"))
+    }
+}
+
+fn rgb(hex: u32) -> [f32; 4] {
+    let channel = |shift: u32| ((hex >> shift) & 0xff) as f32 / 255.0;
+    [channel(16), channel(8), channel(0), 1.0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::canvas::{add_tile, CanvasOperation, InfiniteCanvas, NewTile};
+
+    #[test]
+    fn style_presets_are_stored_and_applied() {
+        let mut canvas = InfiniteCanvas::default();
+        let id = add_tile(&mut canvas, NewTile::Skia);
+        let sticky = TileStyle {
+            background: [1.0, 0.9, 0.4, 1.0],
+            show_title_bar: false,
+            ..Default::default()
+        };
+
+        let batch = canvas.apply_operations(vec![
+            CanvasOperation::SaveStylePreset { name: "sticky".into(), style: sticky.clone() },
+            CanvasOperation::ApplyStylePreset { tile_id: id, preset: "sticky".into() },
+        ]).unwrap();
+        assert!(batch.presets_changed);
+        assert_eq!(canvas.get_tile(id).unwrap().style, sticky);
+
+        let invalid = TileStyle { opacity: 2.0, ..Default::default() };
+        assert!(canvas.apply_operations(vec![CanvasOperation::SetStyle { tile_id: id, style: invalid }]).is_err());
+
+        // A failing batch leaves the presets alone too
+        assert!(canvas.apply_operations(vec![
+            CanvasOperation::DeleteStylePreset { name: "sticky".into() },
+            CanvasOperation::ApplyStylePreset { tile_id: id, preset: "sticky".into() },
+        ]).is_err());
+        assert!(canvas.style_presets.contains_key("sticky"));
+    }
+
+}
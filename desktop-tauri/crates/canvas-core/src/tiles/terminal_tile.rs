@@ -0,0 +1,80 @@
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+
+use super::{Tile, TileData, TileType, TITLE_BAR_HEIGHT};
+
+/// Font size of terminal text in world units.
+pub const TERMINAL_FONT_SIZE: f32 = 13.0;
+/// Width and height of one terminal cell in world units. The frontend lays
+/// out the grid with the same metrics, so the shell's idea of the size
+/// matches what is drawn.
+pub const TERMINAL_CELL_SIZE: Vec2 = Vec2::new(7.8, 16.0);
+/// Inset of the grid from the frame and title bar.
+pub const TERMINAL_PADDING: f32 = 6.0;
+
+/// Fewest rows and columns a terminal tile can be resized to.
+const MIN_GRID: (u16, u16) = (4, 20);
+
+/// A local shell. Only the working directory is part of the document: the
+/// program is picked when the session starts, so opening a board or joining
+/// a shared one never runs something another user chose.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TerminalTile {
+    pub cwd: Option<String>,
+}
+
+impl TerminalTile {
+    pub fn new(cwd: Option<String>) -> Self {
+        Self { cwd }
+    }
+
+    pub fn to_tile(self, position: Vec2, size: Vec2, title: String) -> Tile {
+        let mut tile = Tile::new(
+            position,
+            size,
+            TileType::Terminal,
+            TileData::Terminal { cwd: self.cwd },
+            title,
+        );
+        let (rows, cols) = MIN_GRID;
+        let chrome = Vec2::new(0.0, TITLE_BAR_HEIGHT) + Vec2::splat(2.0 * TERMINAL_PADDING);
+        tile.constraints.min_size = TERMINAL_CELL_SIZE * Vec2::new(cols as f32, rows as f32) + chrome;
+        tile.size = tile.constraints.fit(size);
+        tile
+    }
+
+    pub fn from_tile(tile: &Tile) -> Option<Self> {
+        if let TileData::Terminal { cwd } = &tile.data {
+            Some(Self { cwd: cwd.clone() })
+        } else {
+            None
+        }
+    }
+
+    /// Rows and columns of the grid that fits into `tile` below its title
+    /// bar, at least one of each.
+    pub fn grid_size(tile: &Tile) -> (u16, u16) {
+        let inner = tile.size - Vec2::new(0.0, tile.style.title_bar_height(tile.size)) - Vec2::splat(2.0 * TERMINAL_PADDING);
+        // The epsilon keeps sizes made from whole cells from losing one to
+        // rounding
+        let cells = (inner / TERMINAL_CELL_SIZE + 1e-3).floor().clamp(Vec2::ONE, Vec2::splat(u16::MAX as f32));
+        (cells.y as u16, cells.x as u16)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_follows_the_tile_size() {
+        let tile = TerminalTile::new(None).to_tile(Vec2::ZERO, Vec2::new(640.0, 400.0), "Shell".into());
+        let header = tile.style.title_bar_height(tile.size);
+        let (rows, cols) = TerminalTile::grid_size(&tile);
+        assert_eq!(cols, ((640.0 - 2.0 * TERMINAL_PADDING) / TERMINAL_CELL_SIZE.x) as u16);
+        assert_eq!(rows, ((400.0 - header - 2.0 * TERMINAL_PADDING) / TERMINAL_CELL_SIZE.y) as u16);
+
+        let tiny = TerminalTile::new(None).to_tile(Vec2::ZERO, Vec2::splat(10.0), "Shell".into());
+        assert_eq!(TerminalTile::grid_size(&tiny), MIN_GRID);
+    }
+}
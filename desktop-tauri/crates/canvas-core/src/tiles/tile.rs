@@ -0,0 +1,407 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::str::FromStr;
+
+use glam::Vec2;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::CanvasError;
+use crate::geometry::Rect;
+use crate::text::TextStyle;
+use crate::tiles::constraints::{ConstraintViolation, SizeConstraints};
+use crate::tiles::metadata::{self, PropertyValue};
+use crate::tiles::skia_tile::SkiaTile;
+use crate::tiles::style::TileStyle;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TileId(pub Uuid);
+
+impl fmt::Display for TileId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for TileId {
+    type Err = CanvasError;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Uuid::parse_str(value)
+            .map(TileId)
+            .map_err(|_| CanvasError::InvalidTileId { value: value.to_string() })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum TileType {
+    WebView,
+    Egui,
+    Skia,
+    Note,
+    Image,
+    Terminal,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TileData {
+    WebView {
+        url: String,
+        webview_id: Option<String>,
+    },
+    Egui {
+        widget_type: String,
+        config: serde_json::Value,
+    },
+    /// Commands in JSON, [compactly encoded](crate::drawing_codec) in
+    /// binary formats.
+    Skia {
+        #[serde(with = "crate::drawing_codec::compact")]
+        drawing_commands: Vec<DrawingCommand>,
+    },
+    /// Markdown source of a note.
+    Note {
+        markdown: String,
+    },
+    /// An image file on the local disk.
+    Image {
+        path: String,
+        natural_size: Option<[u32; 2]>,
+    },
+    /// A local shell; the session itself lives outside the document.
+    Terminal {
+        cwd: Option<String>,
+    },
+}
+
+/// One step of a Skia tile's drawing. See [`crate::render::skia`] for what
+/// the commands do when they run.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum DrawingCommand {
+    MoveTo(Vec2),
+    LineTo(Vec2),
+    QuadTo(Vec2, Vec2),
+    CubicTo(Vec2, Vec2, Vec2),
+    ClosePath,
+    SetFillColor([f32; 4]),
+    SetStrokeColor([f32; 4]),
+    SetStrokeWidth(f32),
+    Fill,
+    Stroke,
+    DrawRect(Vec2, Vec2),
+    DrawCircle(Vec2, f32),
+    DrawText(String, Vec2, f32),
+    /// Pushes the drawing state: paints, stroke settings, transform, clip
+    /// and global alpha.
+    Save,
+    /// Pops the state pushed by the matching `Save`.
+    Restore,
+    Translate(Vec2),
+    Scale(Vec2),
+    /// Rotates by an angle in radians, clockwise on screen.
+    Rotate(f32),
+    /// Multiplies the alpha of everything painted afterwards, 0 to 1.
+    SetGlobalAlpha(f32),
+    SetFillGradient(Gradient),
+    SetStrokeGradient(Gradient),
+    /// Lengths of alternating dashes and gaps, and how far into the pattern
+    /// strokes start. An odd list is repeated once, an empty one strokes
+    /// solid lines.
+    SetLineDash(Vec<f32>, f32),
+    SetLineCap(LineCap),
+    SetLineJoin(LineJoin),
+    SetMiterLimit(f32),
+    /// Adds a circular arc around a center with a radius, from a start to
+    /// an end angle in radians. It sweeps clockwise on screen when the end
+    /// is the larger angle, and at most one full turn.
+    Arc(Vec2, f32, f32, f32),
+    /// Limits everything painted afterwards to the inside of the current
+    /// path, intersected with the clip there already is.
+    Clip,
+    /// A rectangle by position and size whose corners are rounded with a
+    /// radius.
+    DrawRoundedRect(Vec2, Vec2, f32),
+    /// An ellipse by center and radii.
+    DrawEllipse(Vec2, Vec2),
+    /// Text laid out in a box, see [`TextBox`].
+    DrawTextBox(TextBox),
+}
+
+/// Text set in a box by its top-left corner, wrapped to the box width if
+/// it has one and aligned inside it. See [`crate::text::layout`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextBox {
+    pub text: String,
+    pub position: Vec2,
+    #[serde(default)]
+    pub width: Option<f32>,
+    #[serde(default)]
+    pub style: TextStyle,
+}
+
+/// Shape of the ends of stroked open subpaths.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LineCap {
+    #[default]
+    Butt,
+    Round,
+    Square,
+}
+
+/// Shape of the corners of strokes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum LineJoin {
+    #[default]
+    Miter,
+    Round,
+    Bevel,
+}
+
+/// A color at `offset`, 0 to 1 along a gradient.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct GradientStop {
+    pub offset: f32,
+    pub color: [f32; 4],
+}
+
+/// A paint that blends between colors. Coordinates are in the space of the
+/// transform current when it is painted with, and the colors beyond the
+/// first and last stop extend outwards.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Gradient {
+    Linear {
+        start: Vec2,
+        end: Vec2,
+        stops: Vec<GradientStop>,
+    },
+    Radial {
+        center: Vec2,
+        radius: f32,
+        stops: Vec<GradientStop>,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Tile {
+    pub position: Vec2,
+    pub size: Vec2,
+    pub rotation: f32,
+    pub z_index: f32,
+    pub tile_type: TileType,
+    pub data: TileData,
+    pub title: String,
+    pub resizable: bool,
+    pub movable: bool,
+    pub visible: bool,
+    #[serde(default)]
+    pub constraints: SizeConstraints,
+    #[serde(default)]
+    pub style: TileStyle,
+    /// Free-form labels such as an owner, status or topic.
+    #[serde(default)]
+    pub tags: BTreeSet<String>,
+    /// Typed custom properties.
+    #[serde(default)]
+    pub properties: BTreeMap<String, PropertyValue>,
+}
+
+impl Tile {
+    pub fn new(
+        position: Vec2,
+        size: Vec2,
+        tile_type: TileType,
+        data: TileData,
+        title: String,
+    ) -> Self {
+        Self {
+            position,
+            size,
+            rotation: 0.0,
+            z_index: 0.0,
+            tile_type,
+            data,
+            title,
+            resizable: true,
+            movable: true,
+            visible: true,
+            constraints: SizeConstraints::default(),
+            style: TileStyle::default(),
+            tags: BTreeSet::new(),
+            properties: BTreeMap::new(),
+        }
+    }
+
+    /// Axis aligned bounds around the tile center, ignoring rotation.
+    pub fn bounds(&self) -> Rect {
+        Rect::from_center_size(self.position, self.size)
+    }
+
+    pub fn contains_point(&self, point: Vec2) -> bool {
+        // For simplicity, we're ignoring rotation here
+        self.bounds().contains(point)
+    }
+
+    /// Moves the tile unless it is locked. Moving to the current position
+    /// always succeeds.
+    pub fn move_to(&mut self, position: Vec2) -> Result<(), ConstraintViolation> {
+        if position == self.position {
+            return Ok(());
+        }
+        if !self.movable {
+            return Err(ConstraintViolation::NotMovable);
+        }
+        self.position = position;
+        Ok(())
+    }
+
+    pub fn move_by(&mut self, delta: Vec2) -> Result<(), ConstraintViolation> {
+        self.move_to(self.position + delta)
+    }
+
+    /// Checks the current size against the constraints, e.g. for a tile
+    /// about to be added.
+    pub fn check_size(&self) -> Result<(), ConstraintViolation> {
+        self.constraints.check(self.size)
+    }
+
+    /// Checks what the canvas operations ensure for the tiles they make, for
+    /// a tile that comes from elsewhere, such as another replica: finite
+    /// geometry, usable constraints the size satisfies, and a valid style,
+    /// metadata and drawing.
+    pub fn validate(&self, tile_id: TileId) -> Result<(), CanvasError> {
+        for (field, finite) in [
+            ("position", self.position.is_finite()),
+            ("rotation", self.rotation.is_finite()),
+            ("z_index", self.z_index.is_finite()),
+        ] {
+            if !finite {
+                return Err(CanvasError::invalid_argument(field, "must be finite"));
+            }
+        }
+        if !self.size.is_finite() || self.size.min_element() <= 0.0 {
+            return Err(CanvasError::invalid_argument("size", "must be finite and positive"));
+        }
+        self.constraints.validate()
+            .and_then(|_| self.check_size())
+            .map_err(|violation| CanvasError::constraint(tile_id, violation))?;
+        self.style.validate()?;
+        for tag in &self.tags {
+            if metadata::normalize_tag(tag)? != *tag {
+                return Err(CanvasError::invalid_argument("tag", "must not start or end with whitespace"));
+            }
+        }
+        for (key, value) in &self.properties {
+            metadata::validate_property(key, value)?;
+        }
+        if let TileData::Skia { drawing_commands } = &self.data {
+            SkiaTile::validate_commands(drawing_commands)?;
+        }
+        Ok(())
+    }
+
+    /// Resizes the tile to the admissible size closest to `size`, e.g.
+    /// while dragging a handle, and returns the size it ended up with.
+    pub fn resize(&mut self, size: Vec2) -> Result<Vec2, ConstraintViolation> {
+        let size = self.constraints.fit(size);
+        self.resize_exact(size)?;
+        Ok(size)
+    }
+
+    /// Resizes the tile to exactly `size`, which must satisfy its
+    /// constraints. Resizing to the current size always succeeds.
+    pub fn resize_exact(&mut self, size: Vec2) -> Result<(), ConstraintViolation> {
+        if size == self.size {
+            return Ok(());
+        }
+        if !self.resizable {
+            return Err(ConstraintViolation::NotResizable);
+        }
+        self.constraints.check(size)?;
+        self.size = size;
+        Ok(())
+    }
+
+    pub fn set_lock(&mut self, movable: bool, resizable: bool) {
+        self.movable = movable;
+        self.resizable = resizable;
+    }
+
+    /// Replaces the size constraints and fits the current size into them.
+    /// A tile locked against resizing only takes constraints its current
+    /// size already satisfies.
+    pub fn set_constraints(&mut self, constraints: SizeConstraints) -> Result<(), ConstraintViolation> {
+        constraints.validate()?;
+        if !self.resizable {
+            constraints.check(self.size)?;
+        }
+        self.constraints = constraints;
+        self.size = constraints.fit(self.size);
+        Ok(())
+    }
+
+    pub fn set_z_index(&mut self, z_index: f32) {
+        self.z_index = z_index;
+    }
+
+    pub fn rotate(&mut self, angle: f32) {
+        self.rotation += angle;
+        // Normalize rotation to [0, 2π)
+        self.rotation %= 2.0 * std::f32::consts::PI;
+    }
+
+    pub fn set_visibility(&mut self, visible: bool) {
+        self.visible = visible;
+    }
+
+    /// Adds `tags`, trimmed. Returns whether any of them was new.
+    pub fn add_tags<S: AsRef<str>>(&mut self, tags: &[S]) -> Result<bool, CanvasError> {
+        let tags = tags.iter()
+            .map(|tag| metadata::normalize_tag(tag.as_ref()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut added = false;
+        for tag in tags {
+            added |= self.tags.insert(tag);
+        }
+        Ok(added)
+    }
+
+    /// Removes `tags`. Returns whether any of them was present.
+    pub fn remove_tags<S: AsRef<str>>(&mut self, tags: &[S]) -> bool {
+        let mut removed = false;
+        for tag in tags {
+            removed |= self.tags.remove(tag.as_ref().trim());
+        }
+        removed
+    }
+
+    pub fn set_property(&mut self, key: &str, value: PropertyValue) -> Result<(), CanvasError> {
+        metadata::validate_property(key, &value)?;
+        self.properties.insert(key.trim().to_string(), value);
+        Ok(())
+    }
+
+    pub fn remove_property(&mut self, key: &str) -> Option<PropertyValue> {
+        self.properties.remove(key.trim())
+    }
+
+    /// Drops state that only makes sense on this machine, such as the id of
+    /// the native webview, before the tile is shared with other replicas.
+    pub fn clear_local_state(&mut self) {
+        if let TileData::WebView { webview_id, .. } = &mut self.data {
+            *webview_id = None;
+        }
+    }
+
+    /// Carries the local-only state of `existing` over to this tile, which
+    /// replaces it.
+    pub fn keep_local_state(&mut self, existing: &Tile) {
+        if let (
+            TileData::WebView { webview_id, .. },
+            TileData::WebView { webview_id: existing_id, .. },
+        ) = (&mut self.data, &existing.data)
+        {
+            *webview_id = existing_id.clone();
+        }
+    }
+}
\ No newline at end of file
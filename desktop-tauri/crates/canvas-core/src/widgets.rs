@@ -0,0 +1,532 @@
+//! Kinds of widgets egui tiles can show.
+//!
+//! An egui tile names its widget kind in `widget_type` and keeps the
+//! widget's settings as free-form JSON in `config`. A [`WidgetRegistry`]
+//! knows which kinds exist and what their configs look like: each
+//! [`WidgetKind`] has a JSON Schema for its config, a default config and a
+//! check that turns a config into its complete form. The built-in kinds are
+//! backed by typed config structs implementing [`WidgetConfig`].
+//!
+//! Tiles and operations are checked when they come from the app, see
+//! [`WidgetRegistry::normalize_operations`]; documents and replicas may
+//! still carry kinds a registry doesn't know.
+
+use std::collections::{BTreeMap, HashMap};
+use std::marker::PhantomData;
+
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::canvas::{CanvasOperation, InfiniteCanvas, NewTile};
+use crate::error::CanvasError;
+use crate::tiles::{validate_color, TileData, TileId};
+
+/// Dialect of the schemas of [`WidgetKind::schema`].
+pub const SCHEMA_DIALECT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// A kind of widget, known to a [`WidgetRegistry`] by its name.
+pub trait WidgetKind: Send + Sync {
+    /// Name stored in the `widget_type` of tiles.
+    fn name(&self) -> &str;
+    /// Name to show people.
+    fn label(&self) -> &str;
+    /// JSON Schema the configs of the kind follow.
+    fn schema(&self) -> Value;
+    /// Config of a new widget of the kind.
+    fn default_config(&self) -> Value;
+    /// `config` with defaults filled in for what it leaves out, or why it
+    /// is invalid. `null` stands for the default config.
+    fn normalize(&self, config: &Value) -> Result<Value, CanvasError>;
+}
+
+/// Typed config of a widget kind, registered with
+/// [`WidgetRegistry::register`]. Fields left out of a config take their
+/// value from [`Default`], fields the type doesn't know are rejected.
+pub trait WidgetConfig: Serialize + DeserializeOwned + Default {
+    const NAME: &'static str;
+    const LABEL: &'static str;
+
+    /// Schemas of the config's fields by name. Their defaults are filled in
+    /// from [`Default`].
+    fn properties() -> Map<String, Value>;
+
+    /// Checks what the types alone don't, such as ranges.
+    fn validate(&self) -> Result<(), CanvasError> {
+        Ok(())
+    }
+}
+
+/// Description of a widget kind for the frontend, such as for a dialog
+/// that creates widgets.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WidgetType {
+    pub name: String,
+    pub label: String,
+    pub schema: Value,
+    pub default_config: Value,
+}
+
+/// Widget kinds by name.
+pub struct WidgetRegistry {
+    kinds: BTreeMap<String, Box<dyn WidgetKind>>,
+}
+
+impl Default for WidgetRegistry {
+    /// A registry of the built-in kinds.
+    fn default() -> Self {
+        let mut registry = Self::empty();
+        registry.register::<ButtonConfig>();
+        registry.register::<SliderConfig>();
+        registry.register::<CheckboxConfig>();
+        registry.register::<TextBoxConfig>();
+        registry.register::<ColorPickerConfig>();
+        registry
+    }
+}
+
+impl WidgetRegistry {
+    /// A registry without any kinds.
+    pub fn empty() -> Self {
+        Self { kinds: BTreeMap::new() }
+    }
+
+    /// Adds the kind of config `C`, replacing a kind of the same name.
+    pub fn register<C: WidgetConfig + 'static>(&mut self) {
+        self.register_kind(Box::new(Typed::<C>(PhantomData)));
+    }
+
+    /// Adds `kind`, replacing a kind of the same name.
+    pub fn register_kind(&mut self, kind: Box<dyn WidgetKind>) {
+        self.kinds.insert(kind.name().to_string(), kind);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn WidgetKind> {
+        self.kinds.get(name).map(|kind| kind.as_ref())
+    }
+
+    /// Names of the kinds, in order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.kinds.keys().map(String::as_str)
+    }
+
+    /// The kinds, in order of their names.
+    pub fn types(&self) -> Vec<WidgetType> {
+        self.kinds.values().map(|kind| WidgetType {
+            name: kind.name().to_string(),
+            label: kind.label().to_string(),
+            schema: kind.schema(),
+            default_config: kind.default_config(),
+        }).collect()
+    }
+
+    /// The complete config of a widget of kind `widget_type`, see
+    /// [`WidgetKind::normalize`]. Fails for kinds the registry doesn't know.
+    pub fn normalize(&self, widget_type: &str, config: &Value) -> Result<Value, CanvasError> {
+        let kind = self.get(widget_type).ok_or_else(|| {
+            let known = self.names().collect::<Vec<_>>().join(", ");
+            CanvasError::invalid_argument("widget_type", format!("unknown widget type {:?}, expected one of {}", widget_type, known))
+        })?;
+        kind.normalize(config)
+    }
+
+    /// Normalizes the configs of the egui tiles `operations` add or upsert
+    /// and of their `set_widget_config`s, for applying them to `canvas`.
+    /// Fails like [`InfiniteCanvas::apply_operations`], with the index of
+    /// the first invalid operation.
+    pub fn normalize_operations(
+        &self,
+        canvas: &InfiniteCanvas,
+        operations: &mut [CanvasOperation],
+    ) -> Result<(), CanvasError> {
+        // Widget types of the tiles created or replaced earlier in the batch,
+        // `None` for those that are no egui tile anymore
+        let mut created = HashMap::new();
+        for (index, operation) in operations.iter_mut().enumerate() {
+            self.normalize_operation(canvas, &mut created, operation)
+                .map_err(|source| CanvasError::OperationFailed { index, source: Box::new(source) })?;
+        }
+        Ok(())
+    }
+
+    fn normalize_operation(
+        &self,
+        canvas: &InfiniteCanvas,
+        created: &mut HashMap<TileId, Option<String>>,
+        operation: &mut CanvasOperation,
+    ) -> Result<(), CanvasError> {
+        match operation {
+            CanvasOperation::Add { tile_id, tile: NewTile::Egui { widget_type, config }, .. } => {
+                *config = self.normalize(widget_type, config)?;
+                if let Some(tile_id) = tile_id {
+                    created.insert(*tile_id, Some(widget_type.clone()));
+                }
+            }
+            CanvasOperation::Upsert { tile_id, tile: NewTile::Egui { widget_type, config }, .. } => {
+                *config = self.normalize(widget_type, config)?;
+                created.insert(*tile_id, Some(widget_type.clone()));
+            }
+            CanvasOperation::Upsert { tile_id, .. } | CanvasOperation::Remove { tile_id } => {
+                created.insert(*tile_id, None);
+            }
+            CanvasOperation::SetWidgetConfig { tile_id, config } => {
+                let widget_type = match created.get(tile_id) {
+                    Some(widget_type) => widget_type.as_deref(),
+                    None => match canvas.get_tile(*tile_id).map(|tile| &tile.data) {
+                        Some(TileData::Egui { widget_type, .. }) => Some(widget_type.as_str()),
+                        _ => None,
+                    },
+                };
+                // Missing and other tiles fail when the batch is applied
+                if let Some(widget_type) = widget_type {
+                    *config = self.normalize(widget_type, config)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// The [`WidgetKind`] of a [`WidgetConfig`].
+struct Typed<C>(PhantomData<fn() -> C>);
+
+impl<C: WidgetConfig> WidgetKind for Typed<C> {
+    fn name(&self) -> &str {
+        C::NAME
+    }
+
+    fn label(&self) -> &str {
+        C::LABEL
+    }
+
+    fn schema(&self) -> Value {
+        let defaults = to_value(&C::default());
+        let mut properties = C::properties();
+        for (name, schema) in &mut properties {
+            if let (Some(schema), Some(default)) = (schema.as_object_mut(), defaults.get(name)) {
+                schema.insert("default".into(), default.clone());
+            }
+        }
+        json!({
+            "$schema": SCHEMA_DIALECT,
+            "title": C::LABEL,
+            "type": "object",
+            "properties": properties,
+            "additionalProperties": false,
+        })
+    }
+
+    fn default_config(&self) -> Value {
+        to_value(&C::default())
+    }
+
+    fn normalize(&self, config: &Value) -> Result<Value, CanvasError> {
+        let config = match config {
+            Value::Null => C::default(),
+            Value::Object(_) => {
+                C::deserialize(config).map_err(|e| CanvasError::invalid_argument("config", e.to_string()))?
+            }
+            _ => return Err(CanvasError::invalid_argument("config", "must be an object")),
+        };
+        config.validate()?;
+        Ok(to_value(&config))
+    }
+}
+
+fn to_value(config: &impl Serialize) -> Value {
+    serde_json::to_value(config).expect("widget configs serialize to JSON")
+}
+
+fn string_property(description: &str) -> Value {
+    json!({ "type": "string", "description": description })
+}
+
+fn number_property(description: &str) -> Value {
+    json!({ "type": "number", "description": description })
+}
+
+/// A button that shows `text`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ButtonConfig {
+    pub text: String,
+}
+
+impl Default for ButtonConfig {
+    fn default() -> Self {
+        Self { text: "Click Me".into() }
+    }
+}
+
+impl WidgetConfig for ButtonConfig {
+    const NAME: &'static str = "button";
+    const LABEL: &'static str = "Button";
+
+    fn properties() -> Map<String, Value> {
+        Map::from_iter([("text".into(), string_property("Caption of the button"))])
+    }
+}
+
+/// A slider that picks `value` between `min` and `max`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SliderConfig {
+    pub min: f64,
+    pub max: f64,
+    pub value: f64,
+}
+
+impl Default for SliderConfig {
+    fn default() -> Self {
+        Self { min: 0.0, max: 100.0, value: 50.0 }
+    }
+}
+
+impl WidgetConfig for SliderConfig {
+    const NAME: &'static str = "slider";
+    const LABEL: &'static str = "Slider";
+
+    fn properties() -> Map<String, Value> {
+        Map::from_iter([
+            ("min".into(), number_property("Lowest value")),
+            ("max".into(), number_property("Highest value, above min")),
+            ("value".into(), number_property("Current value, from min to max")),
+        ])
+    }
+
+    fn validate(&self) -> Result<(), CanvasError> {
+        if !self.min.is_finite() || !self.max.is_finite() || self.min >= self.max {
+            return Err(CanvasError::invalid_argument("max", "must be finite and above min"));
+        }
+        if !(self.min..=self.max).contains(&self.value) {
+            return Err(CanvasError::invalid_argument("value", "must be between min and max"));
+        }
+        Ok(())
+    }
+}
+
+/// A checkbox labelled `text`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheckboxConfig {
+    pub checked: bool,
+    pub text: String,
+}
+
+impl Default for CheckboxConfig {
+    fn default() -> Self {
+        Self { checked: false, text: "Check me".into() }
+    }
+}
+
+impl WidgetConfig for CheckboxConfig {
+    const NAME: &'static str = "checkbox";
+    const LABEL: &'static str = "Checkbox";
+
+    fn properties() -> Map<String, Value> {
+        Map::from_iter([
+            ("checked".into(), json!({ "type": "boolean", "description": "Whether the box is ticked" })),
+            ("text".into(), string_property("Label next to the box")),
+        ])
+    }
+}
+
+/// An editable single line of text.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TextBoxConfig {
+    pub text: String,
+}
+
+impl Default for TextBoxConfig {
+    fn default() -> Self {
+        Self { text: "Edit me".into() }
+    }
+}
+
+impl WidgetConfig for TextBoxConfig {
+    const NAME: &'static str = "textbox";
+    const LABEL: &'static str = "Text Box";
+
+    fn properties() -> Map<String, Value> {
+        Map::from_iter([("text".into(), string_property("Text being edited"))])
+    }
+}
+
+/// A picker of an RGBA `color` with components from 0 to 1.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColorPickerConfig {
+    pub color: [f32; 4],
+}
+
+impl Default for ColorPickerConfig {
+    fn default() -> Self {
+        Self { color: [1.0, 0.0, 0.0, 1.0] }
+    }
+}
+
+impl WidgetConfig for ColorPickerConfig {
+    const NAME: &'static str = "colorpicker";
+    const LABEL: &'static str = "Color Picker";
+
+    fn properties() -> Map<String, Value> {
+        Map::from_iter([(
+            "color".into(),
+            json!({
+                "type": "array",
+                "description": "Red, green, blue and alpha",
+                "items": { "type": "number", "minimum": 0, "maximum": 1 },
+                "minItems": 4,
+                "maxItems": 4,
+            }),
+        )])
+    }
+
+    fn validate(&self) -> Result<(), CanvasError> {
+        validate_color("color", self.color)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fills_in_defaults_and_rejects_invalid_configs() {
+        let registry = WidgetRegistry::default();
+        assert_eq!(
+            registry.normalize("slider", &json!({ "max": 10, "value": 2.5 })).unwrap(),
+            json!({ "min": 0.0, "max": 10.0, "value": 2.5 }),
+        );
+        assert_eq!(registry.normalize("button", &Value::Null).unwrap(), json!({ "text": "Click Me" }));
+
+        let invalid = [
+            ("sldier", json!({})),
+            ("slider", json!({ "max": 10 })),
+            ("slider", json!({ "min": 5, "max": 5, "value": 5 })),
+            ("slider", json!({ "value": "half" })),
+            ("button", json!({ "txt": "typo" })),
+            ("checkbox", json!([true])),
+            ("colorpicker", json!({ "color": [1, 0, 0] })),
+            ("colorpicker", json!({ "color": [2, 0, 0, 1] })),
+        ];
+        for (widget_type, config) in invalid {
+            let error = registry.normalize(widget_type, &config).unwrap_err();
+            assert_eq!(error.code(), "invalid_argument", "{} {}", widget_type, config);
+        }
+    }
+
+    #[test]
+    fn normalizes_the_widgets_of_operations() {
+        let registry = WidgetRegistry::default();
+        let mut canvas = InfiniteCanvas::default();
+        let add = |tile_id, widget_type: &str, config| CanvasOperation::Add {
+            tile_id,
+            tile: NewTile::Egui { widget_type: widget_type.into(), config },
+            position: [0.0, 0.0],
+            size: [200.0, 100.0],
+            title: "Widget".into(),
+        };
+        let existing = canvas.apply_operations(vec![add(None, "checkbox", Value::Null)]).unwrap().added[0];
+        let new = TileId(uuid::Uuid::new_v4());
+
+        let mut operations = vec![
+            add(Some(new), "slider", json!({ "max": 10, "value": 1 })),
+            CanvasOperation::SetWidgetConfig { tile_id: new, config: json!({ "value": 2 }) },
+            CanvasOperation::SetWidgetConfig { tile_id: existing, config: json!({ "checked": true }) },
+        ];
+        registry.normalize_operations(&canvas, &mut operations).unwrap();
+        let CanvasOperation::Add { tile: NewTile::Egui { config, .. }, .. } = &operations[0] else { unreachable!() };
+        assert_eq!(config, &json!({ "min": 0.0, "max": 10.0, "value": 1.0 }));
+        let CanvasOperation::SetWidgetConfig { config, .. } = &operations[1] else { unreachable!() };
+        assert_eq!(config, &json!({ "min": 0.0, "max": 100.0, "value": 2.0 }));
+
+        // Once a tile is removed or replaced by another kind, its config is
+        // left for applying the batch to reject
+        let stray = json!({ "checked": "yes" });
+        let mut operations = vec![
+            CanvasOperation::Remove { tile_id: existing },
+            CanvasOperation::SetWidgetConfig { tile_id: existing, config: stray.clone() },
+            add(Some(new), "slider", Value::Null),
+            CanvasOperation::Upsert {
+                tile_id: new,
+                tile: NewTile::Skia,
+                position: [0.0, 0.0],
+                size: [200.0, 100.0],
+                title: "Sketch".into(),
+            },
+            CanvasOperation::SetWidgetConfig { tile_id: new, config: stray.clone() },
+        ];
+        registry.normalize_operations(&canvas, &mut operations).unwrap();
+        assert!(matches!(&operations[1], CanvasOperation::SetWidgetConfig { config, .. } if config == &stray));
+        assert!(matches!(&operations[4], CanvasOperation::SetWidgetConfig { config, .. } if config == &stray));
+
+        let invalid = [
+            add(None, "sldier", json!({})),
+            CanvasOperation::Upsert {
+                tile_id: new,
+                tile: NewTile::Egui { widget_type: "slider".into(), config: json!({ "max": -1 }) },
+                position: [0.0, 0.0],
+                size: [200.0, 100.0],
+                title: "Widget".into(),
+            },
+            CanvasOperation::SetWidgetConfig { tile_id: existing, config: json!({ "checked": "yes" }) },
+        ];
+        for operation in invalid {
+            let mut operations = vec![CanvasOperation::PanCamera { delta: [1.0, 0.0] }, operation];
+            let error = registry.normalize_operations(&canvas, &mut operations).unwrap_err();
+            let CanvasError::OperationFailed { index: 1, source } = error else { panic!("unexpected {:?}", error) };
+            assert_eq!(source.code(), "invalid_argument");
+        }
+    }
+
+    #[test]
+    fn lists_types_with_schemas() {
+        let types = WidgetRegistry::default().types();
+        let names = types.iter().map(|t| t.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, ["button", "checkbox", "colorpicker", "slider", "textbox"]);
+
+        let slider = types.iter().find(|t| t.name == "slider").unwrap();
+        assert_eq!(slider.label, "Slider");
+        assert_eq!(slider.schema["$schema"], SCHEMA_DIALECT);
+        assert_eq!(slider.schema["additionalProperties"], false);
+        assert_eq!(slider.schema["properties"]["max"]["default"], 100.0);
+        assert_eq!(slider.default_config, json!({ "min": 0.0, "max": 100.0, "value": 50.0 }));
+        for widget in &types {
+            let properties = widget.schema["properties"].as_object().unwrap();
+            let config = widget.default_config.as_object().unwrap();
+            assert!(config.keys().eq(properties.keys()), "{}", widget.name);
+        }
+    }
+
+    #[test]
+    fn custom_kinds_can_be_registered() {
+        struct Anything;
+
+        impl WidgetKind for Anything {
+            fn name(&self) -> &str {
+                "anything"
+            }
+            fn label(&self) -> &str {
+                "Anything"
+            }
+            fn schema(&self) -> Value {
+                json!({ "$schema": SCHEMA_DIALECT })
+            }
+            fn default_config(&self) -> Value {
+                json!({})
+            }
+            fn normalize(&self, config: &Value) -> Result<Value, CanvasError> {
+                Ok(config.clone())
+            }
+        }
+
+        let mut registry = WidgetRegistry::empty();
+        assert!(registry.normalize("anything", &json!(1)).is_err());
+        registry.register_kind(Box::new(Anything));
+        assert_eq!(registry.normalize("anything", &json!(1)).unwrap(), json!(1));
+        assert_eq!(registry.names().collect::<Vec<_>>(), ["anything"]);
+    }
+}
@@ -0,0 +1,146 @@
+//! Drives a canvas through the public API only, the way a headless tool or
+//! the desktop app would.
+
+use canvas_core::glam::Vec2;
+use canvas_core::{
+    document, CanvasOperation, InfiniteCanvas, InkBrush, InkSample, InkStroke, NewTile, PropertyValue, Rect, TileData, TileQuery,
+    TileType,
+};
+
+fn add(tile: NewTile, position: [f32; 2], title: &str) -> CanvasOperation {
+    CanvasOperation::Add {
+        tile_id: None,
+        tile,
+        position,
+        size: [200.0, 100.0],
+        title: title.to_string(),
+    }
+}
+
+#[test]
+fn build_query_and_undo() {
+    let mut canvas = InfiniteCanvas::new(Vec2::new(1280.0, 720.0));
+
+    let batch = canvas.apply_operations(vec![
+        add(NewTile::WebView { url: "https://example.com".into() }, [0.0, 0.0], "Docs"),
+        add(
+            NewTile::Egui {
+                widget_type: "slider".into(),
+                config: serde_json::json!({ "min": 0, "max": 10 }),
+            },
+            [400.0, 0.0],
+            "Volume",
+        ),
+        add(NewTile::Skia, [800.0, 0.0], "Sketch"),
+    ]).unwrap();
+    let [docs, volume, sketch] = batch.added[..] else { panic!("expected three tiles") };
+
+    let egui = canvas.query(&TileQuery { tile_type: Some(TileType::Egui), ..Default::default() });
+    assert_eq!(egui.len(), 1);
+    assert_eq!(egui[0].0, volume);
+
+    let left = canvas.tiles_in_rect(Rect::new(Vec2::new(-150.0, -10.0), Vec2::new(350.0, 10.0)));
+    assert_eq!(left.len(), 2);
+    assert!(left.contains(&docs) && left.contains(&volume));
+
+    canvas.apply_operations(vec![
+        CanvasOperation::Remove { tile_id: sketch },
+        CanvasOperation::Resize { tile_id: docs, size: [640.0, 480.0], fit: false },
+    ]).unwrap();
+    assert!(canvas.get_tile(sketch).is_none());
+
+    assert!(canvas.undo());
+    assert!(canvas.get_tile(sketch).is_some());
+    assert_eq!(canvas.get_tile(docs).unwrap().size, Vec2::new(200.0, 100.0));
+
+    assert!(canvas.redo());
+    assert!(canvas.get_tile(sketch).is_none());
+}
+
+#[test]
+fn document_round_trip() {
+    let mut canvas = InfiniteCanvas::default();
+    let tile_id = canvas.apply_operations(vec![
+        add(NewTile::WebView { url: "https://example.com".into() }, [10.0, 20.0], "Docs"),
+        CanvasOperation::PanCamera { delta: [50.0, 0.0] },
+    ]).unwrap().added[0];
+    canvas.apply_operations(serde_json::from_value(serde_json::json!([
+        { "op": "add_tags", "tile_id": tile_id, "tags": ["reference"] },
+        { "op": "set_property", "tile_id": tile_id, "key": "owner", "value": "ana" },
+        { "op": "set_property", "tile_id": tile_id, "key": "reviewed", "value": true },
+        // Only the width is limited
+        { "op": "set_constraints", "tile_id": tile_id, "constraints": { "max_size": [400.0, null] } },
+    ])).unwrap()).unwrap();
+
+    let json = document::to_json(&canvas).unwrap();
+    let loaded = document::from_json(&json).unwrap();
+
+    let tile = loaded.get_tile(tile_id).unwrap();
+    assert_eq!(tile.position, Vec2::new(10.0, 20.0));
+    assert!(matches!(&tile.data, TileData::WebView { url, .. } if url == "https://example.com"));
+    assert!(tile.tags.contains("reference"));
+    assert_eq!(tile.properties["owner"], PropertyValue::Text("ana".into()));
+    assert_eq!(tile.properties["reviewed"], PropertyValue::Bool(true));
+    assert_eq!(tile.constraints.max_size, Some(Vec2::new(400.0, f32::INFINITY)));
+    assert_eq!(loaded.camera.position, canvas.camera.position);
+    assert!(!loaded.history.can_undo());
+}
+
+#[test]
+fn binary_document_round_trip() {
+    let mut canvas = InfiniteCanvas::default();
+    let batch = canvas.apply_operations(vec![
+        add(NewTile::Skia, [0.0, 0.0], "Sketch"),
+        add(NewTile::Egui { widget_type: "slider".into(), config: serde_json::json!({ "min": 0, "max": 1.5 }) }, [300.0, 0.0], "Volume"),
+    ]).unwrap();
+    let [sketch, volume] = batch.added[..] else { panic!("expected two tiles") };
+
+    let mut stroke = InkStroke::new(InkBrush::new([0.0, 0.0, 0.0, 1.0], 3.0)).unwrap();
+    stroke.extend((0..50).map(|i| InkSample::new(Vec2::new(i as f32 * 1.7, (i as f32 * 0.3).sin() * 20.0), 0.6)));
+    canvas.apply_operations(vec![
+        CanvasOperation::SetDrawing { tile_id: sketch, commands: stroke.to_commands() },
+        CanvasOperation::PanCamera { delta: [-20.0, 5.0] },
+    ]).unwrap();
+
+    let bytes = document::to_binary(&canvas).unwrap();
+    assert!(bytes.len() < document::to_json(&canvas).unwrap().len());
+    let loaded = document::from_binary(&bytes).unwrap();
+
+    let TileData::Skia { drawing_commands } = &loaded.get_tile(sketch).unwrap().data else { panic!("expected a drawing") };
+    assert_eq!(drawing_commands, &stroke.to_commands());
+    let TileData::Egui { config, .. } = &loaded.get_tile(volume).unwrap().data else { panic!("expected a widget") };
+    assert_eq!(config, &serde_json::json!({ "min": 0, "max": 1.5 }));
+    assert_eq!(loaded.camera.position, canvas.camera.position);
+    assert!(!loaded.history.can_undo());
+}
+
+#[test]
+fn operations_deserialize_from_frontend_json() {
+    let operations: Vec<CanvasOperation> = serde_json::from_value(serde_json::json!([
+        { "op": "add", "tile": { "kind": "skia" }, "position": [0, 0], "size": [100, 100], "title": "A" },
+        { "op": "pan_camera", "delta": [5, 5] },
+        { "op": "reset_camera" },
+    ])).unwrap();
+
+    let mut canvas = InfiniteCanvas::default();
+    let batch = canvas.apply_operations(operations).unwrap();
+    assert_eq!(batch.added.len(), 1);
+    assert!(batch.camera_changed);
+    assert_eq!(canvas.camera.position, Vec2::ZERO);
+}
+
+#[test]
+fn errors_serialize_with_stable_codes() {
+    let mut canvas = InfiniteCanvas::default();
+    let missing = "00000000-0000-0000-0000-000000000000".parse().unwrap();
+    let error = canvas.apply_operations(vec![CanvasOperation::Remove { tile_id: missing }]).unwrap_err();
+
+    let value = serde_json::to_value(&error).unwrap();
+    assert_eq!(value["code"], "operation_failed");
+    assert_eq!(value["context"]["index"], 0);
+    assert_eq!(value["context"]["error"]["code"], "tile_not_found");
+    assert_eq!(value["context"]["error"]["context"]["tile_id"], "00000000-0000-0000-0000-000000000000");
+
+    let invalid = "not-a-uuid".parse::<canvas_core::TileId>().unwrap_err();
+    assert_eq!(invalid.code(), "invalid_tile_id");
+}
@@ -0,0 +1,188 @@
+//! Compares Skia tile renders with reference images in `tests/golden`.
+//!
+//! Run with `UPDATE_GOLDEN=1` to rewrite the references after an intended
+//! change in the output, and look at the new images before committing them.
+
+use std::path::PathBuf;
+
+use canvas_core::glam::Vec2;
+use canvas_core::render::skia::render_commands;
+use canvas_core::{DrawingCommand, Gradient, GradientStop, LineCap, LineJoin, TextAlign, TextBox, TextStyle};
+use tiny_skia::Pixmap;
+
+/// Largest difference allowed in any channel of a pixel, to absorb
+/// rounding differences in anti-aliasing.
+const CHANNEL_TOLERANCE: u8 = 2;
+
+fn check(name: &str, commands: &[DrawingCommand], size: Vec2, scale: f32) {
+    let actual = render_commands(commands, size, scale).unwrap();
+    let path = PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{}.png", name));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        actual.save_png(&path).unwrap();
+        return;
+    }
+
+    let expected = Pixmap::load_png(&path)
+        .unwrap_or_else(|e| panic!("missing golden image {}, run with UPDATE_GOLDEN=1: {}", path.display(), e));
+    assert_eq!(
+        (actual.width(), actual.height()),
+        (expected.width(), expected.height()),
+        "{} has the wrong size",
+        name
+    );
+
+    let differing = actual.data().chunks(4).zip(expected.data().chunks(4))
+        .filter(|(a, e)| a.iter().zip(e.iter()).any(|(a, e)| a.abs_diff(*e) > CHANNEL_TOLERANCE))
+        .count();
+    if differing > 0 {
+        let failed = path.with_extension("actual.png");
+        actual.save_png(&failed).unwrap();
+        panic!("{} differs from its golden image in {} pixels, see {}", name, differing, failed.display());
+    }
+}
+
+fn shapes() -> Vec<DrawingCommand> {
+    vec![
+        DrawingCommand::SetFillColor([0.9, 0.2, 0.2, 1.0]),
+        DrawingCommand::DrawRect(Vec2::new(8.0, 8.0), Vec2::new(40.0, 24.0)),
+        // Negative sizes grow to the left and up
+        DrawingCommand::SetFillColor([0.2, 0.4, 0.9, 0.5]),
+        DrawingCommand::DrawRect(Vec2::new(60.0, 40.0), Vec2::new(-30.0, -20.0)),
+        DrawingCommand::SetFillColor([0.1, 0.7, 0.3, 1.0]),
+        DrawingCommand::DrawCircle(Vec2::new(80.0, 30.0), 16.0),
+    ]
+}
+
+#[test]
+fn rects_and_circles() {
+    check("shapes", &shapes(), Vec2::new(112.0, 56.0), 1.0);
+}
+
+#[test]
+fn filled_and_stroked_paths() {
+    let commands = vec![
+        DrawingCommand::SetFillColor([1.0, 0.8, 0.2, 1.0]),
+        DrawingCommand::SetStrokeColor([0.2, 0.2, 0.2, 1.0]),
+        DrawingCommand::SetStrokeWidth(3.0),
+        DrawingCommand::MoveTo(Vec2::new(10.0, 50.0)),
+        DrawingCommand::LineTo(Vec2::new(30.0, 10.0)),
+        DrawingCommand::LineTo(Vec2::new(50.0, 50.0)),
+        DrawingCommand::ClosePath,
+        DrawingCommand::Fill,
+        DrawingCommand::Stroke,
+        DrawingCommand::SetStrokeColor([0.6, 0.1, 0.7, 1.0]),
+        DrawingCommand::SetStrokeWidth(2.0),
+        DrawingCommand::MoveTo(Vec2::new(60.0, 50.0)),
+        DrawingCommand::QuadTo(Vec2::new(75.0, 0.0), Vec2::new(90.0, 50.0)),
+        DrawingCommand::CubicTo(Vec2::new(95.0, 20.0), Vec2::new(105.0, 60.0), Vec2::new(110.0, 20.0)),
+        DrawingCommand::Stroke,
+    ];
+    check("paths", &commands, Vec2::new(120.0, 60.0), 1.0);
+}
+
+#[test]
+fn text_with_the_bundled_font() {
+    let commands = vec![
+        DrawingCommand::DrawText("Infinite canvas".into(), Vec2::new(6.0, 20.0), 16.0),
+        DrawingCommand::SetFillColor([0.2, 0.3, 0.8, 1.0]),
+        DrawingCommand::DrawText("Ågy 123".into(), Vec2::new(6.0, 48.0), 24.0),
+    ];
+    check("text", &commands, Vec2::new(140.0, 56.0), 1.0);
+}
+
+#[test]
+fn wrapped_and_aligned_text_boxes() {
+    let text_box = |text: &str, y: f32, align: TextAlign| {
+        DrawingCommand::DrawTextBox(TextBox {
+            text: text.into(),
+            position: Vec2::new(8.0, y),
+            width: Some(104.0),
+            style: TextStyle { align, ..TextStyle::sized(12.0) },
+        })
+    };
+    let commands = vec![
+        DrawingCommand::SetFillColor([0.9, 0.9, 0.9, 1.0]),
+        DrawingCommand::DrawRect(Vec2::new(8.0, 4.0), Vec2::new(104.0, 112.0)),
+        DrawingCommand::SetFillColor([0.1, 0.1, 0.1, 1.0]),
+        text_box("Text wraps inside its box at spaces", 4.0, TextAlign::Left),
+        text_box("centered\nlines", 48.0, TextAlign::Center),
+        text_box("right", 84.0, TextAlign::Right),
+        text_box("Unbreakablewordsplit", 98.0, TextAlign::Left),
+    ];
+    check("text_box", &commands, Vec2::new(120.0, 130.0), 1.0);
+}
+
+#[test]
+fn scale_renders_more_pixels_of_the_same_drawing() {
+    check("shapes_2x", &shapes(), Vec2::new(112.0, 56.0), 2.0);
+}
+
+#[test]
+fn gradients_and_rounded_shapes() {
+    let stops = |from: [f32; 4], to: [f32; 4]| {
+        vec![GradientStop { offset: 0.0, color: from }, GradientStop { offset: 1.0, color: to }]
+    };
+    let commands = vec![
+        DrawingCommand::SetFillGradient(Gradient::Linear {
+            start: Vec2::new(8.0, 0.0),
+            end: Vec2::new(56.0, 0.0),
+            stops: stops([1.0, 0.3, 0.1, 1.0], [0.2, 0.2, 0.9, 1.0]),
+        }),
+        DrawingCommand::DrawRoundedRect(Vec2::new(8.0, 8.0), Vec2::new(48.0, 40.0), 10.0),
+        DrawingCommand::SetFillGradient(Gradient::Radial {
+            center: Vec2::new(88.0, 28.0),
+            radius: 24.0,
+            stops: stops([1.0, 1.0, 0.6, 1.0], [0.1, 0.6, 0.3, 0.0]),
+        }),
+        DrawingCommand::DrawEllipse(Vec2::new(88.0, 28.0), Vec2::new(24.0, 16.0)),
+    ];
+    check("gradients", &commands, Vec2::new(120.0, 56.0), 1.0);
+}
+
+#[test]
+fn dashes_caps_joins_and_arcs() {
+    let commands = vec![
+        DrawingCommand::SetStrokeWidth(4.0),
+        DrawingCommand::SetLineDash(vec![10.0, 5.0, 2.0], 0.0),
+        DrawingCommand::MoveTo(Vec2::new(8.0, 10.0)),
+        DrawingCommand::LineTo(Vec2::new(112.0, 10.0)),
+        DrawingCommand::Stroke,
+        DrawingCommand::SetLineDash(Vec::new(), 0.0),
+        DrawingCommand::SetLineCap(LineCap::Round),
+        DrawingCommand::SetLineJoin(LineJoin::Round),
+        DrawingCommand::SetStrokeColor([0.2, 0.5, 0.8, 1.0]),
+        DrawingCommand::MoveTo(Vec2::new(10.0, 50.0)),
+        DrawingCommand::LineTo(Vec2::new(30.0, 25.0)),
+        DrawingCommand::LineTo(Vec2::new(50.0, 50.0)),
+        DrawingCommand::Stroke,
+        // A pie slice: line to the arc, around three quarters, back home
+        DrawingCommand::SetFillColor([0.9, 0.6, 0.1, 1.0]),
+        DrawingCommand::MoveTo(Vec2::new(85.0, 38.0)),
+        DrawingCommand::Arc(Vec2::new(85.0, 38.0), 16.0, 0.0, 4.712389),
+        DrawingCommand::ClosePath,
+        DrawingCommand::Fill,
+    ];
+    check("strokes", &commands, Vec2::new(120.0, 60.0), 1.0);
+}
+
+#[test]
+fn transforms_clips_and_global_alpha() {
+    let mut commands = vec![
+        DrawingCommand::Arc(Vec2::new(40.0, 40.0), 30.0, 0.0, std::f32::consts::TAU),
+        DrawingCommand::Clip,
+        DrawingCommand::Translate(Vec2::new(40.0, 40.0)),
+    ];
+    for (index, rgba) in [[0.9, 0.2, 0.2, 1.0], [0.2, 0.7, 0.3, 1.0], [0.2, 0.3, 0.9, 1.0]].into_iter().enumerate() {
+        commands.extend([
+            DrawingCommand::Save,
+            DrawingCommand::Rotate(index as f32 * 0.6),
+            DrawingCommand::SetGlobalAlpha(0.7),
+            DrawingCommand::SetFillColor(rgba),
+            DrawingCommand::DrawRect(Vec2::new(-8.0, -40.0), Vec2::new(16.0, 80.0)),
+            DrawingCommand::Restore,
+        ]);
+    }
+    commands.push(DrawingCommand::DrawCircle(Vec2::ZERO, 6.0));
+    check("transforms", &commands, Vec2::new(80.0, 80.0), 1.0);
+}
@@ -0,0 +1,116 @@
+//! Input forwarded from the frontend to a tile's egui context.
+
+use canvas_core::CanvasError;
+use egui::{Event, Key, Pos2, Vec2};
+use serde::{Deserialize, Serialize};
+
+/// A pointer or keyboard event. Positions are in world units from the
+/// top-left corner of the tile's content area, below its title bar.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum WidgetInput {
+    PointerMove {
+        position: [f32; 2],
+    },
+    PointerButton {
+        position: [f32; 2],
+        button: PointerButton,
+        pressed: bool,
+    },
+    /// The pointer left the tile.
+    PointerLeave,
+    /// Scrolled by `delta` world units, positive to scroll content right
+    /// and down into view.
+    Scroll {
+        delta: [f32; 2],
+    },
+    /// A key went down or up. `key` is a key name as browsers report it,
+    /// such as `"Enter"`, `"ArrowLeft"` or `"a"`.
+    Key {
+        key: String,
+        pressed: bool,
+        #[serde(default)]
+        modifiers: Modifiers,
+    },
+    /// Text typed, separately from the keys that typed it.
+    Text {
+        text: String,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PointerButton {
+    Primary,
+    Secondary,
+    Middle,
+}
+
+/// Modifier keys held during a key event. `command` is Ctrl, or Cmd on
+/// macOS.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Modifiers {
+    pub alt: bool,
+    pub ctrl: bool,
+    pub shift: bool,
+    pub command: bool,
+}
+
+impl From<Modifiers> for egui::Modifiers {
+    fn from(modifiers: Modifiers) -> Self {
+        Self {
+            alt: modifiers.alt,
+            ctrl: modifiers.ctrl,
+            shift: modifiers.shift,
+            mac_cmd: cfg!(target_os = "macos") && modifiers.command,
+            command: modifiers.command,
+        }
+    }
+}
+
+impl WidgetInput {
+    /// The egui event, with `modifiers` the modifiers last reported by a key
+    /// event. Fails for key names egui doesn't know and positions that
+    /// aren't finite.
+    pub fn to_event(&self, modifiers: egui::Modifiers) -> Result<Event, CanvasError> {
+        Ok(match self {
+            WidgetInput::PointerMove { position } => Event::PointerMoved(to_pos(*position)?),
+            WidgetInput::PointerButton { position, button, pressed } => Event::PointerButton {
+                pos: to_pos(*position)?,
+                button: match button {
+                    PointerButton::Primary => egui::PointerButton::Primary,
+                    PointerButton::Secondary => egui::PointerButton::Secondary,
+                    PointerButton::Middle => egui::PointerButton::Middle,
+                },
+                pressed: *pressed,
+                modifiers,
+            },
+            WidgetInput::PointerLeave => Event::PointerGone,
+            WidgetInput::Scroll { delta: [x, y] } => {
+                if !x.is_finite() || !y.is_finite() {
+                    return Err(CanvasError::invalid_argument("delta", "must be finite"));
+                }
+                // egui scrolls by how far the content moves, the opposite way
+                Event::Scroll(-Vec2::new(*x, *y))
+            }
+            WidgetInput::Key { key, pressed, modifiers } => Event::Key {
+                key: Key::from_name(key)
+                    .ok_or_else(|| CanvasError::invalid_argument("key", format!("unknown key {:?}", key)))?,
+                physical_key: None,
+                pressed: *pressed,
+                repeat: false,
+                modifiers: (*modifiers).into(),
+            },
+            WidgetInput::Text { text } => Event::Text(text.clone()),
+        })
+    }
+}
+
+fn to_pos([x, y]: [f32; 2]) -> Result<Pos2, CanvasError> {
+    if x.is_finite() && y.is_finite() {
+        Ok(Pos2::new(x, y))
+    } else {
+        Err(CanvasError::invalid_argument("position", "must be finite"))
+    }
+}
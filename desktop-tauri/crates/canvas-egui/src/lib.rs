@@ -0,0 +1,22 @@
+//! Headless egui for egui tiles.
+//!
+//! An [`EguiSession`] runs an egui context for one tile without a window or
+//! GPU: each frame shows the tile's widget, tessellates it and rasterizes
+//! the triangles on the CPU into a [`tiny_skia::Pixmap`], which the app
+//! sends to the frontend as a PNG. Pointer and keyboard input from the
+//! frontend comes back as [`WidgetInput`]s, in the coordinates of the tile's
+//! content area, and reaches the widget on the next frame. What the widget
+//! changes, such as a slider's value, ends up in its config, which the
+//! session hands out once the interaction is over so the tile can keep it.
+
+pub mod input;
+pub mod raster;
+pub mod session;
+pub mod ui;
+
+pub use input::{Modifiers, PointerButton, WidgetInput};
+pub use raster::Rasterizer;
+pub use session::{EguiFrame, EguiSession};
+
+/// Largest width or height of a rendered frame, in pixels.
+pub const MAX_FRAME_PIXELS: u32 = 4096;
@@ -0,0 +1,290 @@
+//! CPU rasterization of tessellated egui output.
+//!
+//! egui paints everything as textured triangles: shapes sample a white
+//! texel of the font atlas and anti-alias with feathered edges of
+//! translucent vertices, so covering pixel centers is enough and no
+//! coverage has to be computed here. Colors are premultiplied sRGB, as in
+//! egui, and blend in sRGB like egui's web backends.
+
+use std::collections::HashMap;
+
+use egui::epaint::textures::{TextureFilter, TexturesDelta};
+use egui::epaint::{ClippedPrimitive, ImageData, ImageDelta, Primitive, Vertex};
+use egui::{Color32, Pos2, TextureId};
+use tiny_skia::Pixmap;
+
+/// Textures egui uploaded, and painting with them.
+#[derive(Default)]
+pub struct Rasterizer {
+    textures: HashMap<TextureId, Texture>,
+}
+
+struct Texture {
+    size: [usize; 2],
+    /// Premultiplied, row by row.
+    pixels: Vec<Color32>,
+    filter: TextureFilter,
+}
+
+impl Rasterizer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Uploads the new and changed textures of a frame. Call before
+    /// [`Rasterizer::paint`].
+    pub fn set_textures(&mut self, delta: &TexturesDelta) {
+        for (id, image) in &delta.set {
+            self.set_texture(*id, image);
+        }
+    }
+
+    /// Drops the textures a frame no longer needs. Call after
+    /// [`Rasterizer::paint`].
+    pub fn free_textures(&mut self, delta: &TexturesDelta) {
+        for id in &delta.free {
+            self.textures.remove(id);
+        }
+    }
+
+    fn set_texture(&mut self, id: TextureId, delta: &ImageDelta) {
+        let (size, pixels) = match &delta.image {
+            ImageData::Color(image) => (image.size, image.pixels.clone()),
+            ImageData::Font(image) => (image.size, image.srgba_pixels(None).collect()),
+        };
+        let filter = delta.options.magnification;
+        match (delta.pos, self.textures.get_mut(&id)) {
+            (Some([left, top]), Some(texture)) => {
+                for row in 0..size[1].min(texture.size[1].saturating_sub(top)) {
+                    let width = size[0].min(texture.size[0].saturating_sub(left));
+                    let start = (top + row) * texture.size[0] + left;
+                    texture.pixels[start..start + width].copy_from_slice(&pixels[row * size[0]..row * size[0] + width]);
+                }
+            }
+            // A patch of a texture that doesn't exist is all there is of it
+            _ => {
+                self.textures.insert(id, Texture { size, pixels, filter });
+            }
+        }
+    }
+
+    /// Paints `primitives` over `pixmap`, `pixels_per_point` pixels per
+    /// egui point. Meshes of unknown textures and paint callbacks, which
+    /// need a GPU, are skipped.
+    pub fn paint(&self, primitives: &[ClippedPrimitive], pixels_per_point: f32, pixmap: &mut Pixmap) {
+        let (width, height) = (pixmap.width() as usize, pixmap.height() as usize);
+        let pixels = pixmap.data_mut();
+        for ClippedPrimitive { clip_rect, primitive } in primitives {
+            let Primitive::Mesh(mesh) = primitive else {
+                continue;
+            };
+            let Some(texture) = self.textures.get(&mesh.texture_id) else {
+                continue;
+            };
+            let clip = PixelRect {
+                left: (clip_rect.min.x * pixels_per_point).round().clamp(0.0, width as f32) as usize,
+                top: (clip_rect.min.y * pixels_per_point).round().clamp(0.0, height as f32) as usize,
+                right: (clip_rect.max.x * pixels_per_point).round().clamp(0.0, width as f32) as usize,
+                bottom: (clip_rect.max.y * pixels_per_point).round().clamp(0.0, height as f32) as usize,
+            };
+            if clip.left >= clip.right || clip.top >= clip.bottom {
+                continue;
+            }
+            for triangle in mesh.indices.chunks_exact(3) {
+                let vertex = |index: u32| mesh.vertices.get(index as usize);
+                let (Some(a), Some(b), Some(c)) = (vertex(triangle[0]), vertex(triangle[1]), vertex(triangle[2])) else {
+                    continue;
+                };
+                fill_triangle([a, b, c], pixels_per_point, texture, &clip, pixels, width);
+            }
+        }
+    }
+}
+
+/// Pixels from `left` and `top` up to but excluding `right` and `bottom`.
+struct PixelRect {
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+}
+
+/// Fills the pixels whose centers lie in the triangle. Pixels on an edge
+/// belong to the triangle only if the edge is a top or left one, so
+/// triangles sharing an edge don't both cover it.
+fn fill_triangle(
+    vertices: [&Vertex; 3],
+    pixels_per_point: f32,
+    texture: &Texture,
+    clip: &PixelRect,
+    pixels: &mut [u8],
+    width: usize,
+) {
+    let [a, mut b, mut c] = vertices;
+    let position = |vertex: &Vertex| Pos2::new(vertex.pos.x * pixels_per_point, vertex.pos.y * pixels_per_point);
+    let mut area = edge(position(a), position(b), position(c));
+    if area.abs() < f32::EPSILON || !area.is_finite() {
+        return;
+    }
+    // Clockwise on screen, so that the inside is where all edges are positive
+    if area < 0.0 {
+        std::mem::swap(&mut b, &mut c);
+        area = -area;
+    }
+    let (pa, pb, pc) = (position(a), position(b), position(c));
+
+    let left = (pa.x.min(pb.x).min(pc.x).floor().max(0.0) as usize).max(clip.left);
+    let top = (pa.y.min(pb.y).min(pc.y).floor().max(0.0) as usize).max(clip.top);
+    let right = (pa.x.max(pb.x).max(pc.x).ceil().max(0.0) as usize).min(clip.right);
+    let bottom = (pa.y.max(pb.y).max(pc.y).ceil().max(0.0) as usize).min(clip.bottom);
+
+    let edges = [(pb, pc), (pc, pa), (pa, pb)];
+    let owns = edges.map(|(from, to)| is_top_left(from, to));
+    for y in top..bottom {
+        for x in left..right {
+            let center = Pos2::new(x as f32 + 0.5, y as f32 + 0.5);
+            let mut weights = [0.0; 3];
+            let mut inside = true;
+            for (i, (from, to)) in edges.iter().enumerate() {
+                let distance = edge(*from, *to, center);
+                inside &= distance > 0.0 || (distance == 0.0 && owns[i]);
+                weights[i] = distance / area;
+            }
+            if !inside {
+                continue;
+            }
+
+            let mix = |value: fn(&Vertex) -> [f32; 2]| {
+                let [va, vb, vc] = [value(a), value(b), value(c)];
+                [0, 1].map(|i| weights[0] * va[i] + weights[1] * vb[i] + weights[2] * vc[i])
+            };
+            let uv = mix(|vertex| [vertex.uv.x, vertex.uv.y]);
+            let texel = texture.sample(uv);
+            let color = [0, 1, 2, 3].map(|i| {
+                let channel = |vertex: &Vertex| vertex.color[i] as f32;
+                weights[0] * channel(a) + weights[1] * channel(b) + weights[2] * channel(c)
+            });
+
+            // Both are premultiplied, so their product is too
+            let source = [0, 1, 2, 3].map(|i| color[i] * texel[i] / 255.0);
+            let pixel = &mut pixels[(y * width + x) * 4..][..4];
+            let keep = 1.0 - source[3] / 255.0;
+            for i in 0..4 {
+                pixel[i] = (source[i] + pixel[i] as f32 * keep).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+    }
+}
+
+/// Twice the signed area of the triangle `from`, `to`, `point`; positive
+/// when `point` is to the right of the edge on screen, where y points down.
+fn edge(from: Pos2, to: Pos2, point: Pos2) -> f32 {
+    (to.x - from.x) * (point.y - from.y) - (to.y - from.y) * (point.x - from.x)
+}
+
+/// Whether an edge of a clockwise triangle is a top edge, horizontal with
+/// the triangle below it, or a left edge, going up.
+fn is_top_left(from: Pos2, to: Pos2) -> bool {
+    (from.y == to.y && to.x > from.x) || to.y < from.y
+}
+
+impl Texture {
+    /// Premultiplied color at `uv`, 0 to 1 across the texture, with edges
+    /// extended outwards.
+    fn sample(&self, [u, v]: [f32; 2]) -> [f32; 4] {
+        let [width, height] = self.size;
+        if width == 0 || height == 0 {
+            return [0.0; 4];
+        }
+        let texel = |x: isize, y: isize| {
+            let x = x.clamp(0, width as isize - 1) as usize;
+            let y = y.clamp(0, height as isize - 1) as usize;
+            self.pixels[y * width + x].to_array().map(f32::from)
+        };
+        let (x, y) = (u * width as f32 - 0.5, v * height as f32 - 0.5);
+        match self.filter {
+            TextureFilter::Nearest => texel(x.round() as isize, y.round() as isize),
+            TextureFilter::Linear => {
+                let (left, top) = (x.floor(), y.floor());
+                let (fx, fy) = (x - left, y - top);
+                let (left, top) = (left as isize, top as isize);
+                let [a, b, c, d] = [texel(left, top), texel(left + 1, top), texel(left, top + 1), texel(left + 1, top + 1)];
+                [0, 1, 2, 3].map(|i| {
+                    let upper = a[i] + (b[i] - a[i]) * fx;
+                    let lower = c[i] + (d[i] - c[i]) * fx;
+                    upper + (lower - upper) * fy
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use egui::epaint::textures::TextureOptions;
+    use egui::epaint::Mesh;
+    use egui::{ColorImage, Rect};
+
+    fn white_texture() -> Rasterizer {
+        let mut rasterizer = Rasterizer::new();
+        let image = ColorImage::new([1, 1], Color32::WHITE);
+        rasterizer.set_textures(&TexturesDelta {
+            set: vec![(TextureId::default(), ImageDelta::full(image, TextureOptions::LINEAR))],
+            free: Vec::new(),
+        });
+        rasterizer
+    }
+
+    fn alpha(pixmap: &Pixmap, x: u32, y: u32) -> u8 {
+        pixmap.pixel(x, y).unwrap().alpha()
+    }
+
+    #[test]
+    fn rectangles_cover_their_pixels_once() {
+        let mut mesh = Mesh::default();
+        let half = Color32::from_rgba_premultiplied(0, 0, 128, 128);
+        // Two halves of a rectangle sharing a diagonal, at 2 pixels per point
+        mesh.add_colored_rect(Rect::from_min_max(Pos2::new(1.0, 1.0), Pos2::new(4.0, 3.0)), half);
+        let primitives = [ClippedPrimitive { clip_rect: Rect::EVERYTHING, primitive: Primitive::Mesh(mesh) }];
+
+        let mut pixmap = Pixmap::new(10, 10).unwrap();
+        white_texture().paint(&primitives, 2.0, &mut pixmap);
+        for y in 0..10 {
+            for x in 0..10 {
+                let inside = (2..8).contains(&x) && (2..6).contains(&y);
+                assert_eq!(alpha(&pixmap, x, y), if inside { 128 } else { 0 }, "pixel {}, {}", x, y);
+            }
+        }
+        assert_eq!(pixmap.pixel(4, 4).unwrap().blue(), 128);
+    }
+
+    #[test]
+    fn clip_rects_and_partial_updates_apply() {
+        let mut rasterizer = white_texture();
+        let id = TextureId::default();
+        // Turn the white texel black; black over nothing stays opaque
+        let patch = ColorImage::new([1, 1], Color32::BLACK);
+        rasterizer.set_textures(&TexturesDelta {
+            set: vec![(id, ImageDelta::partial([0, 0], patch, TextureOptions::NEAREST))],
+            free: Vec::new(),
+        });
+
+        let mut mesh = Mesh::default();
+        mesh.add_colored_rect(Rect::from_min_max(Pos2::ZERO, Pos2::new(8.0, 8.0)), Color32::WHITE);
+        let clip_rect = Rect::from_min_max(Pos2::new(2.0, 2.0), Pos2::new(4.0, 8.0));
+        let primitives = [ClippedPrimitive { clip_rect, primitive: Primitive::Mesh(mesh) }];
+        let mut pixmap = Pixmap::new(8, 8).unwrap();
+        rasterizer.paint(&primitives, 1.0, &mut pixmap);
+
+        assert_eq!(alpha(&pixmap, 1, 4), 0);
+        assert_eq!(alpha(&pixmap, 2, 4), 255);
+        assert_eq!(pixmap.pixel(3, 7).unwrap().red(), 0);
+        assert_eq!(alpha(&pixmap, 4, 4), 0);
+
+        rasterizer.free_textures(&TexturesDelta { set: Vec::new(), free: vec![id] });
+        let mut cleared = Pixmap::new(8, 8).unwrap();
+        rasterizer.paint(&primitives, 1.0, &mut cleared);
+        assert!(cleared.data().iter().all(|&byte| byte == 0));
+    }
+}
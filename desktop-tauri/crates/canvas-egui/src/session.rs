@@ -0,0 +1,145 @@
+//! One egui context per egui tile.
+
+use std::time::Instant;
+
+use canvas_core::glam::Vec2;
+use canvas_core::CanvasError;
+use egui::{Pos2, RawInput, Rect, ViewportId};
+use serde_json::Value;
+use tiny_skia::Pixmap;
+
+use crate::input::WidgetInput;
+use crate::raster::Rasterizer;
+use crate::{ui, MAX_FRAME_PIXELS};
+
+/// A rendered frame.
+#[derive(Debug)]
+pub struct EguiFrame {
+    pub pixmap: Pixmap,
+    /// Whether egui wants another frame right away, such as while an
+    /// animation runs or after input it only partly handled.
+    pub repaint: bool,
+}
+
+/// The egui context of an egui tile, with the widget's config as the
+/// widget last changed it.
+pub struct EguiSession {
+    ctx: egui::Context,
+    rasterizer: Rasterizer,
+    widget_type: String,
+    /// The config shown, with the widget's changes.
+    config: Value,
+    /// The tile's config the changes started from.
+    tile_config: Value,
+    events: Vec<egui::Event>,
+    modifiers: egui::Modifiers,
+    started: Instant,
+}
+
+impl EguiSession {
+    pub fn new(widget_type: &str, config: &Value) -> Self {
+        Self {
+            ctx: egui::Context::default(),
+            rasterizer: Rasterizer::new(),
+            widget_type: widget_type.to_string(),
+            config: config.clone(),
+            tile_config: config.clone(),
+            events: Vec::new(),
+            modifiers: egui::Modifiers::NONE,
+            started: Instant::now(),
+        }
+    }
+
+    /// Follows the tile's widget. When the tile took over the
+    /// [`EguiSession::changes`] they stop being changes; when it was changed
+    /// otherwise, such as by undo or a collaborator, the changes are dropped.
+    pub fn sync(&mut self, widget_type: &str, config: &Value) {
+        if self.widget_type != widget_type || self.tile_config != *config {
+            self.widget_type = widget_type.to_string();
+            self.config = config.clone();
+            self.tile_config = config.clone();
+        }
+    }
+
+    /// Queues input for the next frame.
+    pub fn input(&mut self, input: &WidgetInput) -> Result<(), CanvasError> {
+        let event = input.to_event(self.modifiers)?;
+        if let egui::Event::Key { modifiers, .. } = &event {
+            self.modifiers = *modifiers;
+        }
+        self.events.push(event);
+        Ok(())
+    }
+
+    /// Runs a frame over the queued input without drawing it, so the widget
+    /// handles the input before its [`EguiSession::changes`] are read.
+    pub fn update(&mut self, size: Vec2) {
+        let output = self.run(size, 1.0);
+        self.rasterizer.free_textures(&output.textures_delta);
+    }
+
+    /// Runs a frame over the queued input and renders it for a content area
+    /// of `size` world units at `scale` pixels per unit.
+    pub fn render(&mut self, size: Vec2, scale: f32) -> Result<EguiFrame, CanvasError> {
+        if !scale.is_finite() || scale <= 0.0 {
+            return Err(CanvasError::invalid_argument("scale", "must be a positive number"));
+        }
+        let pixels = (size * scale).ceil();
+        if !pixels.is_finite() || pixels.min_element() < 1.0 || pixels.max_element() > MAX_FRAME_PIXELS as f32 {
+            return Err(CanvasError::invalid_argument(
+                "scale",
+                format!("must render the tile at 1 to {} pixels along each side", MAX_FRAME_PIXELS),
+            ));
+        }
+        let mut pixmap = Pixmap::new(pixels.x as u32, pixels.y as u32)
+            .ok_or_else(|| CanvasError::Internal { message: "could not allocate the frame".into() })?;
+
+        let output = self.run(size, scale);
+        let primitives = self.ctx.tessellate(output.shapes, output.pixels_per_point);
+        self.rasterizer.paint(&primitives, output.pixels_per_point, &mut pixmap);
+        self.rasterizer.free_textures(&output.textures_delta);
+
+        Ok(EguiFrame { pixmap, repaint: self.ctx.has_requested_repaint() })
+    }
+
+    /// Runs a frame over the queued input and takes in the textures it
+    /// creates. The caller frees the textures it lets go of once it drew.
+    fn run(&mut self, size: Vec2, scale: f32) -> egui::FullOutput {
+        let mut input = RawInput {
+            screen_rect: Some(Rect::from_min_size(Pos2::ZERO, egui::vec2(size.x, size.y))),
+            max_texture_side: Some(MAX_FRAME_PIXELS as usize),
+            time: Some(self.started.elapsed().as_secs_f64()),
+            modifiers: self.modifiers,
+            events: std::mem::take(&mut self.events),
+            focused: true,
+            ..RawInput::default()
+        };
+        input.viewports.entry(ViewportId::ROOT).or_default().native_pixels_per_point = Some(scale);
+
+        let (widget_type, config) = (&self.widget_type, &mut self.config);
+        let output = self.ctx.run(input, |ctx| {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                ui::show(ui, widget_type, config);
+            });
+        });
+
+        self.rasterizer.set_textures(&output.textures_delta);
+        output
+    }
+
+    /// The config the widget changed to, once nothing is being dragged, so
+    /// a drag is kept as one change. They stay changes until the tile takes
+    /// them over and the session is [synced](EguiSession::sync) with it.
+    pub fn changes(&self) -> Option<Value> {
+        let dragging = self.ctx.input(|input| input.pointer.any_down());
+        if dragging || self.config == self.tile_config {
+            return None;
+        }
+        Some(self.config.clone())
+    }
+
+    /// The config shown, with the widget's changes.
+    pub fn config(&self) -> &Value {
+        &self.config
+    }
+}
@@ -0,0 +1,69 @@
+//! The egui UI of each built-in widget kind.
+
+use canvas_core::widgets::{ButtonConfig, CheckboxConfig, ColorPickerConfig, SliderConfig, TextBoxConfig, WidgetConfig};
+use egui::{Color32, RichText, Ui};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Shows the widget of kind `widget_type` configured by `config`, and
+/// writes what the widget changed back into `config`. Returns whether it
+/// did. Unknown kinds and configs that don't fit their kind show as an
+/// error instead, so they never render as nothing.
+pub fn show(ui: &mut Ui, widget_type: &str, config: &mut Value) -> bool {
+    match widget_type {
+        ButtonConfig::NAME => edit(ui, config, |ui, button: &mut ButtonConfig| {
+            // Buttons have nothing to change
+            let _ = ui.button(button.text.as_str());
+        }),
+        SliderConfig::NAME => edit(ui, config, |ui, slider: &mut SliderConfig| {
+            ui.add(egui::Slider::new(&mut slider.value, slider.min..=slider.max));
+        }),
+        CheckboxConfig::NAME => edit(ui, config, |ui, checkbox: &mut CheckboxConfig| {
+            ui.checkbox(&mut checkbox.checked, checkbox.text.as_str());
+        }),
+        TextBoxConfig::NAME => edit(ui, config, |ui, text_box: &mut TextBoxConfig| {
+            ui.add(egui::TextEdit::singleline(&mut text_box.text).desired_width(f32::INFINITY));
+        }),
+        ColorPickerConfig::NAME => edit(ui, config, |ui, picker: &mut ColorPickerConfig| {
+            let mut srgba = picker.color.map(|c| (c * 255.0).round() as u8);
+            if ui.color_edit_button_srgba_unmultiplied(&mut srgba).changed() {
+                picker.color = srgba.map(|c| c as f32 / 255.0);
+            }
+        }),
+        _ => {
+            error(ui, &format!("Unknown widget type {:?}", widget_type));
+            false
+        }
+    }
+}
+
+/// Shows `config` as a `C` with `widget`, keeping fields the widget didn't
+/// change as they were.
+fn edit<C: WidgetConfig + PartialEq + Clone>(ui: &mut Ui, config: &mut Value, widget: impl FnOnce(&mut Ui, &mut C)) -> bool {
+    let parsed = match &*config {
+        Value::Null => Ok(C::default()),
+        config => C::deserialize(config),
+    };
+    let mut typed = match parsed {
+        Ok(typed) => typed,
+        Err(e) => {
+            error(ui, &format!("Invalid {} config: {}", C::LABEL.to_lowercase(), e));
+            return false;
+        }
+    };
+    let before = typed.clone();
+    widget(ui, &mut typed);
+    if typed == before {
+        return false;
+    }
+    *config = to_value(&typed);
+    true
+}
+
+fn to_value(config: &impl Serialize) -> Value {
+    serde_json::to_value(config).expect("widget configs serialize to JSON")
+}
+
+fn error(ui: &mut Ui, message: &str) {
+    ui.label(RichText::new(message).color(Color32::from_rgb(0xd3, 0x2f, 0x2f)));
+}
@@ -0,0 +1,105 @@
+//! Drives egui sessions with forwarded input, the way the app does.
+
+use canvas_core::glam::Vec2;
+use canvas_core::WidgetRegistry;
+use canvas_egui::{EguiSession, PointerButton, WidgetInput};
+use serde_json::json;
+
+const SIZE: Vec2 = Vec2::new(200.0, 80.0);
+
+fn click(session: &mut EguiSession, position: [f32; 2]) {
+    for input in [
+        WidgetInput::PointerMove { position },
+        WidgetInput::PointerButton { position, button: PointerButton::Primary, pressed: true },
+    ] {
+        session.input(&input).unwrap();
+        session.update(SIZE);
+    }
+    let release = WidgetInput::PointerButton { position, button: PointerButton::Primary, pressed: false };
+    session.input(&release).unwrap();
+    session.update(SIZE);
+}
+
+fn pixels_like(pixmap: &tiny_skia::Pixmap, matches: impl Fn(tiny_skia::PremultipliedColorU8) -> bool) -> usize {
+    pixmap.pixels().iter().filter(|pixel| matches(**pixel)).count()
+}
+
+#[test]
+fn clicking_a_checkbox_changes_its_config() {
+    let config = WidgetRegistry::default().normalize("checkbox", &json!({ "text": "Done" })).unwrap();
+    let mut session = EguiSession::new("checkbox", &config);
+
+    let before = session.render(SIZE, 2.0).unwrap().pixmap;
+    assert_eq!((before.width(), before.height()), (400, 160));
+    // The panel is opaque and the label is drawn on it
+    assert!(before.pixels().iter().all(|pixel| pixel.alpha() == 255));
+    assert!(pixels_like(&before, |pixel| pixel.red() > 128) > 100);
+    assert!(session.changes().is_none());
+
+    click(&mut session, [16.0, 16.0]);
+    let changes = json!({ "checked": true, "text": "Done" });
+    // Kept until the tile takes them over
+    assert_eq!(session.changes(), Some(changes.clone()));
+    assert_eq!(session.changes(), Some(changes.clone()));
+    session.sync("checkbox", &changes);
+    assert!(session.changes().is_none());
+
+    let after = session.render(SIZE, 2.0).unwrap().pixmap;
+    assert_ne!(before.data(), after.data());
+}
+
+#[test]
+fn drags_are_one_change() {
+    let mut session = EguiSession::new("slider", &json!({ "min": 0.0, "max": 10.0, "value": 0.0 }));
+    session.render(SIZE, 1.0).unwrap();
+
+    let press = [12.0, 16.0];
+    for input in [
+        WidgetInput::PointerMove { position: press },
+        WidgetInput::PointerButton { position: press, button: PointerButton::Primary, pressed: true },
+        WidgetInput::PointerMove { position: [60.0, 16.0] },
+    ] {
+        session.input(&input).unwrap();
+        session.render(SIZE, 1.0).unwrap();
+    }
+    let dragged = session.config()["value"].as_f64().unwrap();
+    assert!(dragged > 0.0);
+    assert!(session.changes().is_none());
+
+    let release = WidgetInput::PointerButton { position: [60.0, 16.0], button: PointerButton::Primary, pressed: false };
+    session.input(&release).unwrap();
+    session.render(SIZE, 1.0).unwrap();
+    let changes = session.changes().unwrap();
+    assert_eq!(changes["value"].as_f64(), Some(dragged));
+}
+
+#[test]
+fn typing_edits_text_and_the_tile_wins_on_sync() {
+    let mut session = EguiSession::new("textbox", &json!({ "text": "" }));
+    session.render(SIZE, 1.0).unwrap();
+    click(&mut session, [40.0, 16.0]);
+    session.input(&WidgetInput::Text { text: "hi".into() }).unwrap();
+    session.render(SIZE, 1.0).unwrap();
+    assert_eq!(session.config(), &json!({ "text": "hi" }));
+
+    // Undone elsewhere before the change was taken
+    session.sync("textbox", &json!({ "text": "undone" }));
+    assert_eq!(session.config(), &json!({ "text": "undone" }));
+    assert!(session.changes().is_none());
+
+    let error = session.input(&WidgetInput::Key { key: "NoSuchKey".into(), pressed: true, modifiers: Default::default() });
+    assert_eq!(error.unwrap_err().code(), "invalid_argument");
+}
+
+#[test]
+fn unknown_widgets_and_bad_scales_are_reported() {
+    let mut session = EguiSession::new("sldier", &json!({}));
+    let frame = session.render(SIZE, 1.0).unwrap().pixmap;
+    // The error message is drawn in red
+    assert!(pixels_like(&frame, |pixel| pixel.red() > 150 && pixel.green() < 100) > 10);
+
+    for scale in [0.0, f32::NAN, 100.0] {
+        assert_eq!(session.render(SIZE, scale).unwrap_err().code(), "invalid_argument");
+    }
+    assert!(session.render(Vec2::ZERO, 1.0).is_err());
+}
@@ -0,0 +1,42 @@
+//! Self-hostable relay for collaborative canvas sessions.
+//!
+//! Usage: `canvas-relay [--bind <address>]`
+
+use async_std::net::TcpListener;
+
+use canvas_sync::DEFAULT_RELAY_ADDR;
+
+#[async_std::main]
+async fn main() {
+    let mut bind = DEFAULT_RELAY_ADDR.to_string();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bind" => match args.next() {
+                Some(address) => bind = address,
+                None => exit_with_usage("--bind needs an address"),
+            },
+            "--help" | "-h" => {
+                println!("usage: canvas-relay [--bind <address>]");
+                return;
+            }
+            other => exit_with_usage(&format!("unknown argument: {}", other)),
+        }
+    }
+
+    let listener = match TcpListener::bind(&bind).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            eprintln!("failed to bind {}: {}", bind, err);
+            std::process::exit(1);
+        }
+    };
+    println!("canvas relay listening on ws://{}", bind);
+
+    canvas_sync::serve(listener).await;
+}
+
+fn exit_with_usage(message: &str) -> ! {
+    eprintln!("{}\nusage: canvas-relay [--bind <address>]", message);
+    std::process::exit(2);
+}
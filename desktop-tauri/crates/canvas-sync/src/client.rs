@@ -0,0 +1,88 @@
+//! Connection of one replica to a relay.
+
+use async_tungstenite::async_std::connect_async;
+use async_tungstenite::tungstenite::Message;
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+
+use canvas_core::SyncOp;
+
+use crate::protocol::{ClientMessage, ServerMessage};
+use crate::SyncError;
+
+/// A joined room.
+///
+/// Operations pushed into `sender` are sent to the relay in order; batches
+/// from other peers arrive on `incoming`. Dropping `sender` closes the
+/// connection, after which `incoming` ends.
+pub struct SyncConnection {
+    /// State of the room when we joined.
+    pub welcome: Vec<SyncOp>,
+    pub sender: mpsc::UnboundedSender<Vec<SyncOp>>,
+    pub incoming: mpsc::UnboundedReceiver<Vec<SyncOp>>,
+}
+
+/// Connects to the relay at `url` (e.g. `ws://127.0.0.1:9137`) and joins
+/// `room`.
+pub async fn connect(url: &str, room: &str) -> Result<SyncConnection, SyncError> {
+    let (websocket, _) = connect_async(url).await?;
+    let (mut sink, mut source) = websocket.split();
+
+    let join = ClientMessage::Join { room: room.to_string() }.encode()?;
+    sink.send(Message::text(join)).await?;
+
+    let welcome = loop {
+        match source.next().await {
+            Some(Ok(Message::Text(text))) => match ServerMessage::decode(text.as_str())? {
+                ServerMessage::Welcome { ops } => break ops,
+                ServerMessage::Ops { .. } => log::warn!("ignoring ops sent before welcome"),
+            },
+            Some(Ok(Message::Close(_))) | None => return Err(SyncError::NotWelcomed),
+            Some(Ok(_)) => {}
+            Some(Err(err)) => return Err(err.into()),
+        }
+    };
+
+    let (sender, mut outgoing) = mpsc::unbounded::<Vec<SyncOp>>();
+    async_std::task::spawn(async move {
+        while let Some(ops) = outgoing.next().await {
+            let message = match (ClientMessage::Ops { ops }).encode() {
+                Ok(message) => message,
+                Err(err) => {
+                    log::error!("failed to encode ops: {}", err);
+                    continue;
+                }
+            };
+            if let Err(err) = sink.send(Message::text(message)).await {
+                log::warn!("failed to send ops to relay: {}", err);
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    let (incoming_tx, incoming) = mpsc::unbounded::<Vec<SyncOp>>();
+    async_std::task::spawn(async move {
+        while let Some(message) = source.next().await {
+            match message {
+                Ok(Message::Text(text)) => match ServerMessage::decode(text.as_str()) {
+                    Ok(ServerMessage::Ops { ops }) => {
+                        if incoming_tx.unbounded_send(ops).is_err() {
+                            break;
+                        }
+                    }
+                    Ok(ServerMessage::Welcome { .. }) => log::warn!("ignoring second welcome"),
+                    Err(err) => log::warn!("invalid message from relay: {}", err),
+                },
+                Ok(Message::Close(_)) => break,
+                Ok(_) => {}
+                Err(err) => {
+                    log::warn!("connection to relay failed: {}", err);
+                    break;
+                }
+            }
+        }
+    });
+
+    Ok(SyncConnection { welcome, sender, incoming })
+}
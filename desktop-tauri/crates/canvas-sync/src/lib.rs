@@ -0,0 +1,31 @@
+//! Collaborative editing transport for the infinite canvas.
+//!
+//! Replicas exchange [`canvas_core::SyncOp`]s through a small relay over a
+//! websocket. The relay does not need to understand the canvas: it keeps one
+//! [`canvas_core::SyncDocument`] per room to hand late joiners a compact
+//! snapshot and forwards everything else to the other peers. Merging is done
+//! by the CRDT in `canvas_core::sync`, so peers converge regardless of the
+//! order in which the relay delivers operations.
+
+pub mod client;
+pub mod protocol;
+pub mod relay;
+
+pub use client::{connect, SyncConnection};
+pub use protocol::{ClientMessage, ServerMessage};
+pub use relay::serve;
+
+/// Address the relay listens on when none is given.
+pub const DEFAULT_RELAY_ADDR: &str = "127.0.0.1:9137";
+
+#[derive(Debug, thiserror::Error)]
+pub enum SyncError {
+    #[error("websocket error: {0}")]
+    WebSocket(#[from] async_tungstenite::tungstenite::Error),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("invalid message: {0}")]
+    Protocol(#[from] serde_json::Error),
+    #[error("relay closed the connection before welcoming us")]
+    NotWelcomed,
+}
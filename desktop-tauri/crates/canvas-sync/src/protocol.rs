@@ -0,0 +1,44 @@
+//! Messages exchanged between replicas and the relay, sent as JSON text
+//! frames.
+
+use serde::{Deserialize, Serialize};
+
+use canvas_core::SyncOp;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    /// Must be the first message; selects the document to edit.
+    Join { room: String },
+    /// Local changes to merge and forward.
+    Ops { ops: Vec<SyncOp> },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ServerMessage {
+    /// Current state of the room, sent once after joining.
+    Welcome { ops: Vec<SyncOp> },
+    /// Changes made by other peers.
+    Ops { ops: Vec<SyncOp> },
+}
+
+impl ClientMessage {
+    pub fn decode(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    pub fn encode(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
+
+impl ServerMessage {
+    pub fn decode(text: &str) -> Result<Self, serde_json::Error> {
+        serde_json::from_str(text)
+    }
+
+    pub fn encode(&self) -> Result<String, serde_json::Error> {
+        serde_json::to_string(self)
+    }
+}
@@ -0,0 +1,170 @@
+//! The relay every replica of a session connects to.
+//!
+//! Peers join a named room. The relay merges their operations into the
+//! room's [`SyncDocument`] and forwards them to the other peers of the room;
+//! a peer joining later receives a snapshot instead of the full history.
+//! A room is deleted once its last peer leaves.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use async_std::net::{TcpListener, TcpStream};
+use async_tungstenite::tungstenite::Message;
+use futures::channel::mpsc;
+use futures::{SinkExt, StreamExt};
+
+use canvas_core::{ReplicaId, SyncDocument, Tile, TileId};
+
+use crate::protocol::{ClientMessage, ServerMessage};
+use crate::SyncError;
+
+/// Pause after a failed accept, so a lasting failure doesn't spin.
+const ACCEPT_RETRY_DELAY: Duration = Duration::from_millis(100);
+
+struct Room {
+    document: SyncDocument,
+    tiles: HashMap<TileId, Tile>,
+    peers: HashMap<u64, mpsc::UnboundedSender<Message>>,
+}
+
+impl Room {
+    fn new() -> Self {
+        Self {
+            document: SyncDocument::new(ReplicaId::new()),
+            tiles: HashMap::new(),
+            peers: HashMap::new(),
+        }
+    }
+}
+
+#[derive(Clone, Default)]
+struct Rooms {
+    rooms: Arc<Mutex<HashMap<String, Room>>>,
+    next_peer: Arc<Mutex<u64>>,
+}
+
+impl Rooms {
+    /// Locks the rooms. A peer task that panicked while holding the lock
+    /// doesn't take the relay down with it: rooms are only changed by whole
+    /// merges, which leave them consistent.
+    fn lock(&self) -> MutexGuard<'_, HashMap<String, Room>> {
+        self.rooms.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Accepts peers on `listener` for as long as the process runs. Failing to
+/// accept one connection, e.g. when out of file descriptors, is logged and
+/// retried after [`ACCEPT_RETRY_DELAY`].
+pub async fn serve(listener: TcpListener) {
+    let rooms = Rooms::default();
+    loop {
+        let (stream, address) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(err) => {
+                log::error!("failed to accept a peer: {}", err);
+                async_std::task::sleep(ACCEPT_RETRY_DELAY).await;
+                continue;
+            }
+        };
+        let rooms = rooms.clone();
+        async_std::task::spawn(async move {
+            if let Err(err) = handle_peer(rooms, stream).await {
+                log::warn!("peer {} disconnected: {}", address, err);
+            }
+        });
+    }
+}
+
+async fn handle_peer(rooms: Rooms, stream: TcpStream) -> Result<(), SyncError> {
+    let websocket = async_tungstenite::accept_async(stream).await?;
+    let (mut sink, mut source) = websocket.split();
+
+    let room_name = loop {
+        match source.next().await {
+            Some(Ok(Message::Text(text))) => match ClientMessage::decode(text.as_str())? {
+                ClientMessage::Join { room } => break room,
+                ClientMessage::Ops { .. } => log::warn!("ignoring ops sent before join"),
+            },
+            Some(Ok(_)) => {}
+            Some(Err(err)) => return Err(err.into()),
+            None => return Ok(()),
+        }
+    };
+
+    let (outgoing, mut outgoing_rx) = mpsc::unbounded::<Message>();
+    let peer = {
+        let mut next_peer = rooms.next_peer.lock().unwrap_or_else(PoisonError::into_inner);
+        *next_peer += 1;
+        *next_peer
+    };
+
+    {
+        let mut rooms = rooms.lock();
+        let room = rooms.entry(room_name.clone()).or_insert_with(Room::new);
+        // Queued before registering, so the welcome precedes any forwarded ops
+        let welcome = ServerMessage::Welcome { ops: room.document.snapshot() }.encode()?;
+        let _ = outgoing.unbounded_send(Message::text(welcome));
+        room.peers.insert(peer, outgoing);
+    }
+    log::info!("peer {} joined room {}", peer, room_name);
+
+    let writer = async_std::task::spawn(async move {
+        while let Some(message) = outgoing_rx.next().await {
+            if sink.send(message).await.is_err() {
+                break;
+            }
+        }
+        let _ = sink.close().await;
+    });
+
+    let result = forward_ops(&rooms, &room_name, peer, &mut source).await;
+
+    {
+        let mut rooms = rooms.lock();
+        if let Some(room) = rooms.get_mut(&room_name) {
+            // Dropping the sender ends the writer
+            room.peers.remove(&peer);
+            if room.peers.is_empty() {
+                rooms.remove(&room_name);
+                log::info!("room {} is empty and was deleted", room_name);
+            }
+        }
+    }
+    writer.await;
+    log::info!("peer {} left room {}", peer, room_name);
+    result
+}
+
+async fn forward_ops<S>(rooms: &Rooms, room_name: &str, peer: u64, source: &mut S) -> Result<(), SyncError>
+where
+    S: futures::Stream<Item = Result<Message, async_tungstenite::tungstenite::Error>> + Unpin,
+{
+    while let Some(message) = source.next().await {
+        let ops = match message? {
+            Message::Text(text) => match ClientMessage::decode(text.as_str())? {
+                ClientMessage::Ops { ops } => ops,
+                ClientMessage::Join { .. } => {
+                    log::warn!("peer {} tried to join twice", peer);
+                    continue;
+                }
+            },
+            Message::Close(_) => break,
+            _ => continue,
+        };
+
+        let mut rooms = rooms.lock();
+        let Some(room) = rooms.get_mut(room_name) else {
+            break;
+        };
+        room.document.apply_remote(&ops, &mut room.tiles);
+
+        let forwarded = Message::text(ServerMessage::Ops { ops }.encode()?);
+        for (other, sender) in &room.peers {
+            if *other != peer {
+                let _ = sender.unbounded_send(forwarded.clone());
+            }
+        }
+    }
+    Ok(())
+}
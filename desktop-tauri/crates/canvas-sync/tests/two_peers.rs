@@ -0,0 +1,157 @@
+//! Two in-process replicas editing one room through a local relay.
+
+use std::time::Duration;
+
+use async_std::net::TcpListener;
+use futures::StreamExt;
+
+use canvas_core::glam::Vec2;
+use canvas_core::{CanvasOperation, InfiniteCanvas, NewTile, ReplicaId, SyncDocument, TileId};
+use canvas_sync::{connect, SyncConnection};
+
+struct Peer {
+    canvas: InfiniteCanvas,
+    document: SyncDocument,
+    connection: SyncConnection,
+}
+
+impl Peer {
+    async fn join(url: &str, room: &str) -> Self {
+        let connection = connect(url, room).await.expect("join room");
+        let mut canvas = InfiniteCanvas::new(Vec2::new(1280.0, 720.0));
+        let mut document = SyncDocument::new(ReplicaId::new());
+        document.apply_remote(&connection.welcome, &mut canvas.tiles);
+        Self { canvas, document, connection }
+    }
+
+    fn edit(&mut self, operations: Vec<CanvasOperation>) -> Vec<TileId> {
+        let batch = self.canvas.apply_operations(operations).expect("apply operations");
+        let ops = self.document.commit(&self.canvas.tiles);
+        self.connection.sender.unbounded_send(ops).expect("send ops");
+        batch.added
+    }
+
+    /// Waits for the next batch forwarded by the relay and merges it.
+    async fn receive(&mut self) {
+        let ops = async_std::future::timeout(Duration::from_secs(5), self.connection.incoming.next())
+            .await
+            .expect("timed out waiting for ops")
+            .expect("relay closed the connection");
+        self.document.apply_remote(&ops, &mut self.canvas.tiles);
+    }
+
+    /// Tile ids and positions, in a stable order for comparison.
+    fn layout(&self) -> Vec<(String, Vec2)> {
+        let mut layout: Vec<_> = self.canvas.tiles.iter()
+            .map(|(id, tile)| (id.to_string(), tile.position))
+            .collect();
+        layout.sort_by(|a, b| a.0.cmp(&b.0));
+        layout
+    }
+}
+
+async fn start_relay() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+    async_std::task::spawn(canvas_sync::serve(listener));
+    format!("ws://{}", address)
+}
+
+fn add_sketch(position: [f32; 2]) -> CanvasOperation {
+    CanvasOperation::Add {
+        tile_id: None,
+        tile: NewTile::Skia,
+        position,
+        size: [200.0, 100.0],
+        title: "Sketch".to_string(),
+    }
+}
+
+#[async_std::test]
+async fn edits_reach_the_other_peer_and_late_joiners() {
+    let url = start_relay().await;
+    let mut a = Peer::join(&url, "board").await;
+    let mut b = Peer::join(&url, "board").await;
+
+    let [tile] = a.edit(vec![add_sketch([0.0, 0.0])])[..] else { panic!("expected one tile") };
+    b.receive().await;
+    assert_eq!(b.canvas.tiles[&tile].title, "Sketch");
+
+    b.edit(vec![CanvasOperation::Move { tile_id: tile, position: [50.0, 60.0] }]);
+    a.receive().await;
+    assert_eq!(a.canvas.tiles[&tile].position, Vec2::new(50.0, 60.0));
+
+    // The relay has merged everything it forwarded, so a new peer starts
+    // from the same state
+    let c = Peer::join(&url, "board").await;
+    assert_eq!(c.layout(), a.layout());
+
+    let other_room = Peer::join(&url, "elsewhere").await;
+    assert!(other_room.canvas.tiles.is_empty());
+}
+
+#[async_std::test]
+async fn rooms_are_deleted_when_the_last_peer_leaves() {
+    let url = start_relay().await;
+    let mut a = Peer::join(&url, "board").await;
+    let mut b = Peer::join(&url, "board").await;
+    a.edit(vec![add_sketch([0.0, 0.0])]);
+    b.receive().await;
+    drop((a, b));
+
+    // The relay notices the peers leaving in its own time
+    for _ in 0..100 {
+        if Peer::join(&url, "board").await.canvas.tiles.is_empty() {
+            return;
+        }
+        async_std::task::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("the room outlived its peers");
+}
+
+#[async_std::test]
+async fn concurrent_edits_converge() {
+    let url = start_relay().await;
+    let mut a = Peer::join(&url, "board").await;
+    let mut b = Peer::join(&url, "board").await;
+
+    let [shared] = a.edit(vec![add_sketch([0.0, 0.0])])[..] else { panic!("expected one tile") };
+    b.receive().await;
+
+    // Neither peer sees the other's changes before making its own
+    a.edit(vec![
+        CanvasOperation::Move { tile_id: shared, position: [10.0, 0.0] },
+        add_sketch([300.0, 0.0]),
+    ]);
+    b.edit(vec![
+        CanvasOperation::Move { tile_id: shared, position: [20.0, 0.0] },
+        CanvasOperation::Resize { tile_id: shared, size: [400.0, 300.0], fit: false },
+        add_sketch([600.0, 0.0]),
+    ]);
+    a.receive().await;
+    b.receive().await;
+
+    assert_eq!(a.canvas.tiles.len(), 3);
+    assert_eq!(a.layout(), b.layout());
+    // Resize did not conflict with anything and survives on both sides
+    assert_eq!(a.canvas.tiles[&shared].size, Vec2::new(400.0, 300.0));
+    assert_eq!(b.canvas.tiles[&shared].size, Vec2::new(400.0, 300.0));
+}
+
+#[async_std::test]
+async fn remove_wins_over_concurrent_move() {
+    let url = start_relay().await;
+    let mut a = Peer::join(&url, "board").await;
+    let mut b = Peer::join(&url, "board").await;
+
+    let [tile] = a.edit(vec![add_sketch([0.0, 0.0])])[..] else { panic!("expected one tile") };
+    b.receive().await;
+
+    a.edit(vec![CanvasOperation::Remove { tile_id: tile }]);
+    b.edit(vec![CanvasOperation::Move { tile_id: tile, position: [99.0, 99.0] }]);
+    a.receive().await;
+    b.receive().await;
+
+    assert!(a.canvas.tiles.is_empty());
+    assert!(b.canvas.tiles.is_empty());
+}
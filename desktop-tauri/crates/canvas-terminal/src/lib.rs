@@ -0,0 +1,29 @@
+//! Local shells for terminal tiles.
+//!
+//! A [`TerminalSession`] runs a shell under a pseudo-terminal and feeds its
+//! output through a VT100/xterm parser, so the screen lives in Rust and
+//! survives the frontend reloading. Instead of the raw byte stream, the
+//! frontend gets [`ScreenUpdate`]s: the rows that changed since the last
+//! update as runs of equally styled text, coalesced to about one update per
+//! frame however chatty the program is.
+
+pub mod screen;
+pub mod session;
+
+pub use screen::{CellStyle, Color, Run, ScreenLine, ScreenUpdate, TerminalSize};
+pub use session::{TerminalConfig, TerminalSession};
+
+/// Rows of history kept above the screen when none is configured.
+pub const DEFAULT_SCROLLBACK: usize = 5000;
+
+#[derive(Debug, thiserror::Error)]
+pub enum TerminalError {
+    #[error("pseudo-terminal error: {0}")]
+    Pty(String),
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("terminal size must be at least one row and one column, got {rows}x{cols}")]
+    InvalidSize { rows: u16, cols: u16 },
+    #[error("the shell has exited")]
+    Exited,
+}
@@ -0,0 +1,313 @@
+//! Serializable views of a terminal screen and the diffs between them.
+
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TerminalSize {
+    pub rows: u16,
+    pub cols: u16,
+}
+
+impl TerminalSize {
+    pub fn new(rows: u16, cols: u16) -> Self {
+        Self { rows, cols }
+    }
+}
+
+impl Default for TerminalSize {
+    fn default() -> Self {
+        Self { rows: 24, cols: 80 }
+    }
+}
+
+/// A foreground or background color. Indexed colors are the 256 color
+/// palette, the frontend decides what the first 16 look like.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Color {
+    #[default]
+    Default,
+    Indexed(u8),
+    Rgb([u8; 3]),
+}
+
+impl From<vt100::Color> for Color {
+    fn from(color: vt100::Color) -> Self {
+        match color {
+            vt100::Color::Default => Color::Default,
+            vt100::Color::Idx(index) => Color::Indexed(index),
+            vt100::Color::Rgb(r, g, b) => Color::Rgb([r, g, b]),
+        }
+    }
+}
+
+fn is_false(flag: &bool) -> bool {
+    !flag
+}
+
+fn is_default(color: &Color) -> bool {
+    *color == Color::Default
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CellStyle {
+    #[serde(skip_serializing_if = "is_default")]
+    pub fg: Color,
+    #[serde(skip_serializing_if = "is_default")]
+    pub bg: Color,
+    #[serde(skip_serializing_if = "is_false")]
+    pub bold: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub dim: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub italic: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub underline: bool,
+    #[serde(skip_serializing_if = "is_false")]
+    pub inverse: bool,
+}
+
+impl CellStyle {
+    fn of(cell: &vt100::Cell) -> Self {
+        Self {
+            fg: cell.fgcolor().into(),
+            bg: cell.bgcolor().into(),
+            bold: cell.bold(),
+            dim: cell.dim(),
+            italic: cell.italic(),
+            underline: cell.underline(),
+            inverse: cell.inverse(),
+        }
+    }
+
+    /// Whether a blank cell in this style looks like no cell at all.
+    fn is_invisible(&self) -> bool {
+        self.bg == Color::Default && !self.inverse && !self.underline
+    }
+}
+
+/// Consecutive cells of one row sharing a style. `text` holds one grapheme
+/// per column, except that a wide character covers two.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Run {
+    pub text: String,
+    #[serde(default)]
+    pub style: CellStyle,
+}
+
+/// The runs of one screen row. Trailing blanks are left out.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenLine {
+    pub row: u16,
+    pub runs: Vec<Run>,
+}
+
+/// What changed on a terminal since the previous update.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ScreenUpdate {
+    pub size: TerminalSize,
+    /// `lines` holds every row, so earlier updates can be discarded. Rows
+    /// missing from an incremental update are unchanged.
+    pub full: bool,
+    pub lines: Vec<ScreenLine>,
+    /// Row and column of the cursor, `None` while it is hidden or scrolled
+    /// out of view.
+    pub cursor: Option<[u16; 2]>,
+    /// Rows the view is scrolled up into the history, 0 at the bottom.
+    pub scrollback: usize,
+    /// Rows of history available above the screen.
+    pub scrollback_len: usize,
+    /// Arrow keys should be sent in application mode (`ESC O A` instead of
+    /// `ESC [ A`).
+    pub application_cursor: bool,
+    pub title: String,
+    pub exited: bool,
+}
+
+/// Remembers what the frontend was last sent, to turn the current screen
+/// into an incremental [`ScreenUpdate`].
+#[derive(Debug, Default)]
+pub struct ScreenTracker {
+    rows: Vec<Vec<Run>>,
+    last: Option<ScreenUpdate>,
+}
+
+impl ScreenTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The rows of `screen` that differ from the last update, or `None` if
+    /// nothing visible changed.
+    pub fn update(&mut self, screen: &mut vt100::Screen, title: &str, exited: bool) -> Option<ScreenUpdate> {
+        let mut update = self.capture(screen, title, exited, false);
+        if update.lines.is_empty() && self.last.as_ref().is_some_and(|last| same_state(last, &update)) {
+            return None;
+        }
+        update.full = self.last.as_ref().is_none_or(|last| last.size != update.size);
+        if update.full {
+            update = self.capture(screen, title, exited, true);
+        }
+        self.last = Some(update.clone());
+        Some(update)
+    }
+
+    /// Every row of `screen`, for a frontend that has nothing yet.
+    pub fn snapshot(&mut self, screen: &mut vt100::Screen, title: &str, exited: bool) -> ScreenUpdate {
+        let update = self.capture(screen, title, exited, true);
+        self.last = Some(update.clone());
+        update
+    }
+
+    fn capture(&mut self, screen: &mut vt100::Screen, title: &str, exited: bool, full: bool) -> ScreenUpdate {
+        let (rows, cols) = screen.size();
+        self.rows.resize(rows as usize, Vec::new());
+        let mut lines = Vec::new();
+        for row in 0..rows {
+            let runs = row_runs(screen, row, cols);
+            if full || self.rows[row as usize] != runs {
+                self.rows[row as usize] = runs.clone();
+                lines.push(ScreenLine { row, runs });
+            }
+        }
+
+        let scrollback = screen.scrollback();
+        // vt100 clamps the offset to the history, which is the only way to
+        // learn how long it is
+        screen.set_scrollback(usize::MAX);
+        let scrollback_len = screen.scrollback();
+        screen.set_scrollback(scrollback);
+
+        let (cursor_row, cursor_col) = screen.cursor_position();
+        let cursor_row = cursor_row as usize + scrollback;
+        let cursor = (!screen.hide_cursor() && cursor_row < rows as usize)
+            .then_some([cursor_row as u16, cursor_col]);
+
+        ScreenUpdate {
+            size: TerminalSize { rows, cols },
+            full,
+            lines,
+            cursor,
+            scrollback,
+            scrollback_len,
+            application_cursor: screen.application_cursor(),
+            title: title.to_string(),
+            exited,
+        }
+    }
+}
+
+fn same_state(a: &ScreenUpdate, b: &ScreenUpdate) -> bool {
+    a.size == b.size
+        && a.cursor == b.cursor
+        && a.scrollback == b.scrollback
+        && a.scrollback_len == b.scrollback_len
+        && a.application_cursor == b.application_cursor
+        && a.title == b.title
+        && a.exited == b.exited
+}
+
+fn row_runs(screen: &vt100::Screen, row: u16, cols: u16) -> Vec<Run> {
+    let mut runs: Vec<Run> = Vec::new();
+    for col in 0..cols {
+        let Some(cell) = screen.cell(row, col) else { break };
+        if cell.is_wide_continuation() {
+            continue;
+        }
+        let style = CellStyle::of(cell);
+        let text = if cell.has_contents() { cell.contents() } else { " " };
+        match runs.last_mut() {
+            Some(run) if run.style == style => run.text.push_str(text),
+            _ => runs.push(Run { text: text.to_string(), style }),
+        }
+    }
+
+    // Blank cells at the end of a row are what the frontend draws anyway
+    while let Some(run) = runs.last_mut() {
+        if !run.style.is_invisible() {
+            break;
+        }
+        let trimmed = run.text.trim_end_matches(' ').len();
+        run.text.truncate(trimmed);
+        if !run.text.is_empty() {
+            break;
+        }
+        runs.pop();
+    }
+    runs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(line: &ScreenLine) -> String {
+        line.runs.iter().map(|run| run.text.as_str()).collect()
+    }
+
+    #[test]
+    fn updates_only_carry_changed_rows() {
+        let mut parser = vt100::Parser::new(4, 20, 0);
+        let mut tracker = ScreenTracker::new();
+        parser.process(b"first\r\nsecond");
+
+        let update = tracker.update(parser.screen_mut(), "", false).unwrap();
+        assert!(update.full);
+        assert_eq!(update.lines.len(), 4);
+        assert_eq!(text(&update.lines[1]), "second");
+        assert_eq!(update.lines[2].runs, []);
+        assert_eq!(update.cursor, Some([1, 6]));
+        assert!(tracker.update(parser.screen_mut(), "", false).is_none());
+
+        parser.process(b"\r\nthird");
+        let update = tracker.update(parser.screen_mut(), "", false).unwrap();
+        assert!(!update.full);
+        assert_eq!(update.lines.iter().map(|line| line.row).collect::<Vec<_>>(), [2]);
+        assert_eq!(text(&update.lines[0]), "third");
+
+        parser.screen_mut().set_size(6, 20);
+        assert!(tracker.update(parser.screen_mut(), "", false).unwrap().full);
+    }
+
+    #[test]
+    fn rows_are_split_into_styled_runs() {
+        let mut parser = vt100::Parser::new(2, 20, 0);
+        let mut tracker = ScreenTracker::new();
+        parser.process("a\x1b[1;31mbold\x1b[0m \x1b[44m  \x1b[0m界!   ".as_bytes());
+
+        let update = tracker.snapshot(parser.screen_mut(), "title", false);
+        let runs = &update.lines[0].runs;
+        assert_eq!(runs.len(), 5);
+        assert_eq!(runs[0].text, "a");
+        assert_eq!(runs[1].text, "bold");
+        assert_eq!(runs[1].style, CellStyle { fg: Color::Indexed(1), bold: true, ..Default::default() });
+        assert_eq!(runs[2].text, " ");
+        assert_eq!(runs[3].style.bg, Color::Indexed(4));
+        // The wide character takes two columns but appears once, and the
+        // trailing blanks are dropped
+        assert_eq!(runs[4].text, "界!");
+
+        let json = serde_json::to_value(&runs[1]).unwrap();
+        assert_eq!(json, serde_json::json!({ "text": "bold", "style": { "fg": { "indexed": 1 }, "bold": true } }));
+    }
+
+    #[test]
+    fn scrolled_views_report_history_and_hide_the_cursor() {
+        let mut parser = vt100::Parser::new(3, 10, 100);
+        let mut tracker = ScreenTracker::new();
+        for line in 0..10 {
+            parser.process(format!("{}\r\n", line).as_bytes());
+        }
+
+        let bottom = tracker.snapshot(parser.screen_mut(), "", false);
+        assert_eq!(bottom.scrollback_len, 8);
+        assert_eq!(bottom.cursor, Some([2, 0]));
+
+        parser.screen_mut().set_scrollback(5);
+        let scrolled = tracker.update(parser.screen_mut(), "", false).unwrap();
+        assert_eq!(scrolled.scrollback, 5);
+        assert_eq!(scrolled.cursor, None);
+        assert_eq!(text(&scrolled.lines[0]), "3");
+    }
+}
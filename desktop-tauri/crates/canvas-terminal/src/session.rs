@@ -0,0 +1,331 @@
+//! A shell running under a pseudo-terminal.
+
+use std::io::{Read, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use portable_pty::{native_pty_system, Child, CommandBuilder, MasterPty, PtySize};
+
+use crate::screen::{ScreenTracker, ScreenUpdate, TerminalSize};
+use crate::{TerminalError, DEFAULT_SCROLLBACK};
+
+/// Output arriving within this long of the first change is sent in the same
+/// update.
+const FRAME: Duration = Duration::from_millis(16);
+
+/// How long [`TerminalSession::shutdown`] waits for the shell to exit after
+/// hanging up on it.
+const EXIT_TIMEOUT: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Clone)]
+pub struct TerminalConfig {
+    /// Program to run, the user's login shell if `None`.
+    pub shell: Option<String>,
+    pub args: Vec<String>,
+    /// Working directory, the shell's default if `None`.
+    pub cwd: Option<PathBuf>,
+    pub size: TerminalSize,
+    /// Rows of history kept above the screen.
+    pub scrollback: usize,
+}
+
+impl Default for TerminalConfig {
+    fn default() -> Self {
+        Self {
+            shell: None,
+            args: Vec::new(),
+            cwd: None,
+            size: TerminalSize::default(),
+            scrollback: DEFAULT_SCROLLBACK,
+        }
+    }
+}
+
+/// Escape sequences that don't change the screen but that we care about.
+#[derive(Debug, Default)]
+struct Events {
+    title: String,
+}
+
+impl vt100::Callbacks for Events {
+    fn set_window_title(&mut self, _: &mut vt100::Screen, title: &[u8]) {
+        self.title = String::from_utf8_lossy(title).into_owned();
+    }
+}
+
+struct State {
+    parser: vt100::Parser<Events>,
+    tracker: ScreenTracker,
+    /// The screen changed since the last update was sent.
+    dirty: bool,
+    /// The shell closed its end of the terminal.
+    exited: bool,
+    /// The session is shutting down, the emitter should stop.
+    closed: bool,
+}
+
+impl State {
+    fn update(&mut self) -> Option<ScreenUpdate> {
+        let title = self.parser.callbacks().title.clone();
+        self.tracker.update(self.parser.screen_mut(), &title, self.exited)
+    }
+
+    fn snapshot(&mut self) -> ScreenUpdate {
+        let title = self.parser.callbacks().title.clone();
+        self.tracker.snapshot(self.parser.screen_mut(), &title, self.exited)
+    }
+}
+
+struct Shared {
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        // A panic while parsing leaves a screen that is still worth showing
+        self.state.lock().unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+
+    fn mark_dirty(&self, state: &mut State) {
+        state.dirty = true;
+        self.changed.notify_one();
+    }
+}
+
+/// A shell under a pseudo-terminal plus the screen it draws.
+///
+/// Output is parsed on a reader thread; an emitter thread hands
+/// `on_update` a [`ScreenUpdate`] at most once per frame while the screen
+/// keeps changing. Dropping the session hangs up on the shell.
+pub struct TerminalSession {
+    shared: Arc<Shared>,
+    master: Box<dyn MasterPty + Send>,
+    writer: Box<dyn Write + Send>,
+    child: Box<dyn Child + Send + Sync>,
+    size: TerminalSize,
+    emitter: Option<JoinHandle<()>>,
+}
+
+impl TerminalSession {
+    pub fn spawn(
+        config: TerminalConfig,
+        on_update: impl FnMut(ScreenUpdate) + Send + 'static,
+    ) -> Result<Self, TerminalError> {
+        let TerminalSize { rows, cols } = config.size;
+        if rows == 0 || cols == 0 {
+            return Err(TerminalError::InvalidSize { rows, cols });
+        }
+
+        let pair = native_pty_system().openpty(pty_size(config.size)).map_err(pty_error)?;
+        let mut command = match &config.shell {
+            Some(shell) => {
+                let mut command = CommandBuilder::new(shell);
+                command.args(&config.args);
+                command
+            }
+            None => CommandBuilder::new_default_prog(),
+        };
+        command.env("TERM", "xterm-256color");
+        if let Some(cwd) = &config.cwd {
+            command.cwd(cwd);
+        }
+        let child = pair.slave.spawn_command(command).map_err(pty_error)?;
+        // Our copy of the slave end would keep the terminal open after the
+        // shell exits
+        drop(pair.slave);
+
+        let reader = pair.master.try_clone_reader().map_err(pty_error)?;
+        let writer = pair.master.take_writer().map_err(pty_error)?;
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State {
+                parser: vt100::Parser::new_with_callbacks(rows, cols, config.scrollback, Events::default()),
+                tracker: ScreenTracker::new(),
+                dirty: false,
+                exited: false,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        });
+
+        // The reader blocks until the terminal closes, which happens when
+        // the shell exits or the master is dropped, so it is never joined
+        thread::Builder::new()
+            .name("terminal-reader".into())
+            .spawn({
+                let shared = shared.clone();
+                move || read_output(reader, &shared)
+            })?;
+        let emitter = thread::Builder::new()
+            .name("terminal-emitter".into())
+            .spawn({
+                let shared = shared.clone();
+                move || emit_updates(&shared, on_update)
+            })?;
+
+        Ok(Self {
+            shared,
+            master: pair.master,
+            writer,
+            child,
+            size: config.size,
+            emitter: Some(emitter),
+        })
+    }
+
+    /// Sends keystrokes or pasted text to the shell. Scrolls back to the
+    /// bottom first, like any terminal does when you type.
+    pub fn write(&mut self, data: &[u8]) -> Result<(), TerminalError> {
+        {
+            let mut state = self.shared.lock();
+            if state.exited {
+                return Err(TerminalError::Exited);
+            }
+            if state.parser.screen().scrollback() != 0 {
+                state.parser.screen_mut().set_scrollback(0);
+                self.shared.mark_dirty(&mut state);
+            }
+        }
+        self.writer.write_all(data)?;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Resizes the screen and tells the shell, which redraws through the
+    /// usual updates.
+    pub fn resize(&mut self, size: TerminalSize) -> Result<(), TerminalError> {
+        if size.rows == 0 || size.cols == 0 {
+            return Err(TerminalError::InvalidSize { rows: size.rows, cols: size.cols });
+        }
+        if size == self.size {
+            return Ok(());
+        }
+        self.master.resize(pty_size(size)).map_err(pty_error)?;
+        self.size = size;
+        let mut state = self.shared.lock();
+        state.parser.screen_mut().set_size(size.rows, size.cols);
+        self.shared.mark_dirty(&mut state);
+        Ok(())
+    }
+
+    /// Scrolls the view `offset` rows up into the history, 0 being the
+    /// bottom. Returns the offset after clamping it to the history.
+    pub fn scroll_to(&self, offset: usize) -> usize {
+        let mut state = self.shared.lock();
+        state.parser.screen_mut().set_scrollback(offset);
+        self.shared.mark_dirty(&mut state);
+        state.parser.screen().scrollback()
+    }
+
+    pub fn size(&self) -> TerminalSize {
+        self.size
+    }
+
+    /// The whole screen; later updates are relative to it.
+    pub fn snapshot(&self) -> ScreenUpdate {
+        self.shared.lock().snapshot()
+    }
+
+    /// Plain text of the visible rows.
+    pub fn contents(&self) -> String {
+        self.shared.lock().parser.screen().contents()
+    }
+
+    pub fn has_exited(&self) -> bool {
+        self.shared.lock().exited
+    }
+
+    pub fn process_id(&self) -> Option<u32> {
+        self.child.process_id()
+    }
+
+    /// Hangs up on the shell, waits briefly for it to exit and stops the
+    /// emitter. Called on drop; calling it again does nothing.
+    pub fn shutdown(&mut self) {
+        let Some(emitter) = self.emitter.take() else { return };
+        {
+            let mut state = self.shared.lock();
+            state.closed = true;
+            self.shared.changed.notify_one();
+        }
+
+        if let Ok(None) = self.child.try_wait() {
+            if let Err(e) = self.child.kill() {
+                log::warn!("Failed to stop terminal process {:?}: {}", self.child.process_id(), e);
+            }
+            let deadline = Instant::now() + EXIT_TIMEOUT;
+            while matches!(self.child.try_wait(), Ok(None)) {
+                if Instant::now() >= deadline {
+                    log::warn!("Terminal process {:?} did not exit after hangup", self.child.process_id());
+                    break;
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+        }
+
+        if emitter.join().is_err() {
+            log::error!("Terminal update callback panicked");
+        }
+    }
+}
+
+impl Drop for TerminalSession {
+    fn drop(&mut self) {
+        self.shutdown();
+    }
+}
+
+fn read_output(mut reader: Box<dyn Read + Send>, shared: &Shared) {
+    let mut buffer = [0u8; 8192];
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) | Err(_) => break,
+            Ok(n) => {
+                let mut state = shared.lock();
+                state.parser.process(&buffer[..n]);
+                shared.mark_dirty(&mut state);
+            }
+        }
+    }
+    let mut state = shared.lock();
+    state.exited = true;
+    shared.mark_dirty(&mut state);
+}
+
+fn emit_updates(shared: &Shared, mut on_update: impl FnMut(ScreenUpdate)) {
+    loop {
+        {
+            let state = shared.lock();
+            let state = shared.changed
+                .wait_while(state, |state| !state.dirty && !state.closed)
+                .unwrap_or_else(|poisoned| poisoned.into_inner());
+            if state.closed {
+                return;
+            }
+        }
+        // Let the rest of a burst of output arrive
+        thread::sleep(FRAME);
+
+        let update = {
+            let mut state = shared.lock();
+            if state.closed {
+                return;
+            }
+            state.dirty = false;
+            state.update()
+        };
+        if let Some(update) = update {
+            on_update(update);
+        }
+    }
+}
+
+fn pty_size(size: TerminalSize) -> PtySize {
+    PtySize { rows: size.rows, cols: size.cols, pixel_width: 0, pixel_height: 0 }
+}
+
+fn pty_error(error: impl std::fmt::Display) -> TerminalError {
+    TerminalError::Pty(error.to_string())
+}
@@ -0,0 +1,87 @@
+//! Real shells under a pseudo-terminal.
+
+use std::sync::mpsc::{channel, Receiver};
+use std::time::{Duration, Instant};
+
+use canvas_terminal::{ScreenUpdate, TerminalConfig, TerminalSession, TerminalSize};
+
+fn spawn_sh(size: TerminalSize) -> (TerminalSession, Receiver<ScreenUpdate>) {
+    let (sender, updates) = channel();
+    let config = TerminalConfig {
+        shell: Some("/bin/sh".into()),
+        size,
+        ..Default::default()
+    };
+    let session = TerminalSession::spawn(config, move |update| {
+        let _ = sender.send(update);
+    })
+    .expect("spawn shell");
+    (session, updates)
+}
+
+/// Waits until an update satisfies `done`.
+fn wait_for(updates: &Receiver<ScreenUpdate>, mut done: impl FnMut(&ScreenUpdate) -> bool) -> ScreenUpdate {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        let update = updates.recv_timeout(remaining).expect("timed out waiting for the terminal");
+        if done(&update) {
+            return update;
+        }
+    }
+}
+
+fn shows(update: &ScreenUpdate, text: &str) -> bool {
+    update.lines.iter().any(|line| line.runs.iter().map(|run| run.text.as_str()).collect::<String>().contains(text))
+}
+
+#[test]
+fn commands_run_and_their_output_is_streamed() {
+    let (mut session, updates) = spawn_sh(TerminalSize::new(24, 80));
+    session.write(b"echo hello-$((6 * 7))\r").unwrap();
+    wait_for(&updates, |update| shows(update, "hello-42"));
+    assert!(session.contents().contains("hello-42"));
+
+    session.write(b"exit\r").unwrap();
+    let last = wait_for(&updates, |update| update.exited);
+    assert_eq!(last.size, TerminalSize::new(24, 80));
+    assert!(matches!(session.write(b"echo again\r"), Err(canvas_terminal::TerminalError::Exited)));
+}
+
+#[test]
+fn resizing_reaches_the_shell() {
+    let (mut session, updates) = spawn_sh(TerminalSize::new(24, 80));
+    session.resize(TerminalSize::new(10, 40)).unwrap();
+    wait_for(&updates, |update| update.full && update.size == TerminalSize::new(10, 40));
+
+    session.write(b"stty size\r").unwrap();
+    wait_for(&updates, |update| shows(update, "10 40"));
+    assert!(session.resize(TerminalSize::new(0, 40)).is_err());
+}
+
+#[test]
+fn history_can_be_scrolled_and_typing_returns_to_the_bottom() {
+    let (mut session, updates) = spawn_sh(TerminalSize::new(5, 40));
+    session.write(b"for i in 1 2 3 4 5 6 7 8 9 10 11 12; do echo line-$i; done\r").unwrap();
+    wait_for(&updates, |update| shows(update, "line-12"));
+
+    let offset = session.scroll_to(usize::MAX);
+    assert!(offset > 0);
+    let scrolled = wait_for(&updates, |update| update.scrollback == offset);
+    assert!(shows(&scrolled, "line-1"));
+    assert!(session.snapshot().scrollback_len >= offset);
+
+    session.write(b"\r").unwrap();
+    wait_for(&updates, |update| update.scrollback == 0);
+}
+
+#[test]
+fn shutdown_stops_the_shell() {
+    let (mut session, _updates) = spawn_sh(TerminalSize::new(24, 80));
+    let pid = session.process_id().expect("shell has a process id");
+    session.shutdown();
+    session.shutdown();
+    if cfg!(target_os = "linux") {
+        assert!(!std::path::Path::new(&format!("/proc/{}", pid)).exists());
+    }
+}
@@ -0,0 +1,176 @@
+use std::any::Any;
+use std::collections::HashMap;
+use std::panic::{self, AssertUnwindSafe};
+use std::path::Path;
+use std::sync::Arc;
+
+use futures::channel::{mpsc, oneshot};
+use futures::StreamExt;
+use tauri::AppHandle;
+
+use canvas_core::{
+    AppliedBatch, CanvasError, ImageCache, InfiniteCanvas, InkStroke, RenderCache, SearchIndex, TileData, TileId,
+    WidgetRegistry,
+};
+use canvas_egui::EguiSession;
+
+use crate::collab::SyncSession;
+use crate::terminal::TerminalManager;
+use crate::webview::WebViewManager;
+
+/// Everything owned by the canvas actor. Only ever touched from its task.
+pub struct CanvasState {
+    pub canvas: InfiniteCanvas,
+    pub webview_manager: WebViewManager,
+    /// Shells of terminal tiles.
+    pub terminals: TerminalManager,
+    /// Full-text index, updated with every change of the tiles.
+    pub search: SearchIndex,
+    /// Decoded files of image tiles, with their mip levels and thumbnails.
+    pub images: ImageCache,
+    /// Rendered drawings of Skia tiles by mip level.
+    pub renders: RenderCache,
+    /// Ink strokes being drawn, by Skia tile and pointer.
+    pub ink: HashMap<(TileId, u32), InkStroke>,
+    /// Headless egui contexts of egui tiles, from their first frame or
+    /// input on.
+    pub egui: HashMap<TileId, EguiSession>,
+    /// Set while collaborating through a relay.
+    pub sync: Option<SyncSession>,
+    /// Kinds of widgets egui tiles can have, shared with the app state.
+    pub widgets: Arc<WidgetRegistry>,
+}
+
+impl CanvasState {
+    /// Call after tiles were added, changed or removed on this machine.
+    /// Updates what is derived from the tiles, and has the changes
+    /// published to collaborators once the job is done.
+    pub fn tiles_changed(&mut self, batch: &AppliedBatch) {
+        if !batch.changes_tiles() {
+            return;
+        }
+        self.update_derived(batch);
+        if let Some(sync) = &mut self.sync {
+            sync.mark_changed();
+        }
+    }
+
+    /// Updates what is derived from the tiles `batch` touched, such as the
+    /// search index and the image and render caches, without publishing
+    /// anything. For
+    /// changes that came from collaborators.
+    pub fn update_derived(&mut self, batch: &AppliedBatch) {
+        self.search.update(&self.canvas.tiles, batch);
+        if !batch.removed.is_empty() {
+            // Forget decoded images no remaining tile shows
+            let tiles = &self.canvas.tiles;
+            self.images.retain(|path| {
+                tiles.values().any(|tile| {
+                    matches!(&tile.data, TileData::Image { path: shown, .. } if Path::new(shown) == path)
+                })
+            });
+            // and the drawings, widgets and unfinished strokes of removed tiles
+            self.renders.retain(|tile_id| tiles.contains_key(&tile_id));
+            self.egui.retain(|tile_id, _| tiles.contains_key(tile_id));
+            self.ink.retain(|(tile_id, _), _| tiles.contains_key(tile_id));
+        }
+    }
+
+    /// Rebuilds everything derived from the canvas, after a job panicked
+    /// halfway through a change or the canvas was replaced: the search index
+    /// and caches start over, and the webviews and shells are brought in
+    /// line with the tiles. Collaborators get the canvas as it is now.
+    pub fn rebuild(&mut self, app: &AppHandle) {
+        self.search = SearchIndex::new();
+        self.search.refresh(&self.canvas.tiles);
+        self.images = ImageCache::new();
+        self.renders = RenderCache::new();
+        self.ink.clear();
+        self.egui.clear();
+
+        if let Err(e) = self.webview_manager.reconcile(app, &mut self.canvas) {
+            log::error!("failed to restore the webviews: {}", e);
+        }
+        if let Err(e) = self.terminals.reconcile(app, &self.canvas, &[]) {
+            log::error!("failed to restore the terminals: {}", e);
+        }
+        if let Some(sync) = &mut self.sync {
+            sync.mark_changed();
+        }
+    }
+}
+
+/// A queued command. Returns `false` if it panicked.
+type Job = Box<dyn FnOnce(&mut CanvasState) -> bool + Send>;
+
+/// Handle to the task that owns the [`CanvasState`].
+///
+/// Jobs are queued on a channel and run one after another, so canvas and
+/// webview updates of one command can never interleave with another command
+/// and there are no locks to take in the wrong order or to poison. A job
+/// that panics fails its own command; the actor then rebuilds the state
+/// derived from the canvas and keeps serving the rest.
+#[derive(Clone)]
+pub struct CanvasActor {
+    sender: mpsc::UnboundedSender<Job>,
+}
+
+impl CanvasActor {
+    pub fn spawn(app: AppHandle, state: CanvasState) -> Self {
+        let (sender, mut receiver) = mpsc::unbounded::<Job>();
+
+        async_std::task::spawn(async move {
+            let mut state = state;
+            while let Some(job) = receiver.next().await {
+                if !job(&mut state) {
+                    state.rebuild(&app);
+                }
+                if let Some(sync) = &mut state.sync {
+                    sync.publish(&state.canvas);
+                }
+            }
+            log::debug!("canvas actor stopped");
+        });
+
+        Self { sender }
+    }
+
+    /// Runs `job` on the actor and waits for its result.
+    pub async fn run<T, F>(&self, job: F) -> Result<T, CanvasError>
+    where
+        F: FnOnce(&mut CanvasState) -> Result<T, CanvasError> + Send + 'static,
+        T: Send + 'static,
+    {
+        let (reply, response) = oneshot::channel();
+
+        let job: Job = Box::new(move |state| {
+            let result = panic::catch_unwind(AssertUnwindSafe(|| job(state)));
+            let completed = result.is_ok();
+            let result = result.unwrap_or_else(|payload| Err(CanvasError::Internal {
+                message: panic_message(payload),
+            }));
+            // The caller may have given up waiting; that's fine
+            let _ = reply.send(result);
+            completed
+        });
+
+        self.sender.unbounded_send(job).map_err(|_| actor_stopped())?;
+        response.await.map_err(|_| actor_stopped())?
+    }
+}
+
+fn actor_stopped() -> CanvasError {
+    CanvasError::StateUnavailable {
+        message: "canvas actor is not running".to_string(),
+    }
+}
+
+fn panic_message(payload: Box<dyn Any + Send>) -> String {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        format!("command panicked: {}", message)
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        format!("command panicked: {}", message)
+    } else {
+        "command panicked".to_string()
+    }
+}
@@ -1,17 +1,47 @@
-use std::sync::{Arc, Mutex};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::Path;
+use std::sync::Arc;
 
 use glam::Vec2;
 use serde::{Deserialize, Serialize};
-use tauri::{AppHandle, Manager, State};
+use tauri::{AppHandle, Emitter, State};
+use uuid::Uuid;
 
-use crate::canvas::InfiniteCanvas;
-use crate::tiles::{EguiTile, SkiaTile, Tile, TileId, WebViewTile};
-use crate::webview::WebViewManager;
+use canvas_core::document;
+use canvas_core::render::{self, ExportOptions};
+use canvas_core::shapes;
+use canvas_core::tiles::TITLE_BAR_HEIGHT;
+use canvas_core::{
+    decode_commands, encode_commands, AppliedBatch, Camera, CanvasError, CanvasOperation, DrawingCommand, DrawingShape,
+    ImageTile, InfiniteCanvas, InkBrush, InkSample, InkStroke, NewTile, NoteDocument, NoteLine, NoteTile, PathOp,
+    PropertyValue, RenderCacheStats, SearchMatch, SizeConstraints, SkiaTile, TextLayout, TextStyle, Tile, TileData,
+    TileId, TileQuery, TileStyle, WidgetRegistry, WidgetType,
+};
+
+use canvas_egui::{EguiSession, WidgetInput};
+
+use crate::actor::{CanvasActor, CanvasState};
+
+/// Event emitted once per batch, undo or redo with an [`AppliedBatch`] payload.
+pub const CANVAS_CHANGED_EVENT: &str = "canvas-changed";
+
+/// Number of results `search_canvas` returns when no limit is given.
+const DEFAULT_SEARCH_LIMIT: usize = 20;
+
+/// Longer side of a new image tile when no size is given, unless the image
+/// is smaller.
+const DEFAULT_IMAGE_DIMENSION: f32 = 480.0;
+
+/// Extension of saved boards.
+const BOARD_EXTENSION: &str = "board";
+
+/// Header naming the tile whose drawing `set_drawing_data` replaces.
+const DRAWING_TILE_ID_HEADER: &str = "Tile-Id";
 
-#[derive(Default)]
 pub struct AppState {
-    pub canvas: Arc<Mutex<InfiniteCanvas>>,
-    pub webview_manager: Arc<Mutex<WebViewManager>>,
+    pub actor: CanvasActor,
+    /// Kinds of widgets egui tiles can be created with.
+    pub widgets: Arc<WidgetRegistry>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,12 +54,24 @@ pub struct TileInfo {
     pub tile_type: String,
     pub title: String,
     pub visible: bool,
+    pub movable: bool,
+    pub resizable: bool,
+    pub constraints: SizeConstraints,
+    pub style: TileStyle,
+    pub tags: BTreeSet<String>,
+    pub properties: BTreeMap<String, PropertyValue>,
+    /// Markdown source of a note tile.
+    pub markdown: Option<String>,
+    /// Laid out lines of a note tile, for drawing.
+    pub note_lines: Option<Vec<NoteLine>>,
+    /// File and natural size of an image tile.
+    pub image: Option<ImageTile>,
 }
 
 impl From<(&TileId, &Tile)> for TileInfo {
     fn from((id, tile): (&TileId, &Tile)) -> Self {
         Self {
-            id: id.0.to_string(),
+            id: id.to_string(),
             position: [tile.position.x, tile.position.y],
             size: [tile.size.x, tile.size.y],
             rotation: tile.rotation,
@@ -37,43 +79,92 @@ impl From<(&TileId, &Tile)> for TileInfo {
             tile_type: format!("{:?}", tile.tile_type),
             title: tile.title.clone(),
             visible: tile.visible,
+            movable: tile.movable,
+            resizable: tile.resizable,
+            constraints: tile.constraints,
+            style: tile.style.clone(),
+            tags: tile.tags.clone(),
+            properties: tile.properties.clone(),
+            markdown: NoteTile::from_tile(tile).map(|note| note.markdown),
+            note_lines: NoteTile::from_tile(tile).map(|note| NoteDocument::parse(&note.markdown).lines()),
+            image: ImageTile::from_tile(tile),
         }
     }
 }
 
+/// The caller supplied id of the `add_*_tile` commands, or a fresh one.
+fn new_tile_id(tile_id: Option<String>) -> Result<TileId, CanvasError> {
+    match tile_id {
+        Some(tile_id) => tile_id.parse::<TileId>(),
+        None => Ok(TileId(Uuid::new_v4())),
+    }
+}
+
+/// The operation adding the tile of an `add_*_tile` command. Applied with
+/// [`apply_batch`] like every other edit, the new tile is one undo step,
+/// emits a change event and gets its webview or shell.
+fn add_operation(tile_id: TileId, tile: NewTile, position: Vec2, size: Vec2, title: String) -> CanvasOperation {
+    CanvasOperation::Add {
+        tile_id: Some(tile_id),
+        tile,
+        position: position.to_array(),
+        size: size.to_array(),
+        title,
+    }
+}
+
 #[tauri::command]
-pub fn get_canvas_info(state: State<'_, AppState>) -> Result<serde_json::Value, String> {
-    let canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    
-    let canvas_info = serde_json::json!({
-        "size": [canvas.canvas_size.x, canvas.canvas_size.y],
-        "camera": {
-            "position": [canvas.camera.position.x, canvas.camera.position.y],
-            "zoom": canvas.camera.zoom,
-            "rotation": canvas.camera.rotation,
-        },
-        "background_color": canvas.background_color,
-        "grid_visible": canvas.grid_visible,
-        "grid_size": canvas.grid_size,
-        "grid_color": canvas.grid_color,
-    });
-    
-    Ok(canvas_info)
+pub async fn get_canvas_info(state: State<'_, AppState>) -> Result<serde_json::Value, CanvasError> {
+    state.actor.run(|CanvasState { canvas, .. }| {
+        let canvas_info = serde_json::json!({
+            "size": [canvas.canvas_size.x, canvas.canvas_size.y],
+            "camera": {
+                "position": [canvas.camera.position.x, canvas.camera.position.y],
+                "zoom": canvas.camera.zoom,
+                "rotation": canvas.camera.rotation,
+            },
+            "background_color": canvas.background_color,
+            "grid_visible": canvas.grid_visible,
+            "grid_size": canvas.grid_size,
+            "grid_color": canvas.grid_color,
+        });
+
+        Ok(canvas_info)
+    }).await
 }
 
+/// Tiles matching `query` (all tiles without one), bottom to top.
 #[tauri::command]
-pub fn get_tiles(state: State<'_, AppState>) -> Result<Vec<TileInfo>, String> {
-    let canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    
-    let tiles: Vec<TileInfo> = canvas.tiles.iter()
-        .map(|(id, tile)| TileInfo::from((id, tile)))
-        .collect();
-    
-    Ok(tiles)
+pub async fn get_tiles(
+    state: State<'_, AppState>,
+    query: Option<TileQuery>,
+) -> Result<Vec<TileInfo>, CanvasError> {
+    state.actor.run(move |CanvasState { canvas, .. }| {
+        let tiles: Vec<TileInfo> = canvas.query(&query.unwrap_or_default())
+            .into_iter()
+            .map(|(id, tile)| TileInfo::from((&id, tile)))
+            .collect();
+
+        Ok(tiles)
+    }).await
+}
+
+/// Tiles whose title, URL, drawn text or egui config contain every word of
+/// `query`, best match first.
+#[tauri::command]
+pub async fn search_canvas(
+    state: State<'_, AppState>,
+    query: String,
+    limit: Option<usize>,
+) -> Result<Vec<SearchMatch>, CanvasError> {
+    state.actor.run(move |CanvasState { canvas, search, .. }| {
+        Ok(search.search(canvas, &query, limit.unwrap_or(DEFAULT_SEARCH_LIMIT)))
+    }).await
 }
 
 #[tauri::command]
-pub fn add_webview_tile(
+#[allow(clippy::too_many_arguments)]
+pub async fn add_webview_tile(
     app: AppHandle,
     state: State<'_, AppState>,
     url: String,
@@ -82,47 +173,27 @@ pub fn add_webview_tile(
     width: f32,
     height: f32,
     title: String,
-) -> Result<String, String> {
+    tile_id: Option<String>,
+) -> Result<String, CanvasError> {
+    let tile_id = new_tile_id(tile_id)?;
     let position = Vec2::new(position_x, position_y);
     let size = Vec2::new(width, height);
-    
-    let webview_tile = WebViewTile::new(url.clone());
-    let tile = webview_tile.to_tile(position, size, title);
-    
-    let tile_id = {
-        let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-        canvas.add_tile(tile)
-    };
-    
-    // Create the actual webview
-    let webview_id = {
-        let mut webview_manager = state.webview_manager.lock().map_err(|e| e.to_string())?;
-        webview_manager.create_webview(
-            &app,
-            tile_id,
-            &url,
-            position_x as f64,
-            position_y as f64,
-            width as f64,
-            height as f64,
-        ).map_err(|e| e.to_string())?
-    };
-    
-    // Update the tile with the webview ID
-    {
-        let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-        if let Some(tile) = canvas.get_tile_mut(tile_id) {
-            if let crate::tiles::TileData::WebView { webview_id: id_ref, .. } = &mut tile.data {
-                *id_ref = Some(webview_id);
-            }
-        }
-    }
-    
-    Ok(tile_id.0.to_string())
+
+    state.actor.run(move |canvas_state| {
+        let add = add_operation(tile_id, NewTile::WebView { url }, position, size, title);
+        apply_batch(&app, canvas_state, vec![add])?;
+
+        Ok(tile_id.to_string())
+    }).await
 }
 
+/// Fails for widget types `list_widget_types` doesn't list and configs that
+/// don't match their schema. Fields the config leaves out take their
+/// defaults.
 #[tauri::command]
-pub fn add_egui_tile(
+#[allow(clippy::too_many_arguments)]
+pub async fn add_egui_tile(
+    app: AppHandle,
     state: State<'_, AppState>,
     widget_type: String,
     config: serde_json::Value,
@@ -131,184 +202,1276 @@ pub fn add_egui_tile(
     width: f32,
     height: f32,
     title: String,
-) -> Result<String, String> {
+    tile_id: Option<String>,
+) -> Result<String, CanvasError> {
+    let tile_id = new_tile_id(tile_id)?;
     let position = Vec2::new(position_x, position_y);
     let size = Vec2::new(width, height);
-    
-    let egui_tile = EguiTile::new(widget_type, config);
-    let tile = egui_tile.to_tile(position, size, title);
-    
-    let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    let tile_id = canvas.add_tile(tile);
-    
-    Ok(tile_id.0.to_string())
+
+    state.actor.run(move |canvas_state| {
+        let add = add_operation(tile_id, NewTile::Egui { widget_type, config }, position, size, title);
+        apply_batch(&app, canvas_state, vec![add])?;
+
+        Ok(tile_id.to_string())
+    }).await
 }
 
+/// Kinds of widgets egui tiles can show, with the schemas and defaults of
+/// their configs.
 #[tauri::command]
-pub fn add_skia_tile(
+pub async fn list_widget_types(state: State<'_, AppState>) -> Result<Vec<WidgetType>, CanvasError> {
+    Ok(state.widgets.types())
+}
+
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn add_skia_tile(
+    app: AppHandle,
     state: State<'_, AppState>,
     position_x: f32,
     position_y: f32,
     width: f32,
     height: f32,
     title: String,
-) -> Result<String, String> {
+    tile_id: Option<String>,
+) -> Result<String, CanvasError> {
+    let tile_id = new_tile_id(tile_id)?;
     let position = Vec2::new(position_x, position_y);
     let size = Vec2::new(width, height);
-    
-    let skia_tile = SkiaTile::new();
-    let tile = skia_tile.to_tile(position, size, title);
-    
-    let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    let tile_id = canvas.add_tile(tile);
-    
-    Ok(tile_id.0.to_string())
+
+    state.actor.run(move |canvas_state| {
+        apply_batch(&app, canvas_state, vec![add_operation(tile_id, NewTile::Skia, position, size, title)])?;
+
+        Ok(tile_id.to_string())
+    }).await
 }
 
 #[tauri::command]
-pub fn remove_tile(
+#[allow(clippy::too_many_arguments)]
+pub async fn add_note_tile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    markdown: Option<String>,
+    position_x: f32,
+    position_y: f32,
+    width: f32,
+    height: f32,
+    title: String,
+    tile_id: Option<String>,
+) -> Result<String, CanvasError> {
+    let tile_id = new_tile_id(tile_id)?;
+    let position = Vec2::new(position_x, position_y);
+    let size = Vec2::new(width, height);
+
+    state.actor.run(move |canvas_state| {
+        let note = NewTile::Note { markdown: markdown.unwrap_or_default() };
+        apply_batch(&app, canvas_state, vec![add_operation(tile_id, note, position, size, title)])?;
+
+        Ok(tile_id.to_string())
+    }).await
+}
+
+/// Adds a tile showing the image at `path`, or at a file picked in a dialog
+/// when no path is given. Returns `None` if the dialog was cancelled. The
+/// size defaults to the natural size, scaled down to a sensible maximum;
+/// with only `width` or `height` given, the other follows the image.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn add_image_tile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: Option<String>,
+    position_x: f32,
+    position_y: f32,
+    width: Option<f32>,
+    height: Option<f32>,
+    title: Option<String>,
+    tile_id: Option<String>,
+) -> Result<Option<String>, CanvasError> {
+    let tile_id = new_tile_id(tile_id)?;
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let picked = rfd::AsyncFileDialog::new()
+                .add_filter("Images", &["png", "jpg", "jpeg", "webp"])
+                .pick_file()
+                .await;
+            match picked {
+                Some(file) => file.path().display().to_string(),
+                None => return Ok(None),
+            }
+        }
+    };
+
+    // Fails with a clear error for missing or unsupported files
+    let image_tile = ImageTile::open(path)?;
+    let natural = image_tile.natural_size
+        .filter(|[w, h]| *w > 0 && *h > 0)
+        .map_or(Vec2::ONE, |[w, h]| Vec2::new(w as f32, h as f32));
+    // A single given side scales the natural size
+    let size = match (width, height) {
+        (Some(width), Some(height)) => Vec2::new(width, height),
+        (Some(width), None) => natural * (width / natural.x),
+        (None, Some(height)) => natural * (height / natural.y),
+        (None, None) => natural * (DEFAULT_IMAGE_DIMENSION / natural.max_element()).min(1.0),
+    };
+    let title = title.unwrap_or_else(|| {
+        Path::new(&image_tile.path)
+            .file_name()
+            .map_or_else(|| image_tile.path.clone(), |name| name.to_string_lossy().into_owned())
+    });
+    let position = Vec2::new(position_x, position_y);
+    let image = NewTile::Image { path: image_tile.path };
+
+    state.actor.run(move |canvas_state| {
+        // Fitted to the constraints, so tiny images get the smallest size a
+        // tile can have
+        apply_batch(&app, canvas_state, vec![add_operation(tile_id, image, position, size, title)])?;
+
+        Ok(Some(tile_id.to_string()))
+    }).await
+}
+
+/// Result of [`import_svg_file`].
+#[derive(Debug, Serialize)]
+pub struct ImportedSvg {
+    pub tile_id: String,
+    /// SVG features the drawing couldn't keep, e.g. `<text>`.
+    pub dropped: Vec<String>,
+}
+
+/// Adds a Skia tile drawing the SVG file at `path`, or at a file picked in
+/// a dialog when no path is given. Returns `None` if the dialog was
+/// cancelled. The content area is the size of the SVG's viewBox.
+#[tauri::command]
+pub async fn import_svg_file(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: Option<String>,
+    position_x: f32,
+    position_y: f32,
+    title: Option<String>,
+    tile_id: Option<String>,
+) -> Result<Option<ImportedSvg>, CanvasError> {
+    let tile_id = new_tile_id(tile_id)?;
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let picked = rfd::AsyncFileDialog::new()
+                .add_filter("SVG", &["svg"])
+                .pick_file()
+                .await;
+            match picked {
+                Some(file) => file.path().display().to_string(),
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let source = std::fs::read_to_string(&path)
+        .map_err(|e| CanvasError::invalid_argument("path", format!("can't be read: {}", e)))?;
+    let svg = canvas_core::import_svg(&source)?;
+    let title = title.unwrap_or_else(|| {
+        Path::new(&path)
+            .file_name()
+            .map_or_else(|| path.clone(), |name| name.to_string_lossy().into_owned())
+    });
+    let position = Vec2::new(position_x, position_y);
+    // The drawing goes below the title bar
+    let size = SizeConstraints::default().fit(svg.size + Vec2::new(0.0, TITLE_BAR_HEIGHT));
+    let (commands, dropped) = (svg.commands, svg.dropped);
+
+    state.actor.run(move |canvas_state| {
+        apply_batch(&app, canvas_state, vec![
+            add_operation(tile_id, NewTile::Skia, position, size, title),
+            CanvasOperation::SetDrawing { tile_id, commands },
+        ])?;
+
+        Ok(Some(ImportedSvg { tile_id: tile_id.to_string(), dropped }))
+    }).await
+}
+
+/// Adds a terminal tile running `shell`, or the user's login shell, in
+/// `cwd`. The shell gets as many rows and columns as fit into the tile.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn add_terminal_tile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    shell: Option<String>,
+    cwd: Option<String>,
+    position_x: f32,
+    position_y: f32,
+    width: f32,
+    height: f32,
+    title: String,
+    tile_id: Option<String>,
+) -> Result<String, CanvasError> {
+    let tile_id = new_tile_id(tile_id)?;
+    let position = Vec2::new(position_x, position_y);
+    let size = Vec2::new(width, height);
+
+    state.actor.run(move |canvas_state| {
+        // Reconciling the terminals starts the shell, and takes the tile
+        // back if that fails
+        canvas_state.terminals.set_shell(tile_id, shell);
+        let add = add_operation(tile_id, NewTile::Terminal { cwd }, position, size, title);
+        let applied = apply_batch(&app, canvas_state, vec![add]);
+        canvas_state.terminals.set_shell(tile_id, None);
+        applied?;
+
+        Ok(tile_id.to_string())
+    }).await
+}
+
+/// PNG encoded copy of an image tile's file that is at least
+/// `max_dimension` pixels along its longer side, from its cached mip
+/// levels, sent as raw bytes rather than JSON. Fails with
+/// `image_unavailable` when the file can't be read.
+#[tauri::command]
+pub async fn get_image_thumbnail(
+    state: State<'_, AppState>,
+    tile_id: String,
+    max_dimension: u32,
+) -> Result<tauri::ipc::Response, CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { canvas, images, .. }| {
+        let tile = canvas.get_tile(tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+        let image = ImageTile::from_tile(tile)
+            .ok_or_else(|| CanvasError::invalid_argument("tile_id", "is not an image tile"))?;
+        let thumbnail = images.thumbnail(Path::new(&image.path), max_dimension)?;
+        Ok(tauri::ipc::Response::new(thumbnail.as_ref().clone()))
+    }).await
+}
+
+/// PNG encoded drawing of a Skia tile's content area with a transparent
+/// background, at the power of two scale of at least `scale` pixels per
+/// world unit, sent as raw bytes rather than JSON. Drawings are cached by
+/// mip level and only the changed parts are redrawn after an edit.
+#[tauri::command]
+pub async fn render_skia_tile(
+    state: State<'_, AppState>,
+    tile_id: String,
+    scale: f32,
+) -> Result<tauri::ipc::Response, CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { canvas, renders, .. }| {
+        let tile = canvas.get_tile(tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+        let png = renders.png(tile_id, tile, scale)?;
+
+        Ok(tauri::ipc::Response::new(png.as_ref().clone()))
+    }).await
+}
+
+/// Size, memory use and hit rate of the cache of rendered Skia drawings.
+#[tauri::command]
+pub async fn get_render_cache_stats(state: State<'_, AppState>) -> Result<RenderCacheStats, CanvasError> {
+    state.actor.run(|CanvasState { renders, .. }| {
+        Ok(renders.stats())
+    }).await
+}
+
+/// Renders the widget of an egui tile at `scale` pixels per world unit.
+///
+/// The frame is sent as raw bytes rather than JSON: one byte that is 1 if
+/// the widget wants another frame right away, such as while it animates,
+/// and 0 otherwise, followed by the PNG encoded content area.
+#[tauri::command]
+pub async fn render_egui_tile(
+    state: State<'_, AppState>,
+    tile_id: String,
+    scale: f32,
+) -> Result<tauri::ipc::Response, CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { canvas, egui, .. }| {
+        let (session, size) = egui_session(canvas, egui, tile_id)?;
+        let frame = session.render(size, scale)?;
+        let png = frame.pixmap.encode_png()
+            .map_err(|e| CanvasError::Internal { message: format!("could not encode the frame: {}", e) })?;
+        let mut bytes = Vec::with_capacity(png.len() + 1);
+        bytes.push(u8::from(frame.repaint));
+        bytes.extend_from_slice(&png);
+        Ok(tauri::ipc::Response::new(bytes))
+    }).await
+}
+
+/// Forwards pointer and keyboard input to an egui tile's widget, which
+/// shows its effect with the next `render_egui_tile`. Once an interaction
+/// with the widget is over, what it changed becomes the tile's config as one
+/// undoable step. Changes the tile rejects stay with the widget and are
+/// tried again after the next input.
+#[tauri::command]
+pub async fn egui_input(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    events: Vec<WidgetInput>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |canvas_state| {
+        let CanvasState { canvas, egui, .. } = &mut *canvas_state;
+        let (session, size) = egui_session(canvas, egui, tile_id)?;
+        events.iter().try_for_each(|event| session.input(event))?;
+        session.update(size);
+
+        if let Some(config) = session.changes() {
+            apply_batch(&app, canvas_state, vec![CanvasOperation::SetWidgetConfig { tile_id, config }])?;
+        }
+        Ok(())
+    }).await
+}
+
+/// The egui session of `tile_id`, started if there is none and following
+/// the tile's widget, with the size of the tile's content area.
+fn egui_session<'a>(
+    canvas: &InfiniteCanvas,
+    sessions: &'a mut HashMap<TileId, EguiSession>,
+    tile_id: TileId,
+) -> Result<(&'a mut EguiSession, Vec2), CanvasError> {
+    let tile = canvas.get_tile(tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+    let TileData::Egui { widget_type, config } = &tile.data else {
+        return Err(CanvasError::invalid_argument("tile_id", "is not an egui tile"));
+    };
+    let session = sessions.entry(tile_id).or_insert_with(|| EguiSession::new(widget_type, config));
+    session.sync(widget_type, config);
+    let size = tile.size - Vec2::new(0.0, tile.style.title_bar_height(tile.size));
+    Ok((session, size))
+}
+
+#[tauri::command]
+pub async fn remove_tile(
     app: AppHandle,
     state: State<'_, AppState>,
     tile_id_str: String,
-) -> Result<(), String> {
-    let tile_id = TileId(uuid::Uuid::parse_str(&tile_id_str).map_err(|e| e.to_string())?);
-    
-    // First, remove any associated webview
-    {
-        let mut webview_manager = state.webview_manager.lock().map_err(|e| e.to_string())?;
-        webview_manager.remove_webview(tile_id).map_err(|e| e.to_string())?;
-    }
-    
-    // Then remove the tile from the canvas
-    let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    canvas.remove_tile(tile_id);
-    
-    Ok(())
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id_str.parse::<TileId>()?;
+    // Reconciling closes the tile's webview or shell
+    apply_single(app, &state, CanvasOperation::Remove { tile_id }).await
 }
 
+/// Moves a tile unless it is locked against moving.
 #[tauri::command]
-pub fn move_tile(
+pub async fn move_tile(
     app: AppHandle,
     state: State<'_, AppState>,
     tile_id_str: String,
     position_x: f32,
     position_y: f32,
-) -> Result<(), String> {
-    let tile_id = TileId(uuid::Uuid::parse_str(&tile_id_str).map_err(|e| e.to_string())?);
-    let new_position = Vec2::new(position_x, position_y);
-    
-    let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    
-    if let Some(tile) = canvas.get_tile_mut(tile_id) {
-        let old_position = tile.position;
-        tile.position = new_position;
-        
-        // If it's a webview, update the actual webview position
-        if let crate::tiles::TileData::WebView { webview_id: Some(webview_id), .. } = &tile.data {
-            let webview_manager = state.webview_manager.lock().map_err(|e| e.to_string())?;
-            webview_manager.update_webview_position(
-                webview_id,
-                new_position.x as f64,
-                new_position.y as f64,
-            ).map_err(|e| e.to_string())?;
-        }
-    }
-    
-    Ok(())
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id_str.parse::<TileId>()?;
+    let position = [position_x, position_y];
+    apply_single(app, &state, CanvasOperation::Move { tile_id, position }).await
 }
 
+/// Resizes a tile within its lock and size constraints. The closest
+/// admissible size is used, unless `fit` is false, which rejects sizes that
+/// break the constraints instead.
 #[tauri::command]
-pub fn resize_tile(
+pub async fn resize_tile(
     app: AppHandle,
     state: State<'_, AppState>,
     tile_id_str: String,
     width: f32,
     height: f32,
-) -> Result<(), String> {
-    let tile_id = TileId(uuid::Uuid::parse_str(&tile_id_str).map_err(|e| e.to_string())?);
-    let new_size = Vec2::new(width, height);
-    
-    let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    
-    if let Some(tile) = canvas.get_tile_mut(tile_id) {
-        tile.size = new_size;
-        
-        // If it's a webview, update the actual webview size
-        if let crate::tiles::TileData::WebView { webview_id: Some(webview_id), .. } = &tile.data {
-            let webview_manager = state.webview_manager.lock().map_err(|e| e.to_string())?;
-            webview_manager.update_webview_size(
-                webview_id,
-                width as f64,
-                height as f64,
-            ).map_err(|e| e.to_string())?;
-        }
-    }
-    
-    Ok(())
+    fit: Option<bool>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id_str.parse::<TileId>()?;
+    let size = [width, height];
+    let fit = fit.unwrap_or(true);
+    apply_single(app, &state, CanvasOperation::Resize { tile_id, size, fit }).await
 }
 
+/// Locks a tile against moving and resizing.
 #[tauri::command]
-pub fn set_tile_visibility(
+pub async fn lock_tile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::SetLock { tile_id, movable: false, resizable: false }).await
+}
+
+#[tauri::command]
+pub async fn unlock_tile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::SetLock { tile_id, movable: true, resizable: true }).await
+}
+
+/// Sets the size limits of a tile. `lock_aspect_ratio` keeps the ratio the
+/// tile has now.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn set_tile_constraints(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    min_width: f32,
+    min_height: f32,
+    max_width: Option<f32>,
+    max_height: Option<f32>,
+    lock_aspect_ratio: bool,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    state.actor.run(move |canvas_state| {
+        let tile = canvas_state.canvas.get_tile(tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+        let constraints = SizeConstraints {
+            min_size: Vec2::new(min_width, min_height),
+            max_size: match (max_width, max_height) {
+                (None, None) => None,
+                (width, height) => Some(Vec2::new(
+                    width.unwrap_or(f32::INFINITY),
+                    height.unwrap_or(f32::INFINITY),
+                )),
+            },
+            aspect_ratio: lock_aspect_ratio.then(|| tile.size.x / tile.size.y),
+        };
+        apply_batch(&app, canvas_state, vec![CanvasOperation::SetConstraints { tile_id, constraints }])
+            .map(|_| ())
+    }).await
+}
+
+#[tauri::command]
+pub async fn set_tile_visibility(
     app: AppHandle,
     state: State<'_, AppState>,
     tile_id_str: String,
     visible: bool,
-) -> Result<(), String> {
-    let tile_id = TileId(uuid::Uuid::parse_str(&tile_id_str).map_err(|e| e.to_string())?);
-    
-    let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    
-    if let Some(tile) = canvas.get_tile_mut(tile_id) {
-        tile.visible = visible;
-        
-        // If it's a webview, update the actual webview visibility
-        if let crate::tiles::TileData::WebView { webview_id: Some(webview_id), .. } = &tile.data {
-            let webview_manager = state.webview_manager.lock().map_err(|e| e.to_string())?;
-            webview_manager.show_webview(webview_id, visible).map_err(|e| e.to_string())?;
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id_str.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::SetVisibility { tile_id, visible }).await
+}
+
+/// Lets the webview of a WebView tile load the page a collaborator opened,
+/// see `webview-blocked`.
+#[tauri::command]
+pub async fn allow_webview(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { canvas, webview_manager, .. }| {
+        match canvas.get_tile(tile_id) {
+            Some(Tile { data: TileData::WebView { url, .. }, .. }) => webview_manager.allow_url(url),
+            Some(_) => return Err(CanvasError::invalid_argument("tile_id", "is not a WebView tile")),
+            None => return Err(CanvasError::TileNotFound { tile_id }),
         }
+        webview_manager.reconcile(&app, canvas)
+    }).await
+}
+
+/// Applies a list of operations atomically: either all of them take effect
+/// or none do. The batch is one undo step and emits one change event.
+/// Widget configs are checked and completed like in `add_egui_tile`.
+#[tauri::command]
+pub async fn apply_operations(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    operations: Vec<CanvasOperation>,
+) -> Result<Vec<String>, CanvasError> {
+    state.actor.run(move |canvas_state| {
+        let batch = apply_batch(&app, canvas_state, operations)?;
+        Ok(batch.added.iter().map(|id| id.to_string()).collect())
+    }).await
+}
+
+/// Creates the tile `tile_id` or, if it exists, replaces its content,
+/// position, size and title. Returns `true` if the tile was created.
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn upsert_tile(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    tile: NewTile,
+    position_x: f32,
+    position_y: f32,
+    width: f32,
+    height: f32,
+    title: String,
+) -> Result<bool, CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    let operation = CanvasOperation::Upsert {
+        tile_id,
+        tile,
+        position: [position_x, position_y],
+        size: [width, height],
+        title,
+    };
+
+    state.actor.run(move |canvas_state| {
+        let batch = apply_batch(&app, canvas_state, vec![operation])?;
+        Ok(batch.added.contains(&tile_id))
+    }).await
+}
+
+#[tauri::command]
+pub async fn add_tile_tags(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    tags: Vec<String>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::AddTags { tile_id, tags }).await
+}
+
+#[tauri::command]
+pub async fn remove_tile_tags(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    tags: Vec<String>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::RemoveTags { tile_id, tags }).await
+}
+
+#[tauri::command]
+pub async fn set_tile_property(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    key: String,
+    value: PropertyValue,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::SetProperty { tile_id, key, value }).await
+}
+
+#[tauri::command]
+pub async fn remove_tile_property(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    key: String,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::RemoveProperty { tile_id, key }).await
+}
+
+#[tauri::command]
+pub async fn set_tile_style(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    style: TileStyle,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::SetStyle { tile_id, style }).await
+}
+
+#[tauri::command]
+pub async fn apply_style_preset(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    preset: String,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::ApplyStylePreset { tile_id, preset }).await
+}
+
+#[tauri::command]
+pub async fn save_style_preset(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+    style: TileStyle,
+) -> Result<(), CanvasError> {
+    apply_single(app, &state, CanvasOperation::SaveStylePreset { name, style }).await
+}
+
+#[tauri::command]
+pub async fn delete_style_preset(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    name: String,
+) -> Result<(), CanvasError> {
+    apply_single(app, &state, CanvasOperation::DeleteStylePreset { name }).await
+}
+
+#[tauri::command]
+pub async fn get_style_presets(
+    state: State<'_, AppState>,
+) -> Result<BTreeMap<String, TileStyle>, CanvasError> {
+    state.actor.run(|CanvasState { canvas, .. }| Ok(canvas.style_presets.clone())).await
+}
+
+/// Replaces the Markdown source of a note tile.
+#[tauri::command]
+pub async fn set_note_text(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    markdown: String,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::SetNoteText { tile_id, markdown }).await
+}
+
+/// Replaces the `[start, end)` character range of a note's source.
+#[tauri::command]
+pub async fn replace_note_text(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    start: usize,
+    end: usize,
+    text: String,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::ReplaceNoteText { tile_id, start, end, text }).await
+}
+
+/// Sets checkbox `index` of a note, or flips it when `checked` is omitted.
+#[tauri::command]
+pub async fn toggle_note_checkbox(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    index: usize,
+    checked: Option<bool>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::ToggleNoteCheckbox { tile_id, index, checked }).await
+}
+
+/// Replaces all drawing commands of a Skia tile.
+#[tauri::command]
+pub async fn set_drawing_commands(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    commands: Vec<DrawingCommand>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::SetDrawing { tile_id, commands }).await
+}
+
+/// Adds drawing commands to the end of a Skia tile's drawing.
+#[tauri::command]
+pub async fn append_drawing_commands(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    commands: Vec<DrawingCommand>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::AppendDrawing { tile_id, commands }).await
+}
+
+/// Inserts drawing commands before command `index`, or at the end when
+/// `index` is the number of commands.
+#[tauri::command]
+pub async fn insert_drawing_commands(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    index: usize,
+    commands: Vec<DrawingCommand>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::SpliceDrawing { tile_id, start: index, end: index, commands }).await
+}
+
+/// Replaces the `[start, end)` range of a Skia tile's drawing commands.
+#[tauri::command]
+pub async fn replace_drawing_commands(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    start: usize,
+    end: usize,
+    commands: Vec<DrawingCommand>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::SpliceDrawing { tile_id, start, end, commands }).await
+}
+
+/// Removes the `[start, end)` range of a Skia tile's drawing commands.
+#[tauri::command]
+pub async fn remove_drawing_commands(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    start: usize,
+    end: usize,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::SpliceDrawing { tile_id, start, end, commands: Vec::new() }).await
+}
+
+/// Commands of a Skia tile's drawing as JSON, for debugging; the drawing
+/// travels as [`get_drawing_data`] otherwise.
+#[tauri::command]
+pub async fn get_drawing_commands(
+    state: State<'_, AppState>,
+    tile_id: String,
+) -> Result<Vec<DrawingCommand>, CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { canvas, .. }| {
+        Ok(skia_commands(canvas, tile_id)?.to_vec())
+    }).await
+}
+
+/// A Skia tile's drawing in the compact binary encoding of
+/// [`canvas_core::drawing_codec`], sent as raw bytes rather than JSON.
+#[tauri::command]
+pub async fn get_drawing_data(
+    state: State<'_, AppState>,
+    tile_id: String,
+) -> Result<tauri::ipc::Response, CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { canvas, .. }| {
+        let bytes = encode_commands(skia_commands(canvas, tile_id)?)?;
+        Ok(tauri::ipc::Response::new(bytes))
+    }).await
+}
+
+/// Replaces a Skia tile's drawing with one encoded like
+/// [`get_drawing_data`]. The body is the raw encoding and the tile id comes
+/// in the `Tile-Id` header.
+#[tauri::command]
+pub async fn set_drawing_data(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    request: tauri::ipc::Request<'_>,
+) -> Result<(), CanvasError> {
+    let tile_id = request.headers().get(DRAWING_TILE_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .ok_or_else(|| CanvasError::invalid_argument("tile_id", "missing the Tile-Id header"))?
+        .parse::<TileId>()?;
+    let tauri::ipc::InvokeBody::Raw(bytes) = request.body() else {
+        return Err(CanvasError::invalid_argument("body", "must be raw bytes"));
+    };
+    let commands = decode_commands(bytes)?;
+    apply_single(app, &state, CanvasOperation::SetDrawing { tile_id, commands }).await
+}
+
+/// Shapes of a Skia tile's drawing, with their command ranges and bounds.
+#[tauri::command]
+pub async fn get_drawing_shapes(
+    state: State<'_, AppState>,
+    tile_id: String,
+) -> Result<Vec<DrawingShape>, CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { canvas, .. }| {
+        Ok(shapes::shapes(skia_commands(canvas, tile_id)?))
+    }).await
+}
+
+/// Index of the topmost shape of a Skia tile's drawing at `point`, in
+/// content area coordinates, or within `tolerance` of it.
+#[tauri::command]
+pub async fn hit_test_drawing(
+    state: State<'_, AppState>,
+    tile_id: String,
+    point: [f32; 2],
+    tolerance: Option<f32>,
+) -> Result<Option<usize>, CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { canvas, .. }| {
+        let commands = skia_commands(canvas, tile_id)?;
+        Ok(shapes::hit_test(commands, Vec2::from(point), tolerance.unwrap_or(0.0)))
+    }).await
+}
+
+#[tauri::command]
+pub async fn delete_drawing_shapes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    shapes: Vec<usize>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::DeleteShapes { tile_id, shapes }).await
+}
+
+#[tauri::command]
+pub async fn move_drawing_shapes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    shapes: Vec<usize>,
+    offset: [f32; 2],
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::MoveShapes { tile_id, shapes, offset }).await
+}
+
+#[tauri::command]
+pub async fn recolor_drawing_shapes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    shapes: Vec<usize>,
+    fill: Option<[f32; 4]>,
+    stroke: Option<[f32; 4]>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::RecolorShapes { tile_id, shapes, fill, stroke }).await
+}
+
+/// Erases along `path` in a Skia tile's content area, cutting strokes and
+/// removing other shapes the eraser touches.
+#[tauri::command]
+pub async fn erase_drawing(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    path: Vec<[f32; 2]>,
+    radius: f32,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::EraseDrawing { tile_id, path, radius }).await
+}
+
+/// Combines shapes of a Skia tile's drawing into the lowest of them by
+/// union, intersection, difference or XOR.
+#[tauri::command]
+pub async fn combine_drawing_shapes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    shapes: Vec<usize>,
+    operation: PathOp,
+    tolerance: Option<f32>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::CombineShapes { tile_id, shapes, operation, tolerance }).await
+}
+
+#[tauri::command]
+pub async fn offset_drawing_shapes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    shapes: Vec<usize>,
+    distance: f32,
+    tolerance: Option<f32>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::OffsetShapes { tile_id, shapes, distance, tolerance }).await
+}
+
+#[tauri::command]
+pub async fn outline_drawing_strokes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    shapes: Vec<usize>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::OutlineStrokes { tile_id, shapes }).await
+}
+
+#[tauri::command]
+pub async fn flatten_drawing_shapes(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    shapes: Vec<usize>,
+    tolerance: Option<f32>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+    apply_single(app, &state, CanvasOperation::FlattenShapes { tile_id, shapes, tolerance }).await
+}
+
+fn skia_commands(canvas: &InfiniteCanvas, tile_id: TileId) -> Result<&[DrawingCommand], CanvasError> {
+    match canvas.get_tile(tile_id) {
+        Some(Tile { data: TileData::Skia { drawing_commands }, .. }) => Ok(drawing_commands),
+        Some(_) => Err(CanvasError::invalid_argument("tile_id", "is not a Skia tile")),
+        None => Err(CanvasError::TileNotFound { tile_id }),
+    }
+}
+
+/// Starts an ink stroke of `pointer_id` on a Skia tile, replacing one the
+/// pointer left unfinished.
+#[tauri::command]
+pub async fn begin_ink_stroke(
+    state: State<'_, AppState>,
+    tile_id: String,
+    pointer_id: u32,
+    brush: InkBrush,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { canvas, ink, .. }| {
+        let tile = canvas.get_tile(tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+        if SkiaTile::from_tile(tile).is_none() {
+            return Err(CanvasError::invalid_argument("tile_id", "is not a Skia tile"));
+        }
+        ink.insert((tile_id, pointer_id), InkStroke::new(brush)?);
+        Ok(())
+    }).await
+}
+
+/// Adds pointer samples, in the coordinates of the tile's drawing, to a
+/// stroke started with `begin_ink_stroke`.
+#[tauri::command]
+pub async fn add_ink_samples(
+    state: State<'_, AppState>,
+    tile_id: String,
+    pointer_id: u32,
+    samples: Vec<InkSample>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { ink, .. }| {
+        let stroke = ink.get_mut(&(tile_id, pointer_id))
+            .ok_or_else(|| CanvasError::invalid_argument("pointer_id", "has no ink stroke in progress"))?;
+        stroke.extend(samples);
+        Ok(())
+    }).await
+}
+
+/// Finishes a stroke and appends its outline to the tile's drawing as one
+/// undo step. A stroke without samples leaves the drawing alone.
+#[tauri::command]
+pub async fn end_ink_stroke(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    pointer_id: u32,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |canvas_state| {
+        let stroke = canvas_state.ink.remove(&(tile_id, pointer_id))
+            .ok_or_else(|| CanvasError::invalid_argument("pointer_id", "has no ink stroke in progress"))?;
+        if stroke.is_empty() {
+            return Ok(());
+        }
+        let operation = CanvasOperation::AppendDrawing { tile_id, commands: stroke.to_commands() };
+        apply_batch(&app, canvas_state, vec![operation]).map(|_| ())
+    }).await
+}
+
+/// Drops a stroke without drawing it, e.g. when the pointer was cancelled.
+#[tauri::command]
+pub async fn cancel_ink_stroke(
+    state: State<'_, AppState>,
+    tile_id: String,
+    pointer_id: u32,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { ink, .. }| {
+        ink.remove(&(tile_id, pointer_id));
+        Ok(())
+    }).await
+}
+
+/// Lays out text the way Skia tiles and exports draw it, wrapped to
+/// `width` if given.
+#[tauri::command]
+pub async fn measure_text(
+    text: String,
+    style: Option<TextStyle>,
+    width: Option<f32>,
+) -> Result<TextLayout, CanvasError> {
+    let style = style.unwrap_or_default();
+    style.validate()?;
+    if width.is_some_and(|width| !width.is_finite() || width <= 0.0) {
+        return Err(CanvasError::invalid_argument("width", "must be positive"));
+    }
+    Ok(canvas_core::text::layout(&text, &style, width))
+}
+
+/// Loads a TrueType or OpenType font file and returns its family name.
+#[tauri::command]
+pub async fn load_font_file(path: String) -> Result<String, CanvasError> {
+    canvas_core::text::load_font_file(Path::new(&path))
+}
+
+/// Families of the loaded fonts, the bundled default first.
+#[tauri::command]
+pub async fn list_fonts() -> Result<Vec<String>, CanvasError> {
+    Ok(canvas_core::text::families())
+}
+
+/// Bytes of a loaded font, so the frontend can draw with the same font.
+/// Sent raw rather than as JSON.
+#[tauri::command]
+pub async fn get_font_data(family: String) -> Result<tauri::ipc::Response, CanvasError> {
+    canvas_core::text::font_data(&family)
+        .map(|data| tauri::ipc::Response::new(data.to_vec()))
+        .ok_or_else(|| CanvasError::invalid_argument("family", "is not loaded"))
+}
+
+/// Renders the visible tiles as an SVG document.
+#[tauri::command]
+pub async fn export_board_svg(
+    state: State<'_, AppState>,
+    options: Option<ExportOptions>,
+) -> Result<String, CanvasError> {
+    state.actor.run(move |CanvasState { canvas, .. }| {
+        render::svg::to_svg(canvas, &options.unwrap_or_default())
+    }).await
+}
+
+/// Renders the visible tiles as PNG bytes, sent raw rather than as JSON.
+#[tauri::command]
+pub async fn export_board_png(
+    state: State<'_, AppState>,
+    options: Option<ExportOptions>,
+) -> Result<tauri::ipc::Response, CanvasError> {
+    state.actor.run(move |CanvasState { canvas, .. }| {
+        render::png::to_png(canvas, &options.unwrap_or_default()).map(tauri::ipc::Response::new)
+    }).await
+}
+
+/// Saves the board in the binary document format to `path`, or to a file
+/// picked in a dialog when no path is given. Returns where it was saved, or
+/// `None` if the dialog was cancelled.
+#[tauri::command]
+pub async fn save_board(
+    state: State<'_, AppState>,
+    path: Option<String>,
+) -> Result<Option<String>, CanvasError> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let picked = rfd::AsyncFileDialog::new()
+                .add_filter("Board", &[BOARD_EXTENSION])
+                .save_file()
+                .await;
+            match picked {
+                Some(file) => file.path().display().to_string(),
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let bytes = state.actor.run(|CanvasState { canvas, .. }| document::to_binary(canvas)).await?;
+    std::fs::write(&path, bytes)
+        .map_err(|e| CanvasError::invalid_argument("path", format!("can't be written: {}", e)))?;
+    Ok(Some(path))
+}
+
+/// Replaces the board with one saved by `save_board`, read from `path` or
+/// from a file picked in a dialog when no path is given. Returns the file
+/// it was read from, or `None` if the dialog was cancelled. Tiles get the
+/// same checks as edits, and the undo history starts out empty. Not
+/// possible while collaborating, as it would replace the other replicas'
+/// tiles too.
+#[tauri::command]
+pub async fn open_board(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    path: Option<String>,
+) -> Result<Option<String>, CanvasError> {
+    let path = match path {
+        Some(path) => path,
+        None => {
+            let picked = rfd::AsyncFileDialog::new()
+                .add_filter("Board", &[BOARD_EXTENSION])
+                .pick_file()
+                .await;
+            match picked {
+                Some(file) => file.path().display().to_string(),
+                None => return Ok(None),
+            }
+        }
+    };
+
+    let bytes = std::fs::read(&path)
+        .map_err(|e| CanvasError::invalid_argument("path", format!("can't be read: {}", e)))?;
+    let mut loaded = document::from_binary(&bytes)?;
+
+    state.actor.run(move |canvas_state| {
+        if canvas_state.sync.is_some() {
+            return Err(CanvasError::StateUnavailable {
+                message: "boards can't be opened while collaborating".to_string(),
+            });
+        }
+        for (tile_id, tile) in loaded.tiles.iter_mut() {
+            check_tile(&canvas_state.widgets, *tile_id, tile)?;
+        }
+        // Pages of a board opened on this machine need no confirmation
+        for tile in loaded.tiles.values() {
+            if let TileData::WebView { url, .. } = &tile.data {
+                canvas_state.webview_manager.allow_url(url);
+            }
+        }
+
+        // The window stays the same size
+        loaded.canvas_size = canvas_state.canvas.canvas_size;
+        let before = std::mem::replace(&mut canvas_state.canvas, loaded);
+        canvas_state.rebuild(&app);
+        let batch = AppliedBatch {
+            camera_changed: true,
+            presets_changed: true,
+            ..AppliedBatch::diff(&before.tiles, &canvas_state.canvas.tiles)
+        };
+        app.emit(CANVAS_CHANGED_EVENT, &batch).map_err(|e| CanvasError::event(CANVAS_CHANGED_EVENT, e))?;
+        Ok(Some(path))
+    }).await
+}
+
+/// Checks a tile that didn't come from an edit on this machine, such as one
+/// from a collaborator or a file, like an edit of it, and completes the
+/// config of an egui tile.
+pub fn check_tile(widgets: &WidgetRegistry, tile_id: TileId, tile: &mut Tile) -> Result<(), CanvasError> {
+    tile.validate(tile_id)?;
+    if let TileData::Egui { widget_type, config } = &mut tile.data {
+        *config = widgets.normalize(widget_type, config)?;
     }
-    
     Ok(())
 }
 
+async fn apply_single(
+    app: AppHandle,
+    state: &State<'_, AppState>,
+    operation: CanvasOperation,
+) -> Result<(), CanvasError> {
+    state.actor.run(move |canvas_state| {
+        apply_batch(&app, canvas_state, vec![operation]).map(|_| ())
+    }).await
+}
+
+fn apply_batch(
+    app: &AppHandle,
+    canvas_state: &mut CanvasState,
+    mut operations: Vec<CanvasOperation>,
+) -> Result<AppliedBatch, CanvasError> {
+    let CanvasState { canvas, webview_manager, terminals, widgets, .. } = &mut *canvas_state;
+    widgets.normalize_operations(canvas, &mut operations)?;
+    // Pages opened on this machine need no confirmation
+    for operation in &operations {
+        if let CanvasOperation::Add { tile: NewTile::WebView { url }, .. }
+        | CanvasOperation::Upsert { tile: NewTile::WebView { url }, .. } = operation {
+            webview_manager.allow_url(url);
+        }
+    }
+    let batch = canvas.apply_operations(operations)?;
+
+    if batch.changes_tiles() {
+        let reconciled = webview_manager.reconcile(app, canvas)
+            .and_then(|_| terminals.reconcile(app, canvas, &batch.added));
+        if let Err(e) = reconciled {
+            // Roll the tiles back so the canvas never keeps half of a batch
+            canvas.undo();
+            canvas.history.clear_redo();
+            webview_manager.reconcile(app, canvas)?;
+            terminals.reconcile(app, canvas, &[])?;
+            return Err(e);
+        }
+        canvas_state.tiles_changed(&batch);
+    }
+
+    if !batch.is_empty() {
+        app.emit(CANVAS_CHANGED_EVENT, &batch)
+            .map_err(|e| CanvasError::event(CANVAS_CHANGED_EVENT, e))?;
+    }
+
+    Ok(batch)
+}
+
+#[tauri::command]
+pub async fn undo(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bool, CanvasError> {
+    state.actor.run(move |canvas_state| step_history(&app, canvas_state, InfiniteCanvas::undo)).await
+}
+
 #[tauri::command]
-pub fn pan_camera(
+pub async fn redo(
+    app: AppHandle,
+    state: State<'_, AppState>,
+) -> Result<bool, CanvasError> {
+    state.actor.run(move |canvas_state| step_history(&app, canvas_state, InfiniteCanvas::redo)).await
+}
+
+/// Steps through the snapshot history, which is off while collaborating:
+/// restoring a snapshot would also revert the edits of the other replicas.
+fn step_history(
+    app: &AppHandle,
+    canvas_state: &mut CanvasState,
+    step: fn(&mut InfiniteCanvas) -> bool,
+) -> Result<bool, CanvasError> {
+    let CanvasState { canvas, webview_manager, terminals, sync, .. } = &mut *canvas_state;
+    if sync.is_some() {
+        return Err(CanvasError::UndoUnavailable {
+            message: "undo and redo are off while collaborating".to_string(),
+        });
+    }
+
+    let before = canvas.tiles.clone();
+    if !step(canvas) {
+        return Ok(false);
+    }
+
+    webview_manager.reconcile(app, canvas)?;
+
+    let batch = AppliedBatch::diff(&before, &canvas.tiles);
+    // Restored terminal tiles get a fresh shell
+    terminals.reconcile(app, canvas, &batch.added)?;
+    canvas_state.tiles_changed(&batch);
+
+    app.emit(CANVAS_CHANGED_EVENT, &batch).map_err(|e| CanvasError::event(CANVAS_CHANGED_EVENT, e))?;
+
+    Ok(true)
+}
+
+#[tauri::command]
+pub async fn pan_camera(
     state: State<'_, AppState>,
     delta_x: f32,
     delta_y: f32,
-) -> Result<(), String> {
-    let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    canvas.camera.pan(Vec2::new(delta_x, delta_y));
-    Ok(())
+) -> Result<(), CanvasError> {
+    state.actor.run(move |CanvasState { canvas, .. }| {
+        canvas.camera.pan(Vec2::new(delta_x, delta_y));
+        Ok(())
+    }).await
 }
 
 #[tauri::command]
-pub fn zoom_camera(
+pub async fn zoom_camera(
     state: State<'_, AppState>,
     factor: f32,
     target_x: f32,
     target_y: f32,
-) -> Result<(), String> {
-    let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    canvas.camera.zoom_at(factor, Vec2::new(target_x, target_y));
-    Ok(())
+) -> Result<(), CanvasError> {
+    state.actor.run(move |CanvasState { canvas, .. }| {
+        canvas.camera.zoom_at(factor, Vec2::new(target_x, target_y));
+        Ok(())
+    }).await
 }
 
 #[tauri::command]
-pub fn rotate_camera(
+pub async fn rotate_camera(
     state: State<'_, AppState>,
     angle: f32,
-) -> Result<(), String> {
-    let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    canvas.camera.rotate(angle);
-    Ok(())
+) -> Result<(), CanvasError> {
+    state.actor.run(move |CanvasState { canvas, .. }| {
+        canvas.camera.rotate(angle);
+        Ok(())
+    }).await
 }
 
 #[tauri::command]
-pub fn reset_camera(
+pub async fn reset_camera(
     state: State<'_, AppState>,
-) -> Result<(), String> {
-    let mut canvas = state.canvas.lock().map_err(|e| e.to_string())?;
-    canvas.camera = crate::canvas::Camera::default();
-    Ok(())
-}
\ No newline at end of file
+) -> Result<(), CanvasError> {
+    state.actor.run(|CanvasState { canvas, .. }| {
+        canvas.camera = Camera::default();
+        Ok(())
+    }).await
+}
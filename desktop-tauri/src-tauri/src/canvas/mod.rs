@@ -1,5 +0,0 @@
-mod infinite_canvas;
-mod camera;
-
-pub use infinite_canvas::InfiniteCanvas;
-pub use camera::Camera;
\ No newline at end of file
@@ -0,0 +1,187 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use futures::channel::mpsc;
+use futures::StreamExt;
+use tauri::{AppHandle, Emitter, State};
+
+use canvas_core::{AppliedBatch, CanvasError, InfiniteCanvas, ReplicaId, SyncDocument, SyncOp, WidgetRegistry};
+use canvas_sync::SyncConnection;
+
+use crate::actor::{CanvasActor, CanvasState};
+use crate::app::{check_tile, AppState, CANVAS_CHANGED_EVENT};
+use crate::terminal::TerminalManager;
+use crate::webview::WebViewManager;
+
+/// Event emitted when the connection to the relay ends without
+/// `stop_collaboration` being called.
+pub const COLLABORATION_ENDED_EVENT: &str = "collaboration-ended";
+
+/// Event emitted with `{ tile_id, url }` when a collaborator opens a page
+/// in a WebView tile. The page only loads after `allow_webview`.
+pub const WEBVIEW_BLOCKED_EVENT: &str = "webview-blocked";
+
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// An active collaboration session, owned by the canvas actor.
+///
+/// Commands report local edits through [`CanvasState::tiles_changed`], and
+/// the actor publishes them after the job that made them. Publishing diffs
+/// the whole board, so jobs that changed nothing skip it.
+///
+/// Undo restores whole snapshots of the tiles, which would revert remote
+/// edits made since the snapshot as well. Undo and redo are therefore off
+/// while a session is active, and the history is cleared when a session
+/// starts or ends, so no snapshot mixing local and remote edits is ever
+/// restored.
+pub struct SyncSession {
+    id: u64,
+    document: SyncDocument,
+    sender: mpsc::UnboundedSender<Vec<SyncOp>>,
+    /// Whether tiles changed locally since the last publish.
+    changed: bool,
+}
+
+impl SyncSession {
+    pub fn mark_changed(&mut self) {
+        self.changed = true;
+    }
+
+    /// Sends local changes made since the last call to the relay, if there
+    /// are any.
+    pub fn publish(&mut self, canvas: &InfiniteCanvas) {
+        if !std::mem::take(&mut self.changed) {
+            return;
+        }
+        let ops = self.document.commit(&canvas.tiles);
+        if !ops.is_empty() && self.sender.unbounded_send(ops).is_err() {
+            log::warn!("relay connection closed, dropping local changes");
+        }
+    }
+}
+
+#[tauri::command]
+pub async fn start_collaboration(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    relay_url: String,
+    room: String,
+) -> Result<String, CanvasError> {
+    let SyncConnection { welcome, sender, incoming } = canvas_sync::connect(&relay_url, &room)
+        .await
+        .map_err(|e| CanvasError::SyncFailed { relay: relay_url.clone(), message: e.to_string() })?;
+    let session_id = NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed);
+
+    let remote_app = app.clone();
+    let replica = state.actor.run(move |canvas_state| {
+        let CanvasState { canvas, webview_manager, terminals, sync, widgets, .. } = &mut *canvas_state;
+        let mut document = SyncDocument::new(ReplicaId::new());
+        let replica = document.replica();
+        let batch = apply_remote(&app, canvas, webview_manager, terminals, widgets, &mut document, &welcome)?;
+        canvas.history.clear();
+        // Replaces any previous session, which closes its connection. Tiles
+        // this replica already has are published by the actor once the
+        // session is installed.
+        *sync = Some(SyncSession { id: session_id, document, sender, changed: true });
+        canvas_state.update_derived(&batch);
+        log::info!("joined room {} as replica {}", room, replica.0);
+        Ok(replica.0.to_string())
+    }).await?;
+
+    async_std::task::spawn(receive_remote(remote_app, state.actor.clone(), session_id, incoming));
+    Ok(replica)
+}
+
+#[tauri::command]
+pub async fn stop_collaboration(state: State<'_, AppState>) -> Result<bool, CanvasError> {
+    state.actor.run(|CanvasState { canvas, sync, .. }| {
+        let stopped = sync.take().is_some();
+        if stopped {
+            canvas.history.clear();
+        }
+        Ok(stopped)
+    }).await
+}
+
+async fn receive_remote(
+    app: AppHandle,
+    actor: CanvasActor,
+    session_id: u64,
+    mut incoming: mpsc::UnboundedReceiver<Vec<SyncOp>>,
+) {
+    while let Some(ops) = incoming.next().await {
+        let app = app.clone();
+        let result = actor.run(move |canvas_state| {
+            let CanvasState { canvas, webview_manager, terminals, sync, widgets, .. } = &mut *canvas_state;
+            let batch = match sync {
+                Some(session) if session.id == session_id => {
+                    apply_remote(&app, canvas, webview_manager, terminals, widgets, &mut session.document, &ops)?
+                }
+                _ => return Ok(()),
+            };
+            canvas_state.update_derived(&batch);
+            Ok(())
+        }).await;
+        if let Err(e) = result {
+            log::error!("failed to apply remote changes: {}", e);
+        }
+    }
+
+    let ended = actor.run(move |CanvasState { canvas, sync, .. }| {
+        if sync.as_ref().is_some_and(|session| session.id == session_id) {
+            *sync = None;
+            canvas.history.clear();
+            Ok(true)
+        } else {
+            Ok(false)
+        }
+    }).await;
+    if let Ok(true) = ended {
+        log::warn!("connection to relay lost");
+        if let Err(e) = app.emit(COLLABORATION_ENDED_EVENT, ()) {
+            log::error!("failed to emit {}: {}", COLLABORATION_ENDED_EVENT, e);
+        }
+    }
+}
+
+/// Applies operations from the relay to the canvas. Tiles get the same
+/// checks as local edits; invalid ones are dropped with a warning.
+fn apply_remote(
+    app: &AppHandle,
+    canvas: &mut InfiniteCanvas,
+    webview_manager: &mut WebViewManager,
+    terminals: &mut TerminalManager,
+    widgets: &WidgetRegistry,
+    document: &mut SyncDocument,
+    ops: &[SyncOp],
+) -> Result<AppliedBatch, CanvasError> {
+    let before = canvas.tiles.clone();
+    let applied = document.apply_remote_checked(ops, &mut canvas.tiles, |tile_id, tile| {
+        match check_tile(widgets, tile_id, tile) {
+            Ok(()) => true,
+            Err(e) => {
+                log::warn!("dropping invalid remote change of tile {}: {}", tile_id, e);
+                false
+            }
+        }
+    });
+    if applied.is_empty() {
+        return Ok(AppliedBatch::default());
+    }
+
+    if canvas.selected_tile_id.is_some_and(|id| !canvas.tiles.contains_key(&id)) {
+        canvas.select_tile(None);
+    }
+    webview_manager.reconcile(app, canvas)?;
+    for (tile_id, url) in webview_manager.take_blocked() {
+        let payload = serde_json::json!({ "tile_id": tile_id, "url": url });
+        if let Err(e) = app.emit(WEBVIEW_BLOCKED_EVENT, payload) {
+            log::error!("failed to emit {}: {}", WEBVIEW_BLOCKED_EVENT, e);
+        }
+    }
+    // Shells only start on this machine's request, never for remote tiles
+    terminals.reconcile(app, canvas, &[])?;
+
+    let batch = AppliedBatch::diff(&before, &canvas.tiles);
+    app.emit(CANVAS_CHANGED_EVENT, &batch).map_err(|e| CanvasError::event(CANVAS_CHANGED_EVENT, e))?;
+    Ok(batch)
+}
@@ -1,21 +1,47 @@
+mod actor;
 mod app;
-mod canvas;
-mod tiles;
+mod collab;
+mod terminal;
 mod webview;
 
-use std::sync::{Arc, Mutex};
+use std::collections::HashMap;
+use std::sync::Arc;
+
 use glam::Vec2;
+use tauri::Manager;
 
+use actor::{CanvasActor, CanvasState};
 use app::AppState;
-use canvas::InfiniteCanvas;
+use canvas_core::{ImageCache, InfiniteCanvas, RenderCache, SearchIndex, WidgetRegistry};
+use terminal::TerminalManager;
 use webview::WebViewManager;
 
+pub use canvas_core::CanvasError;
+
 // Re-export the app commands
 pub use app::{
-    get_canvas_info, get_tiles, add_webview_tile, add_egui_tile, add_skia_tile,
-    remove_tile, move_tile, resize_tile, set_tile_visibility,
+    get_canvas_info, get_tiles, add_webview_tile, add_egui_tile, list_widget_types, add_skia_tile, add_note_tile,
+    add_image_tile, import_svg_file, get_image_thumbnail, render_skia_tile, get_render_cache_stats,
+    render_egui_tile, egui_input, add_terminal_tile,
+    remove_tile, move_tile, resize_tile, set_tile_visibility, allow_webview,
+    apply_operations, upsert_tile, undo, redo,
+    add_tile_tags, remove_tile_tags, set_tile_property, remove_tile_property,
+    search_canvas, lock_tile, unlock_tile, set_tile_constraints,
+    set_tile_style, apply_style_preset, save_style_preset, delete_style_preset, get_style_presets,
+    set_note_text, replace_note_text, toggle_note_checkbox,
+    set_drawing_commands, append_drawing_commands, insert_drawing_commands,
+    replace_drawing_commands, remove_drawing_commands,
+    get_drawing_commands, get_drawing_data, set_drawing_data,
+    get_drawing_shapes, hit_test_drawing, delete_drawing_shapes, move_drawing_shapes,
+    recolor_drawing_shapes, erase_drawing, combine_drawing_shapes, offset_drawing_shapes,
+    outline_drawing_strokes, flatten_drawing_shapes,
+    begin_ink_stroke, add_ink_samples, end_ink_stroke, cancel_ink_stroke,
+    measure_text, load_font_file, list_fonts, get_font_data,
+    export_board_svg, export_board_png, save_board, open_board,
     pan_camera, zoom_camera, rotate_camera, reset_camera,
 };
+pub use collab::{start_collaboration, stop_collaboration};
+pub use terminal::{start_terminal, terminal_input, scroll_terminal, get_terminal_screen};
 
 // Legacy command for backward compatibility
 #[tauri::command]
@@ -28,34 +54,113 @@ pub fn run() {
     // Initialize logging
     env_logger::init();
     
-    // Create the app state
-    let canvas_size = Vec2::new(800.0, 600.0);
-    let canvas = InfiniteCanvas::new(canvas_size);
-    let webview_manager = WebViewManager::new();
-    
-    let app_state = AppState {
-        canvas: Arc::new(Mutex::new(canvas)),
-        webview_manager: Arc::new(Mutex::new(webview_manager)),
-    };
-    
     tauri::Builder::default()
         .plugin(tauri_plugin_opener::init())
-        .manage(app_state)
+        .setup(|app| {
+            // Create the app state
+            let canvas_size = Vec2::new(800.0, 600.0);
+            let canvas = InfiniteCanvas::new(canvas_size);
+            let webview_manager = WebViewManager::new();
+
+            let widgets = Arc::new(WidgetRegistry::default());
+            // The actor needs the app to restore webviews and shells
+            let actor = CanvasActor::spawn(app.handle().clone(), CanvasState {
+                canvas,
+                webview_manager,
+                terminals: TerminalManager::new(),
+                search: SearchIndex::new(),
+                images: ImageCache::new(),
+                renders: RenderCache::new(),
+                ink: HashMap::new(),
+                egui: HashMap::new(),
+                sync: None,
+                widgets: widgets.clone(),
+            });
+            app.manage(AppState { actor, widgets });
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             greet,
             get_canvas_info,
             get_tiles,
             add_webview_tile,
             add_egui_tile,
+            list_widget_types,
             add_skia_tile,
+            add_note_tile,
+            add_image_tile,
+            import_svg_file,
+            get_image_thumbnail,
+            render_skia_tile,
+            get_render_cache_stats,
+            render_egui_tile,
+            egui_input,
+            add_terminal_tile,
+            start_terminal,
+            terminal_input,
+            scroll_terminal,
+            get_terminal_screen,
             remove_tile,
             move_tile,
             resize_tile,
             set_tile_visibility,
+            allow_webview,
+            apply_operations,
+            upsert_tile,
+            undo,
+            redo,
+            add_tile_tags,
+            remove_tile_tags,
+            set_tile_property,
+            remove_tile_property,
+            search_canvas,
+            lock_tile,
+            unlock_tile,
+            set_tile_constraints,
+            set_tile_style,
+            apply_style_preset,
+            save_style_preset,
+            delete_style_preset,
+            get_style_presets,
+            set_note_text,
+            replace_note_text,
+            toggle_note_checkbox,
+            set_drawing_commands,
+            append_drawing_commands,
+            insert_drawing_commands,
+            replace_drawing_commands,
+            remove_drawing_commands,
+            get_drawing_commands,
+            get_drawing_data,
+            set_drawing_data,
+            get_drawing_shapes,
+            hit_test_drawing,
+            delete_drawing_shapes,
+            move_drawing_shapes,
+            recolor_drawing_shapes,
+            erase_drawing,
+            combine_drawing_shapes,
+            offset_drawing_shapes,
+            outline_drawing_strokes,
+            flatten_drawing_shapes,
+            begin_ink_stroke,
+            add_ink_samples,
+            end_ink_stroke,
+            cancel_ink_stroke,
+            measure_text,
+            load_font_file,
+            list_fonts,
+            get_font_data,
+            export_board_svg,
+            export_board_png,
+            save_board,
+            open_board,
             pan_camera,
             zoom_camera,
             rotate_camera,
             reset_camera,
+            start_collaboration,
+            stop_collaboration,
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,176 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use serde::Serialize;
+use tauri::{AppHandle, Emitter, State};
+
+use canvas_core::{CanvasError, InfiniteCanvas, TerminalTile, Tile, TileData, TileId};
+use canvas_terminal::{ScreenUpdate, TerminalConfig, TerminalSession, TerminalSize};
+
+use crate::actor::CanvasState;
+use crate::app::AppState;
+
+/// Event emitted with a [`TerminalUpdateEvent`] whenever a terminal screen
+/// changes.
+pub const TERMINAL_UPDATE_EVENT: &str = "terminal-update";
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TerminalUpdateEvent {
+    pub tile_id: TileId,
+    pub update: ScreenUpdate,
+}
+
+/// Shell sessions by terminal tile.
+///
+/// Sessions are only started for tiles created or restored on this machine,
+/// or on request with `start_terminal`; terminal tiles arriving from other
+/// replicas stay empty until then.
+#[derive(Default)]
+pub struct TerminalManager {
+    sessions: HashMap<TileId, TerminalSession>,
+    /// Programs to run instead of the login shell, by tile.
+    shells: HashMap<TileId, String>,
+}
+
+impl TerminalManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts a shell for the terminal tile `tile_id`, replacing a session
+    /// that has exited. `shell` defaults to the user's login shell.
+    pub fn start(&mut self, app: &AppHandle, tile_id: TileId, tile: &Tile, shell: Option<String>) -> Result<(), CanvasError> {
+        let terminal = TerminalTile::from_tile(tile)
+            .ok_or_else(|| CanvasError::invalid_argument("tile_id", "is not a terminal tile"))?;
+        if self.sessions.get(&tile_id).is_some_and(|session| !session.has_exited()) {
+            return Ok(());
+        }
+        let shell = shell.or_else(|| self.shells.get(&tile_id).cloned());
+
+        let (rows, cols) = TerminalTile::grid_size(tile);
+        let config = TerminalConfig {
+            shell,
+            cwd: terminal.cwd.map(PathBuf::from),
+            size: TerminalSize::new(rows, cols),
+            ..Default::default()
+        };
+        let app = app.clone();
+        let session = TerminalSession::spawn(config, move |update| {
+            if let Err(e) = app.emit(TERMINAL_UPDATE_EVENT, TerminalUpdateEvent { tile_id, update }) {
+                log::error!("failed to emit {}: {}", TERMINAL_UPDATE_EVENT, e);
+            }
+        })
+        .map_err(|e| CanvasError::terminal(tile_id, e))?;
+        self.sessions.insert(tile_id, session);
+        Ok(())
+    }
+
+    /// Sets the program the shells of `tile_id` run when started without
+    /// one, such as by [`TerminalManager::reconcile`]. `None` goes back to
+    /// the login shell.
+    pub fn set_shell(&mut self, tile_id: TileId, shell: Option<String>) {
+        match shell {
+            Some(shell) => self.shells.insert(tile_id, shell),
+            None => self.shells.remove(&tile_id),
+        };
+    }
+
+    /// Hangs up on the shell of `tile_id`. Returns `false` if it had none.
+    pub fn stop(&mut self, tile_id: TileId) -> bool {
+        self.sessions.remove(&tile_id).is_some()
+    }
+
+    pub fn session_mut(&mut self, tile_id: TileId) -> Result<&mut TerminalSession, CanvasError> {
+        self.sessions.get_mut(&tile_id)
+            .ok_or_else(|| CanvasError::terminal(tile_id, "no shell is running"))
+    }
+
+    /// Brings the sessions in line with the terminal tiles of the canvas.
+    ///
+    /// Sessions whose tile is gone or no longer a terminal are stopped, the
+    /// rest are resized to the grid of their tile, and tiles in `start`
+    /// without a session get one. Used after batch edits, undo/redo and
+    /// remote changes.
+    pub fn reconcile(&mut self, app: &AppHandle, canvas: &InfiniteCanvas, start: &[TileId]) -> Result<(), CanvasError> {
+        self.sessions.retain(|tile_id, _| {
+            matches!(canvas.tiles.get(tile_id).map(|tile| &tile.data), Some(TileData::Terminal { .. }))
+        });
+
+        for (tile_id, session) in &mut self.sessions {
+            let (rows, cols) = TerminalTile::grid_size(&canvas.tiles[tile_id]);
+            session.resize(TerminalSize::new(rows, cols)).map_err(|e| CanvasError::terminal(*tile_id, e))?;
+        }
+
+        for tile_id in start {
+            if let Some(tile) = canvas.tiles.get(tile_id) {
+                if matches!(tile.data, TileData::Terminal { .. }) && !self.sessions.contains_key(tile_id) {
+                    self.start(app, *tile_id, tile, None)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Starts or restarts the shell of a terminal tile, e.g. one created by
+/// another replica or whose shell has exited.
+#[tauri::command]
+pub async fn start_terminal(
+    app: AppHandle,
+    state: State<'_, AppState>,
+    tile_id: String,
+    shell: Option<String>,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { canvas, terminals, .. }| {
+        let tile = canvas.get_tile(tile_id).ok_or(CanvasError::TileNotFound { tile_id })?;
+        terminals.start(&app, tile_id, tile, shell)
+    }).await
+}
+
+/// Sends keystrokes or pasted text to the shell of a terminal tile.
+#[tauri::command]
+pub async fn terminal_input(
+    state: State<'_, AppState>,
+    tile_id: String,
+    data: String,
+) -> Result<(), CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { terminals, .. }| {
+        terminals.session_mut(tile_id)?
+            .write(data.as_bytes())
+            .map_err(|e| CanvasError::terminal(tile_id, e))
+    }).await
+}
+
+/// Scrolls a terminal `offset` rows up into its history, 0 being the
+/// bottom. Returns the offset after clamping it to the history.
+#[tauri::command]
+pub async fn scroll_terminal(
+    state: State<'_, AppState>,
+    tile_id: String,
+    offset: usize,
+) -> Result<usize, CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { terminals, .. }| {
+        Ok(terminals.session_mut(tile_id)?.scroll_to(offset))
+    }).await
+}
+
+/// The whole screen of a terminal tile, for a frontend that has not seen
+/// it yet. Later `terminal-update` events are relative to it.
+#[tauri::command]
+pub async fn get_terminal_screen(
+    state: State<'_, AppState>,
+    tile_id: String,
+) -> Result<ScreenUpdate, CanvasError> {
+    let tile_id = tile_id.parse::<TileId>()?;
+
+    state.actor.run(move |CanvasState { terminals, .. }| {
+        Ok(terminals.session_mut(tile_id)?.snapshot())
+    }).await
+}
@@ -1,12 +1,20 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use tauri::{AppHandle, Manager, WebviewBuilder, WebviewUrl, Webview, WebviewWindowBuilder};
 use uuid::Uuid;
 
-use crate::tiles::{TileId, WebViewTile};
+use canvas_core::{CanvasError, InfiniteCanvas, TileData, TileId};
 
 pub struct WebViewManager {
     webviews: HashMap<String, Webview>,
     tile_to_webview: HashMap<TileId, String>,
+    webview_urls: HashMap<String, String>,
+    /// Pages webviews may load: those opened on this machine and those the
+    /// user allowed. Pages collaborators open wait for permission.
+    allowed_urls: HashSet<String>,
+    /// WebView tiles waiting for permission to load their page.
+    blocked: HashMap<TileId, String>,
+    /// Tiles blocked since the last [`WebViewManager::take_blocked`].
+    newly_blocked: Vec<(TileId, String)>,
 }
 
 impl WebViewManager {
@@ -14,9 +22,24 @@ impl WebViewManager {
         Self {
             webviews: HashMap::new(),
             tile_to_webview: HashMap::new(),
+            webview_urls: HashMap::new(),
+            allowed_urls: HashSet::new(),
+            blocked: HashMap::new(),
+            newly_blocked: Vec::new(),
         }
     }
 
+    /// Lets webviews load `url`.
+    pub fn allow_url(&mut self, url: &str) {
+        self.allowed_urls.insert(url.to_string());
+    }
+
+    /// WebView tiles [`WebViewManager::reconcile`] found waiting for
+    /// permission to load their page since the last call, with that page.
+    pub fn take_blocked(&mut self) -> Vec<(TileId, String)> {
+        std::mem::take(&mut self.newly_blocked)
+    }
+
     pub fn create_webview(&mut self, app: &AppHandle, tile_id: TileId, url: &str, x: f64, y: f64, width: f64, height: f64) -> Result<String, tauri::Error> {
         let webview_id = Uuid::new_v4().to_string();
         
@@ -29,11 +52,12 @@ impl WebViewManager {
             .build()?;
         
         // Navigate to the specified URL
-        webview_window.eval(&format!("window.location.href = '{}'", url))?;
+        webview_window.eval(&navigation_script(url))?;
         
         // Store the webview and its association with the tile
         self.webviews.insert(webview_id.clone(), webview_window.webview().clone());
         self.tile_to_webview.insert(tile_id, webview_id.clone());
+        self.webview_urls.insert(webview_id.clone(), url.to_string());
         
         Ok(webview_id)
     }
@@ -54,7 +78,15 @@ impl WebViewManager {
 
     pub fn navigate_webview(&self, webview_id: &str, url: &str) -> Result<(), tauri::Error> {
         if let Some(webview) = self.webviews.get(webview_id) {
-            webview.eval(&format!("window.location.href = '{}'", url))?;
+            webview.eval(&navigation_script(url))?;
+        }
+        Ok(())
+    }
+
+    fn navigate_if_changed(&mut self, webview_id: &str, url: &str) -> Result<(), tauri::Error> {
+        if self.webview_urls.get(webview_id).map(String::as_str) != Some(url) {
+            self.navigate_webview(webview_id, url)?;
+            self.webview_urls.insert(webview_id.to_string(), url.to_string());
         }
         Ok(())
     }
@@ -72,6 +104,7 @@ impl WebViewManager {
 
     pub fn remove_webview(&mut self, tile_id: TileId) -> Result<(), tauri::Error> {
         if let Some(webview_id) = self.tile_to_webview.remove(&tile_id) {
+            self.webview_urls.remove(&webview_id);
             if let Some(webview) = self.webviews.remove(&webview_id) {
                 webview.window().close()?;
             }
@@ -82,4 +115,74 @@ impl WebViewManager {
     pub fn get_webview_id_for_tile(&self, tile_id: TileId) -> Option<String> {
         self.tile_to_webview.get(&tile_id).cloned()
     }
-}
\ No newline at end of file
+
+    /// Brings the native webviews in line with the WebView tiles of the canvas.
+    ///
+    /// Webviews whose tile is gone or no longer a WebView are closed, WebView
+    /// tiles without a live webview get one, and every remaining webview is
+    /// navigated, moved, resized and shown or hidden to match its tile. Used
+    /// after batch edits and undo/redo, where any number of tiles may have
+    /// changed at once.
+    ///
+    /// Tiles showing a page that isn't allowed get no webview until it is.
+    pub fn reconcile(&mut self, app: &AppHandle, canvas: &mut InfiniteCanvas) -> Result<(), CanvasError> {
+        let stale: Vec<TileId> = self.tile_to_webview.keys()
+            .filter(|tile_id| !matches!(
+                canvas.tiles.get(tile_id).map(|tile| &tile.data),
+                Some(TileData::WebView { .. })
+            ))
+            .copied()
+            .collect();
+        for tile_id in stale {
+            self.remove_webview(tile_id).map_err(|e| CanvasError::webview(tile_id, e))?;
+        }
+        self.blocked.retain(|tile_id, _| canvas.tiles.contains_key(tile_id));
+
+        for (tile_id, tile) in canvas.tiles.iter_mut() {
+            let (url, webview_id) = match &mut tile.data {
+                TileData::WebView { url, webview_id } => (url.clone(), webview_id),
+                _ => continue,
+            };
+
+            if !self.allowed_urls.contains(&url) {
+                self.remove_webview(*tile_id).map_err(|e| CanvasError::webview(*tile_id, e))?;
+                *webview_id = None;
+                if self.blocked.get(tile_id) != Some(&url) {
+                    self.blocked.insert(*tile_id, url.clone());
+                    self.newly_blocked.push((*tile_id, url));
+                }
+                continue;
+            }
+            self.blocked.remove(tile_id);
+
+            let live_id = match self.get_webview_id_for_tile(*tile_id) {
+                Some(live_id) => live_id,
+                None => self.create_webview(
+                    app,
+                    *tile_id,
+                    &url,
+                    tile.position.x as f64,
+                    tile.position.y as f64,
+                    tile.size.x as f64,
+                    tile.size.y as f64,
+                ).map_err(|e| CanvasError::webview(*tile_id, e))?,
+            };
+            *webview_id = Some(live_id.clone());
+
+            self.navigate_if_changed(&live_id, &url)
+                .and_then(|_| self.update_webview_position(&live_id, tile.position.x as f64, tile.position.y as f64))
+                .and_then(|_| self.update_webview_size(&live_id, tile.size.x as f64, tile.size.y as f64))
+                .and_then(|_| self.show_webview(&live_id, tile.visible))
+                .map_err(|e| CanvasError::webview(*tile_id, e))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Script sending a webview to `url`, which is quoted so that it can't
+/// break out of the string.
+fn navigation_script(url: &str) -> String {
+    let quoted = serde_json::to_string(url).expect("strings always serialize");
+    format!("window.location.href = {}", quoted)
+}
@@ -1,4 +1,5 @@
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 
 // Canvas and rendering
 let canvas: HTMLCanvasElement;
@@ -15,9 +16,62 @@ let cameraRotation = 0.0;
 let isDragging = false;
 let lastMousePosition = { x: 0, y: 0 };
 let selectedTileId: string | null = null;
-let currentTool = 'pan'; // 'pan', 'select'
+let currentTool = 'pan'; // 'pan', 'select', 'ink', 'erase'
+let draggedTileStart: [number, number] | null = null;
+let editingNoteId: string | null = null;
 
 // Tiles
+type Rgba = [number, number, number, number];
+
+interface TileStyle {
+  border_color: Rgba;
+  border_width: number;
+  background: Rgba;
+  corner_radius: number;
+  opacity: number;
+  shadow: { offset: [number, number]; blur: number; color: Rgba } | null;
+  show_title_bar: boolean;
+  title_bar_color: Rgba;
+  title_color: Rgba;
+}
+
+// Laid out Markdown of a note tile, see `canvas_core::markdown`
+interface NoteSpan {
+  text: string;
+  bold?: boolean;
+  italic?: boolean;
+  strikethrough?: boolean;
+  code?: boolean;
+  link?: string;
+}
+
+type NoteMarker =
+  | { kind: 'bullet' }
+  | { kind: 'number'; value: number }
+  | { kind: 'checkbox'; index: number; checked: boolean };
+
+interface NoteLine {
+  kind: { kind: 'text' | 'heading' | 'code' | 'rule'; level?: number };
+  spans: NoteSpan[];
+  marker: NoteMarker | null;
+  indent: number;
+  quote_bars: number[];
+  top: number;
+  height: number;
+  font_size: number;
+}
+
+// Must match the layout constants of the Rust exporters
+const NOTE_PADDING = 12;
+const NOTE_LIST_INDENT = 22;
+const NOTE_CHECKBOX_SIZE = 11;
+const NOTE_QUOTE_BAR_WIDTH = 3;
+
+// Titles and notes use the font the Rust side lays text out with, loaded
+// from the backend at startup, so exports break lines in the same places
+const TEXT_FONT_FAMILY = 'DejaVu Sans';
+const TEXT_FONT = `"${TEXT_FONT_FAMILY}", sans-serif`;
+
 interface Tile {
   id: string;
   position: [number, number];
@@ -27,10 +81,138 @@ interface Tile {
   tile_type: string;
   title: string;
   visible: boolean;
+  movable: boolean;
+  resizable: boolean;
+  style: TileStyle;
+  tags: string[];
+  properties: Record<string, string | number | boolean>;
+  markdown: string | null;
+  note_lines: NoteLine[] | null;
+  image: { path: string; natural_size: [number, number] | null } | null;
+}
+
+// Decoded thumbnails of image tiles, fetched at the mip level the zoom needs
+interface ImageEntry {
+  path: string;
+  dimension: number;
+  bitmap: ImageBitmap | null;
+  loading: boolean;
+  missing: boolean;
+}
+
+const imageEntries = new Map<string, ImageEntry>();
+
+// Rasterized drawings of Skia tiles, rendered on the Rust side at a power of
+// two scale. Marked stale when the canvas changes and redrawn in place.
+interface SkiaEntry {
+  scale: number;
+  bitmap: ImageBitmap | null;
+  loading: boolean;
+  stale: boolean;
+  failed: boolean;
+}
+
+const skiaEntries = new Map<string, SkiaEntry>();
+
+// Widget kinds egui tiles can be created with, as listed by the backend
+interface WidgetType {
+  name: string;
+  label: string;
+  schema: object;
+  default_config: object;
+}
+
+let widgetTypes: WidgetType[] = [];
+
+// Keeps rendered Skia tiles below this many pixels along either side
+const MAX_SKIA_PIXELS = 4096;
+
+// Frames of egui tiles, rendered on the Rust side like Skia drawings and
+// redrawn after input reached the widget
+const eguiEntries = new Map<string, SkiaEntry>();
+
+// The egui tile a mouse button went down on; it gets the pointer until the
+// button is released
+let eguiPointerTileId: string | null = null;
+
+// Ink strokes on Skia tiles by pointer. Samples are sent to Rust as they
+// come in; the raw points are drawn here until the rendered stroke arrives.
+interface InkStrokePreview {
+  tileId: string;
+  points: [number, number, number][];
+  // Calls for this stroke, chained so they reach the backend in order
+  queue: Promise<unknown>;
+  // When the backend finished the stroke
+  endedAt: number | null;
+}
+
+const INK_BRUSH = { color: [0.1, 0.1, 0.12, 1] as Rgba, width: 3 };
+const inkStrokes = new Map<number, InkStrokePreview>();
+const finishedInkStrokes: InkStrokePreview[] = [];
+
+// Eraser swipes over Skia tiles by pointer, sent to Rust as one erase when
+// the pointer lifts so a swipe undoes in one step
+interface EraserSwipe {
+  tileId: string;
+  points: [number, number][];
+}
+
+const ERASER_RADIUS = 8;
+const eraserSwipes = new Map<number, EraserSwipe>();
+
+// Screens of terminal tiles, streamed from the Rust side, see `canvas_terminal::ScreenUpdate`
+type TerminalColor = 'default' | { indexed: number } | { rgb: [number, number, number] };
+
+interface TerminalRun {
+  text: string;
+  style: {
+    fg?: TerminalColor;
+    bg?: TerminalColor;
+    bold?: boolean;
+    dim?: boolean;
+    italic?: boolean;
+    underline?: boolean;
+    inverse?: boolean;
+  };
+}
+
+interface ScreenUpdate {
+  size: { rows: number; cols: number };
+  full: boolean;
+  lines: { row: number; runs: TerminalRun[] }[];
+  cursor: [number, number] | null;
+  scrollback: number;
+  scrollback_len: number;
+  application_cursor: boolean;
+  title: string;
+  exited: boolean;
+}
+
+interface TerminalScreen {
+  rows: TerminalRun[][];
+  state: ScreenUpdate | null;
+  loading: boolean;
+  // No shell is running for the tile, e.g. one created by another replica
+  stopped: boolean;
 }
 
+// Must match the cell metrics of `canvas_core::TerminalTile`
+const TERMINAL_FONT_SIZE = 13;
+const TERMINAL_CELL_WIDTH = 7.8;
+const TERMINAL_CELL_HEIGHT = 16;
+const TERMINAL_PADDING = 6;
+const TERMINAL_BACKGROUND = '#0c0c0c';
+const TERMINAL_FOREGROUND = '#d0d0d0';
+// Rows scrolled per wheel notch
+const TERMINAL_SCROLL_STEP = 3;
+
+const terminalScreens = new Map<string, TerminalScreen>();
+
 let tiles: Tile[] = [];
 
+// Drawing copied from a Skia tile, in the binary encoding of get_drawing_data
+let copiedDrawing: ArrayBuffer | null = null;
+
 // Initialize the application
 async function initApp() {
   // Get canvas info from Rust
@@ -46,6 +228,9 @@ async function initApp() {
   } catch (error) {
     console.error("Failed to initialize app:", error);
   }
+
+  loadTextFont().catch(err => console.error("Failed to load the text font:", err));
+  loadWidgetTypes().catch(err => console.error("Failed to list widget types:", err));
   
   // Set up canvas
   setupCanvas();
@@ -53,10 +238,61 @@ async function initApp() {
   // Set up event listeners
   setupEventListeners();
   
+  // Refresh tiles whenever a batch, undo or redo changed them
+  listen("canvas-changed", async () => {
+    tiles = await invoke("get_tiles");
+    for (const tileId of terminalScreens.keys()) {
+      if (!tiles.some(t => t.id === tileId)) terminalScreens.delete(tileId);
+    }
+    for (const [tileId, entry] of eguiEntries) {
+      if (tiles.some(t => t.id === tileId)) {
+        entry.stale = true;
+        entry.failed = false;
+      } else {
+        eguiEntries.delete(tileId);
+      }
+    }
+    for (const [tileId, entry] of skiaEntries) {
+      if (tiles.some(t => t.id === tileId)) {
+        entry.stale = true;
+        entry.failed = false;
+      } else {
+        skiaEntries.delete(tileId);
+      }
+    }
+  }).catch(err => console.error("Failed to listen for canvas changes:", err));
+
+  // Pages collaborators open only load once the user agrees
+  listen<{ tile_id: string; url: string }>("webview-blocked", async (event) => {
+    const { tile_id, url } = event.payload;
+    if (confirm(`A collaborator opened ${url}. Load this page?`)) {
+      await invoke("allow_webview", { tileId: tile_id });
+    }
+  }).catch(err => console.error("Failed to listen for blocked webviews:", err));
+
+  listen<{ tile_id: string; update: ScreenUpdate }>("terminal-update", event => {
+    applyScreenUpdate(event.payload.tile_id, event.payload.update);
+  }).catch(err => console.error("Failed to listen for terminal updates:", err));
+  
   // Start render loop
   requestAnimationFrame(render);
 }
 
+// Registers the backend's default font with the page; text falls back to
+// sans-serif until it has loaded
+async function loadTextFont() {
+  const bytes = await invoke<ArrayBuffer>("get_font_data", { family: TEXT_FONT_FAMILY });
+  const face = new FontFace(TEXT_FONT_FAMILY, bytes);
+  document.fonts.add(await face.load());
+}
+
+// Fills the widget type menu of the egui dialog from the registry
+async function loadWidgetTypes() {
+  widgetTypes = await invoke<WidgetType[]>("list_widget_types");
+  const typeSelect = document.getElementById('egui-type') as HTMLSelectElement;
+  typeSelect.replaceChildren(...widgetTypes.map(widget => new Option(widget.label, widget.name)));
+}
+
 function setupCanvas() {
   canvas = document.getElementById('main-canvas') as HTMLCanvasElement;
   ctx = canvas.getContext('2d')!;
@@ -83,17 +319,36 @@ function setupEventListeners() {
   canvas.addEventListener('mousemove', onMouseMove);
   canvas.addEventListener('mouseup', onMouseUp);
   canvas.addEventListener('wheel', onMouseWheel);
+  canvas.addEventListener('dblclick', onDoubleClick);
+  canvas.addEventListener('pointerdown', onInkPointerDown);
+  canvas.addEventListener('pointermove', onInkPointerMove);
+  canvas.addEventListener('pointerup', onInkPointerUp);
+  canvas.addEventListener('pointercancel', onInkPointerCancel);
+  canvas.addEventListener('pointerdown', onEraserPointerDown);
+  canvas.addEventListener('pointermove', onEraserPointerMove);
+  canvas.addEventListener('pointerup', onEraserPointerUp);
+  canvas.addEventListener('pointercancel', onEraserPointerCancel);
+  window.addEventListener('keydown', onKeyDown);
+  window.addEventListener('paste', onPaste);
   
   // Tool buttons
   document.getElementById('pan-tool-btn')?.addEventListener('click', () => setTool('pan'));
   document.getElementById('select-tool-btn')?.addEventListener('click', () => setTool('select'));
+  document.getElementById('ink-tool-btn')?.addEventListener('click', () => setTool('ink'));
+  document.getElementById('eraser-tool-btn')?.addEventListener('click', () => setTool('erase'));
   document.getElementById('reset-view-btn')?.addEventListener('click', resetView);
   document.getElementById('delete-tile-btn')?.addEventListener('click', deleteSelectedTile);
+  document.getElementById('save-board-btn')?.addEventListener('click', saveBoard);
+  document.getElementById('open-board-btn')?.addEventListener('click', openBoard);
   
   // Add tile buttons
   document.getElementById('add-webview-btn')?.addEventListener('click', () => showDialog('webview-dialog'));
   document.getElementById('add-egui-btn')?.addEventListener('click', () => showDialog('egui-dialog'));
   document.getElementById('add-skia-btn')?.addEventListener('click', () => showDialog('skia-dialog'));
+  document.getElementById('add-note-btn')?.addEventListener('click', () => showNoteDialog(null));
+  document.getElementById('add-image-btn')?.addEventListener('click', addImageTile);
+  document.getElementById('import-svg-btn')?.addEventListener('click', importSvg);
+  document.getElementById('add-terminal-btn')?.addEventListener('click', addTerminalTile);
   
   // Dialog buttons
   document.getElementById('webview-cancel')?.addEventListener('click', () => hideDialog('webview-dialog'));
@@ -104,6 +359,9 @@ function setupEventListeners() {
   
   document.getElementById('skia-cancel')?.addEventListener('click', () => hideDialog('skia-dialog'));
   document.getElementById('skia-add')?.addEventListener('click', addSkiaTile);
+
+  document.getElementById('note-cancel')?.addEventListener('click', () => hideDialog('note-dialog'));
+  document.getElementById('note-save')?.addEventListener('click', saveNote);
 }
 
 // Event handlers
@@ -126,10 +384,38 @@ function onMouseDown(e: MouseEvent) {
     }
     
     selectedTileId = clickedTileId;
+    
+    const tile = tiles.find(t => t.id === selectedTileId);
+    // Pressing on an egui widget uses it instead of starting a drag
+    if (tile && tile.tile_type === 'Egui' && worldPos.y >= tile.position[1] - tile.size[1] / 2 + headerHeightOf(tile)) {
+      isDragging = false;
+      eguiPointerTileId = tile.id;
+      const position = drawingPoint(tile, e.clientX, e.clientY);
+      sendEguiInput(tile.id, [
+        { kind: 'pointer_move', position },
+        { kind: 'pointer_button', position, button: eguiButton(e.button), pressed: true },
+      ]);
+      return;
+    }
+    // Clicking a checkbox of a note ticks it instead of starting a drag
+    const checkbox = tile ? noteCheckboxAt(tile, worldPos.x, worldPos.y) : null;
+    if (tile && checkbox !== null) {
+      isDragging = false;
+      invoke("toggle_note_checkbox", { tileId: tile.id, index: checkbox })
+        .catch(err => console.error("Failed to toggle checkbox:", err));
+      return;
+    }
+    // Locked tiles can be selected but not dragged
+    draggedTileStart = tile && tile.movable ? [tile.position[0], tile.position[1]] : null;
   }
 }
 
 function onMouseMove(e: MouseEvent) {
+  const eguiTile = tiles.find(t => t.id === eguiPointerTileId);
+  if (eguiTile) {
+    sendEguiInput(eguiTile.id, [{ kind: 'pointer_move', position: drawingPoint(eguiTile, e.clientX, e.clientY) }]);
+    return;
+  }
   if (!isDragging) return;
   
   const deltaX = e.clientX - lastMousePosition.x;
@@ -143,7 +429,7 @@ function onMouseMove(e: MouseEvent) {
     // Update camera in Rust
     invoke("pan_camera", { deltaX: -deltaX / cameraZoom, deltaY: -deltaY / cameraZoom })
       .catch(err => console.error("Failed to pan camera:", err));
-  } else if (currentTool === 'select' && selectedTileId) {
+  } else if (currentTool === 'select' && selectedTileId && draggedTileStart) {
     // Move the selected tile
     const worldDeltaX = deltaX / cameraZoom;
     const worldDeltaY = deltaY / cameraZoom;
@@ -151,16 +437,9 @@ function onMouseMove(e: MouseEvent) {
     // Find the selected tile
     const tile = tiles.find(t => t.id === selectedTileId);
     if (tile) {
-      // Update tile position
+      // Update the local position only; the move is committed on mouse up
       tile.position[0] += worldDeltaX;
       tile.position[1] += worldDeltaY;
-      
-      // Update tile in Rust
-      invoke("move_tile", { 
-        tileIdStr: selectedTileId, 
-        positionX: tile.position[0], 
-        positionY: tile.position[1] 
-      }).catch(err => console.error("Failed to move tile:", err));
     }
   }
   
@@ -170,12 +449,138 @@ function onMouseMove(e: MouseEvent) {
   updateStatusBar();
 }
 
-function onMouseUp() {
+function onMouseUp(e: MouseEvent) {
   isDragging = false;
+
+  const eguiTile = tiles.find(t => t.id === eguiPointerTileId);
+  eguiPointerTileId = null;
+  if (eguiTile) {
+    const position = drawingPoint(eguiTile, e.clientX, e.clientY);
+    sendEguiInput(eguiTile.id, [
+      { kind: 'pointer_button', position, button: eguiButton(e.button), pressed: false },
+      { kind: 'pointer_leave' },
+    ]);
+    return;
+  }
+  
+  const tile = tiles.find(t => t.id === selectedTileId);
+  if (tile && draggedTileStart &&
+      (tile.position[0] !== draggedTileStart[0] || tile.position[1] !== draggedTileStart[1])) {
+    // Commit the whole drag as a single operation (one undo step)
+    const start: [number, number] = [draggedTileStart[0], draggedTileStart[1]];
+    invoke("apply_operations", {
+      operations: [{ op: "move", tile_id: tile.id, position: tile.position }]
+    }).catch(err => {
+      console.error("Failed to move tile:", err);
+      // Put the tile back where the canvas still has it
+      tile.position = start;
+    });
+  }
+  draggedTileStart = null;
+}
+
+function onDoubleClick(e: MouseEvent) {
+  const worldPos = screenToWorld(e.clientX, e.clientY);
+  for (let i = tiles.length - 1; i >= 0; i--) {
+    const tile = tiles[i];
+    if (isPointInTile(worldPos.x, worldPos.y, tile)) {
+      if (tile.markdown !== null) {
+        showNoteDialog(tile);
+      } else if (tile.tile_type === 'Terminal' && !runningTerminal(tile.id)) {
+        startTerminal(tile.id);
+      }
+      return;
+    }
+  }
+}
+
+function onKeyDown(e: KeyboardEvent) {
+  // A selected terminal gets the keyboard, unless a dialog has the focus
+  const terminal = focusedTerminal();
+  if (terminal) {
+    const data = terminalKeyData(e, terminal.screen.state!.application_cursor);
+    if (data !== null) {
+      e.preventDefault();
+      sendTerminalInput(terminal.tileId, data);
+      return;
+    }
+  }
+
+  // So does a selected egui tile
+  const eguiTileId = focusedEgui();
+  if (eguiTileId) {
+    e.preventDefault();
+    const modifiers = { alt: e.altKey, ctrl: e.ctrlKey, shift: e.shiftKey, command: e.ctrlKey || e.metaKey };
+    const events: object[] = [
+      { kind: 'key', key: e.key, pressed: true, modifiers },
+      { kind: 'key', key: e.key, pressed: false, modifiers },
+    ];
+    // Single characters are text as well, unless they are a shortcut
+    if ([...e.key].length === 1 && !e.ctrlKey && !e.metaKey) {
+      events.push({ kind: 'text', text: e.key });
+    }
+    sendEguiInput(eguiTileId, events);
+    return;
+  }
+
+  if (!(e.ctrlKey || e.metaKey)) return;
+  const key = e.key.toLowerCase();
+  if (key === 'c' || key === 'v') {
+    copyOrPasteDrawing(e, key === 'c');
+    return;
+  }
+  if (key !== 'z') return;
+  
+  e.preventDefault();
+  invoke(e.shiftKey ? "redo" : "undo")
+    .catch(err => console.error("Failed to step history:", err));
+}
+
+// Ctrl+C and Ctrl+V copy the drawing of the selected Skia tile into another
+// one. Drawings travel as raw bytes, as JSON would be many times larger.
+function copyOrPasteDrawing(e: KeyboardEvent, copy: boolean) {
+  const active = document.activeElement;
+  if (active instanceof HTMLInputElement || active instanceof HTMLTextAreaElement) return;
+  const tile = tiles.find(t => t.id === selectedTileId);
+  if (!tile || tile.tile_type !== 'Skia') return;
+
+  e.preventDefault();
+  if (copy) {
+    invoke<ArrayBuffer>("get_drawing_data", { tileId: tile.id })
+      .then(data => { copiedDrawing = data; })
+      .catch(err => console.error("Failed to copy drawing:", err));
+  } else if (copiedDrawing) {
+    invoke("set_drawing_data", copiedDrawing, { headers: { 'Tile-Id': tile.id } })
+      .catch(err => console.error("Failed to paste drawing:", err));
+  }
+}
+
+function onPaste(e: ClipboardEvent) {
+  const terminal = focusedTerminal();
+  const text = e.clipboardData?.getData('text');
+  if (terminal && text) {
+    e.preventDefault();
+    sendTerminalInput(terminal.tileId, text);
+  }
 }
 
 function onMouseWheel(e: WheelEvent) {
   e.preventDefault();
+
+  // Over the selected terminal the wheel scrolls its history instead of zooming
+  const terminal = focusedTerminal();
+  const worldPos = screenToWorld(e.clientX, e.clientY);
+  const hovered = terminal && tiles.find(t => t.id === terminal.tileId);
+  if (terminal && hovered && isPointInTile(worldPos.x, worldPos.y, hovered)) {
+    const state = terminal.screen.state!;
+    const step = e.deltaY < 0 ? TERMINAL_SCROLL_STEP : -TERMINAL_SCROLL_STEP;
+    const offset = Math.max(0, Math.min(state.scrollback_len, state.scrollback + step));
+    if (offset !== state.scrollback) {
+      invoke("scroll_terminal", { tileId: terminal.tileId, offset })
+        .catch(err => console.error("Failed to scroll terminal:", err));
+    }
+    return;
+  }
   
   // Calculate zoom factor
   const zoomFactor = e.deltaY > 0 ? 0.9 : 1.1;
@@ -208,9 +613,11 @@ function setTool(tool: string) {
   // Update UI
   document.getElementById('pan-tool-btn')?.classList.toggle('active', tool === 'pan');
   document.getElementById('select-tool-btn')?.classList.toggle('active', tool === 'select');
+  document.getElementById('ink-tool-btn')?.classList.toggle('active', tool === 'ink');
+  document.getElementById('eraser-tool-btn')?.classList.toggle('active', tool === 'erase');
   
   // Update cursor
-  canvas.style.cursor = tool === 'pan' ? 'grab' : 'default';
+  canvas.style.cursor = tool === 'pan' ? 'grab' : tool === 'ink' || tool === 'erase' ? 'crosshair' : 'default';
 }
 
 function resetView() {
@@ -308,25 +715,8 @@ async function addEguiTile() {
   const centerX = cameraPosition.x;
   const centerY = cameraPosition.y;
   
-  // Default config based on widget type
-  let config = {};
-  switch (widgetType) {
-    case 'button':
-      config = { text: 'Click Me' };
-      break;
-    case 'slider':
-      config = { min: 0, max: 100, value: 50 };
-      break;
-    case 'checkbox':
-      config = { checked: false, text: 'Check me' };
-      break;
-    case 'textbox':
-      config = { text: 'Edit me' };
-      break;
-    case 'colorpicker':
-      config = { color: [1.0, 0.0, 0.0, 1.0] };
-      break;
-  }
+  // Default config of the widget type
+  const config = widgetTypes.find(widget => widget.name === widgetType)?.default_config ?? null;
   
   try {
     // Add tile in Rust
@@ -389,6 +779,123 @@ async function addSkiaTile() {
   }
 }
 
+async function addTerminalTile() {
+  try {
+    const tileId: string = await invoke("add_terminal_tile", {
+      title: 'Terminal',
+      positionX: cameraPosition.x,
+      positionY: cameraPosition.y,
+      width: 640,
+      height: 400
+    });
+
+    tiles = await invoke("get_tiles");
+    selectedTileId = tileId;
+    setTool('select');
+  } catch (error) {
+    console.error("Failed to add terminal tile:", error);
+  }
+}
+
+// Opens the note dialog to add a note, or to edit `tile`
+function showNoteDialog(tile: Tile | null) {
+  editingNoteId = tile ? tile.id : null;
+  (document.getElementById('note-title') as HTMLInputElement).value = tile ? tile.title : 'New Note';
+  (document.getElementById('note-markdown') as HTMLTextAreaElement).value = tile?.markdown ?? '';
+  document.getElementById('note-dialog-heading')!.textContent = tile ? 'Edit Note' : 'Add Note';
+  document.getElementById('note-save')!.textContent = tile ? 'Save' : 'Add';
+  document.getElementById('note-size-fields')!.style.display = tile ? 'none' : 'block';
+  showDialog('note-dialog');
+}
+
+async function saveNote() {
+  const title = (document.getElementById('note-title') as HTMLInputElement).value;
+  const markdown = (document.getElementById('note-markdown') as HTMLTextAreaElement).value;
+
+  try {
+    if (editingNoteId) {
+      await invoke("set_note_text", { tileId: editingNoteId, markdown });
+    } else {
+      const width = parseFloat((document.getElementById('note-width') as HTMLInputElement).value);
+      const height = parseFloat((document.getElementById('note-height') as HTMLInputElement).value);
+      selectedTileId = await invoke("add_note_tile", {
+        markdown,
+        title,
+        positionX: cameraPosition.x,
+        positionY: cameraPosition.y,
+        width,
+        height
+      });
+      tiles = await invoke("get_tiles");
+    }
+    hideDialog('note-dialog');
+  } catch (error) {
+    console.error("Failed to save note:", error);
+  }
+}
+
+async function addImageTile() {
+  try {
+    // Without a path the backend asks for a file
+    const tileId: string | null = await invoke("add_image_tile", {
+      positionX: cameraPosition.x,
+      positionY: cameraPosition.y
+    });
+    if (tileId) {
+      tiles = await invoke("get_tiles");
+      selectedTileId = tileId;
+    }
+  } catch (error) {
+    console.error("Failed to add image tile:", error);
+  }
+}
+
+async function importSvg() {
+  try {
+    // Without a path the backend asks for a file
+    const imported: { tile_id: string, dropped: string[] } | null = await invoke("import_svg_file", {
+      positionX: cameraPosition.x,
+      positionY: cameraPosition.y
+    });
+    if (imported) {
+      if (imported.dropped.length > 0) {
+        console.warn("SVG import skipped unsupported features:", imported.dropped.join(', '));
+      }
+      tiles = await invoke("get_tiles");
+      selectedTileId = imported.tile_id;
+    }
+  } catch (error) {
+    console.error("Failed to import SVG:", error);
+  }
+}
+
+async function saveBoard() {
+  try {
+    // Without a path the backend asks for a file
+    await invoke("save_board");
+  } catch (error) {
+    console.error("Failed to save board:", error);
+  }
+}
+
+async function openBoard() {
+  try {
+    const opened: string | null = await invoke("open_board");
+    if (opened) {
+      const canvasInfo = await invoke("get_canvas_info");
+      cameraPosition.x = canvasInfo.camera.position[0];
+      cameraPosition.y = canvasInfo.camera.position[1];
+      cameraZoom = canvasInfo.camera.zoom;
+      cameraRotation = canvasInfo.camera.rotation;
+      tiles = await invoke("get_tiles");
+      selectedTileId = null;
+      updateStatusBar();
+    }
+  } catch (error) {
+    console.error("Failed to open board:", error);
+  }
+}
+
 // Rendering
 function render() {
   // Clear canvas
@@ -477,47 +984,94 @@ function drawTiles() {
     ctx.rotate(tile.rotation);
     ctx.translate(-tile.position[0], -tile.position[1]);
     
-    // Draw tile background
-    ctx.fillStyle = '#2a2a2a';
-    ctx.strokeStyle = tile.id === selectedTileId ? '#0066cc' : '#3a3a3a';
-    ctx.lineWidth = 2 / cameraZoom;
-    
-    // Draw rounded rectangle
-    const radius = 6 / cameraZoom;
-    ctx.beginPath();
-    ctx.moveTo(x + radius, y);
-    ctx.lineTo(x + width - radius, y);
-    ctx.arcTo(x + width, y, x + width, y + radius, radius);
-    ctx.lineTo(x + width, y + height - radius);
-    ctx.arcTo(x + width, y + height, x + width - radius, y + height, radius);
-    ctx.lineTo(x + radius, y + height);
-    ctx.arcTo(x, y + height, x, y + height - radius, radius);
-    ctx.lineTo(x, y + radius);
-    ctx.arcTo(x, y, x + radius, y, radius);
-    ctx.closePath();
+    const style = tile.style;
+    ctx.globalAlpha = style.opacity;
+
+    // Rounded outline of the tile, reused for the shadow, fill and border
+    const radius = Math.max(0, Math.min(style.corner_radius, width / 2, height / 2));
+    const outline = () => {
+      ctx.beginPath();
+      ctx.moveTo(x + radius, y);
+      ctx.lineTo(x + width - radius, y);
+      ctx.arcTo(x + width, y, x + width, y + radius, radius);
+      ctx.lineTo(x + width, y + height - radius);
+      ctx.arcTo(x + width, y + height, x + width - radius, y + height, radius);
+      ctx.lineTo(x + radius, y + height);
+      ctx.arcTo(x, y + height, x, y + height - radius, radius);
+      ctx.lineTo(x, y + radius);
+      ctx.arcTo(x, y, x + radius, y, radius);
+      ctx.closePath();
+    };
+
+    // Draw tile background with its drop shadow
+    if (style.shadow) {
+      ctx.shadowColor = rgba(style.shadow.color);
+      ctx.shadowBlur = style.shadow.blur * cameraZoom;
+      ctx.shadowOffsetX = style.shadow.offset[0] * cameraZoom;
+      ctx.shadowOffsetY = style.shadow.offset[1] * cameraZoom;
+    }
+    ctx.fillStyle = rgba(style.background);
+    outline();
     ctx.fill();
-    ctx.stroke();
-    
+    ctx.shadowColor = 'transparent';
+
+    const terminal = tile.tile_type === 'Terminal';
+    const skia = tile.tile_type === 'Skia';
+    const egui = tile.tile_type === 'Egui';
+    if (tile.note_lines || tile.image || terminal || skia || egui) {
+      ctx.save();
+      outline();
+      ctx.clip();
+      if (tile.note_lines) {
+        drawNote(tile, tile.note_lines);
+      } else if (terminal) {
+        drawTerminal(tile, x, y + headerHeightOf(tile), width, height - headerHeightOf(tile));
+      } else if (skia) {
+        drawSkia(tile, x, y + headerHeightOf(tile), width, height - headerHeightOf(tile));
+        drawInkPreview(tile, x, y + headerHeightOf(tile));
+        drawEraserPreview(tile, x, y + headerHeightOf(tile));
+      } else if (egui) {
+        drawEgui(tile, x, y + headerHeightOf(tile), width, height - headerHeightOf(tile));
+      } else {
+        drawImage(tile, x, y + headerHeightOf(tile), width, height - headerHeightOf(tile));
+      }
+      ctx.restore();
+    }
+
     // Draw tile header
-    const headerHeight = 30 / cameraZoom;
-    ctx.fillStyle = '#3a3a3a';
-    ctx.beginPath();
-    ctx.moveTo(x + radius, y);
-    ctx.lineTo(x + width - radius, y);
-    ctx.arcTo(x + width, y, x + width, y + radius, radius);
-    ctx.lineTo(x + width, y + headerHeight);
-    ctx.lineTo(x, y + headerHeight);
-    ctx.lineTo(x, y + radius);
-    ctx.arcTo(x, y, x + radius, y, radius);
-    ctx.closePath();
-    ctx.fill();
-    
-    // Draw tile title
-    ctx.fillStyle = '#f6f6f6';
-    ctx.font = `${14 / cameraZoom}px sans-serif`;
-    ctx.textBaseline = 'middle';
-    ctx.fillText(tile.title, x + 10 / cameraZoom, y + headerHeight / 2);
-    
+    const headerHeight = headerHeightOf(tile);
+    if (headerHeight > 0) {
+      const headerRadius = Math.min(radius, headerHeight);
+      ctx.fillStyle = rgba(style.title_bar_color);
+      ctx.beginPath();
+      ctx.moveTo(x + headerRadius, y);
+      ctx.lineTo(x + width - headerRadius, y);
+      ctx.arcTo(x + width, y, x + width, y + headerRadius, headerRadius);
+      ctx.lineTo(x + width, y + headerHeight);
+      ctx.lineTo(x, y + headerHeight);
+      ctx.lineTo(x, y + headerRadius);
+      ctx.arcTo(x, y, x + headerRadius, y, headerRadius);
+      ctx.closePath();
+      ctx.fill();
+
+      // Draw tile title
+      ctx.fillStyle = rgba(style.title_color);
+      ctx.font = `14px ${TEXT_FONT}`;
+      ctx.textBaseline = 'middle';
+      // Terminals also show the title the running program set
+      const programTitle = terminal ? terminalScreens.get(tile.id)?.state?.title : '';
+      ctx.fillText(programTitle ? `${tile.title} — ${programTitle}` : tile.title, x + 10, y + headerHeight / 2);
+    }
+
+    // Draw the border, highlighted when selected
+    const selected = tile.id === selectedTileId;
+    if (style.border_width > 0 || selected) {
+      ctx.strokeStyle = selected ? '#0066cc' : rgba(style.border_color);
+      ctx.lineWidth = selected ? Math.max(style.border_width, 2 / cameraZoom) : style.border_width;
+      outline();
+      ctx.stroke();
+    }
+
     // Draw tile type indicator
     let typeColor = '#f6f6f6';
     switch (tile.tile_type) {
@@ -530,18 +1084,29 @@ function drawTiles() {
       case 'Skia':
         typeColor = '#ff9800';
         break;
+      case 'Note':
+        typeColor = '#ffd54f';
+        break;
+      case 'Image':
+        typeColor = '#e91e63';
+        break;
+      case 'Terminal':
+        typeColor = '#9e9e9e';
+        break;
     }
     
-    ctx.fillStyle = typeColor;
-    ctx.beginPath();
-    ctx.arc(
-      x + width - 15 / cameraZoom, 
-      y + headerHeight / 2, 
-      5 / cameraZoom, 
-      0, 
-      Math.PI * 2
-    );
-    ctx.fill();
+    if (headerHeight > 0) {
+      ctx.fillStyle = typeColor;
+      ctx.beginPath();
+      ctx.arc(
+        x + width - 15 / cameraZoom,
+        y + headerHeight / 2,
+        5 / cameraZoom,
+        0,
+        Math.PI * 2
+      );
+      ctx.fill();
+    }
     
     // Draw resize handle if selected
     if (tile.id === selectedTileId) {
@@ -562,7 +1127,650 @@ function drawTiles() {
   }
 }
 
+function headerHeightOf(tile: Tile) {
+  return tile.style.show_title_bar ? Math.min(30, tile.size[1]) : 0;
+}
+
+// Top-left corner of the area note lines are laid out in
+function noteOrigin(tile: Tile) {
+  const headerHeight = headerHeightOf(tile);
+  return {
+    x: tile.position[0] - tile.size[0] / 2 + NOTE_PADDING,
+    y: tile.position[1] - tile.size[1] / 2 + headerHeight + NOTE_PADDING,
+  };
+}
+
+// Top-left corner of the square a list marker is drawn in, relative to the note origin
+function markerBox(line: NoteLine) {
+  return {
+    x: line.indent - NOTE_LIST_INDENT + (NOTE_LIST_INDENT - NOTE_CHECKBOX_SIZE) / 2,
+    y: line.top + (line.height - NOTE_CHECKBOX_SIZE) / 2,
+  };
+}
+
+function drawNote(tile: Tile, lines: NoteLine[]) {
+  const origin = noteOrigin(tile);
+  const width = tile.size[0] - 2 * NOTE_PADDING;
+  const ink = rgba(tile.style.title_color);
+  ctx.textBaseline = 'middle';
+
+  for (const line of lines) {
+    const top = origin.y + line.top;
+    const middle = top + line.height / 2;
+
+    ctx.fillStyle = rgba(tile.style.border_color);
+    for (const bar of line.quote_bars) {
+      ctx.fillRect(origin.x + bar, top, NOTE_QUOTE_BAR_WIDTH, line.height);
+    }
+    if (line.kind.kind === 'code') {
+      ctx.fillStyle = 'rgba(255, 255, 255, 0.08)';
+      ctx.fillRect(origin.x + line.indent, top, width - line.indent, line.height);
+    } else if (line.kind.kind === 'rule') {
+      ctx.fillRect(origin.x + line.indent, middle - 0.5, width - line.indent, 1);
+    }
+
+    if (line.marker) {
+      const box = markerBox(line);
+      const left = origin.x + box.x;
+      const boxTop = origin.y + box.y;
+      const size = NOTE_CHECKBOX_SIZE;
+      ctx.fillStyle = ink;
+      ctx.strokeStyle = ink;
+      ctx.lineWidth = 1.5;
+      switch (line.marker.kind) {
+        case 'bullet':
+          ctx.beginPath();
+          ctx.arc(left + size / 2, boxTop + size / 2, size * 0.2, 0, Math.PI * 2);
+          ctx.fill();
+          break;
+        case 'number':
+          ctx.font = `${line.font_size}px ${TEXT_FONT}`;
+          ctx.textAlign = 'right';
+          ctx.fillText(`${line.marker.value}.`, left + size, middle);
+          ctx.textAlign = 'left';
+          break;
+        case 'checkbox':
+          ctx.beginPath();
+          ctx.roundRect(left, boxTop, size, size, 2);
+          ctx.stroke();
+          if (line.marker.checked) {
+            ctx.beginPath();
+            ctx.moveTo(left + size * 0.2, boxTop + size * 0.5);
+            ctx.lineTo(left + size * 0.42, boxTop + size * 0.75);
+            ctx.lineTo(left + size * 0.8, boxTop + size * 0.25);
+            ctx.stroke();
+          }
+          break;
+      }
+    }
+
+    let x = origin.x + line.indent;
+    const heading = line.kind.kind === 'heading';
+    for (const span of line.spans) {
+      const weight = span.bold || heading ? 'bold ' : '';
+      const slant = span.italic ? 'italic ' : '';
+      const family = span.code ? 'monospace' : TEXT_FONT;
+      ctx.font = `${slant}${weight}${line.font_size}px ${family}`;
+      ctx.fillStyle = span.link ? '#4da3ff' : ink;
+      ctx.fillText(span.text, x, middle);
+      const spanWidth = ctx.measureText(span.text).width;
+      if (span.link || span.strikethrough) {
+        const offsets: number[] = [];
+        if (span.link) offsets.push(line.font_size * 0.45);
+        if (span.strikethrough) offsets.push(0);
+        for (const offset of offsets) {
+          ctx.fillRect(x, middle + offset, spanWidth, 1);
+        }
+      }
+      x += spanWidth;
+    }
+  }
+}
+
+// Draws an image tile's file fitted into the given area, or a placeholder
+// while it loads or when it is missing
+function drawImage(tile: Tile, x: number, y: number, width: number, height: number) {
+  const image = tile.image!;
+  // Power of two buckets so zooming doesn't refetch on every frame
+  const pixels = Math.max(width, height) * cameraZoom * window.devicePixelRatio;
+  const dimension = 2 ** Math.ceil(Math.log2(Math.max(pixels, 32)));
+
+  let entry = imageEntries.get(tile.id);
+  if (entry && entry.path !== image.path) {
+    entry = undefined;
+  }
+  if (!entry) {
+    entry = { path: image.path, dimension: 0, bitmap: null, loading: false, missing: false };
+    imageEntries.set(tile.id, entry);
+  }
+  if (!entry.loading && !entry.missing && entry.dimension < dimension) {
+    loadThumbnail(tile.id, entry, dimension);
+  }
+
+  if (entry.bitmap) {
+    const scale = Math.min(width / entry.bitmap.width, height / entry.bitmap.height);
+    const drawWidth = entry.bitmap.width * scale;
+    const drawHeight = entry.bitmap.height * scale;
+    ctx.drawImage(entry.bitmap, x + (width - drawWidth) / 2, y + (height - drawHeight) / 2, drawWidth, drawHeight);
+    return;
+  }
+
+  ctx.fillStyle = 'rgba(128, 128, 128, 0.25)';
+  ctx.fillRect(x, y, width, height);
+  if (entry.missing) {
+    ctx.strokeStyle = rgba(tile.style.border_color);
+    ctx.lineWidth = 1.5;
+    ctx.beginPath();
+    ctx.moveTo(x, y);
+    ctx.lineTo(x + width, y + height);
+    ctx.moveTo(x + width, y);
+    ctx.lineTo(x, y + height);
+    ctx.stroke();
+    ctx.fillStyle = rgba(tile.style.title_color);
+    ctx.font = '12px sans-serif';
+    ctx.textAlign = 'center';
+    ctx.textBaseline = 'middle';
+    ctx.fillText(`Missing image: ${image.path.split(/[\\/]/).pop()}`, x + width / 2, y + height / 2);
+    ctx.textAlign = 'left';
+  }
+}
+
+async function loadThumbnail(tileId: string, entry: ImageEntry, dimension: number) {
+  entry.loading = true;
+  try {
+    const bytes = await invoke<ArrayBuffer>("get_image_thumbnail", { tileId, maxDimension: dimension });
+    entry.bitmap = await createImageBitmap(new Blob([bytes], { type: 'image/png' }));
+    entry.dimension = dimension;
+  } catch (error) {
+    entry.missing = true;
+    console.error(`Failed to load image ${entry.path}:`, error);
+  } finally {
+    entry.loading = false;
+  }
+}
+
+// Draws a Skia tile's drawing as rendered by the Rust side, refetching it
+// when the zoom needs more pixels or the canvas changed
+function drawSkia(tile: Tile, x: number, y: number, width: number, height: number) {
+  if (width <= 0 || height <= 0) return;
+  const wanted = cameraZoom * window.devicePixelRatio;
+  // Both are powers of two, the mip levels the backend caches drawings at
+  const limit = 2 ** Math.floor(Math.log2(MAX_SKIA_PIXELS / Math.max(width, height)));
+  const scale = Math.min(2 ** Math.ceil(Math.log2(Math.max(wanted, 0.125))), limit);
+
+  let entry = skiaEntries.get(tile.id);
+  if (!entry) {
+    entry = { scale: 0, bitmap: null, loading: false, stale: false, failed: false };
+    skiaEntries.set(tile.id, entry);
+  }
+  if (!entry.loading && !entry.failed && (entry.stale || entry.scale < scale)) {
+    loadSkiaDrawing(tile.id, entry, scale);
+  }
+
+  // The old bitmap stays up until the new one has arrived
+  if (entry.bitmap) {
+    ctx.drawImage(entry.bitmap, x, y, width, height);
+  }
+}
+
+async function loadSkiaDrawing(tileId: string, entry: SkiaEntry, scale: number) {
+  entry.loading = true;
+  entry.stale = false;
+  const requestedAt = performance.now();
+  try {
+    const bytes = await invoke<ArrayBuffer>("render_skia_tile", { tileId, scale });
+    entry.bitmap = await createImageBitmap(new Blob([bytes], { type: 'image/png' }));
+    entry.scale = scale;
+    // Strokes finished before this render are part of it now
+    for (let i = finishedInkStrokes.length - 1; i >= 0; i--) {
+      const stroke = finishedInkStrokes[i];
+      if (stroke.tileId === tileId && stroke.endedAt !== null && stroke.endedAt <= requestedAt) {
+        finishedInkStrokes.splice(i, 1);
+      }
+    }
+  } catch (error) {
+    entry.failed = true;
+    console.error(`Failed to render Skia tile ${tileId}:`, error);
+  } finally {
+    entry.loading = false;
+  }
+}
+
+// Draws the frame of an egui tile's widget as rendered by the Rust side,
+// refetching it when the zoom needs more pixels, the widget got input or
+// wants to animate
+function drawEgui(tile: Tile, x: number, y: number, width: number, height: number) {
+  if (width <= 0 || height <= 0) return;
+  const wanted = cameraZoom * window.devicePixelRatio;
+  const limit = 2 ** Math.floor(Math.log2(MAX_SKIA_PIXELS / Math.max(width, height)));
+  const scale = Math.min(2 ** Math.ceil(Math.log2(Math.max(wanted, 0.125))), limit);
+
+  let entry = eguiEntries.get(tile.id);
+  if (!entry) {
+    entry = { scale: 0, bitmap: null, loading: false, stale: false, failed: false };
+    eguiEntries.set(tile.id, entry);
+  }
+  if (!entry.loading && !entry.failed && (entry.stale || entry.scale !== scale)) {
+    loadEguiFrame(tile.id, entry, scale);
+  }
+
+  if (entry.bitmap) {
+    ctx.drawImage(entry.bitmap, x, y, width, height);
+  }
+}
+
+async function loadEguiFrame(tileId: string, entry: SkiaEntry, scale: number) {
+  entry.loading = true;
+  entry.stale = false;
+  try {
+    // A repaint flag byte, then the PNG
+    const frame = new Uint8Array(await invoke<ArrayBuffer>("render_egui_tile", { tileId, scale }));
+    entry.bitmap = await createImageBitmap(new Blob([frame.subarray(1)], { type: 'image/png' }));
+    entry.scale = scale;
+    entry.stale ||= frame[0] === 1;
+  } catch (error) {
+    entry.failed = true;
+    console.error(`Failed to render egui tile ${tileId}:`, error);
+  } finally {
+    entry.loading = false;
+  }
+}
+
+// The selected egui tile, which gets the keyboard unless a dialog has the focus
+function focusedEgui(): string | null {
+  const active = document.activeElement;
+  if (!selectedTileId || active instanceof HTMLInputElement || active instanceof HTMLTextAreaElement) {
+    return null;
+  }
+  return tiles.find(t => t.id === selectedTileId)?.tile_type === 'Egui' ? selectedTileId : null;
+}
+
+function sendEguiInput(tileId: string, events: object[]) {
+  invoke("egui_input", { tileId, events })
+    .then(() => {
+      const entry = eguiEntries.get(tileId);
+      if (entry) entry.stale = true;
+    })
+    .catch(err => console.error("Failed to send egui input:", err));
+}
+
+function eguiButton(button: number) {
+  return button === 2 ? 'secondary' : button === 1 ? 'middle' : 'primary';
+}
+
+// Coordinates of a screen point in the content of a Skia or egui tile, whose origin
+// is the top-left corner of the area below the header
+function drawingPoint(tile: Tile, clientX: number, clientY: number): [number, number] {
+  const world = screenToWorld(clientX, clientY);
+  return [
+    world.x - (tile.position[0] - tile.size[0] / 2),
+    world.y - (tile.position[1] - tile.size[1] / 2) - headerHeightOf(tile),
+  ];
+}
+
+function inkSample(tile: Tile, e: PointerEvent) {
+  const position = drawingPoint(tile, e.clientX, e.clientY);
+  // Mice report 0.5 while a button is down, pens their real pressure
+  const pressure = e.pointerType === 'mouse' ? 0.5 : e.pressure;
+  return { position, pressure };
+}
+
+function queueInk(stroke: InkStrokePreview, command: string, args: Record<string, unknown>) {
+  stroke.queue = stroke.queue
+    .then(() => invoke(command, { tileId: stroke.tileId, ...args }))
+    .catch(err => console.error(`Failed to ${command}:`, err));
+}
+
+// The Skia tile whose drawing is under the pointer, if the topmost tile
+// there is one
+function skiaTileAt(e: PointerEvent): Tile | undefined {
+  const world = screenToWorld(e.clientX, e.clientY);
+  let tile: Tile | undefined;
+  for (let i = tiles.length - 1; i >= 0; i--) {
+    if (isPointInTile(world.x, world.y, tiles[i])) {
+      tile = tiles[i];
+      break;
+    }
+  }
+  if (!tile || tile.tile_type !== 'Skia' || drawingPoint(tile, e.clientX, e.clientY)[1] < 0) return undefined;
+  return tile;
+}
+
+function onInkPointerDown(e: PointerEvent) {
+  if (currentTool !== 'ink') return;
+  const tile = skiaTileAt(e);
+  if (!tile) return;
+
+  canvas.setPointerCapture(e.pointerId);
+  const sample = inkSample(tile, e);
+  const stroke: InkStrokePreview = {
+    tileId: tile.id,
+    points: [[sample.position[0], sample.position[1], sample.pressure]],
+    queue: Promise.resolve(),
+    endedAt: null,
+  };
+  inkStrokes.set(e.pointerId, stroke);
+  queueInk(stroke, "begin_ink_stroke", { pointerId: e.pointerId, brush: INK_BRUSH });
+  queueInk(stroke, "add_ink_samples", { pointerId: e.pointerId, samples: [sample] });
+}
+
+function onInkPointerMove(e: PointerEvent) {
+  const stroke = inkStrokes.get(e.pointerId);
+  const tile = stroke && tiles.find(t => t.id === stroke.tileId);
+  if (!stroke || !tile) return;
+  // Pens deliver more samples than there are move events
+  const events = e.getCoalescedEvents?.() ?? [e];
+  const samples = (events.length > 0 ? events : [e]).map(event => inkSample(tile, event));
+  for (const sample of samples) {
+    stroke.points.push([sample.position[0], sample.position[1], sample.pressure]);
+  }
+  queueInk(stroke, "add_ink_samples", { pointerId: e.pointerId, samples });
+}
+
+function onInkPointerUp(e: PointerEvent) {
+  const stroke = inkStrokes.get(e.pointerId);
+  if (!stroke) return;
+  inkStrokes.delete(e.pointerId);
+  finishedInkStrokes.push(stroke);
+  queueInk(stroke, "end_ink_stroke", { pointerId: e.pointerId });
+  stroke.queue = stroke.queue.then(() => {
+    stroke.endedAt = performance.now();
+  });
+}
+
+function onInkPointerCancel(e: PointerEvent) {
+  const stroke = inkStrokes.get(e.pointerId);
+  if (!stroke) return;
+  inkStrokes.delete(e.pointerId);
+  queueInk(stroke, "cancel_ink_stroke", { pointerId: e.pointerId });
+}
+
+function onEraserPointerDown(e: PointerEvent) {
+  if (currentTool !== 'erase') return;
+  const tile = skiaTileAt(e);
+  if (!tile) return;
+  canvas.setPointerCapture(e.pointerId);
+  eraserSwipes.set(e.pointerId, { tileId: tile.id, points: [drawingPoint(tile, e.clientX, e.clientY)] });
+}
+
+function onEraserPointerMove(e: PointerEvent) {
+  const swipe = eraserSwipes.get(e.pointerId);
+  const tile = swipe && tiles.find(t => t.id === swipe.tileId);
+  if (!swipe || !tile) return;
+  swipe.points.push(drawingPoint(tile, e.clientX, e.clientY));
+}
+
+function onEraserPointerUp(e: PointerEvent) {
+  const swipe = eraserSwipes.get(e.pointerId);
+  if (!swipe) return;
+  eraserSwipes.delete(e.pointerId);
+  // Erasing at the tile's scale, so the eraser looks the same at any zoom
+  invoke("erase_drawing", { tileId: swipe.tileId, path: swipe.points, radius: ERASER_RADIUS / cameraZoom })
+    .catch(err => console.error("Failed to erase:", err));
+}
+
+function onEraserPointerCancel(e: PointerEvent) {
+  eraserSwipes.delete(e.pointerId);
+}
+
+// Draws the raw points of strokes on a Skia tile that Rust hasn't rendered yet
+function drawInkPreview(tile: Tile, x: number, y: number) {
+  const strokes = [...inkStrokes.values(), ...finishedInkStrokes].filter(stroke => stroke.tileId === tile.id);
+  if (strokes.length === 0) return;
+  ctx.strokeStyle = rgba(INK_BRUSH.color);
+  ctx.lineCap = 'round';
+  ctx.lineJoin = 'round';
+  for (const stroke of strokes) {
+    for (let i = 1; i < stroke.points.length; i++) {
+      const [x0, y0] = stroke.points[i - 1];
+      const [x1, y1, pressure] = stroke.points[i];
+      // Same width curve as the Rust outline, see `canvas_core::ink`
+      ctx.lineWidth = INK_BRUSH.width * (0.3 + 0.7 * pressure);
+      ctx.beginPath();
+      ctx.moveTo(x + x0, y + y0);
+      ctx.lineTo(x + x1, y + y1);
+      ctx.stroke();
+    }
+  }
+}
+
+// Shades the swipes of the eraser over a Skia tile until the pointer lifts
+function drawEraserPreview(tile: Tile, x: number, y: number) {
+  ctx.strokeStyle = 'rgba(255, 255, 255, 0.35)';
+  ctx.lineCap = 'round';
+  ctx.lineJoin = 'round';
+  ctx.lineWidth = 2 * ERASER_RADIUS / cameraZoom;
+  for (const swipe of eraserSwipes.values()) {
+    if (swipe.tileId !== tile.id) continue;
+    ctx.beginPath();
+    ctx.moveTo(x + swipe.points[0][0], y + swipe.points[0][1]);
+    for (const [px, py] of swipe.points) {
+      ctx.lineTo(x + px, y + py);
+    }
+    ctx.stroke();
+  }
+}
+
+// Merges an update into the terminal's screen. Incremental updates need the
+// snapshot they build on, so they are dropped until it has arrived.
+function applyScreenUpdate(tileId: string, update: ScreenUpdate) {
+  let screen = terminalScreens.get(tileId);
+  if (!screen) {
+    if (!update.full) return;
+    screen = { rows: [], state: null, loading: false, stopped: false };
+    terminalScreens.set(tileId, screen);
+  }
+  if (update.full) {
+    screen.rows = [];
+  } else if (!screen.state) {
+    return;
+  }
+  screen.rows.length = update.size.rows;
+  for (let row = 0; row < update.size.rows; row++) {
+    screen.rows[row] ??= [];
+  }
+  for (const line of update.lines) {
+    screen.rows[line.row] = line.runs;
+  }
+  screen.state = update;
+  screen.stopped = false;
+}
+
+async function loadTerminalScreen(tileId: string, screen: TerminalScreen) {
+  screen.loading = true;
+  try {
+    applyScreenUpdate(tileId, await invoke("get_terminal_screen", { tileId }));
+  } catch (error) {
+    screen.stopped = true;
+  } finally {
+    screen.loading = false;
+  }
+}
+
+async function startTerminal(tileId: string) {
+  try {
+    await invoke("start_terminal", { tileId });
+    const screen = terminalScreens.get(tileId);
+    if (screen) await loadTerminalScreen(tileId, screen);
+  } catch (error) {
+    console.error("Failed to start terminal:", error);
+  }
+}
+
+function runningTerminal(tileId: string) {
+  const screen = terminalScreens.get(tileId);
+  return !!screen && !screen.stopped && !!screen.state && !screen.state.exited;
+}
+
+// The selected terminal tile if it has a running shell and no dialog field has the focus
+function focusedTerminal(): { tileId: string; screen: TerminalScreen } | null {
+  const active = document.activeElement;
+  if (!selectedTileId || active instanceof HTMLInputElement || active instanceof HTMLTextAreaElement) {
+    return null;
+  }
+  if (!runningTerminal(selectedTileId)) return null;
+  return { tileId: selectedTileId, screen: terminalScreens.get(selectedTileId)! };
+}
+
+function sendTerminalInput(tileId: string, data: string) {
+  invoke("terminal_input", { tileId, data })
+    .catch(err => console.error("Failed to send terminal input:", err));
+}
+
+// Bytes a terminal expects for a key press, or null for keys it doesn't take
+function terminalKeyData(e: KeyboardEvent, applicationCursor: boolean): string | null {
+  if (e.metaKey) return null;
+  if (e.ctrlKey && !e.altKey && e.key.length === 1) {
+    if (e.key === ' ') return '\x00';
+    const code = e.key.toUpperCase().charCodeAt(0);
+    return code >= 64 && code <= 95 ? String.fromCharCode(code - 64) : null;
+  }
+  const cursorKeys: Record<string, string> = { ArrowUp: 'A', ArrowDown: 'B', ArrowRight: 'C', ArrowLeft: 'D', Home: 'H', End: 'F' };
+  if (e.key in cursorKeys) {
+    return (applicationCursor ? '\x1bO' : '\x1b[') + cursorKeys[e.key];
+  }
+  const keys: Record<string, string> = {
+    Enter: '\r',
+    Backspace: '\x7f',
+    Tab: '\t',
+    Escape: '\x1b',
+    Insert: '\x1b[2~',
+    Delete: '\x1b[3~',
+    PageUp: '\x1b[5~',
+    PageDown: '\x1b[6~',
+  };
+  if (e.key in keys) return keys[e.key];
+  if (e.key.length === 1) return (e.altKey ? '\x1b' : '') + e.key;
+  return null;
+}
+
+// The xterm palette: 16 ANSI colors, a 6x6x6 cube and 24 grays
+const ANSI_COLORS = [
+  '#000000', '#cd3131', '#0dbc79', '#e5e510', '#2472c8', '#bc3fbc', '#11a8cd', '#e5e5e5',
+  '#666666', '#f14c4c', '#23d18b', '#f5f543', '#3b8eea', '#d670d6', '#29b8db', '#ffffff',
+];
+
+function terminalColor(color: TerminalColor | undefined, fallback: string) {
+  if (!color || color === 'default') return fallback;
+  if ('rgb' in color) return `rgb(${color.rgb.join(', ')})`;
+  const index = color.indexed;
+  if (index < 16) return ANSI_COLORS[index];
+  if (index < 232) {
+    const level = (value: number) => (value === 0 ? 0 : 55 + value * 40);
+    const cube = index - 16;
+    return `rgb(${level(Math.floor(cube / 36))}, ${level(Math.floor(cube / 6) % 6)}, ${level(cube % 6)})`;
+  }
+  const gray = 8 + (index - 232) * 10;
+  return `rgb(${gray}, ${gray}, ${gray})`;
+}
+
+// Characters that take two terminal columns
+const WIDE_CHARACTER = /[\u1100-\u115f\u2e80-\ua4cf\uac00-\ud7a3\uf900-\ufaff\ufe30-\ufe4f\uff00-\uff60\uffe0-\uffe6\u{1f300}-\u{1faff}\u{20000}-\u{3fffd}]/u;
+
+function drawTerminal(tile: Tile, x: number, y: number, width: number, height: number) {
+  ctx.fillStyle = TERMINAL_BACKGROUND;
+  ctx.fillRect(x, y, width, height);
+
+  let screen = terminalScreens.get(tile.id);
+  if (!screen) {
+    screen = { rows: [], state: null, loading: false, stopped: false };
+    terminalScreens.set(tile.id, screen);
+  }
+  if (!screen.state && !screen.loading && !screen.stopped) {
+    loadTerminalScreen(tile.id, screen);
+  }
+  if (screen.stopped) {
+    ctx.fillStyle = TERMINAL_FOREGROUND;
+    ctx.font = '12px sans-serif';
+    ctx.textAlign = 'center';
+    ctx.textBaseline = 'middle';
+    ctx.fillText('No shell running. Double-click to start one.', x + width / 2, y + height / 2);
+    ctx.textAlign = 'left';
+    return;
+  }
+
+  const left = x + TERMINAL_PADDING;
+  const top = y + TERMINAL_PADDING;
+  ctx.textBaseline = 'middle';
+  screen.rows.forEach((runs, row) => {
+    const middle = top + (row + 0.5) * TERMINAL_CELL_HEIGHT;
+    let col = 0;
+    for (const run of runs) {
+      const style = run.style;
+      let fg = terminalColor(style.fg, TERMINAL_FOREGROUND);
+      let bg = style.bg && style.bg !== 'default' ? terminalColor(style.bg, TERMINAL_BACKGROUND) : null;
+      if (style.inverse) {
+        [fg, bg] = [bg ?? TERMINAL_BACKGROUND, fg];
+      }
+      ctx.font = `${style.italic ? 'italic ' : ''}${style.bold ? 'bold ' : ''}${TERMINAL_FONT_SIZE}px monospace`;
+      ctx.globalAlpha = tile.style.opacity * (style.dim ? 0.6 : 1);
+      for (const character of run.text) {
+        const cells = WIDE_CHARACTER.test(character) ? 2 : 1;
+        const cellX = left + col * TERMINAL_CELL_WIDTH;
+        if (bg) {
+          ctx.fillStyle = bg;
+          ctx.fillRect(cellX, middle - TERMINAL_CELL_HEIGHT / 2, cells * TERMINAL_CELL_WIDTH, TERMINAL_CELL_HEIGHT);
+        }
+        if (character !== ' ') {
+          ctx.fillStyle = fg;
+          ctx.fillText(character, cellX, middle);
+        }
+        if (style.underline) {
+          ctx.fillStyle = fg;
+          ctx.fillRect(cellX, middle + TERMINAL_FONT_SIZE / 2, cells * TERMINAL_CELL_WIDTH, 1);
+        }
+        col += cells;
+      }
+    }
+  });
+  ctx.globalAlpha = tile.style.opacity;
+
+  const state = screen.state;
+  if (state?.cursor && !state.exited) {
+    const [row, col] = state.cursor;
+    ctx.strokeStyle = TERMINAL_FOREGROUND;
+    ctx.fillStyle = 'rgba(208, 208, 208, 0.5)';
+    ctx.lineWidth = 1 / cameraZoom;
+    const cursorX = left + col * TERMINAL_CELL_WIDTH;
+    const cursorY = top + row * TERMINAL_CELL_HEIGHT;
+    // Solid while the terminal has the keyboard, an outline otherwise
+    if (tile.id === selectedTileId) {
+      ctx.fillRect(cursorX, cursorY, TERMINAL_CELL_WIDTH, TERMINAL_CELL_HEIGHT);
+    } else {
+      ctx.strokeRect(cursorX, cursorY, TERMINAL_CELL_WIDTH, TERMINAL_CELL_HEIGHT);
+    }
+  }
+  if (state?.exited) {
+    ctx.fillStyle = TERMINAL_FOREGROUND;
+    ctx.font = `${TERMINAL_FONT_SIZE}px monospace`;
+    ctx.textAlign = 'right';
+    ctx.fillText('[process exited, double-click to restart]', x + width - TERMINAL_PADDING, y + height - TERMINAL_CELL_HEIGHT / 2);
+    ctx.textAlign = 'left';
+  }
+}
+
+// Index of the note checkbox under a world position, if any
+function noteCheckboxAt(tile: Tile, worldX: number, worldY: number): number | null {
+  if (!tile.note_lines) return null;
+  const origin = noteOrigin(tile);
+  for (const line of tile.note_lines) {
+    if (line.marker?.kind !== 'checkbox') continue;
+    const box = markerBox(line);
+    const x = worldX - origin.x - box.x;
+    const y = worldY - origin.y - box.y;
+    if (x >= 0 && x <= NOTE_CHECKBOX_SIZE && y >= 0 && y <= NOTE_CHECKBOX_SIZE) {
+      return line.marker.index;
+    }
+  }
+  return null;
+}
+
 // Utility functions
+// CSS color for an RGBA color with components in 0..1
+function rgba([r, g, b, a]: Rgba) {
+  return `rgba(${Math.round(r * 255)}, ${Math.round(g * 255)}, ${Math.round(b * 255)}, ${a})`;
+}
+
 function updateStatusBar() {
   const zoomInfo = document.getElementById('zoom-info');
   const positionInfo = document.getElementById('position-info');